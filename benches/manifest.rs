@@ -0,0 +1,51 @@
+//! Benchmarks of parsing font manifests and planning the files of a fetch, so regressions in either are caught.
+//! Run with `cargo bench`.
+
+use std::{hint::black_box, path::Path};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use fonty::{font_manifest::parse_manifest, http::strip_anti_xssi_prefix};
+
+/// A payload like Google Fonts returns for a large family: the anti-XSSI prefix, a license and a font file for
+/// every weight in every width, upright and italic.
+fn payload() -> String {
+    let file_refs = ["Condensed", "SemiCondensed", "", "SemiExpanded", "Expanded"]
+        .iter()
+        .flat_map(|width| {
+            (100..=900).step_by(100).flat_map(move |weight| {
+                ["", "Italic"].into_iter().map(move |style| {
+                    let name = format!("Family{}-{}{}", width, weight, style);
+                    format!(
+                        r#"{{"filename": "static/{}.ttf", "url": "https://fonts.gstatic.com/s/family/v1/{}.ttf"}}"#,
+                        name, name
+                    )
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    format!(
+        ")]}}'\n{{\"zipName\": \"Family.zip\", \"manifest\": {{\"files\": [{{\"filename\": \"OFL.txt\", \"contents\": \"{}\"}}], \"fileRefs\": [{}]}}}}",
+        "Copyright 2024 The Family Project Authors\\n".repeat(100),
+        file_refs.join(", ")
+    )
+}
+
+fn manifest_benchmarks(c: &mut Criterion) {
+    let payload = payload();
+    c.bench_function("parse manifest", |b| {
+        b.iter(|| {
+            let json = strip_anti_xssi_prefix(black_box(&payload)).unwrap();
+            parse_manifest(json).unwrap()
+        })
+    });
+
+    let mut font_manifest = parse_manifest(strip_anti_xssi_prefix(&payload).unwrap()).unwrap();
+    font_manifest.set_family("Family");
+    let base_path = Path::new("/home/user/.local/share/fonts");
+    c.bench_function("plan files", |b| {
+        b.iter(|| black_box(&font_manifest).planned_files(black_box(base_path)))
+    });
+}
+
+criterion_group!(benches, manifest_benchmarks);
+criterion_main!(benches);
@@ -0,0 +1,212 @@
+//! Writing zip and tar.gz archives for `fonty export`. Entries are compressed with deflate, and every timestamp is fixed,
+//! so the same files always give a byte-identical archive.
+
+use std::io::{Cursor, Write};
+
+use flate2::{Compression, write::GzEncoder};
+use zip::{CompressionMethod, DateTime, ZipWriter, write::SimpleFileOptions};
+
+/// The formats an archive can be written in, chosen by the extension of its path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Picks the format for an archive from its path: `.zip`, or `.tar.gz`/`.tgz`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.to_lowercase();
+        if path.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// A file in an archive, with its path inside the archive (using `/`).
+pub struct ArchiveEntry {
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+/// Writes entries into an archive, in the order given.
+pub fn write_archive(entries: &[ArchiveEntry], format: ArchiveFormat) -> Result<Vec<u8>, String> {
+    match format {
+        ArchiveFormat::Zip => write_zip(entries),
+        ArchiveFormat::TarGz => write_tar(entries).and_then(|tar| gzip(&tar)),
+    }
+}
+
+/// Regular files readable by everyone.
+const FILE_MODE: u32 = 0o644;
+
+fn write_zip(entries: &[ArchiveEntry]) -> Result<Vec<u8>, String> {
+    // 1980-01-01, the earliest date zip can represent, instead of the current time.
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(FILE_MODE)
+        .large_file(
+            entries
+                .iter()
+                .any(|entry| entry.contents.len() > u32::MAX as usize),
+        );
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for entry in entries {
+        zip.start_file(entry.path.as_str(), options)
+            .map_err(|e| format!("Failed to add '{}' to the archive! {}", entry.path, e))?;
+        zip.write_all(&entry.contents)
+            .map_err(|e| format!("Failed to add '{}' to the archive! {}", entry.path, e))?;
+    }
+    let archive = zip
+        .finish()
+        .map_err(|e| format!("Failed to write the archive! {}", e))?;
+    Ok(archive.into_inner())
+}
+
+/// The size of tar headers and the blocks the contents are padded to.
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// Writes a number as zero-padded octal into a tar header field, ending with a NUL.
+fn tar_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+/// Writes a ustar archive. Paths longer than 100 bytes are split into the prefix field.
+fn write_tar(entries: &[ArchiveEntry]) -> Result<Vec<u8>, String> {
+    let mut archive = Vec::new();
+    for entry in entries {
+        let path = entry.path.as_bytes();
+        let (prefix, name) = if path.len() <= 100 {
+            (&path[..0], path)
+        } else {
+            // Split at a `/`, so the name is at most 100 bytes and the prefix at most 155.
+            let split = (0..path.len())
+                .filter(|&index| path[index] == b'/')
+                .find(|&index| index <= 155 && path.len() - index - 1 <= 100)
+                .ok_or_else(|| {
+                    format!("The path '{}' is too long for a tar archive.", entry.path)
+                })?;
+            (&path[..split], &path[split + 1..])
+        };
+
+        let mut header = [0u8; TAR_BLOCK_SIZE];
+        header[..name.len()].copy_from_slice(name);
+        tar_octal(&mut header[100..108], FILE_MODE as u64);
+        tar_octal(&mut header[108..116], 0); // uid
+        tar_octal(&mut header[116..124], 0); // gid
+        tar_octal(&mut header[124..136], entry.contents.len() as u64);
+        tar_octal(&mut header[136..148], 0); // mtime: the unix epoch
+        header[156] = b'0'; // a regular file
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix);
+
+        // The checksum is calculated with the checksum field filled with spaces.
+        header[148..156].fill(b' ');
+        let checksum = header.iter().map(|&byte| byte as u64).sum::<u64>();
+        tar_octal(&mut header[148..155], checksum);
+
+        archive.extend_from_slice(&header);
+        archive.extend_from_slice(&entry.contents);
+        let padding = (TAR_BLOCK_SIZE - entry.contents.len() % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+        archive.resize(archive.len() + padding, 0);
+    }
+    // The end of the archive is marked by two empty blocks.
+    archive.resize(archive.len() + 2 * TAR_BLOCK_SIZE, 0);
+    Ok(archive)
+}
+
+/// Compresses data with gzip. The header has no modification time, so it only depends on the data.
+fn gzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("Failed to compress the archive! {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn entries() -> Vec<ArchiveEntry> {
+        vec![
+            ArchiveEntry {
+                path: "Inter/OFL.txt".to_string(),
+                contents: b"Copyright ".repeat(100),
+            },
+            ArchiveEntry {
+                path: "Inter/static/Inter-Regular.ttf".to_string(),
+                contents: vec![0; 4096],
+            },
+        ]
+    }
+
+    #[test]
+    fn formats_from_paths() {
+        assert_eq!(
+            ArchiveFormat::from_path("fonts.ZIP"),
+            Some(ArchiveFormat::Zip)
+        );
+        assert_eq!(
+            ArchiveFormat::from_path("fonts.tar.gz"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(
+            ArchiveFormat::from_path("fonts.tgz"),
+            Some(ArchiveFormat::TarGz)
+        );
+        assert_eq!(ArchiveFormat::from_path("fonts.tar"), None);
+    }
+
+    #[test]
+    fn zip_round_trips_compressed() {
+        let archive = write_archive(&entries(), ArchiveFormat::Zip).unwrap();
+        let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        assert_eq!(zip.len(), 2);
+        for (index, entry) in entries().iter().enumerate() {
+            let mut file = zip.by_index(index).unwrap();
+            assert_eq!(file.name(), entry.path);
+            assert_eq!(file.compression(), CompressionMethod::Deflated);
+            assert!(file.compressed_size() < file.size());
+            assert_eq!(file.unix_mode(), Some(0o100644));
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(contents, entry.contents);
+        }
+    }
+
+    #[test]
+    fn tar_gz_round_trips_compressed() {
+        let archive = write_archive(&entries(), ArchiveFormat::TarGz).unwrap();
+        let mut tar = Vec::new();
+        GzDecoder::new(archive.as_slice())
+            .read_to_end(&mut tar)
+            .unwrap();
+        assert!(archive.len() < tar.len());
+        assert_eq!(tar, write_tar(&entries()).unwrap());
+    }
+
+    #[test]
+    fn archives_are_byte_identical() {
+        for format in [ArchiveFormat::Zip, ArchiveFormat::TarGz] {
+            assert_eq!(
+                write_archive(&entries(), format).unwrap(),
+                write_archive(&entries(), format).unwrap()
+            );
+        }
+    }
+}
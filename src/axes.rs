@@ -0,0 +1,361 @@
+//! Variation axes for icon fonts like Material Symbols, which are variable fonts with `FILL`, `wght`, `GRAD` and `opsz` axes.
+//! Instead of every file of the family, `--axes FILL=0..1,wght=400` fetches one font with exactly those axis ranges from the CSS API.
+
+use std::fmt;
+
+/// The values a variation axis of a family can have.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisBounds {
+    pub tag: &'static str,
+    pub min: f32,
+    pub max: f32,
+}
+
+/// The axes of the Material Symbols families (Outlined, Rounded and Sharp).
+const MATERIAL_SYMBOLS_AXES: &[AxisBounds] = &[
+    AxisBounds {
+        tag: "FILL",
+        min: 0.0,
+        max: 1.0,
+    },
+    AxisBounds {
+        tag: "GRAD",
+        min: -50.0,
+        max: 200.0,
+    },
+    AxisBounds {
+        tag: "opsz",
+        min: 20.0,
+        max: 48.0,
+    },
+    AxisBounds {
+        tag: "wght",
+        min: 100.0,
+        max: 700.0,
+    },
+];
+
+/// Finds the axes of an icon font family. The older Material Icons families are not variable, so they have none.
+/// Other families give `None`, as they are not icon fonts.
+pub fn icon_font_axes(font_name: &str) -> Option<&'static [AxisBounds]> {
+    let font_name = font_name.to_lowercase();
+    if font_name.starts_with("material symbols") {
+        Some(MATERIAL_SYMBOLS_AXES)
+    } else if font_name.starts_with("material icons") {
+        Some(&[])
+    } else {
+        None
+    }
+}
+
+/// A range of an axis to fetch, e.g. `wght=100..700`. A single value, like `wght=400`, is a range with `min == max`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AxisRange {
+    pub tag: String,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl fmt::Display for AxisRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{}..{}", self.min, self.max)
+        }
+    }
+}
+
+/// Why an axis spec is invalid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AxisError {
+    /// A part of the spec is not `tag=value` or `tag=min..max`.
+    Syntax(String),
+    /// A range with its minimum above its maximum, e.g. `wght=700..100`.
+    InvertedRange(String),
+    /// An axis given twice.
+    Duplicate(String),
+    /// An axis the family does not have.
+    UnknownAxis {
+        tag: String,
+        known: Vec<&'static str>,
+    },
+    /// A range reaching beyond what the family has.
+    OutOfBounds {
+        range: AxisRange,
+        bounds: AxisBounds,
+    },
+}
+
+impl fmt::Display for AxisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisError::Syntax(part) => write!(
+                f,
+                "'{}' is not an axis with a value like 'wght=400' or a range like 'wght=100..700'",
+                part
+            ),
+            AxisError::InvertedRange(part) => {
+                write!(f, "the range of '{}' starts above where it ends", part)
+            }
+            AxisError::Duplicate(tag) => write!(f, "the axis '{}' is given more than once", tag),
+            AxisError::UnknownAxis { tag, known } if known.is_empty() => {
+                write!(f, "the family has no axes, so '{}' can't be set", tag)
+            }
+            AxisError::UnknownAxis { tag, known } => write!(
+                f,
+                "the family has no axis '{}', only '{}'",
+                tag,
+                known.join("', '")
+            ),
+            AxisError::OutOfBounds { range, bounds } => write!(
+                f,
+                "'{}={}' is outside the range of the axis, {}..{}",
+                range.tag, range, bounds.min, bounds.max
+            ),
+        }
+    }
+}
+
+/// Parses an axis spec like `FILL=0..1,wght=400`. Tags are case-sensitive, as `wght` and `WGHT` would be different axes.
+pub fn parse_axes(spec: &str) -> Result<Vec<AxisRange>, AxisError> {
+    let mut axes: Vec<AxisRange> = Vec::new();
+    for part in spec.split(',').map(str::trim) {
+        let syntax_error = || AxisError::Syntax(part.to_string());
+        let (tag, value) = part.split_once('=').ok_or_else(syntax_error)?;
+        let tag = tag.trim();
+        if tag.len() != 4
+            || !tag
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric())
+        {
+            return Err(syntax_error());
+        }
+        let parse_value = |value: &str| {
+            value
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(syntax_error)
+        };
+        let (min, max) = match value.split_once("..") {
+            Some((min, max)) => (parse_value(min)?, parse_value(max)?),
+            None => {
+                let value = parse_value(value)?;
+                (value, value)
+            }
+        };
+        if min > max {
+            return Err(AxisError::InvertedRange(part.to_string()));
+        }
+        if axes.iter().any(|axis| axis.tag == tag) {
+            return Err(AxisError::Duplicate(tag.to_string()));
+        }
+        axes.push(AxisRange {
+            tag: tag.to_string(),
+            min,
+            max,
+        });
+    }
+    Ok(axes)
+}
+
+/// Checks that the family has every axis, and that the ranges are within what it has.
+pub fn check_axes(axes: &[AxisRange], bounds: &[AxisBounds]) -> Result<(), AxisError> {
+    for axis in axes {
+        let Some(axis_bounds) = bounds.iter().find(|bounds| bounds.tag == axis.tag) else {
+            return Err(AxisError::UnknownAxis {
+                tag: axis.tag.clone(),
+                known: bounds.iter().map(|bounds| bounds.tag).collect(),
+            });
+        };
+        if axis.min < axis_bounds.min || axis.max > axis_bounds.max {
+            return Err(AxisError::OutOfBounds {
+                range: axis.clone(),
+                bounds: *axis_bounds,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Sorts axes the way the CSS API wants them: lowercase (registered) tags first, then uppercase (custom) ones, alphabetically.
+fn sorted_axes(axes: &[AxisRange]) -> Vec<&AxisRange> {
+    let mut axes = axes.iter().collect::<Vec<_>>();
+    axes.sort_by_key(|axis| {
+        (
+            axis.tag.starts_with(|c: char| c.is_ascii_uppercase()),
+            &axis.tag,
+        )
+    });
+    axes
+}
+
+/// Builds the family spec for the CSS API with the axis ranges, e.g. `Material Symbols Outlined:wght,FILL@400,0..1`.
+/// Axes that are not given get their default value.
+pub fn css2_axes_spec(font_name: &str, axes: &[AxisRange]) -> String {
+    if axes.is_empty() {
+        return font_name.to_string();
+    }
+    let axes = sorted_axes(axes);
+    format!(
+        "{}:{}@{}",
+        font_name,
+        axes.iter()
+            .map(|axis| axis.tag.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        axes.iter()
+            .map(|axis| axis.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+/// Names the font fetched with axis ranges after the family and the ranges, like the `Roboto[wdth,wght].ttf` files
+/// on Google Fonts: `MaterialSymbolsOutlined[FILL=0..1,wght=400].ttf`.
+pub fn axes_filename(font_name: &str, axes: &[AxisRange], extension: &str) -> String {
+    let family = font_name.replace(' ', "");
+    if axes.is_empty() {
+        return format!("{}.{}", family, extension);
+    }
+    format!(
+        "{}[{}].{}",
+        family,
+        sorted_axes(axes)
+            .iter()
+            .map(|axis| format!("{}={}", axis.tag, axis))
+            .collect::<Vec<_>>()
+            .join(","),
+        extension
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axis(tag: &str, min: f32, max: f32) -> AxisRange {
+        AxisRange {
+            tag: tag.to_string(),
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn axis_specs_are_parsed() {
+        assert_eq!(
+            parse_axes("FILL=0..1, wght=400,GRAD = -25 .. 0"),
+            Ok(vec![
+                axis("FILL", 0.0, 1.0),
+                axis("wght", 400.0, 400.0),
+                axis("GRAD", -25.0, 0.0),
+            ])
+        );
+        for spec in [
+            "wght",
+            "wght=",
+            "wght=bold",
+            "weight=400",
+            "wght=1..2..3",
+            "wght=inf",
+            "",
+        ] {
+            assert_eq!(
+                parse_axes(spec),
+                Err(AxisError::Syntax(spec.trim().to_string())),
+                "{}",
+                spec
+            );
+        }
+        assert_eq!(
+            parse_axes("wght=400,wght=700"),
+            Err(AxisError::Duplicate("wght".to_string()))
+        );
+    }
+
+    #[test]
+    fn inverted_ranges_are_rejected() {
+        let error = parse_axes("FILL=0..1,wght=700..100").unwrap_err();
+        assert_eq!(error, AxisError::InvertedRange("wght=700..100".to_string()));
+        assert_eq!(
+            error.to_string(),
+            "the range of 'wght=700..100' starts above where it ends"
+        );
+    }
+
+    #[test]
+    fn unknown_axes_list_the_ones_the_family_has() {
+        let error = check_axes(
+            &parse_axes("slnt=-10").unwrap(),
+            icon_font_axes("Material Symbols Rounded").unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the family has no axis 'slnt', only 'FILL', 'GRAD', 'opsz', 'wght'"
+        );
+        // Tags are case-sensitive.
+        assert!(matches!(
+            check_axes(&parse_axes("WGHT=400").unwrap(), MATERIAL_SYMBOLS_AXES),
+            Err(AxisError::UnknownAxis { .. })
+        ));
+        assert_eq!(
+            check_axes(
+                &parse_axes("wght=400").unwrap(),
+                icon_font_axes("Material Icons").unwrap()
+            )
+            .unwrap_err()
+            .to_string(),
+            "the family has no axes, so 'wght' can't be set"
+        );
+        assert_eq!(icon_font_axes("Roboto"), None);
+    }
+
+    #[test]
+    fn ranges_must_be_within_the_bounds_of_the_axis() {
+        assert_eq!(
+            check_axes(
+                &parse_axes("FILL=0..1,wght=100..700,GRAD=-50,opsz=48").unwrap(),
+                MATERIAL_SYMBOLS_AXES
+            ),
+            Ok(())
+        );
+        for spec in ["wght=50..700", "wght=100..900", "FILL=2"] {
+            assert!(
+                matches!(
+                    check_axes(&parse_axes(spec).unwrap(), MATERIAL_SYMBOLS_AXES),
+                    Err(AxisError::OutOfBounds { .. })
+                ),
+                "{}",
+                spec
+            );
+        }
+        assert_eq!(
+            check_axes(&parse_axes("wght=100..900").unwrap(), MATERIAL_SYMBOLS_AXES)
+                .unwrap_err()
+                .to_string(),
+            "'wght=100..900' is outside the range of the axis, 100..700"
+        );
+    }
+
+    #[test]
+    fn axes_are_sorted_for_the_css_api_and_filenames() {
+        let axes = parse_axes("wght=400,FILL=0..1,GRAD=0,opsz=24").unwrap();
+        assert_eq!(
+            css2_axes_spec("Material Symbols Outlined", &axes),
+            "Material Symbols Outlined:opsz,wght,FILL,GRAD@24,400,0..1,0"
+        );
+        assert_eq!(
+            axes_filename("Material Symbols Outlined", &axes, "ttf"),
+            "MaterialSymbolsOutlined[opsz=24,wght=400,FILL=0..1,GRAD=0].ttf"
+        );
+        assert_eq!(css2_axes_spec("Material Icons", &[]), "Material Icons");
+        assert_eq!(
+            axes_filename("Material Icons", &[], "woff2"),
+            "MaterialIcons.woff2"
+        );
+    }
+}
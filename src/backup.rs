@@ -0,0 +1,176 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{font_names::family_dir_name, registry::RegistryEntry};
+
+/// The dir backups are kept in, directly inside the font base dir. It starts with a dot, so it is never mistaken for a family.
+pub const BACKUP_DIRNAME: &str = ".fonty-backups";
+
+/// The registry entry of the family as it was before the backup, inside each backup set.
+const ENTRY_FILENAME: &str = "entry.json";
+
+/// The files of a family that were replaced in one run, in `.fonty-backups/<family>/<milliseconds since the epoch>/`.
+/// The files keep their paths relative to the font base dir.
+#[derive(Clone, Debug)]
+pub struct BackupSet {
+    pub path: PathBuf,
+    /// When the backup was made, in milliseconds since the unix epoch.
+    pub created_at: u128,
+}
+
+impl BackupSet {
+    /// The registry entry the family had when the backup was made. `None` if it was not in the registry.
+    pub fn entry(&self) -> Option<RegistryEntry> {
+        let contents = fs::read_to_string(self.path.join(ENTRY_FILENAME)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// The backed up files, relative to the font base dir.
+    pub fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        collect_files(&self.path, &self.path, &mut files)?;
+        files.retain(|file| file != Path::new(ENTRY_FILENAME));
+        Ok(files)
+    }
+}
+
+/// Lists the files in a dir and its subdirs, relative to `root`.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative_path) = path.strip_prefix(root) {
+            files.push(relative_path.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// The dir with the backup sets of a family.
+fn family_backup_dir(base_path: &Path, family: &str) -> PathBuf {
+    base_path.join(BACKUP_DIRNAME).join(family_dir_name(family))
+}
+
+/// Moves the files of a family that are about to be overwritten into a new backup set, together with its registry entry.
+/// `paths` are full paths inside the font base dir. Returns `None` if there was nothing to back up.
+pub fn back_up_files(
+    base_path: &Path,
+    family: &str,
+    entry: Option<&RegistryEntry>,
+    paths: &[PathBuf],
+) -> io::Result<Option<BackupSet>> {
+    let paths = paths
+        .iter()
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis());
+    let set = BackupSet {
+        path: family_backup_dir(base_path, family).join(created_at.to_string()),
+        created_at,
+    };
+    fs::create_dir_all(&set.path)?;
+    if let Some(entry) = entry {
+        fs::write(
+            set.path.join(ENTRY_FILENAME),
+            serde_json::to_string_pretty(entry)?,
+        )?;
+    }
+    for path in paths {
+        let Ok(relative_path) = path.strip_prefix(base_path) else {
+            continue;
+        };
+        let backup_path = set.path.join(relative_path);
+        if let Some(parent_dir) = backup_path.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        fs::rename(path, backup_path)?;
+    }
+    Ok(Some(set))
+}
+
+/// Lists the backup sets of a family, oldest first.
+pub fn backup_sets(base_path: &Path, family: &str) -> Vec<BackupSet> {
+    let Ok(entries) = fs::read_dir(family_backup_dir(base_path, family)) else {
+        return Vec::new();
+    };
+    let mut sets = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            Some(BackupSet {
+                created_at: entry.file_name().to_str()?.parse().ok()?,
+                path: entry.path(),
+            })
+        })
+        .collect::<Vec<_>>();
+    sets.sort_by_key(|set| set.created_at);
+    sets
+}
+
+/// Moves the files of a backup set back into the font base dir, replacing the ones there, and deletes the set.
+/// Returns the restored files, relative to the font base dir.
+pub fn restore_backup(base_path: &Path, set: &BackupSet) -> io::Result<Vec<PathBuf>> {
+    let files = set.files()?;
+    for file in &files {
+        let path = base_path.join(file);
+        if let Some(parent_dir) = path.parent() {
+            fs::create_dir_all(parent_dir)?;
+        }
+        fs::rename(set.path.join(file), path)?;
+    }
+    fs::remove_dir_all(&set.path)?;
+    // Only succeeds once the family has no backups left.
+    if let Some(family_dir) = set.path.parent() {
+        let _ = fs::remove_dir(family_dir);
+    }
+    Ok(files)
+}
+
+/// Deletes the backup sets (of every family) made longer than `retention` ago. Returns the deleted sets.
+pub fn prune_backups(base_path: &Path, retention: Duration) -> io::Result<Vec<BackupSet>> {
+    let backup_dir = base_path.join(BACKUP_DIRNAME);
+    let entries = match fs::read_dir(&backup_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis());
+
+    let mut pruned = Vec::new();
+    for entry in entries {
+        let family_dir = entry?.path();
+        let Ok(sets) = fs::read_dir(&family_dir) else {
+            continue;
+        };
+        for set in sets {
+            let path = set?.path();
+            let Some(created_at) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u128>().ok())
+            else {
+                continue;
+            };
+            if now.saturating_sub(created_at) > retention.as_millis() {
+                fs::remove_dir_all(&path)?;
+                pruned.push(BackupSet { path, created_at });
+            }
+        }
+        // Only succeeds once the family has no backups left.
+        let _ = fs::remove_dir(&family_dir);
+    }
+    let _ = fs::remove_dir(&backup_dir);
+    Ok(pruned)
+}
@@ -0,0 +1,847 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt,
+    fs::{File, create_dir_all, remove_dir, remove_file},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cli::Cli,
+    exit_codes::{EXIT_CODE_CONFIG_ERROR, EXIT_CODE_INVALID_ARGUMENTS},
+};
+
+/// The default system-wide font dir, used with `--system`.
+const DEFAULT_SYSTEM_FONT_DIR: &str = "/usr/local/share/fonts/Google";
+
+/// Where the base path for the font files came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BasePathSource {
+    BasePathFlag,
+    UserFlag,
+    SystemFlag,
+    SystemDirFlag,
+    OutputDirFlag,
+    OutputDirEnv,
+    Env,
+    /// `base_path` in the config file.
+    Config,
+    Default,
+}
+
+impl fmt::Display for BasePathSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BasePathSource::BasePathFlag => write!(f, "--base-path flag"),
+            BasePathSource::UserFlag => write!(f, "--user flag"),
+            BasePathSource::SystemFlag => write!(f, "--system flag"),
+            BasePathSource::SystemDirFlag => write!(f, "--system-dir flag"),
+            BasePathSource::OutputDirFlag => write!(f, "--output-dir flag"),
+            BasePathSource::OutputDirEnv => write!(f, "$FONTY_OUTPUT_DIR"),
+            BasePathSource::Env => write!(f, "$FONTY_BASE_PATH"),
+            BasePathSource::Config => write!(f, "'base_path' in the config file"),
+            BasePathSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Why the base path for the font files could not be resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BasePathError {
+    /// More than one of `--base-path`, `--user`, `--system` and `--output-dir` were given.
+    ConflictingFlags,
+    /// None of the environment variables the default base path is built from are set.
+    NoUsableEnv { variables: &'static [&'static str] },
+    /// The base path references an environment variable that is not set.
+    UndefinedVariable { variable: String, value: String },
+    /// The base path contains a `${` without a matching `}`.
+    UnterminatedVariable { value: String },
+    /// The base path (or one of its parents) exists, but is a file.
+    NotADirectory { path: PathBuf },
+    /// The base path (or its nearest existing parent) can not be written to.
+    NotWritable {
+        path: PathBuf,
+        kind: io::ErrorKind,
+        error: String,
+    },
+}
+
+impl fmt::Display for BasePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BasePathError::ConflictingFlags => {
+                write!(
+                    f,
+                    "Only one of the flags '--base-path', '--user', '--system' and '--output-dir' can be used!"
+                )
+            }
+            BasePathError::NoUsableEnv { variables } => write!(
+                f,
+                "Could not find a font base dir, as none of {} are set! Set one of them, or use --system.",
+                variables
+                    .iter()
+                    .map(|variable| format!("${}", variable))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            BasePathError::UndefinedVariable { variable, value } => write!(
+                f,
+                "The font base dir '{}' uses the variable ${}, but it is not set!",
+                value, variable
+            ),
+            BasePathError::UnterminatedVariable { value } => write!(
+                f,
+                "The font base dir '{}' has a '${{' without a matching '}}'!",
+                value
+            ),
+            BasePathError::NotADirectory { path } => write!(
+                f,
+                "The font base dir can not be created, as '{}' is a file! Use --output-dir to install somewhere else.",
+                path.display()
+            ),
+            BasePathError::NotWritable { path, error, .. } => write!(
+                f,
+                "The font base dir '{}' is not writable, try --output-dir or fix its permissions! Error:\n{}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+impl BasePathError {
+    /// The exit code to exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            BasePathError::ConflictingFlags => EXIT_CODE_INVALID_ARGUMENTS,
+            BasePathError::NoUsableEnv { .. }
+            | BasePathError::UndefinedVariable { .. }
+            | BasePathError::UnterminatedVariable { .. }
+            | BasePathError::NotADirectory { .. }
+            | BasePathError::NotWritable { .. } => EXIT_CODE_CONFIG_ERROR,
+        }
+    }
+}
+
+/// The resolved base path for the font files, and where it came from.
+#[derive(Clone, Debug)]
+pub struct BasePath {
+    pub path: PathBuf,
+    pub source: BasePathSource,
+}
+
+impl BasePath {
+    /// Checks if the base path is a system-wide font dir.
+    pub fn is_system(&self) -> bool {
+        matches!(
+            self.source,
+            BasePathSource::SystemFlag | BasePathSource::SystemDirFlag
+        )
+    }
+}
+
+/// The platforms with different default font dirs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// Linux and other unix-likes, following the XDG base directory spec.
+    Unix,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    /// The platform fonty was compiled for.
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Unix
+        }
+    }
+}
+
+/// The environment variables used to find the base path.
+/// Kept separate from the process environment so the resolution can be done without side effects.
+#[derive(Clone, Debug, Default)]
+pub struct BasePathEnv {
+    pub fonty_base_path: Option<OsString>,
+    pub home: Option<OsString>,
+    pub xdg_data_home: Option<OsString>,
+    pub xdg_cache_home: Option<OsString>,
+    pub xdg_config_home: Option<OsString>,
+    pub local_app_data: Option<OsString>,
+    pub fonty_output_dir: Option<OsString>,
+    pub fonty_config: Option<OsString>,
+    /// All environment variables, for expanding references in the base path.
+    pub vars: HashMap<String, OsString>,
+}
+
+impl BasePathEnv {
+    /// Reads the relevant variables from the process environment. Variables set to an empty value count as unset.
+    pub fn from_process_env() -> Self {
+        let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+        Self {
+            fonty_base_path: var("FONTY_BASE_PATH"),
+            home: var("HOME"),
+            xdg_data_home: var("XDG_DATA_HOME"),
+            xdg_cache_home: var("XDG_CACHE_HOME"),
+            xdg_config_home: var("XDG_CONFIG_HOME"),
+            local_app_data: var("LOCALAPPDATA"),
+            fonty_output_dir: var("FONTY_OUTPUT_DIR"),
+            fonty_config: var("FONTY_CONFIG"),
+            vars: std::env::vars_os()
+                .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+                .collect(),
+        }
+    }
+}
+
+/// Find the default per-user base path for the font files on the given platform.
+/// Fails if the environment variables it is built from are not set, instead of guessing a path.
+pub fn default_user_font_base_path(
+    env: &BasePathEnv,
+    platform: Platform,
+) -> Result<PathBuf, BasePathError> {
+    match platform {
+        Platform::Unix => match (&env.xdg_data_home, &env.home) {
+            (Some(xdg_data_home), _) => Ok(Path::new(xdg_data_home).join("fonts").join("Google")),
+            (None, Some(home_dir)) => Ok(Path::new(home_dir)
+                .join(".local")
+                .join("share")
+                .join("fonts")
+                .join("Google")),
+            (None, None) => Err(BasePathError::NoUsableEnv {
+                variables: &["FONTY_BASE_PATH", "XDG_DATA_HOME", "HOME"],
+            }),
+        },
+        // Fonts outside of ~/Library/Fonts are invisible to apps on macOS.
+        Platform::MacOs => match &env.home {
+            Some(home_dir) => Ok(Path::new(home_dir)
+                .join("Library")
+                .join("Fonts")
+                .join("Google")),
+            None => Err(BasePathError::NoUsableEnv {
+                variables: &["FONTY_BASE_PATH", "HOME"],
+            }),
+        },
+        // The per-user font dir, which does not need admin rights.
+        Platform::Windows => match &env.local_app_data {
+            Some(local_app_data) => Ok(Path::new(local_app_data)
+                .join("Microsoft")
+                .join("Windows")
+                .join("Fonts")),
+            None => Err(BasePathError::NoUsableEnv {
+                variables: &["FONTY_BASE_PATH", "LOCALAPPDATA"],
+            }),
+        },
+    }
+}
+
+/// Find the per-user dir fonty caches things (like the catalog) in on the given platform.
+/// Returns `None` if the environment variables it is built from are not set, in which case nothing is cached.
+pub fn default_cache_dir(env: &BasePathEnv, platform: Platform) -> Option<PathBuf> {
+    match platform {
+        Platform::Unix => match (&env.xdg_cache_home, &env.home) {
+            (Some(xdg_cache_home), _) => Some(Path::new(xdg_cache_home).join("fonty")),
+            (None, Some(home_dir)) => Some(Path::new(home_dir).join(".cache").join("fonty")),
+            (None, None) => None,
+        },
+        Platform::MacOs => env.home.as_ref().map(|home_dir| {
+            Path::new(home_dir)
+                .join("Library")
+                .join("Caches")
+                .join("fonty")
+        }),
+        Platform::Windows => env
+            .local_app_data
+            .as_ref()
+            .map(|local_app_data| Path::new(local_app_data).join("fonty").join("cache")),
+    }
+}
+
+/// Find the per-user dir fonty reads its config (like the user's `fonty.toml`) from on the given platform.
+/// Returns `None` if the environment variables it is built from are not set.
+pub fn default_config_dir(env: &BasePathEnv, platform: Platform) -> Option<PathBuf> {
+    match platform {
+        Platform::Unix => match (&env.xdg_config_home, &env.home) {
+            (Some(xdg_config_home), _) => Some(Path::new(xdg_config_home).join("fonty")),
+            (None, Some(home_dir)) => Some(Path::new(home_dir).join(".config").join("fonty")),
+            (None, None) => None,
+        },
+        Platform::MacOs => env.home.as_ref().map(|home_dir| {
+            Path::new(home_dir)
+                .join("Library")
+                .join("Application Support")
+                .join("fonty")
+        }),
+        Platform::Windows => env
+            .local_app_data
+            .as_ref()
+            .map(|local_app_data| Path::new(local_app_data).join("fonty")),
+    }
+}
+
+/// Find the per-user dir fontconfig reads extra configuration files from, `$XDG_CONFIG_HOME/fontconfig/conf.d`.
+/// Returns `None` if the environment variables it is built from are not set.
+pub fn fontconfig_conf_dir(env: &BasePathEnv) -> Option<PathBuf> {
+    let config_home = match (&env.xdg_config_home, &env.home) {
+        (Some(xdg_config_home), _) => PathBuf::from(xdg_config_home),
+        (None, Some(home_dir)) => Path::new(home_dir).join(".config"),
+        (None, None) => return None,
+    };
+    Some(config_home.join("fontconfig").join("conf.d"))
+}
+
+/// Expands a leading `~` to the home dir, and `$VAR`/`${VAR}` references to the values of environment variables.
+/// A `$` that isn't followed by a variable name is kept as is.
+pub fn expand_path_vars(value: &str, env: &BasePathEnv) -> Result<OsString, BasePathError> {
+    let lookup = |variable: &str| {
+        env.vars
+            .get(variable)
+            .cloned()
+            .ok_or(BasePathError::UndefinedVariable {
+                variable: variable.to_string(),
+                value: value.to_string(),
+            })
+    };
+
+    let mut expanded = OsString::new();
+    let mut rest = value;
+
+    if rest == "~" || rest.starts_with("~/") || (cfg!(windows) && rest.starts_with("~\\")) {
+        match &env.home {
+            Some(home_dir) => expanded.push(home_dir),
+            None => expanded.push(lookup("HOME")?),
+        }
+        rest = &rest[1..];
+    }
+
+    while let Some(index) = rest.find('$') {
+        expanded.push(&rest[..index]);
+        let after_dollar = &rest[index + 1..];
+
+        let (variable, remaining) = match after_dollar.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => {
+                    return Err(BasePathError::UnterminatedVariable {
+                        value: value.to_string(),
+                    });
+                }
+            },
+            None => {
+                let end = after_dollar
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(after_dollar.len());
+                after_dollar.split_at(end)
+            }
+        };
+
+        if variable.is_empty() {
+            expanded.push("$");
+            rest = after_dollar;
+            continue;
+        }
+
+        expanded.push(lookup(variable)?);
+        rest = remaining;
+    }
+    expanded.push(rest);
+
+    Ok(expanded)
+}
+
+/// Resolves the base path for the font files, using the flags given on the command line, the environment
+/// and `base_path` from the config file. The resolved path is made absolute, so it is clear where the fonts end up.
+pub fn resolve_base_path(
+    cli: &Cli,
+    env: &BasePathEnv,
+    config_base_path: Option<&str>,
+) -> Result<BasePath, BasePathError> {
+    let base_path = resolve_base_path_with_env(cli, env, config_base_path, Platform::current())?;
+    Ok(BasePath {
+        path: std::path::absolute(&base_path.path).unwrap_or(base_path.path),
+        source: base_path.source,
+    })
+}
+
+/// Normalizes a path by collapsing redundant separators and `.` components, and removing trailing separators.
+/// `..` components are kept, as resolving them would change the meaning of paths through symlinks.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// Resolves the base path for the font files, using the flags given on the command line, the given environment
+/// and `base_path` from the config file.
+pub fn resolve_base_path_with_env(
+    cli: &Cli,
+    env: &BasePathEnv,
+    config_base_path: Option<&str>,
+    platform: Platform,
+) -> Result<BasePath, BasePathError> {
+    let base_path = resolve_unnormalized_base_path(cli, env, config_base_path, platform)?;
+    Ok(BasePath {
+        path: normalize_path(&base_path.path),
+        source: base_path.source,
+    })
+}
+
+/// Resolves the base path for the font files, exactly as given by the flags, the environment or the config file.
+/// Flags take precedence over the environment, which takes precedence over the config file.
+fn resolve_unnormalized_base_path(
+    cli: &Cli,
+    env: &BasePathEnv,
+    config_base_path: Option<&str>,
+    platform: Platform,
+) -> Result<BasePath, BasePathError> {
+    let system = cli.has_flag("--system") || cli.has_flag("--system-dir");
+    let targets = [
+        cli.has_flag("--base-path"),
+        system,
+        cli.has_flag("--user"),
+        cli.has_flag("--output-dir"),
+    ];
+    if targets.iter().filter(|&&target| target).count() > 1 {
+        return Err(BasePathError::ConflictingFlags);
+    }
+
+    if let Some(base_path) = cli.flag_value("--base-path") {
+        return Ok(BasePath {
+            path: PathBuf::from(base_path),
+            source: BasePathSource::BasePathFlag,
+        });
+    }
+
+    if let Some(output_dir) = cli.flag_value("--output-dir") {
+        return Ok(BasePath {
+            path: PathBuf::from(output_dir),
+            source: BasePathSource::OutputDirFlag,
+        });
+    }
+
+    if let Some(system_dir) = cli.flag_value("--system-dir") {
+        return Ok(BasePath {
+            path: PathBuf::from(system_dir),
+            source: BasePathSource::SystemDirFlag,
+        });
+    }
+
+    if system {
+        return Ok(BasePath {
+            path: PathBuf::from(DEFAULT_SYSTEM_FONT_DIR),
+            source: BasePathSource::SystemFlag,
+        });
+    }
+
+    if cli.has_flag("--user") {
+        return Ok(BasePath {
+            path: default_user_font_base_path(env, platform)?,
+            source: BasePathSource::UserFlag,
+        });
+    }
+
+    // Values that aren't valid UTF-8 can't contain references, so they are used as is.
+    let expand = |value: &OsString| match value.to_str() {
+        Some(value) => expand_path_vars(value, env).map(PathBuf::from),
+        None => Ok(PathBuf::from(value)),
+    };
+
+    Ok(
+        match (
+            &env.fonty_output_dir,
+            &env.fonty_base_path,
+            config_base_path,
+        ) {
+            (Some(fonty_output_dir), _, _) => BasePath {
+                path: expand(fonty_output_dir)?,
+                source: BasePathSource::OutputDirEnv,
+            },
+            (None, Some(fonty_base_path), _) => BasePath {
+                path: expand(fonty_base_path)?,
+                source: BasePathSource::Env,
+            },
+            (None, None, Some(config_base_path)) => BasePath {
+                path: PathBuf::from(expand_path_vars(config_base_path, env)?),
+                source: BasePathSource::Config,
+            },
+            (None, None, None) => BasePath {
+                path: default_user_font_base_path(env, platform)?,
+                source: BasePathSource::Default,
+            },
+        },
+    )
+}
+
+/// Finds the closest ancestor of a path (or the path itself) that exists.
+pub fn nearest_existing_ancestor(path: &Path) -> &Path {
+    let mut dir = path;
+    while !dir.exists() {
+        dir = match dir.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            // A relative path with no existing ancestor ends up in the current directory.
+            _ => Path::new("."),
+        };
+    }
+    dir
+}
+
+/// Checks if the filesystem the base path is on ignores case in filenames, by writing a probe file and looking it up in upper case.
+/// If probing fails, the default of the platform is assumed (case-insensitive on macOS and Windows).
+pub fn is_case_insensitive(base_path: &Path) -> bool {
+    let dir = nearest_existing_ancestor(base_path);
+    let probe_path = dir.join(".fonty-case-probe");
+    if File::create(&probe_path).is_err() {
+        return cfg!(any(target_os = "macos", windows));
+    }
+
+    let case_insensitive = dir.join(".FONTY-CASE-PROBE").exists();
+    let _ = remove_file(&probe_path);
+    case_insensitive
+}
+
+/// Checks that fonty will be able to write into the base path, before anything is downloaded.
+/// The missing dirs of the base path are created to check that they can be, and the probe file is written into the base path itself.
+/// The dirs created for this are removed again afterwards, so a fetch that fails later leaves nothing behind.
+pub fn check_base_path_writable(base_path: &BasePath) -> Result<(), BasePathError> {
+    let dir = nearest_existing_ancestor(&base_path.path);
+
+    // Otherwise every file fails to be written on its own, with a confusing error.
+    if !dir.is_dir() {
+        return Err(BasePathError::NotADirectory {
+            path: dir.to_path_buf(),
+        });
+    }
+    let not_writable = |e: io::Error| BasePathError::NotWritable {
+        path: dir.to_path_buf(),
+        kind: e.kind(),
+        error: e.to_string(),
+    };
+
+    // The only reliable way to know if a directory is writable is to write to it.
+    let created_dirs = base_path
+        .path
+        .ancestors()
+        .take_while(|ancestor| *ancestor != dir)
+        .collect::<Vec<_>>();
+    create_dir_all(&base_path.path).map_err(not_writable)?;
+    let probe_path = base_path.path.join(".fonty-write-probe");
+    let probed = File::create(&probe_path).and_then(|_| remove_file(&probe_path));
+    for created_dir in created_dirs {
+        let _ = remove_dir(created_dir);
+    }
+    probed.map_err(not_writable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> BasePathEnv {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect::<HashMap<_, _>>();
+        BasePathEnv {
+            home: vars.get("HOME").cloned(),
+            fonty_base_path: vars.get("FONTY_BASE_PATH").cloned(),
+            xdg_data_home: vars.get("XDG_DATA_HOME").cloned(),
+            local_app_data: vars.get("LOCALAPPDATA").cloned(),
+            vars,
+            ..BasePathEnv::default()
+        }
+    }
+
+    fn cli(args: &[&str]) -> Cli {
+        Cli::parse(args.iter().map(|arg| arg.to_string())).unwrap()
+    }
+
+    fn default_base_path(env: &BasePathEnv, platform: Platform) -> PathBuf {
+        resolve_base_path_with_env(&cli(&["fetch"]), env, None, platform)
+            .unwrap()
+            .path
+    }
+
+    #[test]
+    fn default_base_path_depends_on_the_platform() {
+        let env = env(&[("HOME", "/home/me")]);
+        assert_eq!(
+            default_base_path(&env, Platform::Unix),
+            PathBuf::from("/home/me/.local/share/fonts/Google")
+        );
+        assert_eq!(
+            default_base_path(&env, Platform::MacOs),
+            PathBuf::from("/home/me/Library/Fonts/Google")
+        );
+    }
+
+    #[test]
+    fn fonty_base_path_overrides_the_macos_default() {
+        let env = env(&[("HOME", "/Users/me"), ("FONTY_BASE_PATH", "/Volumes/Fonts")]);
+        let base_path =
+            resolve_base_path_with_env(&cli(&["fetch"]), &env, None, Platform::MacOs).unwrap();
+        assert_eq!(base_path.path, PathBuf::from("/Volumes/Fonts"));
+        assert_eq!(base_path.source, BasePathSource::Env);
+    }
+
+    #[test]
+    fn default_dirs_on_windows_are_in_local_app_data() {
+        // Built with `join` like the paths themselves, so this holds wherever the tests run.
+        let local_app_data = Path::new(r"C:\Users\me\AppData\Local");
+        let env = env(&[("LOCALAPPDATA", r"C:\Users\me\AppData\Local")]);
+        assert_eq!(
+            default_base_path(&env, Platform::Windows),
+            local_app_data
+                .join("Microsoft")
+                .join("Windows")
+                .join("Fonts")
+        );
+        assert_eq!(
+            default_cache_dir(&env, Platform::Windows),
+            Some(local_app_data.join("fonty").join("cache"))
+        );
+        assert_eq!(
+            default_config_dir(&env, Platform::Windows),
+            Some(local_app_data.join("fonty"))
+        );
+    }
+
+    #[test]
+    fn fonty_base_path_overrides_the_windows_default() {
+        let env = env(&[
+            ("LOCALAPPDATA", r"C:\Users\me\AppData\Local"),
+            ("FONTY_BASE_PATH", r"D:\Fonts"),
+        ]);
+        let base_path =
+            resolve_base_path_with_env(&cli(&["fetch"]), &env, None, Platform::Windows).unwrap();
+        assert_eq!(base_path.path, PathBuf::from(r"D:\Fonts"));
+        assert_eq!(base_path.source, BasePathSource::Env);
+    }
+
+    #[test]
+    fn windows_without_local_app_data_is_a_config_error() {
+        // $HOME doesn't count on Windows.
+        let error = resolve_base_path_with_env(
+            &cli(&["fetch"]),
+            &env(&[("HOME", "/home/me")]),
+            None,
+            Platform::Windows,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error,
+            BasePathError::NoUsableEnv {
+                variables: &["FONTY_BASE_PATH", "LOCALAPPDATA"],
+            }
+        );
+    }
+
+    // The raw paths are compared, which only have `/` as separators on Unix.
+    #[cfg(unix)]
+    #[test]
+    fn trailing_slashes_in_the_env_are_collapsed() {
+        // Paths compare equal regardless of redundant separators, so the raw paths are compared.
+        let xdg_env = env(&[("HOME", "/home/me/"), ("XDG_DATA_HOME", "/home/me/data//")]);
+        assert_eq!(
+            default_base_path(&xdg_env, Platform::Unix).as_os_str(),
+            "/home/me/data/fonts/Google"
+        );
+        let env = env(&[("FONTY_BASE_PATH", "/srv//fonts/./Google/")]);
+        assert_eq!(
+            default_base_path(&env, Platform::Unix).as_os_str(),
+            "/srv/fonts/Google"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn trailing_backslashes_in_the_env_are_collapsed() {
+        let env = env(&[("FONTY_BASE_PATH", r"D:\\Fonts\.\Google\")]);
+        assert_eq!(
+            default_base_path(&env, Platform::Windows).as_os_str(),
+            r"D:\Fonts\Google"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn rooted_paths_without_a_drive_are_made_absolute() {
+        // `\fonts` has no drive, so it is not absolute on Windows and gets the current dir's drive.
+        let env = env(&[("FONTY_BASE_PATH", r"\fonts")]);
+        let base_path = resolve_base_path(&cli(&["fetch"]), &env, None).unwrap();
+        assert!(base_path.path.is_absolute(), "{}", base_path.path.display());
+        assert!(base_path.path.ends_with("fonts"));
+    }
+
+    #[test]
+    fn relative_fonty_base_path_is_made_absolute() {
+        let env = env(&[("FONTY_BASE_PATH", "fonts/Google")]);
+        assert_eq!(
+            default_base_path(&env, Platform::Unix),
+            PathBuf::from("fonts/Google")
+        );
+        let base_path = resolve_base_path(&cli(&["fetch"]), &env, None).unwrap();
+        assert_eq!(
+            base_path.path,
+            std::env::current_dir().unwrap().join("fonts/Google")
+        );
+    }
+
+    #[test]
+    fn base_path_with_spaces() {
+        let env = env(&[
+            ("HOME", "/home/my user"),
+            ("FONTY_BASE_PATH", "~/My Fonts/Google"),
+        ]);
+        assert_eq!(
+            default_base_path(&env, Platform::Unix),
+            PathBuf::from("/home/my user/My Fonts/Google")
+        );
+    }
+
+    #[test]
+    fn each_fallback_of_the_default_base_path() {
+        let cases = [
+            (
+                env(&[
+                    ("FONTY_BASE_PATH", "/fonts"),
+                    ("XDG_DATA_HOME", "/data"),
+                    ("HOME", "/home/me"),
+                ]),
+                "/fonts",
+            ),
+            (
+                env(&[("XDG_DATA_HOME", "/data"), ("HOME", "/home/me")]),
+                "/data/fonts/Google",
+            ),
+            (
+                env(&[("HOME", "/home/me")]),
+                "/home/me/.local/share/fonts/Google",
+            ),
+            // Without $HOME, the other variables still work.
+            (env(&[("FONTY_BASE_PATH", "/fonts")]), "/fonts"),
+            (env(&[("XDG_DATA_HOME", "/data")]), "/data/fonts/Google"),
+        ];
+        for (env, expected) in cases {
+            assert_eq!(
+                default_base_path(&env, Platform::Unix),
+                PathBuf::from(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn no_usable_env_is_a_config_error() {
+        let error = resolve_base_path_with_env(&cli(&["fetch"]), &env(&[]), None, Platform::Unix)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            BasePathError::NoUsableEnv {
+                variables: &["FONTY_BASE_PATH", "XDG_DATA_HOME", "HOME"],
+            }
+        );
+        assert_eq!(error.exit_code(), EXIT_CODE_CONFIG_ERROR);
+        // `--user` ignores $FONTY_BASE_PATH, so it needs the other variables.
+        assert!(
+            resolve_base_path_with_env(
+                &cli(&["fetch", "--user"]),
+                &env(&[("FONTY_BASE_PATH", "/fonts")]),
+                None,
+                Platform::Unix,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn expands_home_and_variables() {
+        let env = env(&[("HOME", "/home/me"), ("FONTS", "fonts")]);
+        assert_eq!(expand_path_vars("~", &env).unwrap(), "/home/me");
+        assert_eq!(
+            expand_path_vars("~/$FONTS/${FONTS}_old", &env).unwrap(),
+            "/home/me/fonts/fonts_old"
+        );
+        // A `~` that isn't a whole leading component is a normal character.
+        assert_eq!(expand_path_vars("a/~b", &env).unwrap(), "a/~b");
+        assert_eq!(expand_path_vars("cost$/x$", &env).unwrap(), "cost$/x$");
+    }
+
+    #[test]
+    fn undefined_and_unterminated_variables_fail() {
+        let env = env(&[]);
+        assert_eq!(
+            expand_path_vars("$NOPE/fonts", &env),
+            Err(BasePathError::UndefinedVariable {
+                variable: "NOPE".to_string(),
+                value: "$NOPE/fonts".to_string(),
+            })
+        );
+        assert_eq!(
+            expand_path_vars("~/fonts", &env),
+            Err(BasePathError::UndefinedVariable {
+                variable: "HOME".to_string(),
+                value: "~/fonts".to_string(),
+            })
+        );
+        assert_eq!(
+            expand_path_vars("${FONTS", &env),
+            Err(BasePathError::UnterminatedVariable {
+                value: "${FONTS".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn flags_take_precedence_over_env_and_config() {
+        let base_path = resolve_base_path_with_env(
+            &cli(&["fetch", "--output-dir", "out"]),
+            &env(&[("HOME", "/home/me"), ("FONTY_BASE_PATH", "/env")]),
+            Some("/config"),
+            Platform::Unix,
+        )
+        .unwrap();
+        assert_eq!(base_path.path, PathBuf::from("out"));
+        assert_eq!(base_path.source, BasePathSource::OutputDirFlag);
+    }
+
+    #[test]
+    fn env_takes_precedence_over_config() {
+        let base_path = resolve_base_path_with_env(
+            &cli(&["fetch"]),
+            &env(&[("HOME", "/home/me"), ("FONTY_BASE_PATH", "~/env")]),
+            Some("/config"),
+            Platform::Unix,
+        )
+        .unwrap();
+        assert_eq!(base_path.path, PathBuf::from("/home/me/env"));
+        assert_eq!(base_path.source, BasePathSource::Env);
+    }
+
+    #[test]
+    fn config_base_path_is_expanded() {
+        let base_path = resolve_base_path_with_env(
+            &cli(&["fetch"]),
+            &env(&[("HOME", "/home/me"), ("PROJECT", "site")]),
+            Some("~/${PROJECT}/fonts/"),
+            Platform::Unix,
+        )
+        .unwrap();
+        assert_eq!(base_path.path, PathBuf::from("/home/me/site/fonts"));
+        assert_eq!(base_path.source, BasePathSource::Config);
+    }
+
+    #[test]
+    fn config_base_path_with_an_undefined_variable_fails() {
+        assert!(matches!(
+            resolve_base_path_with_env(
+                &cli(&["fetch"]),
+                &env(&[("HOME", "/home/me")]),
+                Some("$NOPE/fonts"),
+                Platform::Unix,
+            ),
+            Err(BasePathError::UndefinedVariable { .. })
+        ));
+    }
+}
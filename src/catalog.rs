@@ -0,0 +1,568 @@
+use crate::color::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+
+use crate::{error::FontyError, fixtures, http, options::Options};
+
+/// Where Google Fonts lists the metadata of every family.
+const CATALOG_URL: &str = "https://fonts.google.com/metadata/fonts";
+
+/// The name of the cached catalog, inside the cache dir.
+pub const CATALOG_FILENAME: &str = "catalog.json";
+
+/// A family in the catalog.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CatalogFamily {
+    pub family: String,
+    pub category: String,
+    pub designers: Vec<String>,
+    /// The rank of the family by popularity, where 1 is the most popular. Not every payload has it.
+    pub popularity: Option<u64>,
+    pub date_added: Option<String>,
+    pub last_modified: Option<String>,
+    /// The variants of the family, e.g. `400` or `700i`.
+    pub variants: Vec<String>,
+}
+
+/// Every family on Google Fonts, as cached by fonty.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Catalog {
+    /// When the catalog was fetched, in seconds since the unix epoch.
+    pub fetched_at: u64,
+    pub families: Vec<CatalogFamily>,
+}
+
+/// The orders catalog families can be listed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Alphabetically by name.
+    Name,
+    /// The most popular families first. Families without a popularity rank come last.
+    Popularity,
+    /// The most recently added families first.
+    Recent,
+}
+
+impl FromStr for SortOrder {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(SortOrder::Name),
+            "popularity" => Ok(SortOrder::Popularity),
+            "recent" => Ok(SortOrder::Recent),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A family as Google Fonts lists it. Only the fields fonty uses are parsed.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleFamily {
+    family: String,
+    #[serde(default)]
+    category: String,
+    #[serde(default)]
+    designers: Vec<String>,
+    popularity: Option<u64>,
+    date_added: Option<String>,
+    last_modified: Option<String>,
+    #[serde(default)]
+    fonts: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The payload Google Fonts returns for the catalog.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleCatalog {
+    family_metadata_list: Vec<GoogleFamily>,
+}
+
+/// Normalizes a category for comparing, so e.g. `sans-serif` matches `Sans Serif`.
+fn normalize_category(category: &str) -> String {
+    category
+        .chars()
+        .filter(|character| character.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Keeps only the families in a category.
+/// Fails with every category in the catalog if the category is unknown, as Google may add (or rename) categories at any time.
+pub fn filter_by_category<'a>(
+    catalog: &Catalog,
+    families: Vec<&'a CatalogFamily>,
+    category: &str,
+) -> Result<Vec<&'a CatalogFamily>, Vec<String>> {
+    let category = normalize_category(category);
+    if !catalog
+        .families
+        .iter()
+        .any(|family| normalize_category(&family.category) == category)
+    {
+        return Err(catalog.categories());
+    }
+
+    Ok(families
+        .into_iter()
+        .filter(|family| normalize_category(&family.category) == category)
+        .collect())
+}
+
+/// Sorts families in the given order. Families that are equal in that order keep their order.
+pub fn sort_families(families: &mut [&CatalogFamily], sort_order: SortOrder) {
+    match sort_order {
+        SortOrder::Name => families.sort_by_key(|family| family.family.to_lowercase()),
+        SortOrder::Popularity => {
+            families.sort_by_key(|family| family.popularity.unwrap_or(u64::MAX))
+        }
+        // Dates are ISO 8601 (e.g. `2021-03-04`), so they sort correctly as text.
+        SortOrder::Recent => families.sort_by(|a, b| b.date_added.cmp(&a.date_added)),
+    }
+}
+
+/// The current time, in seconds since the unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+impl Catalog {
+    /// Gets the catalog, from the cache if it is younger than `options.catalog_ttl` (or with `--offline`), otherwise from Google Fonts.
+    /// Every use of the catalog goes through here, so it is only downloaded when needed.
+    /// Without a cache dir, the catalog is downloaded every time.
+    pub fn load(cache_dir: Option<&Path>, options: &Options) -> Result<Self, FontyError> {
+        // With --offline, a cached catalog of any age is better than none.
+        if options.offline {
+            return Self::load_cached(cache_dir).ok_or(FontyError::NotCached {
+                what: "The catalog".to_string(),
+            });
+        }
+
+        let cache_path = cache_dir.map(|cache_dir| cache_dir.join(CATALOG_FILENAME));
+
+        if let Some(cache_path) = &cache_path
+            && !options.refresh_catalog
+            && let Some(catalog) = Self::read_cache(cache_path)
+            && catalog.age() < options.catalog_ttl
+        {
+            return Ok(catalog);
+        }
+
+        let catalog = Self::fetch()?;
+        if let Some(cache_path) = &cache_path
+            && let Err(e) = catalog.write_cache(cache_path)
+        {
+            println!(
+                "{color_yellow}WARN:{color_reset} Failed to cache the catalog in {color_blue}'{}'{color_reset}! Error:\n{}",
+                cache_path.display(),
+                e
+            );
+        }
+        Ok(catalog)
+    }
+
+    /// Gets the cached catalog, however old it is, without downloading it if there is none.
+    pub fn load_cached(cache_dir: Option<&Path>) -> Option<Self> {
+        Self::read_cache(&cache_dir?.join(CATALOG_FILENAME))
+    }
+
+    /// How long ago the catalog was fetched.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.fetched_at))
+    }
+
+    /// Downloads the catalog from Google Fonts.
+    fn fetch() -> Result<Self, FontyError> {
+        let request_error = |error| FontyError::Request {
+            url: CATALOG_URL.to_string(),
+            error,
+        };
+        let invalid_catalog = |error: String| FontyError::InvalidCatalog {
+            url: CATALOG_URL.to_string(),
+            error,
+        };
+
+        let response = http::get(CATALOG_URL).map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(invalid_catalog(format!(
+                "Got status '{}'.",
+                response.status()
+            )));
+        }
+        let text = response.text().map_err(request_error)?;
+        fixtures::record_body(text.as_bytes());
+        http::debug_body(&text);
+
+        let json = http::strip_anti_xssi_prefix(&text).map_err(invalid_catalog)?;
+        let google_catalog: GoogleCatalog =
+            serde_json::from_str(json).map_err(|e| invalid_catalog(e.to_string()))?;
+
+        Ok(Self {
+            fetched_at: now(),
+            families: google_catalog
+                .family_metadata_list
+                .into_iter()
+                .map(|family| CatalogFamily {
+                    family: family.family,
+                    category: family.category,
+                    designers: family.designers,
+                    popularity: family.popularity,
+                    date_added: family.date_added,
+                    last_modified: family.last_modified,
+                    variants: family.fonts.keys().cloned().collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Reads the cached catalog. A cache that is missing, truncated or otherwise invalid counts as no cache.
+    fn read_cache(cache_path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(cache_path).ok()?;
+        let catalog: Self = serde_json::from_str(&contents).ok()?;
+        // A catalog from the future, or without families, can't be trusted either.
+        if catalog.fetched_at > now() || catalog.families.is_empty() {
+            return None;
+        }
+        Some(catalog)
+    }
+
+    /// Writes the catalog to the cache. It is written to a temporary file first and then renamed, so readers never see half of it.
+    fn write_cache(&self, cache_path: &Path) -> std::io::Result<()> {
+        if let Some(cache_dir) = cache_path.parent() {
+            fs::create_dir_all(cache_dir)?;
+        }
+        let temporary_path = cache_path.with_extension("json.tmp");
+        fs::write(&temporary_path, serde_json::to_string(self)?)?;
+        fs::rename(&temporary_path, cache_path)
+    }
+
+    /// Lists every category in the catalog, sorted.
+    pub fn categories(&self) -> Vec<String> {
+        let mut categories = self
+            .families
+            .iter()
+            .map(|family| family.category.clone())
+            .collect::<Vec<_>>();
+        categories.sort();
+        categories.dedup();
+        categories
+    }
+
+    /// Finds the families whose name contains the query, ignoring case.
+    pub fn search(&self, query: &str) -> Vec<&CatalogFamily> {
+        let query = query.to_lowercase();
+        self.families
+            .iter()
+            .filter(|family| family.family.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// Finds the families whose name matches a pattern anywhere in the name.
+    /// To ignore case like `search` does, build the pattern with `case_insensitive`.
+    pub fn search_regex(&self, regex: &Regex) -> Vec<&CatalogFamily> {
+        self.families
+            .iter()
+            .filter(|family| regex.is_match(&family.family))
+            .collect()
+    }
+
+    /// Ranks the families by how similar their name is to the query (see `similarity`), best first.
+    /// Families scoring below `min_score` are left out.
+    pub fn search_fuzzy(&self, query: &str, min_score: f64) -> Vec<(&CatalogFamily, f64)> {
+        let mut families = self
+            .families
+            .iter()
+            .map(|family| (family, similarity(query, &family.family)))
+            .filter(|(_, score)| *score >= min_score)
+            .collect::<Vec<_>>();
+        families.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .total_cmp(a_score)
+                .then_with(|| a.family.cmp(&b.family))
+        });
+        families
+    }
+}
+
+/// The edit distance between two strings: how many characters have to be inserted, removed or replaced.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + (a_char != b_char) as usize;
+            current[j + 1] = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// How similar a family name is to a query, from 0 (nothing alike) to 1 (the same, ignoring case).
+/// The query is compared to the whole name and to every run of as many words as the query has,
+/// so "grotesk" matches "Space Grotesk" as well as "Grotesk" would.
+fn similarity(query: &str, name: &str) -> f64 {
+    let query = query.trim().to_lowercase().chars().collect::<Vec<_>>();
+    let name = name.to_lowercase();
+    let query_words = query
+        .split(|c| c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .count();
+    let words = name.split_whitespace().collect::<Vec<_>>();
+
+    let mut candidates = vec![name.clone()];
+    if query_words > 0 && query_words < words.len() {
+        candidates.extend(words.windows(query_words).map(|window| window.join(" ")));
+    }
+    candidates
+        .iter()
+        .map(|candidate| {
+            let candidate = candidate.chars().collect::<Vec<_>>();
+            let length = query.len().max(candidate.len()).max(1);
+            1.0 - levenshtein(&query, &candidate) as f64 / length as f64
+        })
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::RegexBuilder;
+
+    use super::*;
+
+    fn catalog(names: &[&str]) -> Catalog {
+        Catalog {
+            fetched_at: 0,
+            families: names
+                .iter()
+                .map(|name| CatalogFamily {
+                    family: name.to_string(),
+                    category: "Sans Serif".to_string(),
+                    designers: Vec::new(),
+                    popularity: None,
+                    date_added: None,
+                    last_modified: None,
+                    variants: vec!["400".to_string()],
+                })
+                .collect(),
+        }
+    }
+
+    const NAMES: &[&str] = &[
+        "Hanken Grotesk",
+        "Space Grotesk",
+        "Schibsted Grotesk",
+        "Inter",
+        "Inter Tight",
+        "Roboto",
+        "Roboto Mono",
+        "Noto Sans",
+    ];
+
+    fn names<'a>(families: impl IntoIterator<Item = &'a CatalogFamily>) -> Vec<&'a str> {
+        families
+            .into_iter()
+            .map(|family| family.family.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn substring_search() {
+        let catalog = catalog(NAMES);
+        let cases: &[(&str, &[&str])] = &[
+            ("inter", &["Inter", "Inter Tight"]),
+            ("MONO", &["Roboto Mono"]),
+            ("sans", &["Noto Sans"]),
+            ("comic", &[]),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(names(catalog.search(query)), *expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn regex_search() {
+        let catalog = catalog(NAMES);
+        let cases: &[(&str, &[&str])] = &[
+            ("^inter$", &["Inter"]),
+            (
+                "grotesk$",
+                &["Hanken Grotesk", "Space Grotesk", "Schibsted Grotesk"],
+            ),
+            ("^(roboto|noto) ", &["Roboto Mono", "Noto Sans"]),
+            ("o{2}", &[]),
+            (r"^\w+$", &["Inter", "Roboto"]),
+        ];
+        for (pattern, expected) in cases {
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .unwrap();
+            assert_eq!(
+                names(catalog.search_regex(&regex)),
+                *expected,
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn fuzzy_search() {
+        let catalog = catalog(NAMES);
+        let cases: &[(&str, &[&str])] = &[
+            // Every family with the word ranks equally, alphabetically.
+            (
+                "grotesk",
+                &["Hanken Grotesk", "Schibsted Grotesk", "Space Grotesk"],
+            ),
+            // Typos still find the family.
+            (
+                "grotsk",
+                &["Hanken Grotesk", "Schibsted Grotesk", "Space Grotesk"],
+            ),
+            ("robot", &["Roboto", "Roboto Mono"]),
+            ("space grotesk", &["Space Grotesk"]),
+        ];
+        for (query, expected) in cases {
+            let results = catalog.search_fuzzy(query, 0.8);
+            assert_eq!(
+                names(results.iter().map(|(family, _)| *family)),
+                *expected,
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn similarity_scores() {
+        assert_eq!(similarity("Inter", "inter"), 1.0);
+        assert_eq!(similarity("grotesk", "Space Grotesk"), 1.0);
+        assert_eq!(similarity("", "Inter"), 0.0);
+        assert!(similarity("grotsk", "Hanken Grotesk") > 0.8);
+        assert!(similarity("roboto", "Hanken Grotesk") < 0.5);
+    }
+
+    /// A catalog like Google's, with families in several categories, and some without a popularity or date.
+    fn fixture_catalog() -> Catalog {
+        let family =
+            |name: &str, category: &str, popularity, date_added: Option<&str>| CatalogFamily {
+                family: name.to_string(),
+                category: category.to_string(),
+                designers: Vec::new(),
+                popularity,
+                date_added: date_added.map(str::to_string),
+                last_modified: None,
+                variants: vec!["400".to_string()],
+            };
+        Catalog {
+            fetched_at: 0,
+            families: vec![
+                family("Roboto", "Sans Serif", Some(1), Some("2013-01-09")),
+                family("Lora", "Serif", Some(40), Some("2011-06-01")),
+                family("Roboto Mono", "Monospace", Some(30), Some("2016-02-18")),
+                family("Inter", "Sans Serif", Some(5), Some("2020-06-10")),
+                family("Pacifico", "Handwriting", None, Some("2011-03-30")),
+                family("Bungee", "Display", Some(300), None),
+                family("anek Latin", "Sans Serif", None, Some("2022-06-20")),
+            ],
+        }
+    }
+
+    #[test]
+    fn families_are_filtered_by_category() {
+        let catalog = fixture_catalog();
+        let all = catalog.families.iter().collect::<Vec<_>>();
+        // Categories match however they are written.
+        for category in ["Sans Serif", "sans-serif", "SANSSERIF"] {
+            assert_eq!(
+                names(filter_by_category(&catalog, all.clone(), category).unwrap()),
+                ["Roboto", "Inter", "anek Latin"],
+                "{}",
+                category
+            );
+        }
+        // Only the families given are filtered, e.g. the search results.
+        assert_eq!(
+            names(filter_by_category(&catalog, catalog.search("roboto"), "monospace").unwrap()),
+            ["Roboto Mono"]
+        );
+        assert!(
+            filter_by_category(&catalog, catalog.search("inter"), "serif")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn unknown_categories_list_the_ones_there_are() {
+        let catalog = fixture_catalog();
+        let all = catalog.families.iter().collect::<Vec<_>>();
+        assert_eq!(
+            filter_by_category(&catalog, all, "Comic").unwrap_err(),
+            ["Display", "Handwriting", "Monospace", "Sans Serif", "Serif"]
+        );
+    }
+
+    #[test]
+    fn families_are_sorted_by_each_sort_order() {
+        let catalog = fixture_catalog();
+        let cases: &[(&str, &[&str])] = &[
+            (
+                "name",
+                &[
+                    "anek Latin",
+                    "Bungee",
+                    "Inter",
+                    "Lora",
+                    "Pacifico",
+                    "Roboto",
+                    "Roboto Mono",
+                ],
+            ),
+            // Families without a rank come last, in the order they were in.
+            (
+                "popularity",
+                &[
+                    "Roboto",
+                    "Inter",
+                    "Roboto Mono",
+                    "Lora",
+                    "Bungee",
+                    "Pacifico",
+                    "anek Latin",
+                ],
+            ),
+            // Families without a date come last.
+            (
+                "recent",
+                &[
+                    "anek Latin",
+                    "Inter",
+                    "Roboto Mono",
+                    "Roboto",
+                    "Lora",
+                    "Pacifico",
+                    "Bungee",
+                ],
+            ),
+        ];
+        for (sort, expected) in cases {
+            let mut families = catalog.families.iter().collect::<Vec<_>>();
+            sort_families(&mut families, sort.parse().unwrap());
+            assert_eq!(names(families), *expected, "{}", sort);
+        }
+        assert_eq!("size".parse::<SortOrder>(), Err(()));
+    }
+}
@@ -0,0 +1,43 @@
+// Drop-in replacement for the `inline_colorization` constants, that can be turned off at runtime.
+#![allow(non_upper_case_globals)]
+
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether colors should be printed, set once from the command line.
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Enables (or disables) colors in all output.
+pub fn set_colors_enabled(enabled: bool) {
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// An ANSI escape code, only printed when colors are enabled.
+#[derive(Clone, Copy)]
+pub struct Color(&'static str);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if COLORS_ENABLED.load(Ordering::Relaxed) {
+            f.write_str(self.0)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub const color_red: Color = Color(inline_colorization::color_red);
+pub const color_green: Color = Color(inline_colorization::color_green);
+pub const color_yellow: Color = Color(inline_colorization::color_yellow);
+pub const color_blue: Color = Color(inline_colorization::color_blue);
+pub const color_magenta: Color = Color(inline_colorization::color_magenta);
+pub const color_cyan: Color = Color(inline_colorization::color_cyan);
+pub const color_white: Color = Color(inline_colorization::color_white);
+pub const color_bright_black: Color = Color(inline_colorization::color_bright_black);
+pub const color_bright_blue: Color = Color(inline_colorization::color_bright_blue);
+pub const color_bright_yellow: Color = Color(inline_colorization::color_bright_yellow);
+pub const color_reset: Color = Color(inline_colorization::color_reset);
+pub const style_bold: Color = Color(inline_colorization::style_bold);
+pub const style_reset: Color = Color(inline_colorization::style_reset);
@@ -0,0 +1,177 @@
+use fonty::color::*;
+use std::{
+    fs, io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use fonty::{
+    base_path::{BasePathEnv, Platform, default_cache_dir},
+    catalog::CATALOG_FILENAME,
+    cli::Cli,
+    download_cache::{CacheUsage, DownloadCache, GcPolicy, gc_victims},
+    exit_codes::{EXIT_CODE_CONFIG_ERROR, EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS},
+    fail,
+    format::{format_bytes, format_date, format_size, n_files},
+    info, msg,
+    options::Options,
+};
+
+/// Shows what is in the cache dir (`cache stats`), removes it or parts of it (`cache clear`), or evicts old downloaded files (`cache gc`).
+/// A `--manifest-cache` dir is left alone, it is not part of the cache dir (and might be checked into a repository).
+pub fn cache(cli: &Cli, options: &Options, env: &BasePathEnv) {
+    let Some(cache_dir) = default_cache_dir(env, Platform::current()) else {
+        fail!(EXIT_CODE_CONFIG_ERROR, "{}", msg!("error.no_cache_dir"));
+    };
+    let download_cache = DownloadCache::new(&cache_dir, None);
+    let catalog_path = cache_dir.join(CATALOG_FILENAME);
+    let fail_reading = |e: io::Error| -> ! {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!(
+                "error.read_cache_dir",
+                path = cache_dir.display(),
+                error = e
+            )
+        )
+    };
+
+    match cli.args.as_slice() {
+        [subcommand] if subcommand == "stats" => {
+            let usage = |usage: io::Result<CacheUsage>| usage.unwrap_or_else(|e| fail_reading(e));
+            let mut catalog = CacheUsage::default();
+            if let Ok(metadata) = fs::metadata(&catalog_path) {
+                catalog.add(&metadata);
+            }
+            let rows = [
+                ("Font manifests", usage(download_cache.manifest_usage())),
+                ("Downloaded files", usage(download_cache.blob_usage())),
+                ("Catalog", catalog),
+            ];
+            let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+            let date = |time: Option<SystemTime>| {
+                time.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map_or("-".to_string(), |since_epoch| {
+                        format_date(since_epoch.as_secs())
+                    })
+            };
+
+            println!(
+                "{color_blue}{style_bold}Cache dir:{color_reset}{style_reset} {}",
+                cache_dir.display()
+            );
+            for (label, usage) in rows {
+                let dates = if usage.entries == 0 {
+                    String::new()
+                } else {
+                    format!(
+                        " {color_bright_black}(oldest {}, newest {}){color_reset}",
+                        date(usage.oldest),
+                        date(usage.newest)
+                    )
+                };
+                println!(
+                    "  {color_bright_black}{:<label_width$}{color_reset}  {color_white}{}, {}{color_reset}{}",
+                    label,
+                    n_files(usage.entries as usize),
+                    format_bytes(usage.bytes),
+                    dates
+                );
+            }
+        }
+        [subcommand] if subcommand == "clear" => {
+            // Without any of the flags, everything is cleared.
+            let parts = ["--catalog", "--manifests", "--blobs"];
+            let clear_all = !parts.iter().any(|part| cli.has_flag(part));
+            let clear = |part: &str| clear_all || cli.has_flag(part);
+
+            let mut removed = Ok(());
+            if clear("--catalog") {
+                removed = removed.and_then(|_| match fs::remove_file(&catalog_path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                });
+            }
+            if clear("--manifests") {
+                removed = removed.and_then(|_| download_cache.clear_manifests());
+            }
+            if clear("--blobs") {
+                removed = removed.and_then(|_| download_cache.clear_blobs());
+            }
+            if let Err(e) = removed {
+                fail!(
+                    EXIT_CODE_FILE_IO_ERROR,
+                    "{}",
+                    msg!(
+                        "error.clear_cache_dir",
+                        path = cache_dir.display(),
+                        error = e
+                    )
+                );
+            }
+            if clear_all {
+                info!(
+                    "Cleared the cache dir {color_blue}'{}'{color_reset}.",
+                    cache_dir.display()
+                );
+            } else {
+                let cleared = [
+                    ("--catalog", "the catalog"),
+                    ("--manifests", "the font manifests"),
+                    ("--blobs", "the downloaded files"),
+                ]
+                .iter()
+                .filter(|(part, _)| cli.has_flag(part))
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>();
+                info!(
+                    "Cleared {} from the cache dir {color_blue}'{}'{color_reset}.",
+                    cleared.join(" and "),
+                    cache_dir.display()
+                );
+            }
+        }
+        [subcommand] if subcommand == "gc" => {
+            let policy = GcPolicy {
+                max_size: options.cache_max_size,
+                max_age: options.cache_max_age,
+            };
+            if policy.max_size.is_none() && policy.max_age.is_none() {
+                fail!(
+                    EXIT_CODE_INVALID_ARGUMENTS,
+                    "{}",
+                    msg!("error.cache_gc_usage")
+                );
+            }
+            let entries = download_cache
+                .blob_entries()
+                .unwrap_or_else(|e| fail_reading(e));
+            let victims = gc_victims(&entries, &policy, SystemTime::now());
+            let victim_bytes = victims.iter().map(|victim| victim.bytes).sum::<u64>();
+            if cli.has_flag("--dry-run") {
+                info!(
+                    "Would evict {} {color_bright_black}({}){color_reset} of {} from the cache.",
+                    n_files(victims.len()),
+                    format_size(victim_bytes),
+                    n_files(entries.len())
+                );
+                return;
+            }
+            let evicted = match download_cache.evict(&victims) {
+                Ok(evicted) => evicted,
+                Err(e) => fail!(
+                    EXIT_CODE_FILE_IO_ERROR,
+                    "{}",
+                    msg!("error.evict_cache", path = cache_dir.display(), error = e)
+                ),
+            };
+            info!(
+                "Evicted {} {color_bright_black}({}){color_reset}, kept {}.",
+                n_files(evicted.entries as usize),
+                format_size(evicted.bytes),
+                n_files(entries.len() - evicted.entries as usize)
+            );
+        }
+        _ => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.cache_usage")),
+    }
+}
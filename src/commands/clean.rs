@@ -0,0 +1,16 @@
+use fonty::{
+    backup::prune_backups, base_path::BasePath, exit_codes::EXIT_CODE_FILE_IO_ERROR, fail,
+    format::plural, info, msg, options::Options,
+};
+
+/// Deletes the backups older than `--backup-retention`.
+pub fn clean(base_path: &BasePath, options: &Options) {
+    match prune_backups(&base_path.path, options.backup_retention) {
+        Ok(pruned) => info!("Deleted {}.", plural(pruned.len(), "backup", "backups")),
+        Err(e) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.delete_backups", error = e)
+        ),
+    }
+}
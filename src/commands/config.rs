@@ -0,0 +1,79 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::{BasePath, BasePathError},
+    cli::Cli,
+    config::{Config, ConfigFile},
+    exit_codes::EXIT_CODE_INVALID_ARGUMENTS,
+    fail, msg,
+    options::CONFIG_OPTION_KEYS,
+};
+
+/// Prints which config file was loaded (`config show`), and the settings from it.
+pub fn show_config(
+    cli: &Cli,
+    config: &Config,
+    config_file: Option<&ConfigFile>,
+    base_path: &Result<BasePath, BasePathError>,
+) {
+    if !matches!(cli.args.as_slice(), [subcommand] if subcommand == "show") {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.config_usage")
+        );
+    }
+
+    match (&config.path, config_file) {
+        (Some(path), _) => println!(
+            "{color_blue}{style_bold}Config file:{color_reset}{style_reset} {}",
+            path.display()
+        ),
+        (None, Some(config_file)) => println!(
+            "{color_blue}{style_bold}Config file:{color_reset}{style_reset} none {color_bright_black}('{}' does not exist){color_reset}",
+            config_file.path.display()
+        ),
+        (None, None) => println!(
+            "{color_blue}{style_bold}Config file:{color_reset}{style_reset} none {color_bright_black}(no config dir, set $XDG_CONFIG_HOME or $HOME){color_reset}"
+        ),
+    }
+    let mirrors = match config.mirrors.as_slice() {
+        [] => "none".to_string(),
+        mirrors => mirrors.join(", "),
+    };
+    let mut rows = vec![
+        ("mirrors", mirrors),
+        (
+            "post_install",
+            config.post_install.clone().unwrap_or("none".to_string()),
+        ),
+        ("notify", config.notify.to_string()),
+        (
+            "base_path",
+            match base_path {
+                Ok(base_path) => format!(
+                    "{} {color_bright_black}(from {}){color_reset}",
+                    base_path.path.display(),
+                    base_path.source
+                ),
+                Err(e) => format!(
+                    "{color_red}unknown{color_reset} {color_bright_black}({}){color_reset}",
+                    e
+                ),
+            },
+        ),
+    ];
+    // Tuning options are only shown when they are set, the defaults are in the help of their flags.
+    rows.extend(
+        CONFIG_OPTION_KEYS
+            .iter()
+            .filter_map(|&key| config.options.get(key).map(|value| (key, value.clone()))),
+    );
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        println!(
+            "  {color_bright_black}{:<label_width$}{color_reset}  {color_white}{}{color_reset}",
+            label, value
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use fonty::{
+    base_path::{BasePath, BasePathEnv, Platform, default_cache_dir},
+    cli::Cli,
+    exit_codes::{EXIT_CODE_DIFFERENCES, EXIT_CODE_INVALID_FONT_NAME},
+    fail,
+    font_manifest::FontManifest,
+    msg,
+    notify::exit,
+    options::Options,
+    registry::Registry,
+    update_diff::{diff_update, print_update_diff},
+    warnings::Warnings,
+    worker_pool::run_parallel,
+};
+
+use super::{download_cache, exit_on_fetch_failures, in_installed_layout, selected_families};
+
+/// Shows what `fonty update` would change in installed families (without fonts or --tag, all of them), by comparing their
+/// files with the current manifests. Files that aren't in the download cache are downloaded into it to compare them
+/// (nothing is installed), except with `--offline`. Files that still aren't cached are listed as not compared, and don't
+/// count as changes. Exits with `EXIT_CODE_DIFFERENCES` if anything would change.
+pub fn diff(cli: &Cli, options: &Options, env: &BasePathEnv, base_path: &BasePath) {
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    let font_names = match selected_families(cli, &registry) {
+        font_names if !font_names.is_empty() || !cli.args.is_empty() || cli.has_flag("--tag") => {
+            font_names
+        }
+        _ => registry
+            .families
+            .iter()
+            .map(|family| family.name.clone())
+            .collect(),
+    };
+    if font_names.is_empty() {
+        fail!(
+            EXIT_CODE_INVALID_FONT_NAME,
+            "{}",
+            msg!("fetch.no_fonts_to_update")
+        );
+    }
+
+    let download_cache = download_cache(
+        default_cache_dir(env, Platform::current()).as_deref(),
+        options,
+    );
+    let manifest_results = run_parallel(&font_names, options.prefetch, |_, font_name| {
+        // Boxed, as the error is much larger than a manifest.
+        FontManifest::load(font_name, options, download_cache.as_ref()).map_err(Box::new)
+    });
+    let mut font_manifests = Vec::new();
+    for (font_name, result) in font_names.iter().zip(manifest_results) {
+        match result {
+            Ok(mut font_manifest) => {
+                font_manifest.set_family(font_name);
+                font_manifests.push(in_installed_layout(font_manifest, font_name, &registry));
+            }
+            Err(e) => fail!(
+                e.exit_code(),
+                "{}",
+                msg!("error.manifest_failed", font = font_name, error = e)
+            ),
+        }
+    }
+    let font_manifest = FontManifest::merge(font_manifests);
+    if !options.offline
+        && let Some(download_cache) = &download_cache
+        && let Err(failures) = font_manifest.cache_file_refs(options, download_cache)
+    {
+        exit_on_fetch_failures(&failures, options);
+    }
+
+    let diffs = diff_update(
+        &font_names,
+        &registry,
+        &font_manifest,
+        &base_path.path,
+        download_cache.as_ref(),
+    );
+    print_update_diff(&diffs);
+    if diffs.iter().any(|diff| !diff.changes.is_empty()) {
+        exit(EXIT_CODE_DIFFERENCES);
+    }
+}
@@ -0,0 +1,119 @@
+use fonty::color::*;
+use std::fs;
+
+use fonty::{
+    archive::{ArchiveEntry, ArchiveFormat, write_archive},
+    base_path::BasePath,
+    cli::Cli,
+    exit_codes::{
+        EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME,
+    },
+    fail,
+    font_names::family_dir_name,
+    format::{n_files, n_fonts},
+    info,
+    license::is_license_file,
+    msg,
+    registry::Registry,
+    warnings::Warnings,
+};
+
+use super::selected_families;
+
+/// Bundles the installed files of families (always including their licenses) into the archive given with `--archive`.
+/// Entries are sorted by path, so exporting the same files again gives the same archive.
+pub fn export(cli: &Cli, base_path: &BasePath) {
+    let Some(archive_path) = cli.flag_value("--archive") else {
+        fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.no_archive"));
+    };
+    let Some(format) = ArchiveFormat::from_path(archive_path) else {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.invalid_archive", archive = archive_path)
+        );
+    };
+
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    let font_names = selected_families(cli, &registry);
+    if font_names.is_empty() {
+        fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("fetch.no_fonts"));
+    }
+
+    // Everything is read before the archive is written, so a missing file can't leave a partial archive behind.
+    let mut entries = Vec::new();
+    let mut missing = Vec::new();
+    let mut unlicensed = Vec::new();
+    for family in font_names
+        .iter()
+        .filter_map(|font_name| registry.find(font_name))
+    {
+        let family_dir = family_dir_name(&family.name);
+        if !family
+            .files
+            .iter()
+            .any(|file| is_license_file(&file.path.to_string_lossy()))
+        {
+            unlicensed.push(family.name.clone());
+        }
+        for file in &family.files {
+            match fs::read(base_path.path.join(&file.path)) {
+                Ok(contents) => {
+                    let path = file.path.to_string_lossy().replace('\\', "/");
+                    // Files of families installed together are not in a dir of their own.
+                    let path = if file.path.starts_with(&family_dir) {
+                        path
+                    } else {
+                        format!("{}/{}", family_dir, path)
+                    };
+                    entries.push(ArchiveEntry { path, contents });
+                }
+                Err(_) => missing.push(file.path.display().to_string()),
+            }
+        }
+    }
+    if !unlicensed.is_empty() {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!(
+                "error.export_unlicensed",
+                fonts = unlicensed.join(&format!("'{color_bright_black}, {color_blue}'"))
+            )
+        );
+    }
+    if !missing.is_empty() {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!(
+                "error.export_missing",
+                files = missing.join(&format!("'{color_bright_black}, {color_blue}'"))
+            )
+        );
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let archive = match write_archive(&entries, format) {
+        Ok(archive) => archive,
+        Err(e) => fail!(EXIT_CODE_FILE_IO_ERROR, "{}", e),
+    };
+    // Write to a temporary file first, so a failed export does not leave a broken archive behind.
+    let temporary_path = format!("{}.tmp", archive_path);
+    if let Err(e) =
+        fs::write(&temporary_path, &archive).and_then(|_| fs::rename(&temporary_path, archive_path))
+    {
+        let _ = fs::remove_file(&temporary_path);
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.write_archive", path = archive_path, error = e)
+        );
+    }
+    info!(
+        "Exported {} of {} to {color_blue}'{}'{color_reset}.",
+        n_files(entries.len()),
+        n_fonts(font_names.len()),
+        archive_path
+    );
+}
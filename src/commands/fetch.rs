@@ -0,0 +1,862 @@
+use fonty::color::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    slice,
+    time::Instant,
+};
+
+use fonty::{
+    axes::{AxisRange, check_axes, icon_font_axes, parse_axes},
+    backup::back_up_files,
+    base_path::{
+        BasePath, BasePathEnv, BasePathError, Platform, check_base_path_writable,
+        default_cache_dir, is_case_insensitive,
+    },
+    cli::Cli,
+    download_cache::DownloadCache,
+    error,
+    exit_codes::{
+        EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME,
+        EXIT_CODE_NOT_CACHED,
+    },
+    fail,
+    family_spec::{FamilySpec, face_selections},
+    font_manifest::{FileStatus, FontManifest},
+    format::{format_bytes, format_size, n_files, plural},
+    hooks::{expand_hook_command, run_hook},
+    http::print_http_stats,
+    info,
+    install::{FamilyError, InstallHooks, Installer, load_family_manifest},
+    lock::{BaseDirLock, lock_base_dir},
+    msg, msg_n,
+    notify::{exit, notify_finished},
+    options::Options,
+    permission_hints::print_permission_hints,
+    plan::{audit_installed, print_plan},
+    progress,
+    registry::Registry,
+    summary::PhaseTimings,
+    system_integration::{refresh_font_cache, register_fonts},
+    targets::{TargetReport, copy_to_target, resolve_targets},
+    update_diff::{diff_update, print_update_diff},
+    warnings::{WarningCode, Warnings},
+    worker_pool::run_pipelined,
+};
+
+use super::{
+    check_tag, check_valid_fonts, confirm_or_exit, create_dir_or_exit, download_cache,
+    dump_response, expand_font_urls, fail_if_invalid, find_invalid_fonts, in_installed_layout,
+    lock_base_dir_or_exit, noto::noto, parse_family_specs, prepare_font_names_or_exit,
+    selected_families, sync::sync,
+};
+
+/// Checks which of the files to write already exist, and decides what to do with them.
+/// Returns the existing files that should be kept (skipped). Exits if the user does not want them overwritten.
+/// Once they agreed (`confirmed`), the files of the next families are overwritten without asking again.
+fn check_existing_files(
+    cli: &Cli,
+    destination_paths: &[PathBuf],
+    confirmed: &mut bool,
+) -> HashSet<PathBuf> {
+    if cli.has_flag("--force") || *confirmed {
+        return HashSet::new();
+    }
+
+    let existing = destination_paths
+        .iter()
+        .filter(|path| path.exists())
+        .cloned()
+        .collect::<Vec<_>>();
+    if existing.is_empty() {
+        return HashSet::new();
+    }
+
+    if cli.has_flag("--keep-existing") {
+        info!(
+            "Keeping {} that already exist, they will not be overwritten.",
+            n_files(existing.len())
+        );
+        return existing.into_iter().collect();
+    }
+
+    println!(
+        "{color_yellow}WARN:{color_reset} The following files already exist and would be overwritten:"
+    );
+    for path in &existing {
+        println!("  {color_blue}'{}'{color_reset}", path.display());
+    }
+
+    confirm_or_exit(
+        cli,
+        "Overwrite these files?",
+        &format!(
+            "Refusing to overwrite existing files without confirmation! Pass {color_blue}--yes{color_reset} to overwrite them, or {color_blue}--keep-existing{color_reset} to skip them."
+        ),
+        "Not overwriting existing files, aborting.",
+    );
+    *confirmed = true;
+    HashSet::new()
+}
+
+/// Parses the `--axes` to fetch icon fonts with, and checks that every family has them. Exits if it doesn't.
+fn requested_axes(cli: &Cli, options: &Options, font_names: &[String]) -> Option<Vec<AxisRange>> {
+    let spec = cli.flag_value("--axes")?;
+    if cli.action != "fetch" {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.axes_only_fetch")
+        );
+    }
+    if options.offline {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.axes_offline")
+        );
+    }
+    let axes = match parse_axes(spec) {
+        Ok(axes) => axes,
+        Err(e) => fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.invalid_axes", value = spec, error = e)
+        ),
+    };
+    for font_name in font_names {
+        let Some(bounds) = icon_font_axes(font_name) else {
+            fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!("error.axes_not_icon_font", font = font_name)
+            );
+        };
+        if let Err(e) = check_axes(&axes, bounds) {
+            fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_axes_for_font",
+                    value = spec,
+                    font = font_name,
+                    error = e
+                )
+            );
+        }
+    }
+    Some(axes)
+}
+
+/// Asks for confirmation before downloading more than `--confirm-size`, adding up the families as they are installed in
+/// `downloaded`, which is cleared once there is nothing left to ask. Finding out the size can take a HEAD request for every
+/// file that is not cached and has no size in the manifest, which is skipped when the answer is yes anyway.
+fn confirm_download_size(
+    cli: &Cli,
+    options: &Options,
+    font_manifest: &FontManifest,
+    download_cache: Option<&DownloadCache>,
+    downloaded: &mut Option<u64>,
+) {
+    let Some(download_size) = downloaded else {
+        return;
+    };
+    if options.offline || cli.has_flag("--yes") {
+        *downloaded = None;
+        return;
+    }
+    *download_size += font_manifest.download_size(
+        options,
+        download_cache,
+        options.confirm_size - *download_size,
+    );
+    if *download_size <= options.confirm_size {
+        return;
+    }
+    println!(
+        "{color_yellow}WARN:{color_reset} This will download at least {}, more than {} {color_bright_black}(see --confirm-size){color_reset}.",
+        format_bytes(*download_size),
+        format_size(options.confirm_size)
+    );
+    confirm_or_exit(
+        cli,
+        "Download anyway?",
+        &format!(
+            "Refusing to download this much without confirmation! Pass {color_blue}--yes{color_reset} to download it anyway."
+        ),
+        "Not downloading, aborting.",
+    );
+    *downloaded = None;
+}
+
+/// Describes the installed version of a family, e.g. `v3.011`.
+fn format_version(version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("v{}", version),
+        None => "unknown version".to_string(),
+    }
+}
+
+/// Moves the files of a family that are about to be overwritten into a backup set, together with its registry entry.
+fn back_up_existing_files(
+    font_name: &str,
+    font_manifest: &FontManifest,
+    base_path: &Path,
+    registry: &Registry,
+    skip: &HashSet<PathBuf>,
+) {
+    let paths = font_manifest
+        .destination_paths(base_path)
+        .into_iter()
+        .filter(|path| !skip.contains(path))
+        .collect::<Vec<_>>();
+    match back_up_files(base_path, font_name, registry.find(font_name), &paths) {
+        Ok(Some(set)) => info!(
+            "Backed up the existing files of {color_blue}'{}'{color_reset} to {color_blue}'{}'{color_reset}.",
+            font_name,
+            set.path.display()
+        ),
+        Ok(None) => {}
+        Err(e) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.back_up", font = font_name, error = e)
+        ),
+    }
+}
+
+/// What `fetch` adds to installing every family (see `Installer::install_family`): selecting faces, the layout of `update`,
+/// checking the cache with `--offline`, showing what an update changes, asking before downloading a lot or overwriting files,
+/// creating (and locking) a new font base dir, backing up, the progress, the timings, and the `--tag`s.
+struct FetchHooks<'a> {
+    cli: &'a Cli,
+    options: &'a Options,
+    base_path: &'a BasePath,
+    download_cache: Option<&'a DownloadCache>,
+    face_selections: &'a HashMap<String, Vec<FamilySpec>>,
+    /// The families to install, for the progress.
+    families: &'a [String],
+    json: bool,
+    dry_run: bool,
+    base_dir_lock: Option<BaseDirLock>,
+    base_dir_ready: bool,
+    size_to_confirm: Option<u64>,
+    overwrite_confirmed: bool,
+    progress_started: bool,
+    /// The exit code of the last family that failed, for when none could be installed.
+    exit_code: i32,
+    timings: PhaseTimings,
+    phase_started_at: Instant,
+}
+
+impl InstallHooks for FetchHooks<'_> {
+    fn prepare(
+        &mut self,
+        font_name: &str,
+        mut font_manifest: FontManifest,
+        registry: &Registry,
+    ) -> Result<FontManifest, FamilyError> {
+        if let Some(specs) = self.face_selections.get(&font_name.to_lowercase()) {
+            let faces = specs
+                .iter()
+                .map(FamilySpec::faces)
+                .collect::<Vec<_>>()
+                .join(&format!("'{color_reset} and {color_blue}'"));
+            if font_manifest.retain_faces(specs) == 0 {
+                return Err(FamilyError::MissingFaces(faces));
+            }
+            info!(
+                "Only fetching the faces {color_blue}'{}'{color_reset} of {color_blue}'{}'{color_reset}.",
+                faces, font_name
+            );
+        }
+        let font_manifest = if self.cli.action == "update" {
+            in_installed_layout(font_manifest, font_name, registry)
+        } else {
+            font_manifest
+        };
+        // Check everything is cached before writing anything, so --offline never leaves a half-installed family behind.
+        if !self.dry_run
+            && let Some(download_cache) = self.download_cache.filter(|_| self.options.offline)
+        {
+            let uncached_urls = font_manifest.uncached_urls(download_cache);
+            if !uncached_urls.is_empty() {
+                return Err(FamilyError::NotCached(
+                    uncached_urls.into_iter().map(str::to_string).collect(),
+                ));
+            }
+        }
+        Ok(font_manifest)
+    }
+
+    fn failed(&mut self, font_name: &str, error: &FamilyError) {
+        error!(
+            "{}",
+            msg!("error.font_failed", font = font_name, error = error)
+        );
+        if let FamilyError::Manifest(e) = error {
+            dump_response(self.cli, e);
+        }
+        self.exit_code = error.exit_code();
+    }
+
+    fn before_writing(
+        &mut self,
+        font_name: &str,
+        font_manifest: &FontManifest,
+        registry: &mut Registry,
+        warnings: &mut Warnings,
+    ) -> Option<HashSet<PathBuf>> {
+        let cli = self.cli;
+        let base_path = self.base_path;
+        // Show what the update changes before anything is written, so a dry run shows the same.
+        if cli.action == "update" && !self.json && !cli.has_flag("--quiet") {
+            print_update_diff(&diff_update(
+                slice::from_ref(&font_name.to_string()),
+                registry,
+                font_manifest,
+                &base_path.path,
+                self.download_cache,
+            ));
+        }
+        if self.dry_run {
+            return None;
+        }
+
+        confirm_download_size(
+            cli,
+            self.options,
+            font_manifest,
+            self.download_cache,
+            &mut self.size_to_confirm,
+        );
+
+        // A new font base dir is only created (and locked) now, so nothing is left behind by a fetch that fails early.
+        if !self.base_dir_ready {
+            create_dir_or_exit(&base_path.path, self.options);
+            self.base_dir_lock = lock_base_dir_or_exit(base_path, self.options);
+            *registry = Registry::load(&base_path.path, warnings);
+            self.base_dir_ready = true;
+        }
+
+        // Updating is meant to replace the installed files. (Asking about existing files is not timed.)
+        let skip = if cli.action == "update" {
+            HashSet::new()
+        } else {
+            check_existing_files(
+                cli,
+                &font_manifest.destination_paths(&base_path.path),
+                &mut self.overwrite_confirmed,
+            )
+        };
+        if cli.has_flag("--backup") {
+            back_up_existing_files(font_name, font_manifest, &base_path.path, registry, &skip);
+        }
+
+        let files = font_manifest.filenames().len();
+        if self.progress_started {
+            progress::add_files(files);
+        } else {
+            progress::started(self.families, files);
+            self.progress_started = true;
+        }
+        info!("{}", msg!("fetch.installing", font = font_name));
+        self.phase_started_at = Instant::now();
+        Some(skip)
+    }
+
+    fn text_files_written(&mut self) {
+        self.phase_started_at = self.timings.record("Text files", self.phase_started_at);
+    }
+
+    fn font_files_fetched(&mut self) {
+        self.phase_started_at = self.timings.record("Downloads", self.phase_started_at);
+    }
+
+    fn recorded(
+        &mut self,
+        font_name: &str,
+        old_version: Option<Option<String>>,
+        registry: &mut Registry,
+    ) {
+        if self.cli.action == "update"
+            && let Some(old_version) = old_version
+        {
+            let new_version = registry
+                .find(font_name)
+                .and_then(|family| family.version.as_deref());
+            if old_version.as_deref() == new_version {
+                info!(
+                    "{color_blue}{}{color_reset}: {} {color_bright_black}(unchanged){color_reset}",
+                    font_name,
+                    format_version(new_version)
+                );
+            } else {
+                info!(
+                    "{color_blue}{}{color_reset}: {} {color_green}→{color_reset} {}",
+                    font_name,
+                    format_version(old_version.as_deref()),
+                    format_version(new_version)
+                );
+            }
+        }
+        for tag in self.cli.flag_values("--tag") {
+            registry.add_tag(font_name, tag);
+        }
+    }
+}
+
+/// Installs families: the ones given to `fetch`, the installed ones for `update`, the ones in the project manifest for
+/// `sync`, and the Noto families for `noto`. Exits with the exit code of the run if it isn't 0.
+pub fn fetch(
+    cli: &Cli,
+    options: &Options,
+    env: &BasePathEnv,
+    base_path: &BasePath,
+    base_dir_lock: Option<BaseDirLock>,
+    started_at: Instant,
+    json: bool,
+) {
+    // Warnings are printed as they happen, and repeated at the end of the run.
+    let mut warnings = Warnings::default();
+    let mut registry = Registry::load(&base_path.path, &mut warnings);
+    // Updating fetches the installed families again: the ones given, the tagged ones, or else all of them.
+    let font_names = if cli.action == "update" {
+        match selected_families(cli, &registry) {
+            font_names if !font_names.is_empty() => font_names,
+            _ if !cli.args.is_empty() || cli.has_flag("--tag") => {
+                fail!(
+                    EXIT_CODE_INVALID_FONT_NAME,
+                    "{}",
+                    msg!("fetch.no_fonts_to_update")
+                )
+            }
+            _ => registry
+                .families
+                .iter()
+                .map(|family| family.name.clone())
+                .collect(),
+        }
+    } else if cli.action == "noto" {
+        noto(cli, env, json)
+    } else if cli.action == "sync" {
+        match sync(cli, options, env, base_path, &mut registry) {
+            font_names if font_names.is_empty() => {
+                info!("{}", msg!("sync.up_to_date"));
+                exit(0);
+            }
+            font_names => font_names,
+        }
+    } else {
+        cli.args.clone()
+    };
+    // Families can be given like for the CSS API, e.g. `Inter:wght@400;700`, to only fetch some of their faces.
+    let (font_names, face_selections) = if cli.action == "fetch" {
+        let specs = parse_family_specs(&expand_font_urls(&font_names));
+        let face_selections = face_selections(&specs);
+        (
+            specs
+                .into_iter()
+                .map(|spec| spec.family)
+                .collect::<Vec<_>>(),
+            face_selections,
+        )
+    } else {
+        (font_names, HashMap::new())
+    };
+    if font_names.is_empty() {
+        fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("fetch.no_fonts"));
+    }
+    for tag in cli.flag_values("--tag") {
+        check_tag(tag);
+    }
+
+    // Collapse duplicated font names, so each font is only validated and fetched once.
+    let (args, duplicates) = prepare_font_names_or_exit(&font_names);
+    for (font_name, count) in duplicates {
+        warnings.warn(
+            WarningCode::DuplicateFont,
+            msg!("fetch.duplicate", font = font_name, count = count),
+        );
+    }
+
+    // With --only-new, installed families are left out before anything (even their manifest) is fetched.
+    let args = if cli.action == "fetch" && cli.has_flag("--only-new") && !cli.has_flag("--force") {
+        let (installed, new): (Vec<_>, Vec<_>) = args
+            .into_iter()
+            .partition(|font_name| registry.find(font_name).is_some());
+        if !installed.is_empty() {
+            info!(
+                "{}",
+                msg!(
+                    "fetch.already_installed",
+                    fonts = installed.join(&format!("'{color_bright_black}, {color_blue}'"))
+                )
+            );
+        }
+        if new.is_empty() {
+            info!("{}", msg!("fetch.all_installed"));
+            exit(0);
+        }
+        new
+    } else {
+        args
+    };
+
+    let axes = requested_axes(cli, options, &args);
+    if axes.is_some() && !face_selections.is_empty() {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.axes_with_faces")
+        );
+    }
+    let targets = match resolve_targets(&cli.flag_values("--also-output"), env, &base_path.path) {
+        Ok(targets) => targets,
+        Err(e) => fail!(e.exit_code(), "{}", e),
+    };
+
+    info!(
+        "{}",
+        msg!(
+            "fetch.base_dir",
+            path = base_path.path.display(),
+            source = base_path.source
+        )
+    );
+
+    // Make sure the fonts can be installed, before spending time on downloading them. A dry run writes nothing, so it may be read-only.
+    if !cli.has_flag("--dry-run")
+        && let Err(e) = check_base_path_writable(base_path)
+    {
+        println!("{color_red}ERROR:{color_reset} {}", e);
+        if let BasePathError::NotWritable { path, kind, .. } = &e {
+            print_permission_hints(&path.join(".fonty-write-probe"), *kind);
+        }
+        exit(e.exit_code());
+    }
+
+    let download_cache = download_cache(
+        default_cache_dir(env, Platform::current()).as_deref(),
+        options,
+    );
+    if options.offline && download_cache.is_none() {
+        fail!(
+            EXIT_CODE_NOT_CACHED,
+            "{}",
+            msg!("error.offline_without_cache_dir")
+        );
+    }
+
+    let mut timings = PhaseTimings::default();
+    let mut phase_started_at = Instant::now();
+
+    // Fonts can't be checked with --offline, a font without a cached manifest fails below instead.
+    // With --ignore-invalid, the invalid fonts are left out, and the run ends with the partial failure exit code.
+    let args = if options.offline {
+        args
+    } else if cli.has_flag("--ignore-invalid") {
+        let invalid_fonts = find_invalid_fonts(&args, options);
+        // With only invalid fonts, there is nothing left to install.
+        if invalid_fonts.len() == args.len() {
+            fail_if_invalid(&invalid_fonts);
+        }
+        for font_name in &invalid_fonts {
+            warnings.warn(
+                WarningCode::InvalidFontSkipped,
+                format!(
+                    "{color_blue}'{}'{color_reset} is not a font on Google Fonts, skipping it.",
+                    font_name
+                ),
+            );
+        }
+        args.into_iter()
+            .filter(|font_name| !invalid_fonts.contains(font_name))
+            .collect()
+    } else {
+        check_valid_fonts(&args, options);
+        args
+    };
+    phase_started_at = timings.record("Validation", phase_started_at);
+
+    info!(
+        "{}",
+        msg!(
+            "fetch.will_download",
+            fonts = args.join(&format!("'{color_bright_black}, {color_blue}'"))
+        )
+    );
+
+    let extra_hosts = cli.flag_values("--allow-host");
+    let dry_run = cli.has_flag("--dry-run");
+    let mut installer = Installer::new(
+        &base_path.path,
+        options,
+        download_cache.as_ref(),
+        registry,
+        warnings,
+    );
+    installer.extra_hosts = &extra_hosts;
+    installer.case_insensitive =
+        cli.has_flag("--case-insensitive") || is_case_insensitive(&base_path.path);
+    // If there is only one font to fetch, its files go into a dir with the name of the font. Updating keeps the installed layout.
+    installer.own_dir = cli.action != "update" && args.len() == 1;
+    let mut hooks = FetchHooks {
+        cli,
+        options,
+        base_path,
+        download_cache: download_cache.as_ref(),
+        face_selections: &face_selections,
+        families: &args,
+        json,
+        dry_run,
+        base_dir_ready: base_dir_lock.is_some(),
+        base_dir_lock,
+        size_to_confirm: Some(0),
+        overwrite_confirmed: false,
+        progress_started: false,
+        exit_code: 0,
+        timings,
+        phase_started_at,
+    };
+
+    // Families are installed one after the other, so every file is known to belong to a family. The manifests of the next
+    // --prefetch families are fetched while the files of one are downloaded, and a family is only checked when it is its turn.
+    // A family that can't be installed (e.g. one renamed on Google Fonts) only fails itself, the others are still installed.
+    run_pipelined(
+        &args,
+        options.prefetch,
+        |_, font_name| match &axes {
+            Some(axes) => FontManifest::load_with_axes(font_name, axes)
+                .map_err(|e| FamilyError::Manifest(Box::new(e)))
+                .map(|mut font_manifest| {
+                    font_manifest.set_family(font_name);
+                    font_manifest
+                }),
+            None => load_family_manifest(font_name, options, download_cache.as_ref()),
+        },
+        |_, font_name, font_manifest| {
+            // The time spent waiting for the manifest, the rest of it was fetched during the downloads of the families before.
+            hooks.phase_started_at = hooks.timings.record("Manifests", hooks.phase_started_at);
+            installer.install_family(font_name, font_manifest, &mut hooks);
+        },
+    );
+    let FetchHooks {
+        base_dir_lock: _base_dir_lock,
+        exit_code,
+        mut timings,
+        phase_started_at,
+        ..
+    } = hooks;
+    if installer.families.is_empty() {
+        exit(exit_code);
+    }
+
+    if dry_run {
+        if json {
+            match serde_json::to_string_pretty(installer.plan.files()) {
+                Ok(planned_files) => println!("{}", planned_files),
+                Err(e) => println!(
+                    "{color_red}ERROR:{color_reset} {}",
+                    msg!("error.serialize_plan", error = e)
+                ),
+            }
+        } else {
+            print_plan(installer.plan.files());
+        }
+        exit(0);
+    }
+
+    installer.warn_if_registry_not_saved();
+    let args = installer.families.clone();
+
+    if cli.has_flag("--register") {
+        info!("{}", msg!("fetch.registering"));
+        let font_files = installer
+            .font_files
+            .iter()
+            .filter(|result| result.status == FileStatus::Written)
+            .map(|result| result.path.clone())
+            .collect::<Vec<_>>();
+        register_fonts(&font_files);
+    }
+
+    refresh_font_cache(&base_path.path, options, &mut installer.warnings);
+
+    let all_file_results = installer
+        .text_files
+        .iter()
+        .chain(&installer.font_files)
+        .cloned()
+        .collect::<Vec<_>>();
+    // The other targets get the files from the font base dir. A target failing only fails the run, the font base dir stays installed.
+    let mut target_reports = Vec::new();
+    if !targets.is_empty() {
+        target_reports.push(TargetReport {
+            dir: base_path.path.clone(),
+            primary: true,
+            files: all_file_results
+                .iter()
+                .filter(|result| !result.status.is_failed())
+                .map(|result| result.path.clone())
+                .collect(),
+            failed: Vec::new(),
+        });
+    }
+    for target in &targets {
+        info!(
+            "Putting the files into {color_blue}'{}'{color_reset}...",
+            target.display()
+        );
+        if let Err(e) = fs::create_dir_all(target) {
+            installer.warnings.warn(
+                WarningCode::TargetFailed,
+                format!(
+                    "Failed to create the target {color_blue}'{}'{color_reset}! Error: {}",
+                    target.display(),
+                    e
+                ),
+            );
+            continue;
+        }
+        let _target_lock = match lock_base_dir(target, options.lock_timeout) {
+            Ok(lock) => lock,
+            Err(e) => {
+                installer
+                    .warnings
+                    .warn(WarningCode::TargetFailed, e.to_string());
+                continue;
+            }
+        };
+        let (copied, failed) = copy_to_target(&base_path.path, target, &all_file_results);
+        for failure in &failed {
+            installer.warnings.warn(
+                WarningCode::TargetFailed,
+                format!(
+                    "Failed to put {color_blue}'{}'{color_reset} into the target! Error: {}",
+                    failure.path.display(),
+                    failure.error
+                ),
+            );
+        }
+        let mut target_registry = Registry::load(target, &mut installer.warnings);
+        for font_name in &args {
+            target_registry.record(font_name, target, &copied, options);
+            for tag in cli.flag_values("--tag") {
+                target_registry.add_tag(font_name, tag);
+            }
+        }
+        if let Err(e) = target_registry.save(target) {
+            installer.warnings.warn(
+                WarningCode::RegistryNotSaved,
+                format!(
+                    "Failed to update the registry of installed fonts in {color_blue}'{}'{color_reset}! Error:\n{}",
+                    target.display(),
+                    e
+                ),
+            );
+        }
+        target_reports.push(TargetReport {
+            dir: target.clone(),
+            primary: false,
+            files: copied.into_iter().map(|result| result.path).collect(),
+            failed,
+        });
+    }
+
+    // When only some faces were asked for, check those (and only those) are what ended up on disk.
+    if !face_selections.is_empty() && !cli.has_flag("--no-audit") {
+        let audited = args
+            .iter()
+            .filter(|font_name| face_selections.contains_key(&font_name.to_lowercase()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let discrepancies = audit_installed(
+            installer.plan.files(),
+            &all_file_results,
+            &audited,
+            &installer.registry,
+            &base_path.path,
+        );
+        for discrepancy in discrepancies {
+            installer
+                .warnings
+                .warn(WarningCode::AuditFailed, format!("Audit: {}.", discrepancy));
+        }
+    }
+
+    // The hook only runs when something was written. A failing hook doesn't undo the install, it only fails the run.
+    if let Some(post_install) = &options.post_install
+        && all_file_results
+            .iter()
+            .any(|result| result.status == FileStatus::Written)
+    {
+        let command = expand_hook_command(post_install, &args, &base_path.path);
+        info!(
+            "Running the post-install hook {color_blue}'{}'{color_reset}...",
+            command
+        );
+        let failure = match run_hook(&command) {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("The post-install hook failed ({}).", status)),
+            Err(e) => Some(format!(
+                "The post-install hook could not be run! Error: {}",
+                e
+            )),
+        };
+        if let Some(failure) = failure {
+            installer.warnings.warn(WarningCode::HookFailed, failure);
+        }
+    }
+
+    timings.record("Post-processing", phase_started_at);
+
+    // The files that failed are listed in the summary instead.
+    if !installer
+        .font_files
+        .iter()
+        .any(|result| result.status.is_failed())
+    {
+        info!("{}", msg_n!("fetch.done", installer.font_files.len()));
+    }
+
+    let mut report = installer.into_report(&cli.args, started_at);
+    report.timing = options.timing.then_some(timings);
+    report.targets = target_reports;
+    let summary = &report.summary;
+    let mut notification = format!(
+        "Installed {}, {}.",
+        plural(summary.families_installed, "family", "families"),
+        n_files(
+            summary.text_files_written
+                + summary.font_files_downloaded
+                + summary.font_files_from_cache
+        )
+    );
+    if !report.warnings.is_empty() {
+        notification.push_str(&format!(
+            " {}.",
+            plural(report.warnings.len(), "warning", "warnings")
+        ));
+    }
+    let exit_code = report.exit_code(options.deny_warnings);
+    if json {
+        match report.to_json() {
+            Ok(report) => println!("{}", report),
+            Err(e) => println!(
+                "{color_red}ERROR:{color_reset} {}",
+                msg!("error.serialize_report", error = e)
+            ),
+        }
+    } else if !cli.has_flag("--quiet") {
+        print!("{}", report.render_summary());
+    }
+    if cli.has_flag("--http-stats") {
+        print_http_stats();
+    }
+    notify_finished(exit_code == 0, &notification);
+    if exit_code != 0 {
+        exit(exit_code);
+    }
+}
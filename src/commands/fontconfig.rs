@@ -0,0 +1,76 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv, fontconfig_conf_dir},
+    cli::Cli,
+    exit_codes::{EXIT_CODE_ABORTED, EXIT_CODE_CONFIG_ERROR, EXIT_CODE_FILE_IO_ERROR},
+    fail, info, msg,
+    options::Options,
+    system_integration::{
+        FONTCONFIG_SNIPPET_FILENAME, SnippetInstall, SnippetInstallError, fontconfig_snippet,
+        install_fontconfig_snippet, refresh_font_cache,
+    },
+    warnings::Warnings,
+};
+
+/// Prints a fontconfig snippet adding the font base dir, or writes it into fontconfig's `conf.d` dir with `--install`.
+/// An existing snippet with other contents is only overwritten with `--force`.
+pub fn fontconfig(cli: &Cli, options: &Options, env: &BasePathEnv, base_path: &BasePath) {
+    if !base_path.path.is_dir() {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.no_base_dir", path = base_path.path.display())
+        );
+    }
+    let Some(path) = base_path.path.to_str() else {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.base_dir_not_utf8", path = base_path.path.display())
+        );
+    };
+    let snippet = fontconfig_snippet(path);
+    if !cli.has_flag("--install") {
+        print!("{}", snippet);
+        return;
+    }
+
+    let Some(conf_dir) = fontconfig_conf_dir(env) else {
+        fail!(
+            EXIT_CODE_CONFIG_ERROR,
+            "{}",
+            msg!("error.no_fontconfig_dir")
+        );
+    };
+    let snippet_path = conf_dir.join(FONTCONFIG_SNIPPET_FILENAME);
+    match install_fontconfig_snippet(&snippet_path, &snippet, cli.has_flag("--force")) {
+        Ok(SnippetInstall::AlreadyInstalled) => {
+            info!(
+                "The fontconfig snippet {color_blue}'{}'{color_reset} is already installed.",
+                snippet_path.display()
+            );
+            return;
+        }
+        Ok(SnippetInstall::Written) => {}
+        Err(SnippetInstallError::Exists) => fail!(
+            EXIT_CODE_ABORTED,
+            "{}",
+            msg!("error.snippet_exists", path = snippet_path.display())
+        ),
+        Err(SnippetInstallError::Io(e)) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!(
+                "error.write_snippet",
+                path = snippet_path.display(),
+                error = e
+            )
+        ),
+    }
+    info!(
+        "Wrote the fontconfig snippet to {color_blue}'{}'{color_reset}.",
+        snippet_path.display()
+    );
+    refresh_font_cache(&base_path.path, options, &mut Warnings::default());
+}
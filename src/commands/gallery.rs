@@ -0,0 +1,87 @@
+use fonty::color::*;
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv, Platform, default_cache_dir},
+    catalog::Catalog,
+    cli::Cli,
+    exit_codes::{
+        EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME,
+    },
+    fail,
+    format::n_fonts,
+    gallery::write_gallery,
+    info, msg,
+    registry::Registry,
+    warnings::Warnings,
+};
+
+/// Writes an HTML page showing every installed family in itself to the path given with `--out`.
+pub fn gallery(cli: &Cli, env: &BasePathEnv, base_path: &BasePath) {
+    let Some(out_path) = cli.flag_value("--out") else {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.no_gallery_file")
+        );
+    };
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    if registry.families.is_empty() {
+        fail!(
+            EXIT_CODE_INVALID_FONT_NAME,
+            "{}",
+            msg!("error.no_fonts_installed", path = base_path.path.display())
+        );
+    }
+    // The catalog is only used for grouping by category, so it is not downloaded for it.
+    let catalog = Catalog::load_cached(default_cache_dir(env, Platform::current()).as_deref());
+    let absolute_base_path = match std::path::absolute(&base_path.path) {
+        Ok(path) => path,
+        Err(e) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!(
+                "error.resolve_base_dir",
+                path = base_path.path.display(),
+                error = e
+            )
+        ),
+    };
+
+    // Write to a temporary file first, so a failed run does not leave a half-written page behind.
+    let temporary_path = format!("{}.tmp", out_path);
+    let result = fs::File::create(&temporary_path).and_then(|file| {
+        let mut out = io::BufWriter::new(file);
+        let summary = write_gallery(&mut out, &registry, &absolute_base_path, catalog.as_ref())?;
+        out.flush()?;
+        fs::rename(&temporary_path, out_path)?;
+        Ok(summary)
+    });
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = fs::remove_file(&temporary_path);
+            fail!(
+                EXIT_CODE_FILE_IO_ERROR,
+                "{}",
+                msg!("error.write_gallery", path = out_path, error = e)
+            );
+        }
+    };
+    if !summary.skipped.is_empty() {
+        println!(
+            "{color_yellow}WARN:{color_reset} Left out {color_blue}'{}'{color_reset}, their files could not be attributed to weights.",
+            summary
+                .skipped
+                .join(&format!("'{color_bright_black}, {color_blue}'"))
+        );
+    }
+    info!(
+        "Wrote a gallery of {} to {color_blue}'{}'{color_reset}.",
+        n_fonts(summary.families),
+        out_path
+    );
+}
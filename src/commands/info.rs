@@ -0,0 +1,138 @@
+use fonty::color::*;
+use serde::Serialize;
+
+use fonty::{
+    base_path::{BasePathEnv, Platform, default_cache_dir},
+    catalog::{Catalog, CatalogFamily},
+    cli::Cli,
+    error,
+    exit_codes::{EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME},
+    fail,
+    font_manifest::FontManifest,
+    font_names::normalize_font_name,
+    msg,
+    options::Options,
+};
+
+use super::download_cache;
+
+/// Everything known about a family, printed by `info --json`.
+#[derive(Serialize)]
+struct FamilyInfo<'a> {
+    family: String,
+    /// The metadata from the catalog, if the family is in it.
+    catalog: Option<&'a CatalogFamily>,
+    license: Option<&'static str>,
+    /// The files from the font manifest, if it could be fetched.
+    files: Option<Vec<&'a str>>,
+    /// Notes on what could not be found out, e.g. because the catalog could not be downloaded.
+    missing: Vec<String>,
+}
+
+/// Shows the metadata and the files of the family given on the command line.
+/// The metadata comes from the catalog and the files from the font manifest, if either is unavailable the other one is still shown.
+pub fn info(cli: &Cli, options: &Options, env: &BasePathEnv, json: bool) {
+    let font_name = match cli.args.as_slice() {
+        [font_name] => normalize_font_name(font_name),
+        [] => fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("error.no_font")),
+        _ => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.one_font")),
+    };
+    let mut missing = Vec::new();
+
+    let cache_dir = default_cache_dir(env, Platform::current());
+    let catalog = Catalog::load(cache_dir.as_deref(), options)
+        .inspect_err(|e| missing.push(format!("Could not get the catalog metadata! {}", e)))
+        .ok();
+    let catalog_family = catalog.as_ref().and_then(|catalog| {
+        let catalog_family = catalog
+            .families
+            .iter()
+            .find(|family| family.family.to_lowercase() == font_name.to_lowercase());
+        if catalog_family.is_none() {
+            missing.push("The font is not in the catalog.".to_string());
+        }
+        catalog_family
+    });
+
+    let download_cache = download_cache(cache_dir.as_deref(), options);
+    let font_manifest = match FontManifest::load(&font_name, options, download_cache.as_ref()) {
+        Ok(font_manifest) if font_manifest.is_empty() => {
+            missing.push("The font manifest lists no files.".to_string());
+            None
+        }
+        Ok(font_manifest) => Some(font_manifest),
+        Err(e) => {
+            missing.push(format!("Could not get the files! {}", e));
+            None
+        }
+    };
+
+    if catalog_family.is_none() && font_manifest.is_none() {
+        for missing in &missing {
+            println!("{color_yellow}NOTE:{color_reset} {}", missing);
+        }
+        fail!(
+            EXIT_CODE_INVALID_FONT_NAME,
+            "{}",
+            msg!("error.unknown_font", font = font_name)
+        );
+    }
+
+    let family_info = FamilyInfo {
+        family: catalog_family.map_or(font_name.clone(), |family| family.family.clone()),
+        catalog: catalog_family,
+        license: font_manifest
+            .as_ref()
+            .and_then(|font_manifest| font_manifest.license()),
+        files: font_manifest
+            .as_ref()
+            .map(|font_manifest| font_manifest.filenames()),
+        missing,
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&family_info) {
+            Ok(family_info) => println!("{}", family_info),
+            Err(e) => error!("{}", msg!("error.serialize_info", error = e)),
+        }
+        return;
+    }
+
+    let unknown = format!("{color_bright_black}unknown{color_reset}");
+    println!(
+        "{color_blue}{style_bold}{}{color_reset}{style_reset}",
+        family_info.family
+    );
+    if let Some(family) = family_info.catalog {
+        println!("  Category:      {}", family.category);
+        println!("  Designers:     {}", family.designers.join(", "));
+        println!("  Variants:      {}", family.variants.join(", "));
+        println!(
+            "  Popularity:    {}",
+            family
+                .popularity
+                .map_or(unknown.clone(), |rank| format!("#{}", rank))
+        );
+        println!(
+            "  Added:         {}",
+            family.date_added.as_deref().unwrap_or(&unknown)
+        );
+        println!(
+            "  Last modified: {}",
+            family.last_modified.as_deref().unwrap_or(&unknown)
+        );
+    }
+    println!(
+        "  License:       {}",
+        family_info.license.unwrap_or(&unknown)
+    );
+    if let Some(files) = &family_info.files {
+        println!("  Files:");
+        for filename in files {
+            println!("    {color_bright_black}-{color_reset} {}", filename);
+        }
+    }
+    for missing in &family_info.missing {
+        println!("{color_yellow}NOTE:{color_reset} {}", missing);
+    }
+}
@@ -0,0 +1,349 @@
+use fonty::color::*;
+use std::{path::Path, time::Duration};
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv, Platform, default_cache_dir},
+    catalog::{Catalog, filter_by_category},
+    cli::Cli,
+    csv::CsvWriter,
+    error,
+    exit_codes::EXIT_CODE_INVALID_ARGUMENTS,
+    fail,
+    format::{format_date, format_size},
+    msg,
+    options::Options,
+    output::page_output,
+    registry::{InstalledSortOrder, Registry, sort_installed},
+    system_integration::{scan_font_dir, standard_font_dirs},
+    table::Table,
+    warnings::Warnings,
+};
+
+use super::{parse_count_flag, print_layout_duplicates};
+
+/// Describes how long ago something happened, roughly, e.g. `3 days`.
+fn format_age(age: Duration) -> String {
+    let hours = age.as_secs() / (60 * 60);
+    match hours {
+        0 => "less than an hour".to_string(),
+        1 => "1 hour".to_string(),
+        2..24 => format!("{} hours", hours),
+        24..48 => "1 day".to_string(),
+        _ => format!("{} days", hours / 24),
+    }
+}
+
+/// The formats a list can be printed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ListFormat {
+    /// Aligned columns, the default.
+    Table,
+    /// Only the names, one per line, for piping into other commands.
+    Plain,
+    Json,
+    Csv,
+}
+
+/// Checks the `--format` a list is printed in. `text` is the old name of `table`, and `--json` is the same as `--format json`.
+fn list_format(cli: &Cli, json: bool) -> ListFormat {
+    let format = match cli.flag_value("--format") {
+        None | Some("table" | "text") => ListFormat::Table,
+        Some("plain") => ListFormat::Plain,
+        Some("json") => ListFormat::Json,
+        Some("csv") => ListFormat::Csv,
+        Some(format) => fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!(
+                "error.invalid_choice",
+                value = format,
+                flag = "--format",
+                choices = "'table', 'plain', 'json', 'csv'"
+            )
+        ),
+    };
+    if json { ListFormat::Json } else { format }
+}
+
+/// Lists the families installed in the font base dir, as known from the registry.
+/// With `--tag`, only the families with that tag are listed, and with `--sort` they are sorted (otherwise in the order of the registry).
+pub fn list_installed(cli: &Cli, base_path: &BasePath, json: bool) {
+    let format = list_format(cli, json);
+    let sort_order: Option<InstalledSortOrder> = match cli.flag_value("--sort") {
+        None => None,
+        Some(sort) => match sort.parse() {
+            Ok(sort_order) => Some(sort_order),
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_choice",
+                    value = sort,
+                    flag = "--sort",
+                    choices = "'name', 'size', 'date'"
+                )
+            ),
+        },
+    };
+
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    let mut families = match cli.flag_value("--tag") {
+        Some(tag) => registry.tagged(tag),
+        None => registry.families.iter().collect(),
+    };
+    if let Some(sort_order) = sort_order {
+        sort_installed(&mut families, sort_order);
+    }
+
+    match format {
+        ListFormat::Json => {
+            match serde_json::to_string_pretty(&families) {
+                Ok(families) => println!("{}", families),
+                Err(e) => error!("{}", msg!("error.serialize_installed", error = e)),
+            }
+            return;
+        }
+        ListFormat::Csv => {
+            let mut csv =
+                CsvWriter::new(&["name", "version", "files", "bytes", "tags", "installed_at"]);
+            for family in families {
+                csv.row(&[
+                    family.name.as_str(),
+                    family.version.as_deref().unwrap_or(""),
+                    &family.files.len().to_string(),
+                    &family.bytes().to_string(),
+                    &family.tags.join(";"),
+                    &family
+                        .installed_at
+                        .map_or(String::new(), |installed_at| installed_at.to_string()),
+                ]);
+            }
+            print!("{}", csv.finish());
+            return;
+        }
+        ListFormat::Plain => {
+            for family in families {
+                println!("{}", family.name);
+            }
+            return;
+        }
+        ListFormat::Table => {}
+    }
+
+    if families.is_empty() {
+        match cli.flag_value("--tag") {
+            Some(tag) => println!("No fonts are tagged {color_blue}'{}'{color_reset}.", tag),
+            None => println!(
+                "No fonts are installed in {color_blue}'{}'{color_reset}.",
+                base_path.path.display()
+            ),
+        }
+        return;
+    }
+    let mut table = Table::new(&["Name", "Version", "Files", "Size", "Installed", "Tags"])
+        .align_right(2)
+        .align_right(3);
+    for family in families {
+        table.row(&[
+            family.name.clone(),
+            family.version.clone().unwrap_or_default(),
+            family.files.len().to_string(),
+            format_size(family.bytes()),
+            family.installed_at.map_or(String::new(), format_date),
+            family.tags.join(", "),
+        ]);
+    }
+    print!("{}", table.render());
+    print_layout_duplicates(&registry.layout_duplicates(&base_path.path));
+}
+
+/// Lists the fonts in the font base dir and in the standard font dirs of the platform, saying which ones fonty installed.
+/// The others were installed some other way, fonty never changes them.
+pub fn list_all(cli: &Cli, env: &BasePathEnv, base_path: &BasePath, json: bool) {
+    if cli.has_flag("--sort") || cli.has_flag("--tag") {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.sort_with_all")
+        );
+    }
+    let format = list_format(cli, json);
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    let is_managed = |path: &Path| {
+        path.strip_prefix(&base_path.path)
+            .is_ok_and(|relative_path| {
+                registry.families.iter().any(|family| {
+                    family
+                        .files
+                        .iter()
+                        .any(|file| file.path.starts_with(relative_path))
+                })
+            })
+    };
+
+    // The font base dir is usually inside a standard dir (like ~/.local/share/fonts/Google), it is listed on its own.
+    let mut entries = scan_font_dir(&base_path.path, is_managed);
+    for dir in standard_font_dirs(env, Platform::current()) {
+        if dir == base_path.path {
+            continue;
+        }
+        entries.extend(
+            scan_font_dir(&dir, is_managed)
+                .into_iter()
+                .filter(|entry| !base_path.path.starts_with(&entry.path)),
+        );
+    }
+
+    match format {
+        ListFormat::Json => {
+            match serde_json::to_string_pretty(&entries) {
+                Ok(entries) => println!("{}", entries),
+                Err(e) => error!("{}", msg!("error.serialize_installed", error = e)),
+            }
+            return;
+        }
+        ListFormat::Csv => {
+            let mut csv = CsvWriter::new(&["name", "path", "font_files", "bytes", "managed"]);
+            for entry in &entries {
+                csv.row(&[
+                    entry.name.as_str(),
+                    &entry.path.to_string_lossy(),
+                    &entry.font_files.to_string(),
+                    &entry.bytes.to_string(),
+                    &entry.managed.to_string(),
+                ]);
+            }
+            print!("{}", csv.finish());
+            return;
+        }
+        ListFormat::Plain => {
+            for entry in &entries {
+                println!("{}", entry.name);
+            }
+            return;
+        }
+        ListFormat::Table => {}
+    }
+
+    if entries.is_empty() {
+        println!("No fonts are installed in any font dir.");
+        return;
+    }
+    let mut table = Table::new(&["Name", "Fonts", "Size", "Managed", "Dir"])
+        .align_right(1)
+        .align_right(2);
+    for entry in &entries {
+        table.row(&[
+            entry.name.clone(),
+            entry.font_files.to_string(),
+            format_size(entry.bytes),
+            if entry.managed { "fonty" } else { "foreign" }.to_string(),
+            entry
+                .path
+                .parent()
+                .map_or(String::new(), |dir| dir.display().to_string()),
+        ]);
+    }
+    print!("{}", table.render());
+}
+
+/// Lists the families in the Google Fonts catalog, a page at a time with `--limit` and `--offset`.
+pub fn list_remote(cli: &Cli, options: &Options, env: &BasePathEnv, json: bool) {
+    // Check the flags before downloading the catalog.
+    let format = list_format(cli, json);
+    if cli.flag_value("--sort").is_some() {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.sort_with_remote")
+        );
+    }
+    let limit = parse_count_flag(cli, "--limit");
+    let offset = parse_count_flag(cli, "--offset").unwrap_or(0);
+
+    let cache_dir = default_cache_dir(env, Platform::current());
+    let catalog = match Catalog::load(cache_dir.as_deref(), options) {
+        Ok(catalog) => catalog,
+        Err(e) => fail!(e.exit_code(), "{}", msg!("error.catalog_failed", error = e)),
+    };
+    let mut families = catalog.families.iter().collect::<Vec<_>>();
+
+    if let Some(category) = cli.flag_value("--category") {
+        families = match filter_by_category(&catalog, families, category) {
+            Ok(families) => families,
+            Err(categories) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.unknown_category",
+                    category = category,
+                    categories = categories.join(&format!("'{color_bright_black}, {color_blue}'"))
+                )
+            ),
+        };
+    }
+
+    let total = families.len();
+    let page = families
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+
+    match format {
+        ListFormat::Json => {
+            match serde_json::to_string_pretty(&page) {
+                Ok(families) => println!("{}", families),
+                Err(e) => error!("{}", msg!("error.serialize_catalog", error = e)),
+            }
+            return;
+        }
+        ListFormat::Csv => {
+            let mut csv = CsvWriter::new(&["name", "category", "popularity"]);
+            for family in &page {
+                csv.row(&[
+                    family.family.as_str(),
+                    family.category.as_str(),
+                    &family
+                        .popularity
+                        .map_or(String::new(), |rank| rank.to_string()),
+                ]);
+            }
+            print!("{}", csv.finish());
+            return;
+        }
+        ListFormat::Plain => {
+            for family in &page {
+                println!("{}", family.family);
+            }
+            return;
+        }
+        ListFormat::Table => {}
+    }
+
+    let mut output = String::new();
+    if !page.is_empty() {
+        let mut table = Table::new(&["Name", "Category", "Popularity"]).align_right(2);
+        for family in &page {
+            table.row(&[
+                family.family.as_str(),
+                family.category.as_str(),
+                &family
+                    .popularity
+                    .map_or(String::new(), |rank| format!("#{}", rank)),
+            ]);
+        }
+        output.push_str(&table.render());
+    }
+    let shown = match page.len() {
+        0 => "No fonts".to_string(),
+        _ if page.len() == total => format!("{} fonts", total),
+        count => format!("Fonts {}-{} of {}", offset + 1, offset + count, total),
+    };
+    output.push_str(&format!(
+        "{color_bright_black}{}, from the catalog fetched {} ago.{color_reset}\n",
+        shown,
+        format_age(catalog.age())
+    ));
+    page_output(&output);
+}
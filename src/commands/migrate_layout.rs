@@ -0,0 +1,84 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::BasePath, cli::Cli, exit_codes::EXIT_CODE_FILE_IO_ERROR, fail, format::n_files,
+    info, msg, msg_n, options::Options, registry::Registry, system_integration::refresh_font_cache,
+    warnings::Warnings,
+};
+
+use super::selected_families;
+
+/// Moves the files of the families installed together with others into their own dirs (like single-font fetches install them),
+/// and removes the stale copies left by earlier fetches. With `--dry-run`, only shows what would be moved and removed.
+pub fn migrate_layout(cli: &Cli, options: &Options, base_path: &BasePath) {
+    let mut registry = Registry::load(&base_path.path, &mut Warnings::default());
+    let font_names = match selected_families(cli, &registry) {
+        font_names if !font_names.is_empty() || !cli.args.is_empty() || cli.has_flag("--tag") => {
+            font_names
+        }
+        _ => registry
+            .families
+            .iter()
+            .map(|family| family.name.clone())
+            .collect(),
+    };
+
+    let dry_run = cli.has_flag("--dry-run");
+    let (mut moved, mut removed, mut failed) = (0, 0, 0);
+    for font_name in &font_names {
+        let migration = registry.migrate_layout(font_name, &base_path.path, dry_run);
+        for (from, to) in &migration.moved {
+            info!(
+                "{} {color_blue}'{}'{color_reset} to {color_blue}'{}'{color_reset}",
+                if dry_run { "Would move" } else { "Moved" },
+                from.display(),
+                to.display()
+            );
+        }
+        for path in &migration.removed {
+            info!(
+                "{} the stale copy {color_blue}'{}'{color_reset}",
+                if dry_run { "Would remove" } else { "Removed" },
+                path.display()
+            );
+        }
+        for (path, e) in &migration.failed {
+            println!(
+                "{color_yellow}WARN:{color_reset} Failed to migrate {color_blue}'{}'{color_reset} of {}! Error: {}",
+                path.display(),
+                font_name,
+                e
+            );
+        }
+        moved += migration.moved.len();
+        removed += migration.removed.len();
+        failed += migration.failed.len();
+    }
+    if dry_run {
+        info!(
+            "Would move {} and remove {}.",
+            n_files(moved),
+            n_files(removed)
+        );
+        return;
+    }
+
+    if let Err(e) = registry.save(&base_path.path) {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.update_registry", error = e)
+        );
+    }
+    if moved > 0 || removed > 0 {
+        refresh_font_cache(&base_path.path, options, &mut Warnings::default());
+    }
+    if failed > 0 {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg_n!("error.migrate_failed", failed)
+        );
+    }
+    info!("Moved {} and removed {}.", n_files(moved), n_files(removed));
+}
@@ -0,0 +1,375 @@
+//! The commands of fonty, one module each. `main.rs` only sets up what all of them share and picks the command to run.
+
+pub mod cache;
+pub mod clean;
+pub mod config;
+pub mod diff;
+pub mod export;
+pub mod fetch;
+pub mod fontconfig;
+pub mod gallery;
+pub mod info;
+pub mod list;
+pub mod migrate_layout;
+pub mod nix_prefetch;
+pub mod noto;
+pub mod remove;
+pub mod repair;
+pub mod report;
+pub mod rollback;
+pub mod search;
+pub mod self_update;
+pub mod shell_env;
+pub mod sync;
+pub mod tag;
+pub mod top;
+pub mod webkit;
+
+use fonty::color::*;
+use std::{collections::HashSet, fs, io, path::Path};
+
+use fonty::{
+    base_path::BasePath,
+    cli::Cli,
+    download_cache::DownloadCache,
+    error,
+    error::FontyError,
+    exit_codes::{
+        EXIT_CODE_ABORTED, EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS,
+        EXIT_CODE_INVALID_FONT_NAME, EXIT_CODE_NET_ERROR, EXIT_CODE_NOT_CACHED,
+        EXIT_CODE_UNSAFE_PATH, EXIT_CODE_UNSAFE_URL,
+    },
+    fail,
+    family_spec::FamilySpec,
+    font_manifest::{FontManifest, create_dir, write_file_to_disk},
+    font_names::{PreparedFontNames, family_dir_name, normalize_font_name, prepare_font_names},
+    format::n_files,
+    http::{families_from_url, is_font_url},
+    info,
+    lock::{BaseDirLock, LockError, lock_base_dir},
+    msg, msg_n,
+    notify::exit,
+    options::Options,
+    permission_hints::print_permission_hints,
+    prompt::{Prompt, PromptError},
+    registry::{LayoutDuplicate, Registry},
+    worker_pool::run_parallel,
+};
+
+/// Puts the files of a manifest where the installed files of the family are, for updating it,
+/// so families installed on their own stay in their own directory.
+pub fn in_installed_layout(
+    font_manifest: FontManifest,
+    font_name: &str,
+    registry: &Registry,
+) -> FontManifest {
+    let family_dir = family_dir_name(font_name);
+    match registry.find(font_name) {
+        Some(family)
+            if family
+                .files
+                .iter()
+                .all(|file| file.path.starts_with(&family_dir)) =>
+        {
+            font_manifest.prepend_path_to_files(&family_dir)
+        }
+        _ => font_manifest,
+    }
+}
+
+/// Prepares the font names given on the command line (see `prepare_font_names`), exiting if one is empty.
+pub fn prepare_font_names_or_exit(args: &[String]) -> PreparedFontNames {
+    match prepare_font_names(args) {
+        Ok(font_names) => font_names,
+        Err(e) => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}!", e),
+    }
+}
+
+/// Replaces the Google Fonts URLs given on the command line (e.g. pasted specimen pages) with the families in them.
+/// Exits if a URL is not one that fonty knows how to read a family from.
+pub fn expand_font_urls(args: &[String]) -> Vec<String> {
+    let mut font_names = Vec::new();
+    for arg in args {
+        if !is_font_url(arg) {
+            font_names.push(arg.clone());
+            continue;
+        }
+        match families_from_url(arg) {
+            Ok(families) => {
+                info!(
+                    "Fetching {} from {color_blue}'{}'{color_reset}.",
+                    families
+                        .iter()
+                        .map(|family| format!("{color_blue}'{}'{color_reset}", family))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    arg.trim()
+                );
+                font_names.extend(families);
+            }
+            Err(e) => fail!(EXIT_CODE_INVALID_FONT_NAME, "{}!", e),
+        }
+    }
+    font_names
+}
+
+/// Parses the families given on the command line, which may ask for specific faces like for the CSS API (`Inter:wght@400;700`).
+/// Exits pointing at the offending part of an invalid spec.
+pub fn parse_family_specs(args: &[String]) -> Vec<FamilySpec> {
+    args.iter()
+        .map(|arg| match FamilySpec::parse(arg) {
+            Ok(spec) => spec,
+            Err(e) => {
+                error!("{}", msg!("error.invalid_font", font = arg, error = e));
+                println!("  {}\n  {color_red}{}{color_reset}", arg, e.marker(arg));
+                exit(EXIT_CODE_INVALID_FONT_NAME);
+            }
+        })
+        .collect()
+}
+
+/// Asks a yes/no question (defaulting to no), unless `--yes` is given. Exits with `refusal` if there is no terminal to ask on,
+/// and with `declined` if the answer is no.
+pub fn confirm_or_exit(cli: &Cli, question: &str, refusal: &str, declined: &str) {
+    match Prompt::stdio(cli.has_flag("--yes")).confirm(question, false) {
+        Ok(true) => {}
+        Ok(false) => fail!(EXIT_CODE_ABORTED, "{}", declined),
+        Err(e @ PromptError::NotInteractive) => fail!(e.exit_code(), "{}", refusal),
+        Err(e) => fail!(e.exit_code(), "{}", e),
+    }
+}
+
+/// Lists the files that couldn't be fetched (by URL, with the reason), once every download is done, and exits.
+/// With `--offline`, they weren't cached.
+pub fn exit_on_fetch_failures(failures: &[(String, String)], options: &Options) -> ! {
+    println!(
+        "{color_red}ERROR:{color_reset} {}",
+        msg_n!("error.fetch_failed", failures.len())
+    );
+    for (url, reason) in failures {
+        println!("  {color_blue}'{}'{color_reset}: {}", url, reason);
+    }
+    exit(if options.offline {
+        EXIT_CODE_NOT_CACHED
+    } else {
+        EXIT_CODE_NET_ERROR
+    });
+}
+
+/// Writes a file that the command can't do without (like the stylesheet of a web font kit), exiting if that fails.
+pub fn write_file_or_exit(path: &Path, contents: &[u8], options: &Options) {
+    if let Err(e) = write_file_to_disk(path, contents, options) {
+        println!("{color_red}ERROR:{color_reset} {}", e);
+        print_permission_hints(path, e.kind());
+        exit(EXIT_CODE_FILE_IO_ERROR);
+    }
+}
+
+/// Creates a directory the command needs (like the font base dir), exiting if that fails.
+pub fn create_dir_or_exit(dir: &Path, options: &Options) {
+    if let Err(e) = create_dir(dir, options) {
+        println!("{color_red}ERROR:{color_reset} {}", e);
+        print_permission_hints(dir, e.kind());
+        exit(EXIT_CODE_FILE_IO_ERROR);
+    }
+}
+
+/// Writes the raw response body of a failed request to the path given with `--dump-response`, for bug reports.
+pub fn dump_response(cli: &Cli, error: &FontyError) {
+    let (Some(path), Some(body)) = (cli.flag_value("--dump-response"), error.response_body())
+    else {
+        return;
+    };
+
+    match fs::write(path, body) {
+        Ok(_) => println!(
+            "{color_yellow}HINT:{color_reset} The full response was written to {color_blue}'{}'{color_reset}, please attach it to the bug report.",
+            path
+        ),
+        Err(e) => println!(
+            "{color_yellow}WARN:{color_reset} Failed to write the response to {color_blue}'{}'{color_reset}! Error:\n{}",
+            path, e
+        ),
+    }
+}
+
+/// The cache for font manifests and downloaded files, inside the cache dir.
+pub fn download_cache(cache_dir: Option<&Path>, options: &Options) -> Option<DownloadCache> {
+    cache_dir.map(|cache_dir| DownloadCache::new(cache_dir, options.manifest_cache.as_deref()))
+}
+
+/// Locks the font base dir for changing it, see `lock_base_dir`. Exits if that fails.
+pub fn lock_base_dir_or_exit(base_path: &BasePath, options: &Options) -> Option<BaseDirLock> {
+    match lock_base_dir(&base_path.path, options.lock_timeout) {
+        Ok(lock) => lock,
+        Err(e) => {
+            println!("{color_red}ERROR:{color_reset} {}", e);
+            if let LockError::Io { error, .. } = &e
+                && error.kind() == io::ErrorKind::PermissionDenied
+                && base_path.is_system()
+            {
+                println!(
+                    "{color_yellow}HINT:{color_reset} System-wide installs usually need root, try re-running with {color_blue}sudo{color_reset}."
+                );
+            }
+            exit(e.exit_code());
+        }
+    }
+}
+
+/// Parses a number given with a flag, e.g. `--limit`.
+pub fn parse_count_flag(cli: &Cli, flag: &str) -> Option<usize> {
+    let value = cli.flag_value(flag)?;
+    match value.parse() {
+        Ok(count) => Some(count),
+        Err(_) => fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.invalid_number", value = value, flag = flag)
+        ),
+    }
+}
+
+/// Warns about font files that are installed twice, in the dir of their family and directly in the font base dir.
+pub fn print_layout_duplicates(duplicates: &[LayoutDuplicate]) {
+    if duplicates.is_empty() {
+        return;
+    }
+    println!(
+        "{color_yellow}WARN:{color_reset} {} installed twice, in the dir of their font and directly in the font base dir, so they show up twice in font pickers:",
+        n_files(duplicates.len())
+    );
+    for duplicate in duplicates {
+        println!(
+            "  {color_blue}'{}'{color_reset} and {color_blue}'{}'{color_reset} {color_bright_black}({}){color_reset}",
+            duplicate.path.display(),
+            duplicate.stale_path.display(),
+            duplicate.family
+        );
+    }
+    println!(
+        "{color_yellow}HINT:{color_reset} Run {color_blue}fonty migrate-layout{color_reset} to move every font into its own dir, and remove the copies left from earlier fetches."
+    );
+}
+
+/// Checks a tag given on the command line, so tags stay easy to type.
+pub fn check_tag(tag: &str) {
+    if tag.is_empty()
+        || tag
+            .chars()
+            .any(|character| character.is_whitespace() || character == ',')
+    {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.invalid_tag", tag = tag)
+        );
+    }
+}
+
+/// Finds the fonts that are not on Google Fonts, in the order they were given.
+/// The fonts are checked `options.jobs` at a time, as each check is a request. Can't be used with `--offline`.
+pub fn find_invalid_fonts(font_names: &[String], options: &Options) -> Vec<String> {
+    let valid = run_parallel(font_names, options.jobs, |_, font_name| {
+        FontManifest::check_if_valid_font(font_name)
+    });
+    font_names
+        .iter()
+        .zip(valid)
+        .filter(|(_, valid)| !valid)
+        .map(|(font_name, _)| font_name.clone())
+        .collect()
+}
+
+/// Fails listing the fonts that are not on Google Fonts, if any, see `find_invalid_fonts`.
+pub fn check_valid_fonts(font_names: &[String], options: &Options) {
+    fail_if_invalid(&find_invalid_fonts(font_names, options));
+}
+
+/// Fails listing the invalid fonts, if there are any.
+pub fn fail_if_invalid(invalid_fonts: &[String]) {
+    if !invalid_fonts.is_empty() {
+        fail!(
+            EXIT_CODE_INVALID_FONT_NAME,
+            "{}",
+            msg!(
+                "error.invalid_fonts",
+                fonts = invalid_fonts.join(&format!("'{color_bright_black}, {color_blue}'"))
+            )
+        );
+    }
+}
+
+/// Fails listing the fonts that are not installed, if any.
+pub fn check_installed(registry: &Registry, font_names: &[String]) {
+    let not_installed = font_names
+        .iter()
+        .filter(|font_name| registry.find(font_name).is_none())
+        .map(|font_name| font_name.as_str())
+        .collect::<Vec<_>>();
+    if !not_installed.is_empty() {
+        fail!(
+            EXIT_CODE_INVALID_FONT_NAME,
+            "{}",
+            msg!(
+                "error.not_installed",
+                fonts = not_installed.join(&format!("'{color_bright_black}, {color_blue}'"))
+            )
+        );
+    }
+}
+
+/// The installed families given on the command line, plus the ones with a tag given with `--tag`.
+pub fn selected_families(cli: &Cli, registry: &Registry) -> Vec<String> {
+    let font_names = cli
+        .args
+        .iter()
+        .map(|font_name| normalize_font_name(font_name))
+        .collect::<Vec<_>>();
+    check_installed(registry, &font_names);
+
+    let mut selected = font_names
+        .iter()
+        .filter_map(|font_name| registry.find(font_name))
+        .collect::<Vec<_>>();
+    for tag in cli.flag_values("--tag") {
+        selected.extend(registry.tagged(tag));
+    }
+    let mut seen = HashSet::new();
+    selected
+        .into_iter()
+        .filter(|family| seen.insert(family.name.to_lowercase()))
+        .map(|family| family.name.clone())
+        .collect()
+}
+
+/// Makes sure a font manifest only writes inside the font base dir and only downloads from allowed hosts, exiting otherwise.
+pub fn validate_font_manifest(cli: &Cli, font_manifest: &FontManifest, base_path: &Path) {
+    // The filenames come straight from the downloaded manifest, so make sure none of them escape the base path.
+    if let Err(errors) = font_manifest.validate_paths(base_path) {
+        println!(
+            "{color_red}ERROR:{color_reset} {}",
+            msg!("error.unsafe_manifest_paths")
+        );
+        for error in errors {
+            println!("  {color_blue}{}{color_reset}", error);
+        }
+        exit(EXIT_CODE_UNSAFE_PATH);
+    }
+
+    validate_font_manifest_urls(cli, font_manifest);
+}
+
+/// Makes sure a font manifest only downloads from allowed hosts, exiting otherwise.
+pub fn validate_font_manifest_urls(cli: &Cli, font_manifest: &FontManifest) {
+    if let Err(errors) = font_manifest.validate_urls(&cli.flag_values("--allow-host")) {
+        println!(
+            "{color_red}ERROR:{color_reset} {}",
+            msg!("error.unsafe_manifest_urls")
+        );
+        for (url, reason) in errors {
+            println!("  {color_blue}'{}'{color_reset}: {}", url, reason);
+        }
+        exit(EXIT_CODE_UNSAFE_URL);
+    }
+}
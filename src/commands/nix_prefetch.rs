@@ -0,0 +1,103 @@
+use fonty::{
+    base_path::{BasePathEnv, Platform, default_cache_dir},
+    cli::Cli,
+    error,
+    exit_codes::{
+        EXIT_CODE_EMPTY_FONT_MANIFEST, EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS,
+        EXIT_CODE_INVALID_FONT_NAME,
+    },
+    fail,
+    font_manifest::FontManifest,
+    msg,
+    nix::{NixFetch, NixFormat, render_nix_fetches},
+    notify::exit,
+    options::Options,
+    output::set_quiet,
+};
+
+use super::{
+    check_valid_fonts, download_cache, dump_response, exit_on_fetch_failures,
+    prepare_font_names_or_exit, validate_font_manifest_urls,
+};
+
+/// Prints the URL and SHA-256 of every file of the families given on the command line, for fetching them with Nix.
+/// Nothing is installed, the files are only downloaded (once, they are cached) to hash them.
+pub fn nix_prefetch(cli: &Cli, options: &Options, env: &BasePathEnv) {
+    let format = match cli.flag_value("--format") {
+        None => NixFormat::Nix,
+        Some(format) => match format.parse() {
+            Ok(format) => format,
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_choice",
+                    value = format,
+                    flag = "--format",
+                    choices = "'nix', 'json'"
+                )
+            ),
+        },
+    };
+    let (font_names, _) = prepare_font_names_or_exit(&cli.args);
+    if font_names.is_empty() {
+        fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("fetch.no_fonts"));
+    }
+    // The expression has to be the only thing on stdout, so it can be redirected into a file.
+    set_quiet(true);
+
+    let download_cache = download_cache(
+        default_cache_dir(env, Platform::current()).as_deref(),
+        options,
+    );
+    if !options.offline {
+        check_valid_fonts(&font_names, options);
+    }
+    let mut fetches = Vec::new();
+    let mut embedded_files = Vec::new();
+    for font_name in &font_names {
+        let font_manifest = match FontManifest::load(font_name, options, download_cache.as_ref()) {
+            Ok(font_manifest) => font_manifest,
+            Err(e) => {
+                error!(
+                    "{}",
+                    msg!("error.manifest_failed", font = font_name, error = e)
+                );
+                dump_response(cli, &e);
+                exit(e.exit_code());
+            }
+        };
+        if font_manifest.is_empty() {
+            fail!(
+                EXIT_CODE_EMPTY_FONT_MANIFEST,
+                "{}",
+                msg!("error.no_files", font = font_name)
+            );
+        }
+        validate_font_manifest_urls(cli, &font_manifest);
+
+        let hashed_file_refs = font_manifest
+            .hash_file_refs(options, download_cache.as_ref())
+            .unwrap_or_else(|failures| exit_on_fetch_failures(&failures, options));
+        fetches.extend(hashed_file_refs.iter().map(NixFetch::from));
+        embedded_files.extend(
+            font_manifest
+                .embedded_filenames()
+                .into_iter()
+                .map(|filename| format!("{} ({})", filename, font_name)),
+        );
+    }
+
+    let embedded_files = embedded_files
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    match render_nix_fetches(&fetches, &embedded_files, format) {
+        Ok(output) => print!("{}", output),
+        Err(e) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.render_files", error = e)
+        ),
+    }
+}
@@ -0,0 +1,88 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::{BasePathEnv, Platform, default_cache_dir},
+    catalog::Catalog,
+    cli::Cli,
+    exit_codes::EXIT_CODE_INVALID_ARGUMENTS,
+    fail,
+    format::format_size,
+    msg,
+    notify::exit,
+    noto::{NOTO_COVERAGE, resolve_coverage},
+};
+
+/// Lists the coverage keywords of `noto`, e.g. when none are given.
+fn print_noto_coverage() {
+    let keyword_width = NOTO_COVERAGE
+        .iter()
+        .map(|coverage| coverage.keyword.len())
+        .max()
+        .unwrap_or(0);
+    println!("{color_blue}{style_bold}Coverage keywords:{color_reset}{style_reset}");
+    for coverage in NOTO_COVERAGE {
+        println!(
+            "  {color_blue}{:<keyword_width$}{color_reset}  {} {color_bright_black}({}){color_reset}",
+            coverage.keyword, coverage.description, coverage.family
+        );
+    }
+}
+
+/// Resolves the `--coverage` of `noto` to the Noto families to fetch, and prints them with their estimated sizes.
+/// Families missing from the cached catalog are warned about, as the table may be out of date.
+pub fn noto(cli: &Cli, env: &BasePathEnv, json: bool) -> Vec<String> {
+    let Some(keywords) = cli.flag_value("--coverage") else {
+        print_noto_coverage();
+        exit(0);
+    };
+    let families = match resolve_coverage(keywords) {
+        Ok(families) if families.is_empty() => {
+            print_noto_coverage();
+            fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.no_coverage"));
+        }
+        Ok(families) => families,
+        Err(e) => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", e),
+    };
+
+    // The JSON report has to be the only thing on stdout.
+    if !json {
+        let family_width = families
+            .iter()
+            .map(|family| family.family.len())
+            .max()
+            .unwrap_or(0);
+        println!("{color_blue}{style_bold}Noto families:{color_reset}{style_reset}");
+        for family in &families {
+            println!(
+                "  {color_blue}{:<family_width$}{color_reset}  ~{} {color_bright_black}({}){color_reset}",
+                family.family,
+                format_size(family.estimated_size),
+                family.keywords.join(", ")
+            );
+        }
+        println!(
+            "  {color_bright_black}~{} in total (estimated){color_reset}",
+            format_size(families.iter().map(|family| family.estimated_size).sum())
+        );
+    }
+    let catalog = Catalog::load_cached(default_cache_dir(env, Platform::current()).as_deref());
+    if let Some(catalog) = catalog {
+        for family in &families {
+            if !catalog
+                .families
+                .iter()
+                .any(|catalog_family| catalog_family.family == family.family)
+            {
+                println!(
+                    "{color_yellow}WARN:{color_reset} {color_blue}'{}'{color_reset} is not in the cached catalog of Google Fonts, the coverage table may be out of date!",
+                    family.family
+                );
+            }
+        }
+    }
+
+    families
+        .into_iter()
+        .map(|family| family.family.to_string())
+        .collect()
+}
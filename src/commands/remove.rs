@@ -0,0 +1,144 @@
+use fonty::color::*;
+use std::{collections::HashMap, ffi::OsString};
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv},
+    cli::Cli,
+    exit_codes::{EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_FONT_NAME},
+    fail,
+    format::n_files,
+    info, msg, msg_n,
+    options::Options,
+    registry::Registry,
+    system_integration::refresh_font_cache,
+    warnings::Warnings,
+};
+
+use super::{confirm_or_exit, selected_families};
+
+/// Prints the families that are about to be removed, and asks for confirmation unless `--yes` is given.
+/// Exits if the user does not confirm, or can't be asked.
+pub fn confirm_removal(cli: &Cli, registry: &Registry, font_names: &[String], to_trash: bool) {
+    let heading = if to_trash {
+        msg!("remove.will_trash")
+    } else {
+        msg!("remove.will_delete")
+    };
+    println!("{color_yellow}WARN:{color_reset} {}", heading);
+    for font_name in font_names {
+        let file_count = registry
+            .find(font_name)
+            .map_or(0, |family| family.files.len());
+        println!(
+            "  {color_blue}'{}'{color_reset} {color_bright_black}({}){color_reset}",
+            font_name,
+            n_files(file_count)
+        );
+    }
+    if cli.has_flag("--dry-run") {
+        return;
+    }
+    let question = if to_trash {
+        msg!("remove.confirm_trash")
+    } else {
+        msg!("remove.confirm_delete")
+    };
+    confirm_or_exit(
+        cli,
+        &question,
+        &format!(
+            "Refusing to remove fonts without confirmation! Pass {color_blue}--yes{color_reset} to remove them."
+        ),
+        &msg!("remove.aborted"),
+    );
+}
+
+/// Checks if there is a trash to move files to: the recycle bin on Windows, `~/.Trash` on macOS, and the freedesktop.org
+/// trash elsewhere, which only desktop sessions have (not e.g. root over SSH, or a container).
+fn has_trash(env: &HashMap<String, OsString>) -> bool {
+    let is_set = |name: &str| env.get(name).is_some_and(|value| !value.is_empty());
+    if cfg!(any(windows, target_os = "macos")) {
+        return true;
+    }
+    is_set("HOME")
+        && ["DISPLAY", "WAYLAND_DISPLAY", "XDG_CURRENT_DESKTOP"]
+            .iter()
+            .any(|name| is_set(name))
+}
+
+/// Checks if removed fonts go to the trash, which they do unless `--permanent` is given or there is no trash.
+/// Without a trash, says that they are deleted permanently.
+pub fn removal_to_trash(cli: &Cli, env: &BasePathEnv) -> bool {
+    if cli.has_flag("--permanent") {
+        return false;
+    }
+    let to_trash = has_trash(&env.vars);
+    if !to_trash {
+        println!(
+            "{color_yellow}WARN:{color_reset} There is no trash to move the fonts to (e.g. without a desktop session), so they will be deleted permanently."
+        );
+    }
+    to_trash
+}
+
+/// Removes the families with `Registry::remove_families`, warning about the files that can't be removed, and saves the registry.
+/// Afterwards the font cache is refreshed, so the fonts disappear from font pickers too.
+pub fn remove_families(
+    options: &Options,
+    base_path: &BasePath,
+    registry: &mut Registry,
+    font_names: &[String],
+    to_trash: bool,
+) {
+    let failed = registry.remove_families(&base_path.path, font_names, to_trash);
+    for (path, e) in failed.iter().flat_map(|(_, errors)| errors) {
+        println!(
+            "{color_yellow}WARN:{color_reset} Failed to remove {color_blue}'{}'{color_reset}! Error:\n{}",
+            path.display(),
+            e
+        );
+    }
+    if let Err(e) = registry.save(&base_path.path) {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.update_registry", error = e)
+        );
+    }
+
+    refresh_font_cache(&base_path.path, options, &mut Warnings::default());
+    if !failed.is_empty() {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!(
+                "error.remove_failed",
+                fonts = failed
+                    .iter()
+                    .map(|(font_name, _)| font_name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(&format!("'{color_bright_black}, {color_blue}'"))
+            )
+        );
+    }
+    if to_trash {
+        info!("{}", msg_n!("remove.trashed", font_names.len()));
+    } else {
+        info!("{}", msg_n!("remove.deleted", font_names.len()));
+    }
+}
+
+/// Uninstalls the families given on the command line (or tagged with `--tag`), deleting the files the registry lists for them.
+pub fn remove(cli: &Cli, options: &Options, env: &BasePathEnv, base_path: &BasePath) {
+    let mut registry = Registry::load(&base_path.path, &mut Warnings::default());
+    let font_names = selected_families(cli, &registry);
+    if font_names.is_empty() {
+        fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("fetch.no_fonts"));
+    }
+
+    let to_trash = removal_to_trash(cli, env);
+    confirm_removal(cli, &registry, &font_names, to_trash);
+    if !cli.has_flag("--dry-run") {
+        remove_families(options, base_path, &mut registry, &font_names, to_trash);
+    }
+}
@@ -0,0 +1,145 @@
+use fonty::color::*;
+use std::collections::HashSet;
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv, Platform, default_cache_dir},
+    cli::Cli,
+    exit_codes::EXIT_CODE_DAMAGED_FILES,
+    fail,
+    font_manifest::{FileStatus, FontManifest},
+    format::{n_files, n_fonts},
+    info, msg,
+    notify::exit,
+    options::Options,
+    registry::{FileProblem, Registry},
+    system_integration::refresh_font_cache,
+    warnings::Warnings,
+};
+
+use super::{download_cache, print_layout_duplicates, selected_families, validate_font_manifest};
+
+/// Checks the files of installed families (the ones given on the command line or tagged with `--tag`, or else all of them)
+/// against the registry, and downloads the missing or damaged ones again. With `--check`, they are only reported.
+pub fn repair(cli: &Cli, options: &Options, env: &BasePathEnv, base_path: &BasePath) {
+    let mut registry = Registry::load(&base_path.path, &mut Warnings::default());
+    let font_names = match selected_families(cli, &registry) {
+        font_names if !font_names.is_empty() || !cli.args.is_empty() || cli.has_flag("--tag") => {
+            font_names
+        }
+        _ => registry
+            .families
+            .iter()
+            .map(|family| family.name.clone())
+            .collect(),
+    };
+
+    print_layout_duplicates(
+        &registry
+            .layout_duplicates(&base_path.path)
+            .into_iter()
+            .filter(|duplicate| {
+                font_names
+                    .iter()
+                    .any(|font_name| font_name.eq_ignore_ascii_case(&duplicate.family))
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    let damaged = font_names
+        .iter()
+        .filter_map(|font_name| {
+            let family = registry.find(font_name)?;
+            let problems = family
+                .files
+                .iter()
+                .filter_map(|file| Some((file.path.clone(), file.check(&base_path.path)?)))
+                .collect::<Vec<_>>();
+            (!problems.is_empty()).then(|| (font_name.clone(), problems))
+        })
+        .collect::<Vec<_>>();
+    if damaged.is_empty() {
+        info!("All files of {} are intact.", n_fonts(font_names.len()));
+        return;
+    }
+
+    println!("{color_yellow}WARN:{color_reset} The following files are missing or damaged:");
+    for (path, problem) in damaged.iter().flat_map(|(_, problems)| problems) {
+        let problem = match problem {
+            FileProblem::Missing => "missing".to_string(),
+            FileProblem::SizeMismatch { expected, found } => {
+                format!("{} bytes instead of {}", found, expected)
+            }
+        };
+        println!(
+            "  {color_blue}'{}'{color_reset}: {}",
+            path.display(),
+            problem
+        );
+    }
+    if cli.has_flag("--check") {
+        exit(EXIT_CODE_DAMAGED_FILES);
+    }
+
+    let download_cache = download_cache(
+        default_cache_dir(env, Platform::current()).as_deref(),
+        options,
+    );
+    let mut repaired = 0;
+    for (font_name, problems) in &damaged {
+        // The manifest is fetched again, as the URLs in an old one may not work anymore.
+        let mut font_manifest =
+            match FontManifest::load(font_name, options, download_cache.as_ref()) {
+                Ok(font_manifest) => font_manifest,
+                Err(e) => fail!(
+                    e.exit_code(),
+                    "{}",
+                    msg!("error.manifest_failed", font = font_name, error = e)
+                ),
+            };
+        font_manifest.set_family(font_name);
+
+        let paths = problems
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        let (font_manifest, unmatched) = font_manifest.for_installed_paths(&paths);
+        for path in unmatched {
+            println!(
+                "{color_yellow}WARN:{color_reset} {color_blue}'{}'{color_reset} is not in the font manifest of {color_blue}'{}'{color_reset} anymore, it can't be repaired. Try {color_blue}fonty update{color_reset} instead.",
+                path.display(),
+                font_name
+            );
+        }
+        validate_font_manifest(cli, &font_manifest, &base_path.path);
+
+        let no_skip = HashSet::new();
+        let text_file_results = font_manifest.write_files(&base_path.path, options, &no_skip);
+        let font_file_results = font_manifest.fetch_files_from_refs(
+            &base_path.path,
+            options,
+            &no_skip,
+            download_cache.as_ref(),
+        );
+        for result in text_file_results
+            .iter()
+            .chain(&font_file_results)
+            .filter(|result| result.status == FileStatus::Written)
+        {
+            let path = result
+                .path
+                .strip_prefix(&base_path.path)
+                .unwrap_or(&result.path);
+            registry.update_file(font_name, path, result.bytes);
+            repaired += 1;
+        }
+    }
+
+    if let Err(e) = registry.save(&base_path.path) {
+        println!(
+            "{color_yellow}WARN:{color_reset} Failed to update the registry of installed fonts! Error:\n{}",
+            e
+        );
+    }
+    refresh_font_cache(&base_path.path, options, &mut Warnings::default());
+    info!("Repaired {}.", n_files(repaired));
+}
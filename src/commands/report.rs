@@ -0,0 +1,52 @@
+use fonty::{
+    base_path::BasePath,
+    cli::Cli,
+    exit_codes::{EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS},
+    fail, msg,
+    registry::Registry,
+    report::{ReportFormat, build_report, render_report},
+    warnings::Warnings,
+};
+
+/// Prints an inventory of the installed families (versions, licenses, files and sizes), e.g. for a release's SBOM.
+pub fn report(cli: &Cli, base_path: &BasePath) {
+    let format = match cli.flag_value("--format") {
+        None => ReportFormat::Json,
+        Some(format) => match format.parse() {
+            Ok(format) => format,
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_choice",
+                    value = format,
+                    flag = "--format",
+                    choices = "'json', 'csv', 'markdown'"
+                )
+            ),
+        },
+    };
+
+    let with_licenses = cli.has_flag("--with-licenses");
+    if with_licenses && format != ReportFormat::Markdown {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.licenses_without_markdown")
+        );
+    }
+
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    match render_report(
+        &build_report(&registry, &base_path.path),
+        format,
+        with_licenses,
+    ) {
+        Ok(report) => print!("{}", report),
+        Err(e) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.render_report", error = e)
+        ),
+    }
+}
@@ -0,0 +1,106 @@
+use fonty::color::*;
+use std::{fs, io};
+
+use fonty::{
+    backup::{backup_sets, restore_backup},
+    base_path::BasePath,
+    cli::Cli,
+    exit_codes::{
+        EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME,
+    },
+    fail,
+    format::{format_date, n_files},
+    info, msg,
+    options::Options,
+    registry::{Registry, RegistryEntry, RegistryFile},
+    system_integration::refresh_font_cache,
+    warnings::Warnings,
+};
+
+/// Restores the files of a family from its most recent backup set, and the registry entry it had then.
+/// Files installed since then that are not in the backup are deleted, so the family is as it was.
+pub fn rollback(cli: &Cli, options: &Options, base_path: &BasePath) {
+    let [font_name] = cli.args.as_slice() else {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.rollback_one_font")
+        );
+    };
+    let mut registry = Registry::load(&base_path.path, &mut Warnings::default());
+    // The backups are named after the family as it was requested, which the registry has.
+    let font_name = registry
+        .find(font_name)
+        .map_or(font_name.clone(), |family| family.name.clone());
+    let Some(set) = backup_sets(&base_path.path, &font_name).pop() else {
+        fail!(
+            EXIT_CODE_INVALID_FONT_NAME,
+            "{}",
+            msg!("error.no_backups", font = font_name)
+        );
+    };
+    let entry = set.entry();
+
+    let files = match restore_backup(&base_path.path, &set) {
+        Ok(files) => files,
+        Err(e) => fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.restore_backup", path = set.path.display(), error = e)
+        ),
+    };
+    if let Some(family) = registry.find(&font_name) {
+        for file in &family.files {
+            let kept = files.contains(&file.path)
+                || entry
+                    .as_ref()
+                    .is_some_and(|entry| entry.files.iter().any(|old| old.path == file.path));
+            if !kept
+                && let Err(e) = fs::remove_file(base_path.path.join(&file.path))
+                && e.kind() != io::ErrorKind::NotFound
+            {
+                println!(
+                    "{color_yellow}WARN:{color_reset} Failed to remove {color_blue}'{}'{color_reset}! Error:\n{}",
+                    file.path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    // A family that was not in the registry when it was backed up gets an entry with only its files.
+    registry.insert(entry.unwrap_or_else(|| {
+        RegistryEntry {
+            name: font_name.clone(),
+            installed_at: None,
+            fonty_version: None,
+            files: files
+                .iter()
+                .map(|file| RegistryFile {
+                    path: file.clone(),
+                    bytes: fs::metadata(base_path.path.join(file))
+                        .map_or(0, |metadata| metadata.len()),
+                    original_filename: None,
+                })
+                .collect(),
+            version: None,
+            options: None,
+            tags: Vec::new(),
+        }
+    }));
+    if let Err(e) = registry.save(&base_path.path) {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.update_registry", error = e)
+        );
+    }
+
+    refresh_font_cache(&base_path.path, options, &mut Warnings::default());
+    info!(
+        "Rolled back {color_blue}'{}'{color_reset}, restored {} from {}.",
+        font_name,
+        n_files(files.len()),
+        format_date((set.created_at / 1000) as u64)
+    );
+}
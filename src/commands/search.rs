@@ -0,0 +1,159 @@
+use fonty::color::*;
+use regex::RegexBuilder;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use fonty::{
+    base_path::{BasePathEnv, Platform, default_cache_dir},
+    catalog::{Catalog, CatalogFamily, SortOrder, filter_by_category, sort_families},
+    cli::Cli,
+    error,
+    exit_codes::EXIT_CODE_INVALID_ARGUMENTS,
+    fail, msg,
+    options::Options,
+};
+
+use super::parse_count_flag;
+
+/// Searches the catalog for the query given on the command line, and prints the matching families.
+pub fn search(cli: &Cli, options: &Options, env: &BasePathEnv, json: bool) {
+    let query = match cli.args.as_slice() {
+        // Listing a whole category does not need a query.
+        [] if cli.has_flag("--category") => String::new(),
+        [] => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.no_query")),
+        args => args.join(" "),
+    };
+
+    // Check the flags before downloading the catalog.
+    let sort_order: Option<SortOrder> = match cli.flag_value("--sort") {
+        None => None,
+        Some(sort) => match sort.parse() {
+            Ok(sort_order) => Some(sort_order),
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_choice",
+                    value = sort,
+                    flag = "--sort",
+                    choices = "'name', 'popularity', 'recent'"
+                )
+            ),
+        },
+    };
+
+    if cli.has_flag("--regex") && cli.has_flag("--fuzzy") {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.regex_and_fuzzy")
+        );
+    }
+    let regex = cli.has_flag("--regex").then(|| {
+        match RegexBuilder::new(&query).case_insensitive(true).build() {
+            Ok(regex) => regex,
+            Err(e) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!("error.invalid_pattern", pattern = query, error = e)
+            ),
+        }
+    });
+    let fuzzy_limit = parse_count_flag(cli, "--limit").unwrap_or(FUZZY_SEARCH_LIMIT);
+
+    let cache_dir = default_cache_dir(env, Platform::current());
+    let catalog = match Catalog::load(cache_dir.as_deref(), options) {
+        Ok(catalog) => catalog,
+        Err(e) => fail!(e.exit_code(), "{}", msg!("error.catalog_failed", error = e)),
+    };
+    // Fuzzy results are ranked, the score of each family is shown next to it.
+    let mut scores = HashMap::new();
+    let mut families = if let Some(regex) = &regex {
+        catalog.search_regex(regex)
+    } else if cli.has_flag("--fuzzy") {
+        catalog
+            .search_fuzzy(&query, FUZZY_SEARCH_MIN_SCORE)
+            .into_iter()
+            .take(fuzzy_limit)
+            .map(|(family, score)| {
+                scores.insert(family.family.as_str(), score);
+                family
+            })
+            .collect()
+    } else {
+        catalog.search(&query)
+    };
+
+    if let Some(category) = cli.flag_value("--category") {
+        families = match filter_by_category(&catalog, families, category) {
+            Ok(families) => families,
+            Err(categories) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.unknown_category",
+                    category = category,
+                    categories = categories.join(&format!("'{color_bright_black}, {color_blue}'"))
+                )
+            ),
+        };
+    }
+
+    if let Some(sort_order) = sort_order {
+        sort_families(&mut families, sort_order);
+    }
+
+    if json {
+        let results = if scores.is_empty() {
+            serde_json::to_string_pretty(&families)
+        } else {
+            serde_json::to_string_pretty(
+                &families
+                    .iter()
+                    .map(|family| ScoredFamily {
+                        family,
+                        score: scores[family.family.as_str()],
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        };
+        match results {
+            Ok(results) => println!("{}", results),
+            Err(e) => error!("{}", msg!("error.serialize_search", error = e)),
+        }
+        return;
+    }
+
+    if families.is_empty() {
+        println!("No fonts match {color_blue}'{}'{color_reset}.", query);
+        return;
+    }
+    for family in families {
+        match scores.get(family.family.as_str()) {
+            Some(score) => println!(
+                "  {color_blue}{}{color_reset} {color_bright_black}({}){color_reset} {color_white}{:.0}%{color_reset}",
+                family.family,
+                family.category,
+                score * 100.0
+            ),
+            None => println!(
+                "  {color_blue}{}{color_reset} {color_bright_black}({}){color_reset}",
+                family.family, family.category
+            ),
+        }
+    }
+}
+
+/// How many families `search --fuzzy` shows, unless `--limit` is given.
+const FUZZY_SEARCH_LIMIT: usize = 10;
+
+/// How similar a name has to be to the query to be shown by `search --fuzzy`.
+const FUZZY_SEARCH_MIN_SCORE: f64 = 0.5;
+
+/// A family found by `search --fuzzy`, with how similar its name is to the query, for `--json`.
+#[derive(Serialize)]
+struct ScoredFamily<'a> {
+    #[serde(flatten)]
+    family: &'a CatalogFamily,
+    score: f64,
+}
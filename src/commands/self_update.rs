@@ -0,0 +1,71 @@
+use fonty::{cli::Cli, exit_codes::EXIT_CODE_INVALID_ARGUMENTS, fail, msg, options::Options};
+
+/// Updates fonty itself to the latest release on GitHub, or with `--check` only says if there is a newer one.
+#[cfg(feature = "self-update")]
+pub fn self_update(cli: &Cli, options: &Options) {
+    use fonty::{
+        color::*,
+        info,
+        self_update::{download_release, is_newer, latest_release, replace_executable},
+    };
+
+    if options.offline {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.self_update_offline")
+        );
+    }
+    let current_version = env!("CARGO_PKG_VERSION");
+    let release = match latest_release() {
+        Ok(release) => release,
+        Err(e) => fail!(e.exit_code(), "{}", msg!("error.latest_release", error = e)),
+    };
+    if !is_newer(release.version(), current_version) {
+        info!(
+            "fonty {color_blue}{}{color_reset} is the latest version.",
+            current_version
+        );
+        return;
+    }
+    if cli.has_flag("--check") {
+        info!(
+            "fonty {color_blue}{}{color_reset} is available {color_bright_black}(installed: {}){color_reset}. Run {color_blue}fonty self-update{color_reset} to install it.",
+            release.version(),
+            current_version
+        );
+        return;
+    }
+
+    info!(
+        "Updating fonty {} {color_green}→{color_reset} {}...",
+        current_version,
+        release.version()
+    );
+    let binary = match download_release(&release) {
+        Ok(binary) => binary,
+        Err(e) => fail!(
+            e.exit_code(),
+            "{}",
+            msg!("error.download_release", error = e)
+        ),
+    };
+    match replace_executable(&binary) {
+        Ok(path) => info!(
+            "Updated {color_blue}'{}'{color_reset} to fonty {color_blue}{}{color_reset}.",
+            path.display(),
+            release.version()
+        ),
+        Err(e) => fail!(e.exit_code(), "{}", e),
+    }
+}
+
+/// Without the `self-update` feature, fonty is updated the way it was installed.
+#[cfg(not(feature = "self-update"))]
+pub fn self_update(_cli: &Cli, _options: &Options) {
+    fail!(
+        EXIT_CODE_INVALID_ARGUMENTS,
+        "{}",
+        msg!("error.self_update_disabled")
+    );
+}
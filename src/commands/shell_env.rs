@@ -0,0 +1,80 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::BasePath,
+    cli::Cli,
+    error,
+    exit_codes::EXIT_CODE_INVALID_ARGUMENTS,
+    fail, msg,
+    registry::Registry,
+    shell_env::{Shell, env_var_name, find_name_collisions, render_assignment},
+    warnings::Warnings,
+};
+
+use super::selected_families;
+
+/// Prints the shell commands setting a variable to the dir of every selected (or installed) family.
+pub fn shell_env(cli: &Cli, base_path: &BasePath) {
+    let shell = match cli.flag_value("--shell") {
+        None => Shell::Posix,
+        Some(shell) => match shell.parse() {
+            Ok(shell) => shell,
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_choice",
+                    value = shell,
+                    flag = "--shell",
+                    choices = "'posix', 'fish', 'powershell'"
+                )
+            ),
+        },
+    };
+
+    let registry = Registry::load_snapshot(&base_path.path, &mut Warnings::default());
+    let font_names = if cli.args.is_empty() && !cli.has_flag("--tag") {
+        registry
+            .families
+            .iter()
+            .map(|family| family.name.clone())
+            .collect()
+    } else {
+        selected_families(cli, &registry)
+    };
+
+    // Printing both would leave the variable pointing at whichever came last, without saying so.
+    let collisions =
+        find_name_collisions(&font_names.iter().map(String::as_str).collect::<Vec<_>>());
+    if !collisions.is_empty() {
+        for (name, families) in &collisions {
+            error!(
+                "{}",
+                msg!(
+                    "error.variable_collision",
+                    fonts = families.join(&format!("'{color_bright_black}, {color_blue}'")),
+                    name = name
+                )
+            );
+        }
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.select_variable_fonts")
+        );
+    }
+
+    for family in font_names
+        .iter()
+        .filter_map(|font_name| registry.find(font_name))
+    {
+        println!(
+            "{}",
+            render_assignment(
+                shell,
+                &env_var_name(&family.name),
+                &base_path.path.join(family.dir())
+            )
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use fonty::color::*;
+use std::{env, path::PathBuf};
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv, Platform, default_config_dir},
+    cli::Cli,
+    fail, info,
+    options::Options,
+    project_manifest::ProjectManifest,
+    registry::Registry,
+};
+
+use super::{
+    prepare_font_names_or_exit,
+    remove::{confirm_removal, removal_to_trash, remove_families},
+};
+
+/// Compares the installed families with a `fonty.toml`, pruning the ones not listed with `--prune`.
+/// Returns the listed families that still have to be installed.
+pub fn sync(
+    cli: &Cli,
+    options: &Options,
+    env: &BasePathEnv,
+    base_path: &BasePath,
+    registry: &mut Registry,
+) -> Vec<String> {
+    let current_dir = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let config_dir = default_config_dir(env, Platform::current());
+    let project_manifest = match ProjectManifest::find(&current_dir, config_dir.as_deref()) {
+        Ok(project_manifest) => project_manifest,
+        Err(e) => fail!(e.exit_code(), "{}", e),
+    };
+    let (listed, _) = prepare_font_names_or_exit(&project_manifest.fonts);
+    let is_listed = |name: &str| {
+        listed
+            .iter()
+            .any(|font_name| font_name.to_lowercase() == name.to_lowercase())
+    };
+
+    let added = listed
+        .iter()
+        .filter(|font_name| registry.find(font_name).is_none())
+        .cloned()
+        .collect::<Vec<_>>();
+    // Families found by scanning were not necessarily installed by fonty, so they are never pruned.
+    let removed = if cli.has_flag("--prune") {
+        registry
+            .families
+            .iter()
+            .filter(|family| family.fonty_version.is_some() && !is_listed(&family.name))
+            .map(|family| family.name.clone())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let unchanged = listed.len() - added.len();
+
+    info!(
+        "Syncing with {color_blue}'{}'{color_reset}: {} to add, {} to remove, {} unchanged.",
+        project_manifest.path.display(),
+        added.len(),
+        removed.len(),
+        unchanged
+    );
+
+    if !removed.is_empty() {
+        let to_trash = removal_to_trash(cli, env);
+        confirm_removal(cli, registry, &removed, to_trash);
+        if !cli.has_flag("--dry-run") {
+            remove_families(options, base_path, registry, &removed, to_trash);
+        }
+    }
+    added
+}
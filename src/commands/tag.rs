@@ -0,0 +1,81 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::BasePath,
+    cli::Cli,
+    exit_codes::{
+        EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME,
+    },
+    fail,
+    font_names::normalize_font_name,
+    format::n_fonts,
+    info, msg,
+    registry::Registry,
+    warnings::Warnings,
+};
+
+use super::{check_installed, check_tag};
+
+/// Adds installed families to a tag (`tag add <tag> <font...>`), or removes them from it (`tag remove <tag> [font...]`).
+/// Removing a tag without fonts removes it from every family. Tags never install or uninstall anything.
+pub fn tag(cli: &Cli, base_path: &BasePath) {
+    let (subcommand, tag, font_names) = match cli.args.as_slice() {
+        [subcommand, tag, font_names @ ..] => (subcommand.as_str(), tag, font_names),
+        _ => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.tag_usage")),
+    };
+    check_tag(tag);
+    let font_names = font_names
+        .iter()
+        .map(|font_name| normalize_font_name(font_name))
+        .collect::<Vec<_>>();
+
+    let mut registry = Registry::load(&base_path.path, &mut Warnings::default());
+    check_installed(&registry, &font_names);
+    match subcommand {
+        "add" => {
+            if font_names.is_empty() {
+                fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("fetch.no_fonts"));
+            }
+            for font_name in &font_names {
+                registry.add_tag(font_name, tag);
+            }
+            info!(
+                "Tagged {} with {color_blue}'{}'{color_reset}.",
+                n_fonts(font_names.len()),
+                tag
+            );
+        }
+        "remove" => {
+            let font_names = match font_names.as_slice() {
+                [] => registry
+                    .tagged(tag)
+                    .iter()
+                    .map(|family| family.name.clone())
+                    .collect(),
+                _ => font_names,
+            };
+            let removed = font_names
+                .iter()
+                .filter(|font_name| registry.remove_tag(font_name, tag))
+                .count();
+            info!(
+                "Removed the tag {color_blue}'{}'{color_reset} from {}.",
+                tag,
+                n_fonts(removed)
+            );
+        }
+        _ => fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.unknown_tag_command", command = subcommand)
+        ),
+    }
+
+    if let Err(e) = registry.save(&base_path.path) {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.update_registry", error = e)
+        );
+    }
+}
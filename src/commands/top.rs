@@ -0,0 +1,91 @@
+use fonty::color::*;
+
+use fonty::{
+    base_path::{BasePathEnv, Platform, default_cache_dir},
+    catalog::{Catalog, SortOrder, filter_by_category, sort_families},
+    cli::Cli,
+    error,
+    exit_codes::{EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_MANIFEST},
+    fail, msg,
+    options::Options,
+};
+
+/// Lists the most popular families in the catalog, 20 unless a number is given on the command line.
+pub fn top(cli: &Cli, options: &Options, env: &BasePathEnv, json: bool) {
+    // Check the arguments before downloading the catalog.
+    let count = match cli.args.as_slice() {
+        [] => 20,
+        [count] => match count.parse() {
+            Ok(count) => count,
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!("error.invalid_count", count = count)
+            ),
+        },
+        _ => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", msg!("error.one_count")),
+    };
+
+    let cache_dir = default_cache_dir(env, Platform::current());
+    let catalog = match Catalog::load(cache_dir.as_deref(), options) {
+        Ok(catalog) => catalog,
+        Err(e) => fail!(e.exit_code(), "{}", msg!("error.catalog_failed", error = e)),
+    };
+    // Without popularity ranks, any order would be made up.
+    if catalog
+        .families
+        .iter()
+        .all(|family| family.popularity.is_none())
+    {
+        fail!(
+            EXIT_CODE_INVALID_FONT_MANIFEST,
+            "{}",
+            msg!("error.no_popularity")
+        );
+    }
+
+    let mut families = catalog
+        .families
+        .iter()
+        .filter(|family| family.popularity.is_some())
+        .collect::<Vec<_>>();
+    if let Some(category) = cli.flag_value("--category") {
+        families = match filter_by_category(&catalog, families, category) {
+            Ok(families) => families,
+            Err(categories) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.unknown_category",
+                    category = category,
+                    categories = categories.join(&format!("'{color_bright_black}, {color_blue}'"))
+                )
+            ),
+        };
+    }
+    sort_families(&mut families, SortOrder::Popularity);
+    families.truncate(count);
+
+    if json {
+        match serde_json::to_string_pretty(&families) {
+            Ok(families) => println!("{}", families),
+            Err(e) => error!("{}", msg!("error.serialize_top", error = e)),
+        }
+        return;
+    }
+
+    for (rank, family) in families.iter().enumerate() {
+        println!(
+            "{:>4}. {color_blue}{}{color_reset} {color_bright_black}({}, {} {}){color_reset}",
+            rank + 1,
+            family.family,
+            family.category,
+            family.variants.len(),
+            if family.variants.len() == 1 {
+                "variant"
+            } else {
+                "variants"
+            }
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use fonty::color::*;
+use std::collections::HashMap;
+
+use fonty::{
+    base_path::{BasePath, BasePathEnv, BasePathSource, Platform, default_cache_dir},
+    catalog::Catalog,
+    cli::Cli,
+    exit_codes::{
+        EXIT_CODE_INVALID_ARGUMENTS, EXIT_CODE_INVALID_FONT_NAME, EXIT_CODE_NET_ERROR,
+        EXIT_CODE_UNSAFE_URL,
+    },
+    fail,
+    family_spec::{FamilySpec, face_selections},
+    fixtures,
+    font_manifest::{create_parent_dirs, fetch_cached, write_file_to_disk},
+    font_names::family_dir_name,
+    format::n_files,
+    http, info, msg,
+    options::Options,
+    webkit::{
+        FontDisplay, WEBKIT_PRELOAD_FILENAME, WEBKIT_STYLESHEET_FILENAME, css2_family_spec,
+        parse_font_faces, render_preload_links, rewrite_css,
+    },
+    worker_pool::run_parallel,
+};
+
+use super::{
+    download_cache, exit_on_fetch_failures, expand_font_urls, parse_family_specs,
+    prepare_font_names_or_exit, write_file_or_exit,
+};
+
+/// Builds a web font kit in the output dir with `fetch --webkit`: the WOFF2 files of the families from the CSS API,
+/// and a `fonts.css` pointing at them (through `--url-prefix`). With `--preload`, also a `preload-links.html` fragment.
+/// The kit is for a website, so the fonts are not registered as installed.
+pub fn webkit(cli: &Cli, options: &Options, env: &BasePathEnv, base_path: &BasePath) {
+    if !matches!(
+        base_path.source,
+        BasePathSource::OutputDirFlag | BasePathSource::OutputDirEnv
+    ) {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.webkit_no_output_dir")
+        );
+    }
+    if options.offline {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.webkit_offline")
+        );
+    }
+    let display = match cli.flag_value("--font-display") {
+        None => None,
+        Some(display) => match display.parse::<FontDisplay>() {
+            Ok(display) => Some(display),
+            Err(_) => fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!(
+                    "error.invalid_choice",
+                    value = display,
+                    flag = "--font-display",
+                    choices = "'swap', 'optional', 'block', 'fallback'"
+                )
+            ),
+        },
+    };
+    let url_prefix = cli.flag_value("--url-prefix").unwrap_or("");
+    let specs = parse_family_specs(&expand_font_urls(&cli.args));
+    let face_selections = face_selections(&specs);
+    let (font_names, _) = prepare_font_names_or_exit(
+        &specs
+            .into_iter()
+            .map(|spec| spec.family)
+            .collect::<Vec<_>>(),
+    );
+    if font_names.is_empty() {
+        fail!(EXIT_CODE_INVALID_FONT_NAME, "{}", msg!("fetch.no_fonts"));
+    }
+
+    // Without the variants from the catalog, the CSS API only gives the regular face of each family.
+    let cache_dir = default_cache_dir(env, Platform::current());
+    let catalog = Catalog::load(cache_dir.as_deref(), options)
+        .inspect_err(|e| {
+            println!(
+                "{color_yellow}WARN:{color_reset} Could not get the catalog, only the regular faces are fetched! {}",
+                e
+            )
+        })
+        .ok();
+
+    let mut stylesheet = String::new();
+    let mut downloads = Vec::new();
+    for font_name in &font_names {
+        let variants = catalog
+            .as_ref()
+            .and_then(|catalog| {
+                catalog
+                    .families
+                    .iter()
+                    .find(|family| family.family.eq_ignore_ascii_case(font_name))
+            })
+            .map_or(&[][..], |family| family.variants.as_slice());
+        // A family given with faces (like `Inter:wght@400;700`) gets just those.
+        let family_specs = match face_selections.get(&font_name.to_lowercase()) {
+            Some(specs) => specs.iter().map(FamilySpec::css2_spec).collect(),
+            None => vec![css2_family_spec(font_name, variants)],
+        };
+        let url = http::css2_url(&family_specs);
+        let css = match http::get_stylesheet(&url, options.user_agent.as_deref())
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => match response.text() {
+                Ok(css) => {
+                    fixtures::record_body(css.as_bytes());
+                    css
+                }
+                Err(e) => fail!(
+                    EXIT_CODE_NET_ERROR,
+                    "{}",
+                    msg!("error.stylesheet_failed", font = font_name, error = e)
+                ),
+            },
+            Err(e) => fail!(
+                EXIT_CODE_NET_ERROR,
+                "{}",
+                msg!("error.stylesheet_failed", font = font_name, error = e)
+            ),
+        };
+
+        let family_dir = family_dir_name(font_name);
+        for face in parse_font_faces(&css) {
+            for url in face.urls {
+                if let Err(reason) =
+                    http::validate_download_url(&url, &cli.flag_values("--allow-host"))
+                {
+                    fail!(
+                        EXIT_CODE_UNSAFE_URL,
+                        "{}",
+                        msg!(
+                            "error.stylesheet_unsafe_url",
+                            font = font_name,
+                            url = url,
+                            reason = reason
+                        )
+                    );
+                }
+                // Google names the files by a hash of their contents, so the names are unique.
+                let Some(filename) = url.rsplit('/').next().filter(|filename| {
+                    !filename.is_empty()
+                        && !filename.contains(['?', '#', '\\'])
+                        && filename != &".."
+                }) else {
+                    fail!(
+                        EXIT_CODE_UNSAFE_URL,
+                        "{}",
+                        msg!("error.unnamed_file", url = url)
+                    );
+                };
+                let path = format!("{}/{}", family_dir, filename);
+                if !downloads.iter().any(|(existing, _)| *existing == url) {
+                    downloads.push((url, path));
+                }
+            }
+        }
+        stylesheet.push_str(&format!("/* {} */\n{}", font_name, css));
+    }
+
+    info!(
+        "Will download {} for the web font kit.",
+        n_files(downloads.len())
+    );
+    if cli.has_flag("--dry-run") {
+        for (url, path) in &downloads {
+            println!(
+                "  {color_blue}'{}'{color_reset} {color_bright_black}({}){color_reset}",
+                path, url
+            );
+        }
+        return;
+    }
+    let total = downloads.len();
+    let download_cache = download_cache(cache_dir.as_deref(), options);
+    create_parent_dirs(
+        &downloads
+            .iter()
+            .map(|(_, path)| base_path.path.join(path))
+            .collect::<Vec<_>>(),
+        options,
+    );
+    let failures = run_parallel(&downloads, options.jobs, |index, (url, path)| {
+        let (file, from_cache) = match fetch_cached(url, path, options, download_cache.as_ref()) {
+            Ok(fetched) => fetched,
+            Err(e) => return Some((url.clone(), e.to_string())),
+        };
+        if let Err(e) = write_file_to_disk(&base_path.path.join(path), &file.bytes, options) {
+            return Some((url.clone(), e.to_string()));
+        }
+        info!(
+            "{} file {color_bright_yellow}{}{color_white}/{}{color_reset}: {color_blue}'{}' {color_bright_black}... {color_green}DONE!{color_reset}",
+            if from_cache {
+                "Copying cached"
+            } else {
+                "Downloading"
+            },
+            index + 1,
+            total,
+            path
+        );
+        None
+    })
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    if !failures.is_empty() {
+        exit_on_fetch_failures(&failures, options);
+    }
+
+    let urls = downloads
+        .into_iter()
+        .map(|(url, path)| {
+            let local_url = match url_prefix {
+                "" => path,
+                prefix => format!("{}/{}", prefix.trim_end_matches('/'), path),
+            };
+            (url, local_url)
+        })
+        .collect::<HashMap<_, _>>();
+    let stylesheet_path = base_path.path.join(WEBKIT_STYLESHEET_FILENAME);
+    write_file_or_exit(
+        &stylesheet_path,
+        rewrite_css(&stylesheet, &urls, display).as_bytes(),
+        options,
+    );
+    info!(
+        "Wrote {color_blue}'{}'{color_reset}.",
+        stylesheet_path.display()
+    );
+    if cli.has_flag("--preload") {
+        let preload_path = base_path.path.join(WEBKIT_PRELOAD_FILENAME);
+        let preload_links = render_preload_links(&parse_font_faces(&stylesheet), &urls);
+        write_file_or_exit(&preload_path, preload_links.as_bytes(), options);
+        info!(
+            "Wrote {color_blue}'{}'{color_reset}.",
+            preload_path.display()
+        );
+    }
+}
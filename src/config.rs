@@ -0,0 +1,207 @@
+use crate::color::*;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, fs, io, path::PathBuf};
+
+use crate::{
+    base_path::{BasePathEnv, BasePathError, Platform, default_config_dir, expand_path_vars},
+    cli::Cli,
+    exit_codes::EXIT_CODE_CONFIG_ERROR,
+    options::CONFIG_OPTION_KEYS,
+};
+
+/// The name of the config file, inside the config dir.
+pub const CONFIG_FILENAME: &str = "config.toml";
+
+/// The keys fonty knows about in the config file, besides the tuning options in `CONFIG_OPTION_KEYS`.
+/// Other keys are warned about, but ignored.
+const KNOWN_KEYS: &[&str] = &["base_path", "mirrors", "post_install", "notify"];
+
+/// The config file as it is written. Keys that aren't set get their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigFileContents {
+    base_path: Option<String>,
+    mirrors: Vec<String>,
+    post_install: Option<String>,
+    notify: bool,
+}
+
+/// An error while reading the config file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file given with `--config` or `$FONTY_CONFIG` does not exist.
+    Missing { path: PathBuf },
+    /// The file exists, but can't be read.
+    Read { path: PathBuf, error: String },
+    /// The file is not valid (for fonty).
+    Invalid { path: PathBuf, error: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing { path } => {
+                write!(f, "The config file '{}' does not exist!", path.display())
+            }
+            ConfigError::Read { path, error } => write!(
+                f,
+                "Failed to read the config file '{}'! Error:\n{}",
+                path.display(),
+                error
+            ),
+            ConfigError::Invalid { path, error } => write!(
+                f,
+                "Invalid config file '{}'! Error:\n{}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+impl ConfigError {
+    /// The exit code to exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        EXIT_CODE_CONFIG_ERROR
+    }
+}
+
+/// The config file to read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    /// Whether the file was given with `--config` or `$FONTY_CONFIG`, so it has to exist.
+    pub explicit: bool,
+}
+
+/// Finds the config file: the one given with `--config`, then the one in `$FONTY_CONFIG` (with `~` and `$VAR` expanded),
+/// and otherwise `config.toml` in the config dir. Returns `None` if there is no config dir either.
+pub fn resolve_config_file(
+    cli: &Cli,
+    env: &BasePathEnv,
+    platform: Platform,
+) -> Result<Option<ConfigFile>, BasePathError> {
+    if let Some(path) = cli.flag_value("--config") {
+        return Ok(Some(ConfigFile {
+            path: PathBuf::from(path),
+            explicit: true,
+        }));
+    }
+    if let Some(fonty_config) = &env.fonty_config {
+        // Values that aren't valid UTF-8 can't contain references, so they are used as is.
+        let path = match fonty_config.to_str() {
+            Some(value) => PathBuf::from(expand_path_vars(value, env)?),
+            None => PathBuf::from(fonty_config),
+        };
+        return Ok(Some(ConfigFile {
+            path,
+            explicit: true,
+        }));
+    }
+    Ok(
+        default_config_dir(env, platform).map(|config_dir| ConfigFile {
+            path: config_dir.join(CONFIG_FILENAME),
+            explicit: false,
+        }),
+    )
+}
+
+/// The settings from the user's config file, e.g.:
+///
+/// ```toml
+/// base_path = "~/fonts"
+/// mirrors = ["https://fonts-mirror.example.com"]
+/// jobs = 8
+/// post_install = "rsync -a {base_path}/ other-box:.local/share/fonts/Google/"
+/// ```
+///
+/// Command line flags and environment variables take precedence over it.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// The file the config was read from. `None` if there was none.
+    pub path: Option<PathBuf>,
+    /// The font base dir, as written (`~` and `$VAR` are expanded when the base path is resolved).
+    /// Flags and environment variables take precedence over it.
+    pub base_path: Option<String>,
+    /// The base URLs of mirrors to download font files from, tried in order before the original URL.
+    pub mirrors: Vec<String>,
+    /// A shell command to run after fonts were installed, see `expand_hook_command`.
+    pub post_install: Option<String>,
+    /// Whether to send a desktop notification when a long run ends, like `--notify`.
+    pub notify: bool,
+    /// The tuning options (see `CONFIG_OPTION_KEYS`) by their key, with their values as written, e.g. `jobs = 8`.
+    /// They are parsed and checked when the options are resolved.
+    pub options: HashMap<String, String>,
+}
+
+impl Config {
+    /// Reads the config file. A missing default config file (or no config dir) is the same as an empty one,
+    /// but a config file given explicitly has to exist.
+    pub fn load(config_file: Option<&ConfigFile>) -> Result<Self, ConfigError> {
+        let Some(ConfigFile { path, explicit }) = config_file.cloned() else {
+            return Ok(Self::default());
+        };
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && explicit => {
+                return Err(ConfigError::Missing { path });
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => {
+                return Err(ConfigError::Read {
+                    path,
+                    error: e.to_string(),
+                });
+            }
+        };
+        let invalid = |error: String| ConfigError::Invalid {
+            path: path.clone(),
+            error,
+        };
+
+        let table = contents
+            .parse::<toml::Table>()
+            .map_err(|e| invalid(e.to_string().trim_end().to_string()))?;
+        let mut options = HashMap::new();
+        for (key, value) in &table {
+            if !CONFIG_OPTION_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            // Options are parsed like the values of their flags, so numbers are taken as they are written.
+            let value = match value {
+                toml::Value::String(value) => value.clone(),
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                value => {
+                    return Err(invalid(format!(
+                        "'{}' has to be a string or a number, not {}.",
+                        key,
+                        value.type_str()
+                    )));
+                }
+            };
+            options.insert(key.clone(), value);
+        }
+        // Keys from newer versions of fonty (or typos) should not stop anything from working.
+        for unknown in table.keys().filter(|key| {
+            !KNOWN_KEYS.contains(&key.as_str()) && !CONFIG_OPTION_KEYS.contains(&key.as_str())
+        }) {
+            println!(
+                "{color_yellow}WARN:{color_reset} Unknown key {color_blue}'{}'{color_reset} in the config file {color_blue}'{}'{color_reset}, ignoring it.",
+                unknown,
+                path.display()
+            );
+        }
+        let contents = ConfigFileContents::deserialize(table)
+            .map_err(|e| invalid(e.to_string().trim_end().to_string()))?;
+
+        Ok(Self {
+            path: Some(path),
+            base_path: contents.base_path,
+            mirrors: contents.mirrors,
+            post_install: contents.post_install,
+            notify: contents.notify,
+            options,
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use std::borrow::Cow;
+
+/// Writes CSV as described in RFC 4180: a header row, then one row per record, every row ending with CRLF.
+/// Fields are quoted when they contain a comma, a quote or a line break, with quotes doubled.
+pub struct CsvWriter {
+    output: String,
+}
+
+impl CsvWriter {
+    /// Starts a CSV document with its header row.
+    pub fn new(header: &[&str]) -> Self {
+        let mut writer = Self {
+            output: String::new(),
+        };
+        writer.row(header);
+        writer
+    }
+
+    /// Adds a row. Every row should have as many fields as the header.
+    pub fn row<S: AsRef<str>>(&mut self, fields: &[S]) {
+        let fields = fields
+            .iter()
+            .map(|value| field(value.as_ref()))
+            .collect::<Vec<_>>();
+        self.output.push_str(&fields.join(","));
+        self.output.push_str("\r\n");
+    }
+
+    /// The CSV document.
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+/// Quotes a field if it needs to be.
+fn field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields_are_not_quoted() {
+        let mut writer = CsvWriter::new(&["family", "files"]);
+        writer.row(&["Inter", "18"]);
+        assert_eq!(writer.finish(), "family,files\r\nInter,18\r\n");
+    }
+
+    #[test]
+    fn fields_are_quoted_when_needed() {
+        let mut writer = CsvWriter::new(&["family", "license"]);
+        writer.row(&["Baloo 2, Regular", "OFL-1.1"]);
+        writer.row(&["Say \"Hi\"", "line one\nline two"]);
+        writer.row(&["Carriage", "a\rb"]);
+        assert_eq!(
+            writer.finish(),
+            "family,license\r\n\"Baloo 2, Regular\",OFL-1.1\r\n\"Say \"\"Hi\"\"\",\"line one\nline two\"\r\nCarriage,\"a\rb\"\r\n"
+        );
+    }
+
+    #[test]
+    fn empty_fields_are_kept() {
+        let mut writer = CsvWriter::new(&["a", "b", "c"]);
+        writer.row(&["", "x", ""]);
+        assert_eq!(writer.finish(), "a,b,c\r\n,x,\r\n");
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use crate::{http::encode_family_name, sha256::sha256_hex};
+
+/// How many files a part of the cache holds, their total size, and when the oldest and the newest of them were last written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub entries: u64,
+    pub bytes: u64,
+    pub oldest: Option<SystemTime>,
+    pub newest: Option<SystemTime>,
+}
+
+impl CacheUsage {
+    /// Counts a file into the usage.
+    pub fn add(&mut self, metadata: &fs::Metadata) {
+        self.entries += 1;
+        self.bytes += metadata.len();
+        if let Ok(modified) = metadata.modified() {
+            self.oldest = Some(self.oldest.map_or(modified, |oldest| oldest.min(modified)));
+            self.newest = Some(self.newest.map_or(modified, |newest| newest.max(modified)));
+        }
+    }
+}
+
+/// Lists the entries of a dir. A missing dir is an empty one, so the cache dir is only ever created when something is cached.
+fn read_dir(dir: &Path) -> io::Result<Vec<fs::DirEntry>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Adds up the files directly inside a dir.
+fn dir_usage(dir: &Path) -> io::Result<CacheUsage> {
+    let mut usage = CacheUsage::default();
+    for entry in read_dir(dir)? {
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            usage.add(&metadata);
+        }
+    }
+    Ok(usage)
+}
+
+/// Checks if a name is a SHA-256 in hex, as blobs are named. Anything else in the blob dir (like a temporary file) isn't a blob.
+fn is_hash(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// A cached downloaded file, for `cache gc`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlobEntry {
+    pub hash: String,
+    pub bytes: u64,
+    /// When the file was last downloaded or used from the cache.
+    pub last_used: SystemTime,
+}
+
+/// How much `cache gc` keeps of the downloaded files. Without a limit, nothing is evicted for it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GcPolicy {
+    /// How many bytes the files may take up in total.
+    pub max_size: Option<u64>,
+    /// How long ago a file may have been used last.
+    pub max_age: Option<Duration>,
+}
+
+/// Picks the files `cache gc` evicts: the ones not used within `max_age`, and then the least recently used ones
+/// until the rest fits into `max_size`. The least recently used file comes first.
+pub fn gc_victims<'a>(
+    entries: &'a [BlobEntry],
+    policy: &GcPolicy,
+    now: SystemTime,
+) -> Vec<&'a BlobEntry> {
+    let mut entries = entries.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.last_used.cmp(&b.last_used).then(a.hash.cmp(&b.hash)));
+
+    let mut remaining_bytes = entries.iter().map(|entry| entry.bytes).sum::<u64>();
+    let mut victims = Vec::new();
+    for entry in entries {
+        // A file used "in the future" (e.g. after the clock was set back) is as good as just used.
+        let age = now.duration_since(entry.last_used).unwrap_or_default();
+        let too_old = policy.max_age.is_some_and(|max_age| age > max_age);
+        let too_large = policy
+            .max_size
+            .is_some_and(|max_size| remaining_bytes > max_size);
+        if !too_old && !too_large {
+            // Everything after this was used more recently, and the rest fits.
+            break;
+        }
+        remaining_bytes -= entry.bytes;
+        victims.push(entry);
+    }
+    victims
+}
+
+/// Removes a dir with everything in it. A missing dir is already removed.
+fn remove_dir(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Where font manifests and downloaded files are cached, so later installs (also with `--offline`) don't need the network.
+/// Downloaded files are stored by the SHA-256 of their contents under `blobs/`, and looked up by the SHA-256 of their URL under `refs/`.
+pub struct DownloadCache {
+    cache_dir: PathBuf,
+    manifest_dir: PathBuf,
+    blob_dir: PathBuf,
+    ref_dir: PathBuf,
+}
+
+impl DownloadCache {
+    /// Uses the given cache dir. Manifests are stored in `manifest_dir` instead, if given (`--manifest-cache`).
+    pub fn new(cache_dir: &Path, manifest_dir: Option<&Path>) -> Self {
+        Self {
+            cache_dir: cache_dir.to_path_buf(),
+            manifest_dir: manifest_dir.map_or(cache_dir.join("manifests"), Path::to_path_buf),
+            blob_dir: cache_dir.join("blobs"),
+            ref_dir: cache_dir.join("refs"),
+        }
+    }
+
+    /// The path the manifest of a family is cached at.
+    fn manifest_path(&self, font_name: &str) -> PathBuf {
+        self.manifest_dir.join(format!(
+            "{}.json",
+            encode_family_name(&font_name.to_lowercase())
+        ))
+    }
+
+    /// Caches the (already validated) manifest payload of a family.
+    pub fn store_manifest(&self, font_name: &str, payload: &str) -> io::Result<()> {
+        write_atomically(&self.manifest_path(font_name), payload.as_bytes())
+    }
+
+    /// Gets the cached manifest payload of a family, if there is one.
+    pub fn load_manifest(&self, font_name: &str) -> Option<String> {
+        fs::read_to_string(self.manifest_path(font_name)).ok()
+    }
+
+    /// The path a ref (the hash of the contents downloaded from a URL) is stored at.
+    fn ref_path(&self, url: &str) -> PathBuf {
+        self.ref_dir.join(sha256_hex(url.as_bytes()))
+    }
+
+    /// Reads the ref of a URL: the hash of the contents on the first line, and the Content-Type they were served with
+    /// on the second. Refs cached by older versions only have the hash.
+    fn read_ref(&self, url: &str) -> Option<(String, Option<String>)> {
+        let contents = fs::read_to_string(self.ref_path(url)).ok()?;
+        let mut lines = contents.lines().map(str::trim);
+        let hash = lines.next().filter(|hash| !hash.is_empty())?.to_string();
+        let content_type = lines
+            .next()
+            .filter(|content_type| !content_type.is_empty())
+            .map(str::to_string);
+        Some((hash, content_type))
+    }
+
+    /// Caches a downloaded file, with the Content-Type it was served with.
+    pub fn store_blob(
+        &self,
+        url: &str,
+        contents: &[u8],
+        content_type: Option<&str>,
+    ) -> io::Result<()> {
+        let hash = sha256_hex(contents);
+        let blob_path = self.blob_dir.join(&hash);
+        if !blob_path.exists() {
+            write_atomically(&blob_path, contents)?;
+        }
+        let reference = match content_type {
+            // Headers can't contain line breaks, but a broken one must not break the ref.
+            Some(content_type) => format!("{}\n{}", hash, content_type.replace(['\r', '\n'], "")),
+            None => hash,
+        };
+        write_atomically(&self.ref_path(url), reference.as_bytes())
+    }
+
+    /// Checks if a file downloaded from a URL is cached, without reading it.
+    pub fn has_blob(&self, url: &str) -> bool {
+        self.read_ref(url)
+            .is_some_and(|(hash, _)| self.blob_dir.join(hash).exists())
+    }
+
+    /// Gets a cached file downloaded from a URL.
+    /// The contents are checked against their hash, so a corrupted cache entry counts as missing.
+    pub fn load_blob(&self, url: &str) -> Option<Vec<u8>> {
+        let (hash, _) = self.read_ref(url)?;
+        let blob_path = self.blob_dir.join(&hash);
+        let contents = fs::read(&blob_path).ok()?;
+        if sha256_hex(&contents) != hash {
+            return None;
+        }
+        // Using a file counts for `cache gc`, which evicts the least recently used files first. Access times can't be trusted
+        // (many filesystems are mounted with `noatime` or `relatime`), so the modification time is bumped instead.
+        let _ = fs::File::options()
+            .write(true)
+            .open(&blob_path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        Some(contents)
+    }
+
+    /// The Content-Type a cached file was served with, if it was cached with one.
+    pub fn content_type(&self, url: &str) -> Option<String> {
+        self.read_ref(url)?.1
+    }
+
+    /// How many downloaded files are cached, and their size.
+    pub fn blob_usage(&self) -> io::Result<CacheUsage> {
+        dir_usage(&self.blob_dir)
+    }
+
+    /// How many font manifests are cached, and their size.
+    pub fn manifest_usage(&self) -> io::Result<CacheUsage> {
+        dir_usage(&self.manifest_dir)
+    }
+
+    /// Removes every cached manifest.
+    pub fn clear_manifests(&self) -> io::Result<()> {
+        remove_dir(&self.manifest_dir)
+    }
+
+    /// Removes every cached downloaded file (and the refs to them).
+    pub fn clear_blobs(&self) -> io::Result<()> {
+        remove_dir(&self.ref_dir)?;
+        remove_dir(&self.blob_dir)
+    }
+
+    /// Lists the cached downloaded files, for `cache gc`. Only regular files named by their hash count,
+    /// symlinks and anything else in the blob dir are left alone.
+    pub fn blob_entries(&self) -> io::Result<Vec<BlobEntry>> {
+        let mut blobs = Vec::new();
+        for entry in read_dir(&self.blob_dir)? {
+            let Some(hash) = entry
+                .file_name()
+                .to_str()
+                .filter(|name| is_hash(name))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let metadata = fs::symlink_metadata(entry.path())?;
+            if !metadata.is_file() {
+                continue;
+            }
+            blobs.push(BlobEntry {
+                hash,
+                bytes: metadata.len(),
+                last_used: metadata.modified()?,
+            });
+        }
+        Ok(blobs)
+    }
+
+    /// Evicts cached downloaded files (see `gc_victims`), and then the refs to files that aren't cached anymore.
+    /// Only files inside the cache dir are ever removed: if the blob dir resolves to somewhere else (e.g. it was replaced
+    /// with a symlink), nothing is.
+    pub fn evict(&self, victims: &[&BlobEntry]) -> io::Result<CacheUsage> {
+        let mut evicted = CacheUsage::default();
+        if victims.is_empty() {
+            return Ok(evicted);
+        }
+        let cache_dir = fs::canonicalize(&self.cache_dir)?;
+        let blob_dir = fs::canonicalize(&self.blob_dir)?;
+        if blob_dir.parent() != Some(cache_dir.as_path()) {
+            return Err(io::Error::other(format!(
+                "'{}' is not inside the cache dir '{}'",
+                blob_dir.display(),
+                cache_dir.display()
+            )));
+        }
+
+        for victim in victims {
+            if !is_hash(&victim.hash) {
+                continue;
+            }
+            let blob_path = blob_dir.join(&victim.hash);
+            let Ok(metadata) = fs::symlink_metadata(&blob_path) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            match fs::remove_file(&blob_path) {
+                Ok(()) => evicted.add(&metadata),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        for entry in read_dir(&self.ref_dir)? {
+            let is_ref = entry.file_name().to_str().is_some_and(is_hash);
+            if !is_ref
+                || !fs::symlink_metadata(entry.path()).is_ok_and(|metadata| metadata.is_file())
+            {
+                continue;
+            }
+            let points_to_blob = fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|contents| contents.lines().next().map(|hash| hash.trim().to_string()))
+                .is_some_and(|hash| is_hash(&hash) && blob_dir.join(hash).is_file());
+            if !points_to_blob {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        Ok(evicted)
+    }
+}
+
+/// Counts temporary files, so files written at the same time (by several download workers) don't share a temporary path.
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes a file to a temporary path next to it first and then renames it, so readers never see half of it.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent_dir) = path.parent() {
+        fs::create_dir_all(parent_dir)?;
+    }
+    let temporary_path = path.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = fs::File::create(&temporary_path)?;
+    file.write_all(contents)?;
+    fs::rename(&temporary_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// A cache in a new temporary dir, which is removed again when the test is done.
+    struct TestCache {
+        dir: PathBuf,
+        cache: DownloadCache,
+    }
+
+    impl TestCache {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "fonty-download-cache-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            let cache = DownloadCache::new(&dir.join("cache"), None);
+            Self { dir, cache }
+        }
+
+        /// Caches a file of `bytes` bytes, which was last used `age` ago.
+        fn store(&self, url: &str, bytes: usize, age: Duration) -> String {
+            let contents = url.bytes().cycle().take(bytes).collect::<Vec<_>>();
+            self.cache.store_blob(url, &contents, None).unwrap();
+            let hash = sha256_hex(&contents);
+            fs::File::options()
+                .write(true)
+                .open(self.cache.blob_dir.join(&hash))
+                .and_then(|file| file.set_modified(SystemTime::now() - age))
+                .unwrap();
+            hash
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn entry(hash: &str, bytes: u64, last_used: SystemTime) -> BlobEntry {
+        BlobEntry {
+            hash: hash.to_string(),
+            bytes,
+            last_used,
+        }
+    }
+
+    fn hashes(victims: &[&BlobEntry]) -> Vec<String> {
+        victims.iter().map(|victim| victim.hash.clone()).collect()
+    }
+
+    #[test]
+    fn gc_evicts_the_least_recently_used_until_the_rest_fits() {
+        let now = SystemTime::UNIX_EPOCH + 1000 * DAY;
+        let entries = [
+            entry("new", 300, now - DAY),
+            entry("old", 300, now - 10 * DAY),
+            entry("middle", 300, now - 5 * DAY),
+        ];
+        let policy = |max_size, max_age| GcPolicy { max_size, max_age };
+
+        assert!(gc_victims(&entries, &policy(None, None), now).is_empty());
+        assert!(gc_victims(&entries, &policy(Some(900), None), now).is_empty());
+        assert_eq!(
+            hashes(&gc_victims(&entries, &policy(Some(600), None), now)),
+            ["old"]
+        );
+        assert_eq!(
+            hashes(&gc_victims(&entries, &policy(Some(599), None), now)),
+            ["old", "middle"]
+        );
+        assert_eq!(
+            hashes(&gc_victims(&entries, &policy(None, Some(4 * DAY)), now)),
+            ["old", "middle"]
+        );
+        // Both limits apply, whichever evicts more.
+        assert_eq!(
+            hashes(&gc_victims(&entries, &policy(Some(0), Some(7 * DAY)), now)),
+            ["old", "middle", "new"]
+        );
+        // Files used after `now` count as just used.
+        let future = [entry("future", 100, now + DAY)];
+        assert!(gc_victims(&future, &policy(None, Some(DAY)), now).is_empty());
+    }
+
+    #[test]
+    fn cache_dir_is_created_lazily() {
+        let test_cache = TestCache::new("lazy");
+        assert_eq!(
+            test_cache.cache.blob_usage().unwrap(),
+            CacheUsage::default()
+        );
+        assert!(test_cache.cache.blob_entries().unwrap().is_empty());
+        assert_eq!(test_cache.cache.evict(&[]).unwrap().entries, 0);
+        assert!(!test_cache.dir.exists());
+    }
+
+    #[test]
+    fn gc_over_a_cache_tree() {
+        let test_cache = TestCache::new("gc");
+        let old = test_cache.store("https://fonts.gstatic.com/old.ttf", 100, 100 * DAY);
+        let middle = test_cache.store("https://fonts.gstatic.com/middle.ttf", 100, 10 * DAY);
+        let new = test_cache.store("https://fonts.gstatic.com/new.ttf", 100, Duration::ZERO);
+        // Anything in the blob dir that isn't named by a hash is left alone.
+        fs::write(test_cache.cache.blob_dir.join("notes.txt"), "keep").unwrap();
+
+        let entries = test_cache.cache.blob_entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        let policy = GcPolicy {
+            max_size: Some(150),
+            max_age: Some(30 * DAY),
+        };
+        let victims = gc_victims(&entries, &policy, SystemTime::now());
+        assert_eq!(hashes(&victims), [old.clone(), middle.clone()]);
+        let evicted = test_cache.cache.evict(&victims).unwrap();
+        assert_eq!((evicted.entries, evicted.bytes), (2, 200));
+
+        assert!(
+            !test_cache
+                .cache
+                .has_blob("https://fonts.gstatic.com/old.ttf")
+        );
+        assert!(
+            !test_cache
+                .cache
+                .has_blob("https://fonts.gstatic.com/middle.ttf")
+        );
+        assert!(
+            test_cache
+                .cache
+                .has_blob("https://fonts.gstatic.com/new.ttf")
+        );
+        assert!(test_cache.cache.blob_dir.join("notes.txt").exists());
+        // The refs to evicted files are removed with them.
+        assert_eq!(read_dir(&test_cache.cache.ref_dir).unwrap().len(), 1);
+        assert_eq!(
+            hashes(
+                &test_cache
+                    .cache
+                    .blob_entries()
+                    .unwrap()
+                    .iter()
+                    .collect::<Vec<_>>()
+            ),
+            [new]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn gc_never_evicts_outside_the_cache_dir() {
+        let test_cache = TestCache::new("outside");
+        let outside = test_cache.dir.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&test_cache.cache.cache_dir).unwrap();
+        std::os::unix::fs::symlink(&outside, &test_cache.cache.blob_dir).unwrap();
+        let hash = sha256_hex(b"font");
+        fs::write(outside.join(&hash), "font").unwrap();
+
+        let victim = entry(&hash, 4, SystemTime::UNIX_EPOCH);
+        assert!(test_cache.cache.evict(&[&victim]).is_err());
+        assert!(outside.join(&hash).exists());
+
+        // Names that aren't hashes are never turned into paths.
+        fs::remove_file(&test_cache.cache.blob_dir).unwrap();
+        fs::create_dir_all(&test_cache.cache.blob_dir).unwrap();
+        let escape = entry("../outside/x", 0, SystemTime::UNIX_EPOCH);
+        assert_eq!(test_cache.cache.evict(&[&escape]).unwrap().entries, 0);
+    }
+}
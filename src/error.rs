@@ -0,0 +1,95 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+
+use crate::exit_codes::{
+    EXIT_CODE_INVALID_FONT_MANIFEST, EXIT_CODE_NET_ERROR, EXIT_CODE_NOT_CACHED,
+};
+
+/// How many characters of a response body to include in errors.
+const SNIPPET_CHARS: usize = 300;
+
+/// An error while talking to Google Fonts, with enough context to report it.
+#[derive(Debug)]
+pub enum FontyError {
+    /// A request could not be made, or its response could not be read.
+    Request { url: String, error: reqwest::Error },
+    /// The manifest Google Fonts returned could not be parsed.
+    InvalidManifest {
+        url: String,
+        status: StatusCode,
+        content_type: Option<String>,
+        /// The full raw body, e.g. for `--dump-response`.
+        body: String,
+        /// The body after stripping the anti-XSSI prefix, which is what failed to parse.
+        payload: String,
+        error: String,
+    },
+    /// The catalog of all families Google Fonts returned could not be parsed.
+    InvalidCatalog { url: String, error: String },
+    /// Something is needed with `--offline`, but it is not cached.
+    NotCached { what: String },
+}
+
+impl fmt::Display for FontyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontyError::Request { url, error } => {
+                write!(f, "Request to '{}' failed! Error:\n{}", url, error)
+            }
+            FontyError::InvalidCatalog { url, error } => {
+                write!(f, "Invalid catalog from '{}'! Error:\n{}", url, error)
+            }
+            FontyError::NotCached { what } => write!(
+                f,
+                "{} is not cached, so it can't be used with --offline! Run once without --offline to cache it.",
+                what
+            ),
+            FontyError::InvalidManifest {
+                url,
+                status,
+                content_type,
+                payload,
+                error,
+                ..
+            } => {
+                let mut snippet = payload.chars().take(SNIPPET_CHARS).collect::<String>();
+                if snippet.len() < payload.len() {
+                    snippet.push_str("...");
+                }
+                write!(
+                    f,
+                    "Invalid FontManifest from '{}' (status '{}', Content-Type '{}')! Error:\n{}\nStart of the response:\n{}",
+                    url,
+                    status,
+                    content_type.as_deref().unwrap_or("none"),
+                    error,
+                    snippet
+                )
+            }
+        }
+    }
+}
+
+impl FontyError {
+    /// The exit code to exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FontyError::Request { .. } => EXIT_CODE_NET_ERROR,
+            FontyError::NotCached { .. } => EXIT_CODE_NOT_CACHED,
+            FontyError::InvalidManifest { .. } | FontyError::InvalidCatalog { .. } => {
+                EXIT_CODE_INVALID_FONT_MANIFEST
+            }
+        }
+    }
+
+    /// The raw response body, if the error has one.
+    pub fn response_body(&self) -> Option<&str> {
+        match self {
+            FontyError::Request { .. }
+            | FontyError::InvalidCatalog { .. }
+            | FontyError::NotCached { .. } => None,
+            FontyError::InvalidManifest { body, .. } => Some(body),
+        }
+    }
+}
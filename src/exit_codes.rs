@@ -2,3 +2,78 @@ pub const EXIT_CODE_INVALID_FONT_NAME: i32 = 1;
 pub const EXIT_CODE_INVALID_FONT_MANIFEST: i32 = 2;
 pub const EXIT_CODE_FILE_IO_ERROR: i32 = 3;
 pub const EXIT_CODE_NET_ERROR: i32 = 4;
+pub const EXIT_CODE_INVALID_ARGUMENTS: i32 = 5;
+pub const EXIT_CODE_CONFIG_ERROR: i32 = 6;
+pub const EXIT_CODE_ABORTED: i32 = 7;
+pub const EXIT_CODE_UNSAFE_PATH: i32 = 8;
+pub const EXIT_CODE_UNSAFE_URL: i32 = 9;
+pub const EXIT_CODE_EMPTY_FONT_MANIFEST: i32 = 10;
+pub const EXIT_CODE_NOT_CACHED: i32 = 11;
+pub const EXIT_CODE_DAMAGED_FILES: i32 = 12;
+pub const EXIT_CODE_LOCKED: i32 = 13;
+pub const EXIT_CODE_PARTIAL_FAILURE: i32 = 14;
+pub const EXIT_CODE_WARNINGS: i32 = 15;
+pub const EXIT_CODE_DIFFERENCES: i32 = 16;
+
+/// Every exit code with a description, for the man page.
+pub const EXIT_CODES: &[(i32, &str)] = &[
+    (0, "Success."),
+    (
+        EXIT_CODE_INVALID_FONT_NAME,
+        "A font name is invalid, or no fonts were specified.",
+    ),
+    (
+        EXIT_CODE_INVALID_FONT_MANIFEST,
+        "The font manifest (or catalog) from Google Fonts is invalid.",
+    ),
+    (EXIT_CODE_FILE_IO_ERROR, "A file could not be written."),
+    (EXIT_CODE_NET_ERROR, "A network request failed."),
+    (
+        EXIT_CODE_INVALID_ARGUMENTS,
+        "The command line arguments are invalid.",
+    ),
+    (
+        EXIT_CODE_CONFIG_ERROR,
+        "The configuration (e.g. the font base dir) is invalid.",
+    ),
+    (
+        EXIT_CODE_ABORTED,
+        "The user declined to confirm (e.g. overwriting existing files), or could not be asked as stdin is not a terminal and --yes was not given.",
+    ),
+    (
+        EXIT_CODE_UNSAFE_PATH,
+        "The font manifest contains a file path outside the font base dir.",
+    ),
+    (
+        EXIT_CODE_UNSAFE_URL,
+        "The font manifest contains a download URL that is not allowed.",
+    ),
+    (
+        EXIT_CODE_EMPTY_FONT_MANIFEST,
+        "The font manifest from Google Fonts lists no files for a font.",
+    ),
+    (
+        EXIT_CODE_NOT_CACHED,
+        "Something needed with --offline is not cached.",
+    ),
+    (
+        EXIT_CODE_DAMAGED_FILES,
+        "repair --check found missing or damaged files.",
+    ),
+    (
+        EXIT_CODE_LOCKED,
+        "Another fonty process kept the font base dir locked for longer than --lock-timeout.",
+    ),
+    (
+        EXIT_CODE_PARTIAL_FAILURE,
+        "Only some of the fonts were installed, e.g. as invalid ones were skipped with --ignore-invalid, or a file does not exist (404).",
+    ),
+    (
+        EXIT_CODE_WARNINGS,
+        "The fonts were installed, but there were warnings, with --deny-warnings.",
+    ),
+    (
+        EXIT_CODE_DIFFERENCES,
+        "diff found files that an update would add, remove or change.",
+    ),
+];
@@ -0,0 +1,514 @@
+//! Families given in the syntax of the CSS API, like `Inter:wght@400;700` or `Lora:ital,wght@0,400;1,400`,
+//! as people know it from embedding fonts on websites. Plain family names are specs without any axes.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    font_face::FaceName,
+    http::{families_from_url, is_font_url},
+};
+
+/// The value of an axis in a tuple: a single one like `400`, or a range like `8..144`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AxisValue {
+    Single(f64),
+    Range(f64, f64),
+}
+
+impl AxisValue {
+    /// Checks if a value is the single one, or inside the range.
+    fn contains(&self, value: f64) -> bool {
+        match *self {
+            AxisValue::Single(single) => single == value,
+            AxisValue::Range(min, max) => (min..=max).contains(&value),
+        }
+    }
+}
+
+impl fmt::Display for AxisValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AxisValue::Single(value) => write!(f, "{}", value),
+            AxisValue::Range(min, max) => write!(f, "{}..{}", min, max),
+        }
+    }
+}
+
+/// A family with the faces asked for, e.g. `Lora:ital,wght@0,400;1,400` for the regular and the italic face of Lora.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FamilySpec {
+    pub family: String,
+    /// The axes of the tuples, in the order they were given, e.g. `ital` and `wght`. Empty for a plain family name.
+    pub axes: Vec<String>,
+    /// The faces, each with a value for every axis.
+    pub tuples: Vec<Vec<AxisValue>>,
+}
+
+/// What is wrong with a spec, and where: `start` and `len` are the byte range of the offending part of the spec.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpecError {
+    pub start: usize,
+    pub len: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl SpecError {
+    fn new(start: usize, token: &str, reason: String) -> Self {
+        Self {
+            start,
+            len: token.len().max(1),
+            reason,
+        }
+    }
+
+    /// Marks the offending part of the spec below it, like `   ^^^`, to be printed under the spec.
+    pub fn marker(&self, spec: &str) -> String {
+        let indent = spec[..self.start.min(spec.len())].chars().count();
+        let width = spec
+            .get(self.start..self.start + self.len)
+            .map_or(1, |token| token.chars().count().max(1));
+        format!("{}{}", " ".repeat(indent), "^".repeat(width))
+    }
+}
+
+/// Splits `text` (which starts at `offset` in the spec) at `separator`, with the offset of every part.
+fn split_with_offsets(text: &str, offset: usize, separator: char) -> Vec<(usize, &str)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (index, character) in text.char_indices() {
+        if character == separator {
+            parts.push((offset + start, &text[start..index]));
+            start = index + character.len_utf8();
+        }
+    }
+    parts.push((offset + start, &text[start..]));
+    parts
+}
+
+/// Checks if a tag is an axis tag: four letters, lowercase for the registered axes (like `wght`), uppercase for custom ones (like `GRAD`).
+fn is_axis_tag(tag: &str) -> bool {
+    tag.len() == 4
+        && (tag.bytes().all(|byte| byte.is_ascii_lowercase())
+            || tag.bytes().all(|byte| byte.is_ascii_uppercase()))
+}
+
+/// Parses a number of an axis value, which may have decimals (like `62.5`) and be negative (like `-10`).
+fn parse_number(offset: usize, number: &str) -> Result<f64, SpecError> {
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() && !number.starts_with('+') => Ok(value),
+        _ => Err(SpecError::new(
+            offset,
+            number,
+            format!("'{}' is not a number", number),
+        )),
+    }
+}
+
+/// Parses a single axis value, checking it makes sense for the axis.
+fn parse_axis_value(offset: usize, value: &str, axis: &str) -> Result<AxisValue, SpecError> {
+    let parsed = match value.split_once("..") {
+        Some((min, max)) => {
+            let min = parse_number(offset, min)?;
+            let max = parse_number(offset + value.len() - max.len(), max)?;
+            if min > max {
+                return Err(SpecError::new(
+                    offset,
+                    value,
+                    format!("'{}' is an inverted range, the minimum comes first", value),
+                ));
+            }
+            AxisValue::Range(min, max)
+        }
+        None => AxisValue::Single(parse_number(offset, value)?),
+    };
+
+    match (axis, parsed) {
+        ("ital", AxisValue::Single(ital)) if ital == 0.0 || ital == 1.0 => Ok(parsed),
+        ("ital", _) => Err(SpecError::new(
+            offset,
+            value,
+            format!("'{}' is not a value of 'ital', expected 0 or 1", value),
+        )),
+        ("wght", AxisValue::Single(weight) | AxisValue::Range(weight, _))
+            if !(1.0..=1000.0).contains(&weight) =>
+        {
+            Err(SpecError::new(
+                offset,
+                value,
+                format!("'{}' is not a weight, expected 1 to 1000", value),
+            ))
+        }
+        ("wght", AxisValue::Range(_, max)) if max > 1000.0 => Err(SpecError::new(
+            offset,
+            value,
+            format!("'{}' is not a weight, expected 1 to 1000", value),
+        )),
+        _ => Ok(parsed),
+    }
+}
+
+impl FamilySpec {
+    /// Parses a family name, optionally with the axes and faces in the syntax of the CSS API: `Family:axis,...@value,...;...`.
+    pub fn parse(spec: &str) -> Result<FamilySpec, SpecError> {
+        let Some((family, faces)) = spec.split_once(':') else {
+            return Ok(FamilySpec {
+                family: spec.trim().to_string(),
+                axes: Vec::new(),
+                tuples: Vec::new(),
+            });
+        };
+        if family.trim().is_empty() {
+            return Err(SpecError::new(
+                0,
+                family,
+                "the family name is missing before the ':'".to_string(),
+            ));
+        }
+        let faces_offset = family.len() + 1;
+        let Some((axes, tuples)) = faces.split_once('@') else {
+            return Err(SpecError::new(
+                faces_offset,
+                faces,
+                format!(
+                    "expected the axes, an '@' and their values after the family name, like '{}:wght@400;700'",
+                    family.trim()
+                ),
+            ));
+        };
+
+        let mut axis_tags: Vec<String> = Vec::new();
+        for (offset, tag) in split_with_offsets(axes, faces_offset, ',') {
+            if !is_axis_tag(tag) {
+                return Err(SpecError::new(
+                    offset,
+                    tag,
+                    format!(
+                        "'{}' is not an axis tag, expected four letters like 'wght' or 'ital'",
+                        tag
+                    ),
+                ));
+            }
+            if axis_tags.iter().any(|axis| axis == tag) {
+                return Err(SpecError::new(
+                    offset,
+                    tag,
+                    format!("the axis '{}' is given more than once", tag),
+                ));
+            }
+            axis_tags.push(tag.to_string());
+        }
+
+        let tuples_offset = faces_offset + axes.len() + 1;
+        if tuples.is_empty() {
+            return Err(SpecError::new(
+                tuples_offset,
+                tuples,
+                format!("expected the values for '{}' after the '@'", axes),
+            ));
+        }
+        let mut faces = Vec::new();
+        for (offset, tuple) in split_with_offsets(tuples, tuples_offset, ';') {
+            let values = split_with_offsets(tuple, offset, ',');
+            if values.len() != axis_tags.len() {
+                return Err(SpecError::new(
+                    offset,
+                    tuple,
+                    format!(
+                        "expected {} for '{}', found {} in '{}'",
+                        match axis_tags.len() {
+                            1 => "1 value".to_string(),
+                            count => format!("{} values", count),
+                        },
+                        axes,
+                        values.len(),
+                        tuple
+                    ),
+                ));
+            }
+            let face = values
+                .into_iter()
+                .zip(&axis_tags)
+                .map(|((offset, value), axis)| parse_axis_value(offset, value, axis))
+                .collect::<Result<Vec<_>, _>>()?;
+            if faces.contains(&face) {
+                return Err(SpecError::new(
+                    offset,
+                    tuple,
+                    format!("'{}' is given more than once", tuple),
+                ));
+            }
+            faces.push(face);
+        }
+
+        Ok(FamilySpec {
+            family: family.trim().to_string(),
+            axes: axis_tags,
+            tuples: faces,
+        })
+    }
+
+    /// Checks if the spec asks for specific faces, instead of being a plain family name.
+    pub fn has_faces(&self) -> bool {
+        !self.axes.is_empty()
+    }
+
+    /// The value of an axis in a tuple, if the spec has that axis.
+    fn axis_value(&self, tuple: &[AxisValue], axis: &str) -> Option<AxisValue> {
+        self.axes
+            .iter()
+            .position(|tag| tag == axis)
+            .map(|index| tuple[index])
+    }
+
+    /// The axes and faces, without the family name, e.g. `ital,wght@0,400;1,400`.
+    pub fn faces(&self) -> String {
+        // The CSS API wants the registered (lowercase) axes first, each part in alphabetical order, and the tuples sorted.
+        let mut order = (0..self.axes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&index| {
+            let tag = &self.axes[index];
+            (
+                tag.bytes().all(|byte| byte.is_ascii_uppercase()),
+                tag.clone(),
+            )
+        });
+        let mut tuples = self
+            .tuples
+            .iter()
+            .map(|tuple| order.iter().map(|&index| tuple[index]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        tuples.sort_by(|a, b| {
+            let start = |value: &AxisValue| match *value {
+                AxisValue::Single(value) | AxisValue::Range(value, _) => value,
+            };
+            a.iter()
+                .map(start)
+                .zip(b.iter().map(start))
+                .map(|(a, b)| a.total_cmp(&b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        format!(
+            "{}@{}",
+            order
+                .iter()
+                .map(|&index| self.axes[index].as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+            tuples
+                .iter()
+                .map(|tuple| tuple
+                    .iter()
+                    .map(AxisValue::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","))
+                .collect::<Vec<_>>()
+                .join(";")
+        )
+    }
+
+    /// The family spec for the CSS API (`css2`), e.g. `Lora:ital,wght@0,400;1,400`.
+    pub fn css2_spec(&self) -> String {
+        if self.has_faces() {
+            format!("{}:{}", self.family, self.faces())
+        } else {
+            self.family.clone()
+        }
+    }
+
+    /// Checks if a face (told from the filename of a font file) is one of the faces the spec asks for.
+    /// Like with the CSS API, a spec without `ital` only asks for the upright faces, and one without `wght` for the regular weight.
+    /// Variable fonts have every weight, and what other axes (like `opsz`) they cover can't be told from their filename.
+    pub fn matches(&self, face: &FaceName) -> bool {
+        if !self.has_faces() {
+            return true;
+        }
+        self.tuples.iter().any(|tuple| {
+            let italic = self
+                .axis_value(tuple, "ital")
+                .is_some_and(|ital| ital.contains(1.0));
+            let weight = self
+                .axis_value(tuple, "wght")
+                .unwrap_or(AxisValue::Single(400.0));
+            italic == face.italic
+                && face
+                    .weight
+                    .is_none_or(|face_weight| weight.contains(face_weight as f64))
+        })
+    }
+}
+
+/// Groups the specs of the families given with specific faces by their lowercased name. A family that was also given
+/// as a plain name is left out, as all of its faces are wanted anyway.
+pub fn face_selections(specs: &[FamilySpec]) -> HashMap<String, Vec<FamilySpec>> {
+    let mut selections: HashMap<String, Vec<FamilySpec>> = HashMap::new();
+    for spec in specs.iter().filter(|spec| spec.has_faces()) {
+        selections
+            .entry(spec.family.to_lowercase())
+            .or_default()
+            .push(spec.clone());
+    }
+    for spec in specs.iter().filter(|spec| !spec.has_faces()) {
+        selections.remove(&spec.family.to_lowercase());
+    }
+    selections
+}
+
+/// Finds the argument on the command line a family was asked for with: its name, a spec with faces like `Inter:wght@400`,
+/// or a Google Fonts URL with the family in it.
+pub fn requested_by<'a>(args: &'a [String], family: &str) -> Option<&'a String> {
+    args.iter().find(|arg| {
+        let families = match is_font_url(arg) {
+            true => families_from_url(arg).unwrap_or_default(),
+            false => vec![arg.to_string()],
+        };
+        families.iter().any(|spec| {
+            spec.split(':')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .eq_ignore_ascii_case(family)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use AxisValue::{Range, Single};
+
+    fn spec(spec: &str) -> FamilySpec {
+        FamilySpec::parse(spec).unwrap()
+    }
+
+    /// The reason a spec is invalid, and the part of it that is marked.
+    fn invalid(spec: &str) -> (String, String) {
+        let error = FamilySpec::parse(spec).unwrap_err();
+        let token = spec[error.start..(error.start + error.len).min(spec.len())].to_string();
+        (error.reason, token)
+    }
+
+    #[test]
+    fn plain_names_have_no_axes() {
+        let inter = spec(" Inter ");
+        assert_eq!(inter.family, "Inter");
+        assert!(!inter.has_faces());
+        assert_eq!(inter.css2_spec(), "Inter");
+        assert_eq!(spec("Baloo 2").family, "Baloo 2");
+    }
+
+    #[test]
+    fn axes_and_tuples_are_parsed() {
+        let inter = spec("Inter:wght@400;700");
+        assert_eq!(inter.axes, ["wght"]);
+        assert_eq!(inter.tuples, [[Single(400.0)], [Single(700.0)]]);
+
+        let lora = spec("Lora:ital,wght@0,400;1,400");
+        assert_eq!(lora.axes, ["ital", "wght"]);
+        assert_eq!(
+            lora.tuples,
+            [[Single(0.0), Single(400.0)], [Single(1.0), Single(400.0)]]
+        );
+
+        let fraunces = spec("Fraunces:opsz,wght,SOFT@9..144,100..900,50");
+        assert_eq!(fraunces.axes, ["opsz", "wght", "SOFT"]);
+        assert_eq!(
+            fraunces.tuples,
+            [[Range(9.0, 144.0), Range(100.0, 900.0), Single(50.0)]]
+        );
+        assert_eq!(spec("Recursive:slnt@-15..0").tuples, [[Range(-15.0, 0.0)]]);
+    }
+
+    #[test]
+    fn css2_spec_is_sorted_like_the_css_api_wants() {
+        assert_eq!(
+            spec("Lora:wght,ital@700,1;400,0").css2_spec(),
+            "Lora:ital,wght@0,400;1,700"
+        );
+        assert_eq!(
+            spec("Roboto Flex:GRAD,opsz@0,8..144").css2_spec(),
+            "Roboto Flex:opsz,GRAD@8..144,0"
+        );
+    }
+
+    #[test]
+    fn invalid_specs_point_at_the_offending_token() {
+        let cases = [
+            // An empty token is marked with one caret, at the character after it.
+            (":wght@400", ":"),
+            ("Inter:wght", "wght"),
+            ("Inter:weight@400", "weight"),
+            ("Inter:wght,wght@400,400", "wght"),
+            ("Inter:wght@", ""),
+            ("Inter:wght@400;bold", "bold"),
+            ("Inter:wght@+400", "+400"),
+            ("Inter:wght@1200", "1200"),
+            ("Inter:wght@100..1200", "100..1200"),
+            ("Inter:wght@900..100", "900..100"),
+            ("Lora:ital,wght@0,400;2,400", "2"),
+            ("Lora:ital,wght@0,400;1", "1"),
+            ("Inter:wght@400;400", "400"),
+        ];
+        for (spec, token) in cases {
+            let (reason, marked) = invalid(spec);
+            assert_eq!(marked, token, "{}: {}", spec, reason);
+        }
+        assert_eq!(invalid("Inter:wght@400;bold").0, "'bold' is not a number");
+        let error = FamilySpec::parse("Inter:wght@400;bold").unwrap_err();
+        assert_eq!(error.marker("Inter:wght@400;bold"), "               ^^^^");
+    }
+
+    #[test]
+    fn faces_are_matched_by_weight_and_style() {
+        let face = |weight, italic| FaceName { weight, italic };
+        let lora = spec("Lora:ital,wght@0,400;1,700");
+        assert!(lora.matches(&face(Some(400), false)));
+        assert!(lora.matches(&face(Some(700), true)));
+        assert!(!lora.matches(&face(Some(700), false)));
+        // Variable fonts have every weight.
+        assert!(lora.matches(&face(None, true)));
+
+        let inter = spec("Inter:wght@300..500");
+        assert!(inter.matches(&face(Some(500), false)));
+        assert!(!inter.matches(&face(Some(600), false)));
+        assert!(!inter.matches(&face(Some(400), true)));
+        assert!(spec("Inter").matches(&face(Some(900), true)));
+    }
+
+    #[test]
+    fn plain_names_select_every_face() {
+        let selections =
+            face_selections(&[spec("Inter:wght@400"), spec("Lora:ital@1"), spec("inter")]);
+        assert_eq!(selections.keys().collect::<Vec<_>>(), ["lora"]);
+    }
+
+    #[test]
+    fn families_are_found_in_the_arguments_they_were_asked_for_with() {
+        let args = [
+            " Roboto ",
+            "Inter:wght@400;700",
+            "https://fonts.google.com/specimen/Space+Grotesk?query=space",
+            "fonts.googleapis.com/css2?family=Open+Sans:ital@1&family=Lato&display=swap",
+        ]
+        .map(String::from);
+        for (family, arg) in [
+            ("Roboto", 0),
+            ("inter", 1),
+            ("Space Grotesk", 2),
+            ("Open Sans", 3),
+            ("Lato", 3),
+        ] {
+            assert_eq!(requested_by(&args, family), Some(&args[arg]), "{}", family);
+        }
+        // Not the scheme of a URL.
+        assert_eq!(requested_by(&args, "https"), None);
+        assert_eq!(requested_by(&args, "Arimo"), None);
+    }
+}
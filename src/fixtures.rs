@@ -0,0 +1,224 @@
+//! Recording of HTTP fixtures with `--record-fixtures <dir>`: every response of a real run is saved as a JSON file
+//! (`0001.json`, `0002.json`, ... in the order the requests were made), so what Google Fonts currently serves
+//! can be captured once and served back in tests (see `replay`). Bodies of font files are only recorded by their hash and size,
+//! to keep the fixtures small. The format is described in `tests/fixtures/README.md`.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use crate::color::*;
+use reqwest::blocking::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    http::{self, HttpFetcher, LOGGED_HEADERS},
+    plan::is_font_file,
+    sha256::sha256_hex,
+};
+
+thread_local! {
+    /// The last fixture recorded on this thread, which the body of the response is added to once it has been read.
+    /// Responses are always read on the thread that made the request.
+    static PENDING: RefCell<Option<(PathBuf, Fixture)>> = const { RefCell::new(None) };
+}
+
+/// A recorded request and its response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    pub method: String,
+    pub url: String,
+    /// The URL the request ended up at after redirects.
+    pub final_url: String,
+    pub status: u16,
+    /// The headers that matter for handling the response, like `content-type`.
+    pub headers: BTreeMap<String, String>,
+    /// The body, if it was read and is text. Font files only get `body_sha256` and `body_bytes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_bytes: Option<u64>,
+}
+
+/// Sends requests through another fetcher and records every response as a fixture in a dir.
+pub struct RecordingFetcher {
+    inner: Arc<dyn HttpFetcher>,
+    dir: PathBuf,
+    /// How many fixtures have been recorded, which numbers the files.
+    recorded: AtomicU64,
+}
+
+impl RecordingFetcher {
+    /// Records the responses of `inner` into a dir, creating it.
+    pub fn new(dir: PathBuf, inner: Arc<dyn HttpFetcher>) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            inner,
+            dir,
+            recorded: AtomicU64::new(0),
+        })
+    }
+
+    /// Records a response (without its body, which is added with `record_body` once it has been read).
+    fn record_response(&self, fixture: Fixture) {
+        let index = self.recorded.fetch_add(1, Ordering::Relaxed) + 1;
+        let path = self.dir.join(format!("{:04}.json", index));
+        write_fixture(&path, &fixture);
+        PENDING.with(|pending| *pending.borrow_mut() = Some((path, fixture)));
+    }
+}
+
+impl HttpFetcher for RecordingFetcher {
+    fn send(&self, method: &str, url: &str, request: RequestBuilder) -> reqwest::Result<Response> {
+        let result = self.inner.send(method, url, request);
+        if let Ok(response) = &result {
+            self.record_response(Fixture {
+                method: method.to_string(),
+                url: url.to_string(),
+                final_url: response.url().to_string(),
+                status: response.status().as_u16(),
+                headers: response
+                    .headers()
+                    .iter()
+                    .filter(|(name, _)| LOGGED_HEADERS.contains(&name.as_str()))
+                    .map(|(name, value)| {
+                        (
+                            name.to_string(),
+                            value.to_str().unwrap_or("<binary>").to_string(),
+                        )
+                    })
+                    .collect(),
+                body: None,
+                body_sha256: None,
+                body_bytes: None,
+            });
+        }
+        result
+    }
+}
+
+/// Starts recording fixtures into a dir, creating it, by sending the requests of this thread (and the worker
+/// threads it starts) through a `RecordingFetcher`.
+pub fn set_record_fixtures(dir: PathBuf) -> io::Result<()> {
+    let fetcher = RecordingFetcher::new(dir, http::current_fetcher())?;
+    http::set_fetcher(Arc::new(fetcher));
+    Ok(())
+}
+
+/// Writes a fixture to its file. Failing to record doesn't fail the run, it is only logged.
+fn write_fixture(path: &Path, fixture: &Fixture) {
+    let result = serde_json::to_string_pretty(fixture)
+        .map_err(io::Error::other)
+        .and_then(|json| fs::write(path, json + "\n"));
+    if let Err(e) = result {
+        println!(
+            "{color_yellow}WARN:{color_reset} Failed to record the fixture '{}'! {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// Adds the body of the last response on this thread to its fixture. Font files (by their content type or the
+/// extension in the URL) and bodies that aren't UTF-8 are recorded by their hash and size instead.
+pub fn record_body(body: &[u8]) {
+    let Some((path, mut fixture)) = PENDING.with(|pending| pending.borrow_mut().take()) else {
+        return;
+    };
+    let is_font = fixture
+        .headers
+        .get("content-type")
+        .is_some_and(|content_type| content_type.starts_with("font/"))
+        || is_font_file(fixture.url.split(['?', '#']).next().unwrap_or_default());
+    match std::str::from_utf8(body) {
+        Ok(text) if !is_font => fixture.body = Some(text.to_string()),
+        _ => {
+            fixture.body_sha256 = Some(sha256_hex(body));
+            fixture.body_bytes = Some(body.len() as u64);
+        }
+    }
+    write_fixture(&path, &fixture);
+}
+
+/// Serving recorded fixtures back instead of making requests, so tests don't need a network.
+#[cfg(test)]
+pub mod replay {
+    use std::{fs, io, path::Path};
+
+    use reqwest::{
+        ResponseBuilderExt,
+        blocking::{RequestBuilder, Response},
+    };
+    use url::Url;
+
+    use super::Fixture;
+    use crate::http::HttpFetcher;
+
+    /// Serves the fixtures of a dir, matching requests by their method and URL. A request that was made more than
+    /// once gets the first fixture for it. Font files, which are recorded by their hash, come back empty (unless
+    /// the fixture has a `body`), and requests without a fixture get a 404.
+    pub struct ReplayFetcher {
+        fixtures: Vec<Fixture>,
+    }
+
+    impl ReplayFetcher {
+        /// Loads the fixtures of a dir, in the format `--record-fixtures` writes.
+        pub fn load(dir: &Path) -> io::Result<ReplayFetcher> {
+            let mut paths = fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            paths.retain(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            });
+            paths.sort();
+            let fixtures = paths
+                .iter()
+                .map(|path| {
+                    let json = fs::read_to_string(path)?;
+                    serde_json::from_str(&json).map_err(io::Error::other)
+                })
+                .collect::<io::Result<_>>()?;
+            Ok(ReplayFetcher { fixtures })
+        }
+    }
+
+    impl HttpFetcher for ReplayFetcher {
+        fn send(
+            &self,
+            method: &str,
+            url: &str,
+            _request: RequestBuilder,
+        ) -> reqwest::Result<Response> {
+            let fixture = self
+                .fixtures
+                .iter()
+                .find(|fixture| fixture.method == method && fixture.url == url);
+            let response = match fixture {
+                Some(fixture) => {
+                    let mut response = http::Response::builder()
+                        .status(fixture.status)
+                        .url(Url::parse(&fixture.final_url).expect("fixtures have valid URLs"));
+                    for (name, value) in &fixture.headers {
+                        response = response.header(name, value);
+                    }
+                    response.body(fixture.body.clone().unwrap_or_default())
+                }
+                None => http::Response::builder()
+                    .status(404)
+                    .url(Url::parse(url).expect("requests have valid URLs"))
+                    .body(format!("No fixture was recorded for {} {}", method, url)),
+            };
+            Ok(response.expect("fixtures are valid responses").into())
+        }
+    }
+}
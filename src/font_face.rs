@@ -0,0 +1,126 @@
+use std::fmt;
+
+/// The weight names Google Fonts uses in the filenames of static fonts, e.g. `Inter-SemiBoldItalic.ttf`.
+const WEIGHT_NAMES: &[(&str, u16)] = &[
+    ("Thin", 100),
+    ("ExtraLight", 200),
+    ("Light", 300),
+    ("Regular", 400),
+    ("Medium", 500),
+    ("SemiBold", 600),
+    ("Bold", 700),
+    ("ExtraBold", 800),
+    ("Black", 900),
+];
+
+/// The weight of a face: a single one for static fonts, or a range for variable fonts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontWeight {
+    Fixed(u16),
+    Range(u16, u16),
+}
+
+impl fmt::Display for FontWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontWeight::Fixed(weight) => write!(f, "{}", weight),
+            FontWeight::Range(min, max) => write!(f, "{} {}", min, max),
+        }
+    }
+}
+
+/// What can be told about a face from the filename of its font file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaceName {
+    /// The weight of a static font. `None` for variable fonts, whose weight range is in the font itself.
+    pub weight: Option<u16>,
+    pub italic: bool,
+}
+
+/// Attributes a font file to a face by its filename, the way Google Fonts names them:
+/// `Family-BoldItalic.ttf` for static fonts and `Family-Italic[wght].ttf` for variable ones.
+/// Returns `None` for files that don't follow that (or are not a format browsers load).
+pub fn face_from_filename(filename: &str) -> Option<FaceName> {
+    let filename = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    let (stem, _) = filename.rsplit_once('.')?;
+    font_format(filename)?;
+
+    if let Some((name, axes)) = stem.split_once('[') {
+        if !axes.contains("wght") {
+            return None;
+        }
+        return Some(FaceName {
+            weight: None,
+            italic: name.ends_with("-Italic"),
+        });
+    }
+
+    let (_, style) = stem.rsplit_once('-')?;
+    if style == "Italic" {
+        return Some(FaceName {
+            weight: Some(400),
+            italic: true,
+        });
+    }
+    let (weight_name, italic) = match style.strip_suffix("Italic") {
+        Some(weight_name) => (weight_name, true),
+        None => (style, false),
+    };
+    WEIGHT_NAMES
+        .iter()
+        .find(|(name, _)| *name == weight_name)
+        .map(|&(_, weight)| FaceName {
+            weight: Some(weight),
+            italic,
+        })
+}
+
+/// The CSS `format()` of a font file, by its extension. Collections are left out, as browsers can't load them.
+pub fn font_format(filename: &str) -> Option<&'static str> {
+    let (_, extension) = filename.rsplit_once('.')?;
+    match extension.to_lowercase().as_str() {
+        "ttf" => Some("truetype"),
+        "otf" => Some("opentype"),
+        "woff" => Some("woff"),
+        "woff2" => Some("woff2"),
+        _ => None,
+    }
+}
+
+/// Quotes a string for CSS.
+fn css_string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\a ")
+            .replace('<', "\\3c ")
+    )
+}
+
+/// An `@font-face` rule for a single face.
+#[derive(Clone, Debug)]
+pub struct FontFace {
+    pub family: String,
+    pub weight: FontWeight,
+    pub italic: bool,
+    /// The URL of the font file.
+    pub url: String,
+    /// The CSS `format()` of the font file, see `font_format`.
+    pub format: &'static str,
+}
+
+impl FontFace {
+    /// Renders the rule, indented by `indent`.
+    pub fn render(&self, indent: &str) -> String {
+        format!(
+            "{indent}@font-face {{\n{indent}  font-family: {};\n{indent}  font-style: {};\n{indent}  font-weight: {};\n{indent}  src: url({}) format(\"{}\");\n{indent}}}\n",
+            css_string(&self.family),
+            if self.italic { "italic" } else { "normal" },
+            self.weight,
+            css_string(&self.url),
+            self.format,
+        )
+    }
+}
@@ -1,32 +1,230 @@
-use inline_colorization::*;
+use crate::color::*;
 use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
     fs::{File, create_dir_all},
-    io::Write,
-    path::Path,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use serde::{Deserialize, Serialize};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use serde::{Deserialize, Serialize, Serializer, de::DeserializeOwned};
+use serde_json::{Map, Value};
 
-use crate::exit_codes::{
-    EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_FONT_MANIFEST, EXIT_CODE_NET_ERROR,
+use crate::{
+    axes::{AxisRange, axes_filename, css2_axes_spec},
+    debug,
+    download_cache::DownloadCache,
+    error::FontyError,
+    exit_codes::EXIT_CODE_UNSAFE_PATH,
+    family_spec::FamilySpec,
+    fixtures,
+    font_face::face_from_filename,
+    font_names::family_dir_name,
+    format::{format_size, format_speed},
+    http::{self, FailureKind},
+    info, msg,
+    notify::exit,
+    options::Options,
+    output,
+    permission_hints::print_permission_hints,
+    plan::{PlannedFile, PlannedSource, is_font_file},
+    progress,
+    safe_path::{UnsafePathError, check_symlinks, is_sanitized, long_path, resolve_manifest_path},
+    sha256::sha256_hex,
+    webkit::parse_font_faces,
+    worker_pool::run_parallel,
 };
 
-/// Find the base path for the font files.
-pub fn get_font_base_path() -> String {
-    let home_dir = std::env::var("HOME").unwrap_or("~".to_string());
-    let xdg_data_home =
-        std::env::var("XDG_DATA_HOME").unwrap_or(format!("{}/.local/share", home_dir));
-    match std::env::var("FONTY_BASE_PATH") {
-        Ok(env_var) => env_var,
-        Err(_) => format!("{}/fonts/Google", xdg_data_home),
+/// Builds the path on disk for a file in the manifest.
+/// The manifest is validated with `FontManifest::validate_paths` before anything is written, so this can not fail in practice.
+fn manifest_file_path(base_path: &Path, filename: &str) -> PathBuf {
+    match resolve_manifest_path(base_path, filename) {
+        Ok(path) => path,
+        Err(e) => {
+            println!(
+                "{color_red}ERROR:{color_reset} {}",
+                msg!("error.unsafe_manifest_path", error = e)
+            );
+            exit(EXIT_CODE_UNSAFE_PATH);
+        }
+    }
+}
+
+/// The filename from the manifest for a `FileResult`, if it is written under another name.
+fn original_filename(filename: &str) -> Option<String> {
+    is_sanitized(filename).then(|| filename.to_string())
+}
+
+/// Writes a file from the manifest, giving the reason it failed if it can't be. The error is printed right away,
+/// with hints on how to fix it if fonty may not write there.
+fn write_manifest_file(filepath: &Path, contents: &[u8], options: &Options) -> Result<(), String> {
+    write_file_to_disk(filepath, contents, options).map_err(|e| {
+        println!("{color_red}ERROR:{color_reset} {}", e);
+        print_permission_hints(filepath, e.kind());
+        e.to_string()
+    })
+}
+
+/// The result of a file from the manifest that was not written, e.g. as it was kept or failed.
+fn unwritten_file(family: &str, path: PathBuf, filename: &str, status: FileStatus) -> FileResult {
+    FileResult {
+        family: family.to_string(),
+        path,
+        status,
+        bytes: 0,
+        from_cache: false,
+        seconds: None,
+        transfer_seconds: None,
+        bytes_per_second: None,
+        original_filename: original_filename(filename),
+        content_type: None,
     }
 }
 
+/// What happened to a file from the manifest. In JSON, the status is next to the other fields of the file,
+/// like `"status": "failed", "reason": "...", "failure": "status_404"`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FileStatus {
+    Written,
+    Skipped,
+    /// The file could not be fetched or written, with the reason why and what failed.
+    Failed {
+        reason: String,
+        failure: FileFailure,
+    },
+    /// The file was not even tried, as the network was already down by the time it was its turn.
+    /// Running again (once the network is back) fetches it.
+    NotAttempted {
+        reason: String,
+    },
+}
+
+impl FileStatus {
+    /// Checks if the file was not installed, because it failed or was not attempted.
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self,
+            FileStatus::Failed { .. } | FileStatus::NotAttempted { .. }
+        )
+    }
+}
+
+impl From<FetchError> for FileStatus {
+    fn from(error: FetchError) -> Self {
+        match error {
+            FetchError::Failed { reason, failure } => FileStatus::Failed { reason, failure },
+            FetchError::NotAttempted { reason } => FileStatus::NotAttempted { reason },
+        }
+    }
+}
+
+/// What made a file fail, which decides the exit code of the run (see `InstallReport::exit_code`).
+/// In JSON, it is a name like `timeout` or `status_404`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFailure {
+    /// The download failed, see `FailureKind`.
+    Download(FailureKind),
+    /// Downloads failed because of the network too often, so fonty gave up on it (see `--max-retries-total`).
+    NetworkDown,
+    /// It is not in the download cache, with `--offline`.
+    NotCached,
+    /// It could not be written to disk.
+    Write,
+}
+
+impl FileFailure {
+    /// Whether the failure is a problem with the network (rather than with the file, or the disk).
+    pub fn is_network(self) -> bool {
+        match self {
+            FileFailure::Download(kind) => kind.is_systemic(),
+            FileFailure::NetworkDown => true,
+            FileFailure::NotCached | FileFailure::Write => false,
+        }
+    }
+}
+
+impl Serialize for FileFailure {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            FileFailure::Download(FailureKind::Status(status)) => {
+                serializer.serialize_str(&format!("status_{}", status))
+            }
+            FileFailure::Download(kind) => serializer.serialize_str(kind.name()),
+            FileFailure::NetworkDown => serializer.serialize_str("network_down"),
+            FileFailure::NotCached => serializer.serialize_str("not_cached"),
+            FileFailure::Write => serializer.serialize_str("write"),
+        }
+    }
+}
+
+/// Why a file could not be fetched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FetchError {
+    Failed {
+        reason: String,
+        failure: FileFailure,
+    },
+    /// It was not even tried, as the network was already down.
+    NotAttempted { reason: String },
+}
+
+impl FetchError {
+    fn failed(reason: impl Into<String>, failure: FileFailure) -> Self {
+        FetchError::Failed {
+            reason: reason.into(),
+            failure,
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Failed { reason, .. } | FetchError::NotAttempted { reason } => {
+                f.write_str(reason)
+            }
+        }
+    }
+}
+
+/// The result of writing a file from the manifest to disk.
+#[derive(Clone, Debug, Serialize)]
+pub struct FileResult {
+    pub family: String,
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: FileStatus,
+    pub bytes: u64,
+    /// Whether the contents came from the download cache instead of the network.
+    pub from_cache: bool,
+    /// How long getting the contents took, for font files with `--timing`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seconds: Option<f64>,
+    /// How long reading the response body took, for files downloaded from the network (not from the cache).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer_seconds: Option<f64>,
+    /// The download speed, from `bytes` and `transfer_seconds`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_per_second: Option<f64>,
+    /// The filename from the manifest, if it had to be sanitized to be written (on Windows).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_filename: Option<String>,
+    /// The Content-Type a downloaded file was served with. Files embedded in the manifest have none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+}
+
 /// A file with its contents.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct ManifestFile {
     filename: String,
     contents: String,
+    /// The family the file belongs to. Not part of Google's payload, set after fetching.
+    #[serde(skip)]
+    family: String,
 }
 
 /// A reference to a file.
@@ -34,205 +232,1857 @@ struct ManifestFile {
 struct ManifestFileRef {
     filename: String,
     url: String,
+    /// The size of the file in bytes, if the manifest gives it. Saves asking the server for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    /// The family the file belongs to. Not part of Google's payload, set after fetching.
+    #[serde(skip)]
+    family: String,
 }
 
 /// A manifest of files and file references. Files contain their data in the manifest. File references contain a url to the file.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct FontManifest {
     files: Vec<ManifestFile>,
     #[serde(rename = "fileRefs")]
     file_refs: Vec<ManifestFileRef>,
 }
 
-/// The outer most layer that Google Fonts return.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct FontManifestWrapper {
-    #[serde(rename = "zipName")]
-    zip_name: String,
-    manifest: FontManifest,
+/// The top-level keys fonty knows about in the payload Google Fonts returns.
+const KNOWN_TOP_LEVEL_KEYS: &[&str] = &["zipName", "manifest"];
+
+/// Describes the type of a JSON value, for error messages.
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Extracts a list of files from the manifest object. A missing list is treated like an empty one.
+fn extract_file_list<T: DeserializeOwned>(
+    manifest: &Map<String, Value>,
+    key: &str,
+) -> Result<Vec<T>, String> {
+    let entries = match manifest.get(key) {
+        None | Some(Value::Null) => return Ok(Vec::new()),
+        Some(Value::Array(entries)) => entries,
+        Some(value) => {
+            return Err(format!(
+                "Expected 'manifest.{}' to be an array, found {}!",
+                key,
+                json_type_name(value)
+            ));
+        }
+    };
+
+    entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            T::deserialize(entry)
+                .map_err(|e| format!("Invalid 'manifest.{}[{}]': {}", key, index, e))
+        })
+        .collect()
+}
+
+/// Parses the payload Google Fonts returns, in two stages: first into plain JSON, then into the known structure.
+/// That way a change on Google's side gives an error pointing at what changed, instead of serde's position in the text.
+pub fn parse_manifest(json: &str) -> Result<FontManifest, String> {
+    let payload: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let Value::Object(payload) = payload else {
+        return Err(format!(
+            "Expected the response to be an object, found {}!",
+            json_type_name(&payload)
+        ));
+    };
+
+    if output::is_debug() {
+        let unknown_keys = payload
+            .keys()
+            .filter(|key| !KNOWN_TOP_LEVEL_KEYS.contains(&key.as_str()))
+            .map(|key| key.as_str())
+            .collect::<Vec<_>>();
+        if !unknown_keys.is_empty() {
+            debug!(
+                "Unknown keys in the font manifest: {}",
+                unknown_keys.join(", ")
+            );
+        }
+    }
+
+    let manifest = match payload.get("manifest") {
+        Some(Value::Object(manifest)) => manifest,
+        Some(value) => {
+            return Err(format!(
+                "Expected 'manifest' to be an object, found {}!",
+                json_type_name(value)
+            ));
+        }
+        None => return Err("Expected the response to contain 'manifest'!".to_string()),
+    };
+
+    Ok(FontManifest {
+        files: extract_file_list(manifest, "files")?,
+        file_refs: extract_file_list(manifest, "fileRefs")?,
+    })
 }
 
 impl FontManifest {
-    /// Gets the font manifest from Google Fonts.
-    pub fn fetch(font_names: Vec<&str>) -> Result<FontManifest, reqwest::Error> {
-        // The format for getting fonts from Google Fonts is ?family=font1,font2,font3,...
-        let response = reqwest::blocking::get(format!(
-            "https://fonts.google.com/download/list?family={}",
-            font_names.join(",")
-        ))?;
+    /// Gets the font manifest of a family, from Google Fonts (caching it), or from the cache with `--offline`.
+    pub fn load(
+        font_name: &str,
+        options: &Options,
+        cache: Option<&DownloadCache>,
+    ) -> Result<FontManifest, FontyError> {
+        if !options.offline {
+            let (font_manifest, payload) = Self::fetch(vec![font_name])?;
+            if let Some(cache) = cache
+                && let Err(e) = cache.store_manifest(font_name, &payload)
+            {
+                println!(
+                    "{color_yellow}WARN:{color_reset} Failed to cache the font manifest of {color_blue}'{}'{color_reset}! Error:\n{}",
+                    font_name, e
+                );
+            }
+            return Ok(font_manifest);
+        }
+
+        let not_cached = || FontyError::NotCached {
+            what: format!("The font manifest of '{}'", font_name),
+        };
+        let payload = cache
+            .and_then(|cache| cache.load_manifest(font_name))
+            .ok_or_else(not_cached)?;
+        // The payload was valid when it was cached, so a cached manifest that can't be parsed is as good as none.
+        parse_manifest(&payload).map_err(|_| not_cached())
+    }
+
+    /// Gets the font manifest from Google Fonts, together with its JSON payload (without the anti-XSSI prefix).
+    fn fetch(font_names: Vec<&str>) -> Result<(FontManifest, String), FontyError> {
+        let url = http::download_list_url(&font_names);
+        let request_error = |error| FontyError::Request {
+            url: url.clone(),
+            error,
+        };
+
+        let response = http::get(&url).map_err(request_error)?;
+        let status = response.status();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .map(|content_type| content_type.to_str().unwrap_or("<binary>").to_string());
+
+        let text = response.text().map_err(request_error)?;
+        fixtures::record_body(text.as_bytes());
+        http::debug_body(&text);
+        let invalid_manifest = |payload: &str, error: String| FontyError::InvalidManifest {
+            url: url.clone(),
+            status,
+            content_type: content_type.clone(),
+            body: text.clone(),
+            payload: payload.to_string(),
+            error,
+        };
+
+        let json = http::strip_anti_xssi_prefix(&text).map_err(|e| invalid_manifest(&text, e))?;
+
+        parse_manifest(json)
+            .map(|font_manifest| (font_manifest, json.to_string()))
+            .map_err(|e| invalid_manifest(json, e))
+    }
+
+    /// Gets a manifest with a single font of an icon font family (like Material Symbols), with exactly the given axis ranges.
+    /// The font comes from the stylesheet of the CSS API, which isn't cached, so this can't be used with `--offline`.
+    pub fn load_with_axes(font_name: &str, axes: &[AxisRange]) -> Result<FontManifest, FontyError> {
+        let url = http::css2_url(&[css2_axes_spec(font_name, axes)]);
+        let request_error = |error| FontyError::Request {
+            url: url.clone(),
+            error,
+        };
+        // Without a browser User-Agent, the CSS API points at TrueType files, which are what can be installed.
+        let css = http::get(&url)
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.text())
+            .map_err(request_error)?;
+        fixtures::record_body(css.as_bytes());
+        http::debug_body(&css);
+
+        let urls = parse_font_faces(&css)
+            .into_iter()
+            .filter_map(|face| face.urls.into_iter().next())
+            .collect::<Vec<_>>();
+        let file_refs = urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| {
+                let extension = url
+                    .rsplit('/')
+                    .next()
+                    .and_then(|filename| filename.rsplit_once('.'))
+                    .map_or("ttf", |(_, extension)| extension);
+                let mut filename = axes_filename(font_name, axes, extension);
+                // Icon fonts come as one file, but number them in case a stylesheet ever has more.
+                if urls.len() > 1 {
+                    filename = format!("{}-{}", index + 1, filename);
+                }
+                ManifestFileRef {
+                    filename,
+                    url: url.clone(),
+                    size: None,
+                    family: font_name.to_string(),
+                }
+            })
+            .collect();
+        Ok(FontManifest {
+            files: Vec::new(),
+            file_refs,
+        })
+    }
+
+    /// Lists the URLs of the file references that are not in the cache, which can't be installed with `--offline`.
+    pub fn uncached_urls(&self, cache: &DownloadCache) -> Vec<&str> {
+        self.file_refs
+            .iter()
+            .map(|file_ref| file_ref.url.as_str())
+            .filter(|url| !cache.has_blob(url))
+            .collect()
+    }
 
-        let text = response.text()?;
-        // For some reason Google Fonts adds some nasty extra characters before the JSON payload, remove them.
-        let json = text.replace(")]}'\n", "");
+    /// Adds up the sizes of the files that still have to be downloaded (the ones not in the cache). Sizes the manifest
+    /// gives are used as they are. The others are asked for with HEAD requests (through the mirrors, with retries), but
+    /// only while the total is at most `limit`, since past that the answer doesn't change for the caller.
+    /// Files no size is known for count as empty, so this is a lower bound.
+    pub fn download_size(
+        &self,
+        options: &Options,
+        cache: Option<&DownloadCache>,
+        limit: u64,
+    ) -> u64 {
+        let uncached_refs = self
+            .file_refs
+            .iter()
+            .filter(|file_ref| cache.is_none_or(|cache| !cache.has_blob(&file_ref.url)))
+            .collect::<Vec<_>>();
+        let known_size: u64 = uncached_refs
+            .iter()
+            .filter_map(|file_ref| file_ref.size)
+            .sum();
+        let unknown_urls = uncached_refs
+            .iter()
+            .filter(|file_ref| file_ref.size.is_none())
+            .map(|file_ref| file_ref.url.as_str())
+            .collect::<Vec<_>>();
+        if known_size > limit || unknown_urls.is_empty() {
+            return known_size;
+        }
+        known_size
+            + run_parallel(&unknown_urls, options.jobs, |_, url| {
+                remote_size_from_mirrors(url, options).unwrap_or(0)
+            })
+            .into_iter()
+            .sum::<u64>()
+    }
 
-        // Parse the JSON into a valid FontManifest struct.
-        let font_manifest_wrapper: FontManifestWrapper = match serde_json::from_str(&json) {
-            Ok(font_manifest) => font_manifest,
-            Err(e) => {
-                println!("Invalid FontManifest! Error:\n{}", e);
-                std::process::exit(EXIT_CODE_INVALID_FONT_MANIFEST);
+    /// Lists the filenames of the files whose contents are embedded in the manifest (usually the license), which have no URL.
+    pub fn embedded_filenames(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .map(|file| file.filename.as_str())
+            .collect()
+    }
+
+    /// Finds the contents of a file embedded in the manifest by its filename. Files that are downloaded give `None`.
+    pub fn embedded_contents(&self, filename: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|file| file.filename == filename)
+            .map(|file| file.contents.as_str())
+    }
+
+    /// Lists the filenames of every file in the manifest, in the order of `files` followed by `file_refs`.
+    pub fn filenames(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .map(|file| file.filename.as_str())
+            .chain(
+                self.file_refs
+                    .iter()
+                    .map(|file_ref| file_ref.filename.as_str()),
+            )
+            .collect()
+    }
+
+    /// Guesses the license of the family from the license file that comes with it, as an SPDX identifier.
+    pub fn license(&self) -> Option<&'static str> {
+        self.filenames().iter().find_map(|filename| {
+            match filename.rsplit('/').next()?.to_uppercase().as_str() {
+                "OFL.TXT" => Some("OFL-1.1"),
+                "LICENSE.TXT" => Some("Apache-2.0"),
+                "UFL.TXT" => Some("UFL-1.0"),
+                _ => None,
+            }
+        })
+    }
+
+    /// Checks if the manifest lists no files at all.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.file_refs.is_empty()
+    }
+
+    /// Leaves out the font files whose face none of the specs asks for (see `FamilySpec::matches`), for a family
+    /// given like `Inter:wght@400;700`. Font files whose face can't be told from their filename are kept.
+    /// Returns how many font files are left.
+    pub fn retain_faces(&mut self, specs: &[FamilySpec]) -> usize {
+        self.file_refs.retain(|file_ref| {
+            !is_font_file(&file_ref.filename)
+                || face_from_filename(&file_ref.filename)
+                    .is_none_or(|face| specs.iter().any(|spec| spec.matches(&face)))
+        });
+        self.file_refs
+            .iter()
+            .filter(|file_ref| is_font_file(&file_ref.filename))
+            .count()
+    }
+
+    /// Marks every file in the manifest as belonging to a family.
+    pub fn set_family(&mut self, family: &str) {
+        for file in &mut self.files {
+            file.family = family.to_string();
+        }
+        for file_ref in &mut self.file_refs {
+            file_ref.family = family.to_string();
+        }
+    }
+
+    /// Combines the manifests of several families into one.
+    pub fn merge(manifests: Vec<FontManifest>) -> Self {
+        let mut merged = Self {
+            files: Vec::new(),
+            file_refs: Vec::new(),
+        };
+        for manifest in manifests {
+            merged.files.extend(manifest.files);
+            merged.file_refs.extend(manifest.file_refs);
+        }
+        merged
+    }
+
+    /// Lists every file that will be written, in the order of `files` followed by `file_refs`.
+    pub fn planned_files(&self, base_path: &Path) -> Vec<PlannedFile> {
+        let embedded = self.files.iter().map(|file| PlannedFile {
+            family: file.family.clone(),
+            filename: file.filename.clone(),
+            path: manifest_file_path(base_path, &file.filename),
+            source: PlannedSource::Embedded,
+            is_font: is_font_file(&file.filename),
+        });
+        let downloaded = self.file_refs.iter().map(|file_ref| PlannedFile {
+            family: file_ref.family.clone(),
+            filename: file_ref.filename.clone(),
+            path: manifest_file_path(base_path, &file_ref.filename),
+            source: PlannedSource::Download {
+                url: file_ref.url.clone(),
+            },
+            is_font: is_font_file(&file_ref.filename),
+        });
+        embedded.chain(downloaded).collect()
+    }
+
+    /// Moves a file (by its index in `planned_files`) into the directory of its family.
+    pub fn move_into_family_dir(&mut self, index: usize) {
+        let (filename, family) = match self.files.get_mut(index) {
+            Some(file) => (&mut file.filename, &file.family),
+            None => {
+                let file_ref = &mut self.file_refs[index - self.files.len()];
+                (&mut file_ref.filename, &file_ref.family)
             }
         };
+        *filename = format!("{}/{}", family_dir_name(family), filename);
+    }
 
-        Ok(font_manifest_wrapper.manifest)
+    /// Prepends a path to the file paths in the manifest. An empty path leaves them as they are.
+    pub fn prepend_path_to_files(mut self, path: &str) -> Self {
+        if path.is_empty() {
+            return self;
+        }
+        let filenames = self.files.iter_mut().map(|file| &mut file.filename).chain(
+            self.file_refs
+                .iter_mut()
+                .map(|file_ref| &mut file_ref.filename),
+        );
+        for filename in filenames {
+            *filename = format!("{}/{}", path, filename);
+        }
+        self
     }
 
-    /// Prepends a path to the file paths in the manifest.
+    /// The old (misspelled) name of `prepend_path_to_files`.
+    #[deprecated(note = "use `prepend_path_to_files` instead")]
+    #[allow(dead_code)]
     pub fn prepand_path_to_files(self, path: &str) -> Self {
-        Self {
+        self.prepend_path_to_files(path)
+    }
+
+    /// Keeps only the files that are installed at one of the given paths (relative to the font base dir), moving them to that path.
+    /// Files are matched by filename, as the installed path is the filename with the family dir (if any) in front of it.
+    /// Also returns the paths no file matched.
+    pub fn for_installed_paths(&self, paths: &[PathBuf]) -> (Self, Vec<PathBuf>) {
+        let matching = |filename: &str| {
+            paths
+                .iter()
+                .find(|path| path.ends_with(filename))
+                .map(|path| path.to_string_lossy().into_owned())
+        };
+        let font_manifest = Self {
             files: self
                 .files
                 .iter()
-                .map(|file| ManifestFile {
-                    filename: format!("{}/{}", path, file.filename),
-                    contents: file.contents.clone(),
+                .filter_map(|file| {
+                    Some(ManifestFile {
+                        filename: matching(&file.filename)?,
+                        ..file.clone()
+                    })
                 })
                 .collect(),
             file_refs: self
                 .file_refs
                 .iter()
-                .map(|file| ManifestFileRef {
-                    filename: format!("{}/{}", path, file.filename),
-                    url: file.url.clone(),
+                .filter_map(|file_ref| {
+                    Some(ManifestFileRef {
+                        filename: matching(&file_ref.filename)?,
+                        ..file_ref.clone()
+                    })
                 })
                 .collect(),
-        }
+        };
+        let unmatched = paths
+            .iter()
+            .filter(|path| {
+                !font_manifest
+                    .filenames()
+                    .iter()
+                    .any(|filename| Path::new(filename) == path.as_path())
+            })
+            .cloned()
+            .collect();
+        (font_manifest, unmatched)
     }
 
     /// Checks if a font is a valid font on Google Fonts.
     pub fn check_if_valid_font(font_name: &str) -> bool {
-        // A font is valid if /specimen/font-name can be reached and is a success.
-        reqwest::blocking::get(format!(
-            "https://fonts.google.com/specimen/{}",
-            font_name.replace(" ", "+")
-        ))
-        .is_ok_and(|response| response.status().is_success())
+        is_specimen_page(&http::specimen_url(font_name), font_name)
     }
 
-    /// Write files (with their contents in the manifest) to disk.
-    pub fn write_files(&self) {
-        for file in &self.files {
-            let raw_filepath = format!("{}/{}", get_font_base_path(), file.filename);
-            let filepath = Path::new(&raw_filepath);
+    /// Checks that every file in the manifest ends up inside the base path, also when following symlinks that already exist.
+    /// Returns all unsafe filenames, so they can be reported at once.
+    pub fn validate_paths(&self, base_path: &Path) -> Result<(), Vec<UnsafePathError>> {
+        let errors = self
+            .files
+            .iter()
+            .map(|file| &file.filename)
+            .chain(self.file_refs.iter().map(|file_ref| &file_ref.filename))
+            .filter_map(|filename| {
+                resolve_manifest_path(base_path, filename)
+                    .and_then(|path| check_symlinks(base_path, &path, filename))
+                    .err()
+            })
+            .collect::<Vec<_>>();
 
-            let parent_dir = match filepath.parent() {
-                Some(parent_dir) => parent_dir,
-                None => {
-                    println!("Invalid file path: '{}'", raw_filepath);
-                    std::process::exit(EXIT_CODE_INVALID_FONT_MANIFEST);
-                }
-            };
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
 
-            // Equivalent to `mkdir -p`.
-            match create_dir_all(parent_dir) {
-                Ok(_) => (),
-                Err(e) => {
-                    println!(
-                        "Failed to create directory: '{:?}'! Error:\n{}",
-                        parent_dir, e
+    /// Checks that every file reference points at an allowed https URL.
+    /// Returns every offending URL with the reason it is not allowed.
+    pub fn validate_urls(&self, extra_hosts: &[&str]) -> Result<(), Vec<(String, String)>> {
+        let errors = self
+            .file_refs
+            .iter()
+            .filter_map(|file_ref| {
+                http::validate_download_url(&file_ref.url, extra_hosts)
+                    .err()
+                    .map(|reason| (file_ref.url.clone(), reason))
+            })
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Gets the paths on disk every file in the manifest will be written to.
+    pub fn destination_paths(&self, base_path: &Path) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .map(|file| &file.filename)
+            .chain(self.file_refs.iter().map(|file_ref| &file_ref.filename))
+            .map(|filename| manifest_file_path(base_path, filename))
+            .collect()
+    }
+
+    /// Write files (with their contents in the manifest) to disk, except for the paths in `skip`. Returns the result for each file.
+    pub fn write_files(
+        &self,
+        base_path: &Path,
+        options: &Options,
+        skip: &HashSet<PathBuf>,
+    ) -> Vec<FileResult> {
+        self.files
+            .iter()
+            .map(|file| {
+                let filepath = manifest_file_path(base_path, &file.filename);
+                if skip.contains(&filepath) {
+                    return unwritten_file(
+                        &file.family,
+                        filepath,
+                        &file.filename,
+                        FileStatus::Skipped,
                     );
-                    std::process::exit(EXIT_CODE_INVALID_FONT_MANIFEST);
                 }
-            }
 
-            // Create the file, and get reference to it.
-            let mut file_writer = match File::create(filepath) {
-                Ok(file_writer) => file_writer,
-                Err(e) => {
-                    println!("Failed to create file: '{:?}'! Error:\n{}", filepath, e);
-                    std::process::exit(EXIT_CODE_FILE_IO_ERROR);
+                if let Err(reason) =
+                    write_manifest_file(&filepath, file.contents.as_bytes(), options)
+                {
+                    return unwritten_file(
+                        &file.family,
+                        filepath,
+                        &file.filename,
+                        FileStatus::Failed {
+                            reason,
+                            failure: FileFailure::Write,
+                        },
+                    );
                 }
-            };
-
-            // Write the contents to the file.
-            match file_writer.write_all(file.contents.as_bytes()) {
-                Ok(_) => (),
-                Err(e) => {
-                    println!("Failed to write to file: '{:?}'! Error:\n{}", filepath, e);
-                    std::process::exit(EXIT_CODE_FILE_IO_ERROR);
+                FileResult {
+                    family: file.family.clone(),
+                    path: filepath,
+                    status: FileStatus::Written,
+                    bytes: file.contents.len() as u64,
+                    from_cache: false,
+                    seconds: None,
+                    transfer_seconds: None,
+                    bytes_per_second: None,
+                    original_filename: original_filename(&file.filename),
+                    content_type: None,
                 }
-            };
-        }
+            })
+            .inspect(progress::file_done)
+            .collect()
     }
 
-    /// Fetches the files from the file references.
-    pub fn fetch_files_from_refs(&self) {
+    /// Computes the SHA-256 of every file reference, `options.jobs` at a time, without writing anything to the font base dir.
+    /// Files in the cache are not downloaded again, the others are downloaded once and put in the cache.
+    /// Returns every file that couldn't be fetched, by URL, with the reason.
+    pub fn hash_file_refs(
+        &self,
+        options: &Options,
+        cache: Option<&DownloadCache>,
+    ) -> Result<Vec<HashedFileRef>, Vec<(String, String)>> {
         let downloads = self.file_refs.len();
-        for (index, file_ref) in self.file_refs.iter().enumerate() {
-            print!(
-                "{color_cyan}INFO:{color_reset} Downloading file {color_bright_yellow}{}{color_white}/{}{color_reset}: {color_blue}'{}' {color_bright_black}... ",
+        let results = run_parallel(&self.file_refs, options.jobs, |index, file_ref| {
+            let (file, _) = fetch_file_ref(file_ref, options, cache)
+                .map_err(|e| (file_ref.url.clone(), e.to_string()))?;
+            info!(
+                "Hashing file {color_bright_yellow}{}{color_white}/{}{color_reset}: {color_blue}'{}' {color_bright_black}... {color_green}DONE!{color_reset}",
                 index + 1,
                 downloads,
                 file_ref.filename
             );
+            Ok(HashedFileRef {
+                filename: file_ref.filename.clone(),
+                url: file_ref.url.clone(),
+                sha256: sha256_hex(&file.bytes),
+            })
+        });
+        let failures = results
+            .iter()
+            .filter_map(|result| result.as_ref().err().cloned())
+            .collect::<Vec<_>>();
+        if failures.is_empty() {
+            Ok(results.into_iter().flatten().collect())
+        } else {
+            Err(failures)
+        }
+    }
 
-            let response = match reqwest::blocking::get(&file_ref.url) {
-                Ok(response) => response,
-                Err(e) => {
-                    println!("Failed to fetch file: '{:?}'! Error:\n{}", file_ref.url, e);
-                    std::process::exit(EXIT_CODE_NET_ERROR);
-                }
-            };
+    /// Downloads the files of the file references that aren't in the cache into it, `options.jobs` at a time,
+    /// without writing them anywhere else (e.g. to compare them with the installed files).
+    /// Returns every file that couldn't be fetched, by URL, with the reason.
+    pub fn cache_file_refs(
+        &self,
+        options: &Options,
+        cache: &DownloadCache,
+    ) -> Result<(), Vec<(String, String)>> {
+        let uncached = self
+            .file_refs
+            .iter()
+            .filter(|file_ref| !cache.has_blob(&file_ref.url))
+            .collect::<Vec<_>>();
+        let failures = run_parallel(&uncached, options.jobs, |_, file_ref| {
+            fetch_file_ref(file_ref, options, Some(cache))
+                .err()
+                .map(|e| (file_ref.url.clone(), e.to_string()))
+        })
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
 
-            // If the request failed, exit.
-            if !response.status().is_success() {
-                println!(
-                    "Failed to fetch file: '{:?}'! Got status '{}'.",
-                    file_ref.url,
-                    response.status()
+    /// Fetches the files from the file references, `options.jobs` at a time, except for the paths in `skip`.
+    /// Downloaded files are put in the cache, and with `--offline` they are taken from it instead.
+    /// Returns the result for each file. A file that can't be fetched fails on its own, the others are still fetched.
+    pub fn fetch_files_from_refs(
+        &self,
+        base_path: &Path,
+        options: &Options,
+        skip: &HashSet<PathBuf>,
+        cache: Option<&DownloadCache>,
+    ) -> Vec<FileResult> {
+        let downloads = self.file_refs.len();
+        run_parallel(&self.file_refs, options.jobs, |index, file_ref| {
+            let filepath = manifest_file_path(base_path, &file_ref.filename);
+            if skip.contains(&filepath) {
+                info!(
+                    "Skipping file {color_bright_yellow}{}{color_white}/{}{color_reset}: {color_blue}'{}' {color_bright_black}(already exists){color_reset}",
+                    index + 1,
+                    downloads,
+                    file_ref.filename
+                );
+                let result = unwritten_file(
+                    &file_ref.family,
+                    filepath,
+                    &file_ref.filename,
+                    FileStatus::Skipped,
                 );
-                std::process::exit(EXIT_CODE_NET_ERROR);
+                progress::file_done(&result);
+                return result;
             }
 
-            let file_bytes = match response.bytes() {
-                Ok(file_bytes) => file_bytes,
+            let started_at = Instant::now();
+            let (file, from_cache) = match fetch_file_ref(file_ref, options, cache) {
+                Ok(fetched) => fetched,
                 Err(e) => {
-                    println!("Failed to fetch file: '{:?}'! Error:\n{}", file_ref.url, e);
-                    std::process::exit(EXIT_CODE_NET_ERROR);
+                    println!(
+                        "{color_red}ERROR:{color_reset} {}",
+                        msg!(
+                            "error.fetch_file",
+                            index = index + 1,
+                            downloads = downloads,
+                            url = file_ref.url,
+                            error = e
+                        )
+                    );
+                    let result = unwritten_file(
+                        &file_ref.family,
+                        filepath,
+                        &file_ref.filename,
+                        FileStatus::from(e),
+                    );
+                    progress::file_done(&result);
+                    return result;
                 }
             };
+            let file_bytes = if options.normalize_text {
+                normalize_text(&file_ref.filename, file.content_type.as_deref(), file.bytes)
+            } else {
+                file.bytes
+            };
+            if let Err(reason) = write_manifest_file(&filepath, &file_bytes, options) {
+                let result = unwritten_file(
+                    &file_ref.family,
+                    filepath,
+                    &file_ref.filename,
+                    FileStatus::Failed {
+                        reason,
+                        failure: FileFailure::Write,
+                    },
+                );
+                progress::file_done(&result);
+                return result;
+            }
+            let seconds = options.timing.then(|| started_at.elapsed().as_secs_f64());
+            let transfer_seconds = file.transfer.map(|transfer| transfer.as_secs_f64());
+            let bytes_per_second = transfer_seconds.map(|transfer_seconds| {
+                bytes_per_second(file_bytes.len() as u64, transfer_seconds)
+            });
+
+            info!(
+                "{} file {color_bright_yellow}{}{color_white}/{}{color_reset}: {color_blue}'{}' {color_bright_black}... {color_green}DONE!{color_reset}{}{}",
+                if from_cache {
+                    "Copying cached"
+                } else {
+                    "Downloading"
+                },
+                index + 1,
+                downloads,
+                file_ref.filename,
+                seconds.map_or(String::new(), |seconds| format!(
+                    " {color_bright_black}({:.3}s){color_reset}",
+                    seconds
+                )),
+                bytes_per_second.filter(|_| options.verbose).map_or(
+                    String::new(),
+                    |bytes_per_second| format!(
+                        " {color_bright_black}({} at {}){color_reset}",
+                        format_size(file_bytes.len() as u64),
+                        format_speed(bytes_per_second)
+                    )
+                )
+            );
+            let result = FileResult {
+                family: file_ref.family.clone(),
+                path: filepath,
+                status: FileStatus::Written,
+                bytes: file_bytes.len() as u64,
+                from_cache,
+                seconds,
+                transfer_seconds,
+                bytes_per_second,
+                original_filename: original_filename(&file_ref.filename),
+                content_type: file.content_type,
+            };
+            progress::file_done(&result);
+            result
+        })
+    }
+}
+
+/// Gets the contents of a file from the cache if it is there (the URLs of font files are versioned,
+/// so a cached URL always has the same contents). Otherwise downloads it (from a mirror, if there are any) and puts it in the cache,
+/// unless `--offline` is given. Returns the file (with the Content-Type it was first downloaded with) and whether it came from the cache,
+/// or why it can't be downloaded (or is not cached with `--offline`). What to do about that is up to the caller.
+pub fn fetch_cached(
+    url: &str,
+    filename: &str,
+    options: &Options,
+    cache: Option<&DownloadCache>,
+) -> Result<(DownloadedFile, bool), FetchError> {
+    if let Some(cache) = cache
+        && let Some(bytes) = cache.load_blob(url)
+    {
+        let file = DownloadedFile {
+            bytes,
+            content_type: cache.content_type(url),
+            transfer: None,
+        };
+        return Ok((file, true));
+    }
+    if options.offline {
+        return Err(FetchError::failed(
+            "It is not cached (or the cached file is corrupted).",
+            FileFailure::NotCached,
+        ));
+    }
+
+    let file = match download_from_mirrors(url, filename, options) {
+        Ok(file) => file,
+        // Every download still running fails the same way then. Only the first one says why, the others are just skipped.
+        Err(_) if http::is_network_down() && !http::claim_network_down_report() => {
+            return Err(FetchError::NotAttempted {
+                reason: "Skipped, as the network seems to be down.".to_string(),
+            });
+        }
+        Err(e) => return Err(e),
+    };
+    // The file can still be used if it can't be cached, later runs just have to download it again.
+    if let Some(cache) = cache
+        && let Err(e) = cache.store_blob(url, &file.bytes, file.content_type.as_deref())
+    {
+        println!(
+            "{color_yellow}WARN:{color_reset} Failed to cache the file {color_blue}'{}'{color_reset}! Error:\n{}",
+            url, e
+        );
+    }
+    Ok((file, false))
+}
+
+/// Gets the contents of a file reference, see `fetch_cached`.
+fn fetch_file_ref(
+    file_ref: &ManifestFileRef,
+    options: &Options,
+    cache: Option<&DownloadCache>,
+) -> Result<(DownloadedFile, bool), FetchError> {
+    fetch_cached(&file_ref.url, &file_ref.filename, options, cache)
+}
+
+/// A file reference with the SHA-256 of its contents, e.g. for `pkgs.fetchurl` in Nix.
+#[derive(Clone, Debug)]
+pub struct HashedFileRef {
+    pub filename: String,
+    pub url: String,
+    pub sha256: String,
+}
 
-            let raw_filepath = format!("{}/{}", get_font_base_path(), file_ref.filename);
-            let filepath = Path::new(&raw_filepath);
+/// The first bytes of every font container a font file can be in: sfnt (TrueType), OpenType (CFF), collections, WOFF and WOFF2.
+const FONT_MAGIC_BYTES: &[&[u8]] = &[
+    b"\x00\x01\x00\x00",
+    b"OTTO",
+    b"true",
+    b"ttcf",
+    b"wOFF",
+    b"wOF2",
+];
 
-            let parent_dir = match filepath.parent() {
-                Some(parent_dir) => parent_dir,
-                None => {
-                    println!("Invalid file path: '{}'", raw_filepath);
-                    std::process::exit(EXIT_CODE_INVALID_FONT_MANIFEST);
+/// A downloaded file, with the Content-Type it was served with.
+#[derive(Clone, Debug)]
+pub struct DownloadedFile {
+    pub bytes: Vec<u8>,
+    pub content_type: Option<String>,
+    /// How long reading the body took. `None` if the file came from the cache.
+    pub transfer: Option<Duration>,
+}
+
+/// The speed of a transfer, guarding against transfers too fast to measure.
+pub fn bytes_per_second(bytes: u64, seconds: f64) -> f64 {
+    bytes as f64 / seconds.max(0.000_001)
+}
+
+/// Checks if a Content-Type is for text, like `text/plain; charset=utf-8`.
+fn is_text_content_type(content_type: &str) -> bool {
+    content_type.trim().to_lowercase().starts_with("text/")
+}
+
+/// Converts the CRLF line endings of a downloaded text file to LF, with `--normalize-text`.
+/// Only files served as text are changed, and font files never are, whatever they were served as.
+fn normalize_text(filename: &str, content_type: Option<&str>, bytes: Vec<u8>) -> Vec<u8> {
+    if is_font_file(filename) || !content_type.is_some_and(is_text_content_type) {
+        return bytes;
+    }
+    let mut normalized = Vec::with_capacity(bytes.len());
+    for (index, byte) in bytes.iter().enumerate() {
+        if *byte == b'\r' && bytes.get(index + 1) == Some(&b'\n') {
+            continue;
+        }
+        normalized.push(*byte);
+    }
+    normalized
+}
+
+/// Checks that a downloaded file is what it claims to be, so a CDN hiccup does not end up installed as a font.
+/// The body has to be non-empty, match the Content-Length (if there was one), and font files have to start like a font
+/// and not be served as text.
+fn check_downloaded_file(
+    filename: &str,
+    content_length: Option<u64>,
+    content_type: Option<&str>,
+    file_bytes: &[u8],
+) -> Result<(), String> {
+    if file_bytes.is_empty() {
+        return Err("Got an empty file.".to_string());
+    }
+    if let Some(content_length) = content_length
+        && content_length != file_bytes.len() as u64
+    {
+        return Err(format!(
+            "Got {} bytes, but expected {}.",
+            file_bytes.len(),
+            content_length
+        ));
+    }
+    if is_font_file(filename)
+        && !FONT_MAGIC_BYTES
+            .iter()
+            .any(|magic_bytes| file_bytes.starts_with(magic_bytes))
+    {
+        return Err("Got a file that does not look like a font.".to_string());
+    }
+    if is_font_file(filename)
+        && let Some(content_type) =
+            content_type.filter(|content_type| is_text_content_type(content_type))
+    {
+        return Err(format!(
+            "Got a file that does not look like a font (Content-Type '{}').",
+            content_type
+        ));
+    }
+    Ok(())
+}
+
+/// Downloads a file, retrying up to `options.retries` times if the download fails or the file is invalid.
+/// Files larger than `options.max_file_size` fail right away, without retrying, and so do files the server says don't exist.
+/// Failures because of the network count against `options.max_retries_total` for the whole run. Once that is used up,
+/// the network counts as down, and every download fails right away.
+pub fn download_file(
+    url: &str,
+    filename: &str,
+    options: &Options,
+) -> Result<DownloadedFile, FetchError> {
+    let size_limit_error = || {
+        FetchError::failed(
+            format!(
+                "The file exceeds the size limit of {} bytes (see --max-file-size).",
+                options.max_file_size
+            ),
+            FileFailure::Download(FailureKind::Other),
+        )
+    };
+
+    let network_down_error = || {
+        FetchError::failed(
+            format!(
+                "The network seems to be down, giving up after {}. The files that were not downloaded yet are skipped.",
+                http::network_failure_summary()
+            ),
+            FileFailure::NetworkDown,
+        )
+    };
+
+    let mut attempt = 0;
+    loop {
+        if http::is_network_down() {
+            return Err(network_down_error());
+        }
+        let result = match http::get_download(url) {
+            Ok(response) if !response.status().is_success() => Err((
+                format!("Got status '{}'.", response.status()),
+                FailureKind::Status(response.status().as_u16()),
+            )),
+            Ok(response) => {
+                let content_length = response.content_length();
+                let content_type = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|content_type| content_type.to_str().ok())
+                    .map(str::to_string);
+                if content_length
+                    .is_some_and(|content_length| content_length > options.max_file_size)
+                {
+                    return Err(size_limit_error());
                 }
-            };
 
-            // Equivalent to `mkdir -p`.
-            match create_dir_all(parent_dir) {
-                Ok(_) => (),
-                Err(e) => {
-                    println!(
-                        "Failed to create directory: '{:?}'! Error:\n{}",
-                        parent_dir, e
-                    );
-                    std::process::exit(EXIT_CODE_INVALID_FONT_MANIFEST);
+                // The Content-Length can be missing or wrong, so stop reading once the limit is exceeded.
+                let mut file_bytes = Vec::new();
+                let started_at = Instant::now();
+                let read = response
+                    .take(options.max_file_size + 1)
+                    .read_to_end(&mut file_bytes);
+                let transfer = started_at.elapsed();
+                http::record_transfer(transfer, file_bytes.len() as u64);
+                fixtures::record_body(&file_bytes);
+                match read {
+                    Ok(_) if file_bytes.len() as u64 > options.max_file_size => {
+                        return Err(size_limit_error());
+                    }
+                    Ok(_) => check_downloaded_file(
+                        filename,
+                        content_length,
+                        content_type.as_deref(),
+                        &file_bytes,
+                    )
+                    .map(|_| DownloadedFile {
+                        bytes: file_bytes,
+                        content_type,
+                        transfer: Some(transfer),
+                    })
+                    .map_err(|e| (e, FailureKind::Other)),
+                    Err(e) => Err((
+                        format!("Error:\n{}", e),
+                        if e.kind() == io::ErrorKind::TimedOut {
+                            FailureKind::Timeout
+                        } else {
+                            FailureKind::Other
+                        },
+                    )),
                 }
             }
+            Err(e) => Err((format!("Error:\n{}", e), FailureKind::classify(&e))),
+        };
 
-            // Create the file, and get reference to it.
-            let mut file_writer = match File::create(filepath) {
-                Ok(file_writer) => file_writer,
-                Err(e) => {
-                    println!("Failed to create file: '{:?}'! Error:\n{}", filepath, e);
-                    std::process::exit(EXIT_CODE_FILE_IO_ERROR);
-                }
-            };
+        match result {
+            Ok(file) => return Ok(file),
+            Err((_, kind))
+                if kind.is_systemic()
+                    && http::record_network_failure(kind, options.max_retries_total) =>
+            {
+                return Err(network_down_error());
+            }
+            Err((e, kind)) if attempt >= options.retries || !kind.is_retryable() => {
+                return Err(FetchError::failed(e, FileFailure::Download(kind)));
+            }
+            Err((e, _)) => {
+                attempt += 1;
+                println!(
+                    "{color_yellow}WARN:{color_reset} Failed to fetch file: '{}'! {} Retrying {color_bright_black}({}/{}){color_reset}...",
+                    url, e, attempt, options.retries
+                );
+                // Back off a bit, in case the server is overloaded.
+                std::thread::sleep(Duration::from_millis(500 * attempt as u64));
+            }
+        }
+    }
+}
 
-            // Write the contents to the file.
-            match file_writer.write_all(&file_bytes) {
-                Ok(_) => (),
-                Err(e) => {
-                    println!("Failed to write to file: '{:?}'! Error:\n{}", filepath, e);
-                    std::process::exit(EXIT_CODE_FILE_IO_ERROR);
-                }
+/// Checks if a URL leads to the specimen page of a font: it can be reached and is a success,
+/// and any redirects still end up at the specimen page of the same font (not e.g. at the search).
+fn is_specimen_page(url: &str, font_name: &str) -> bool {
+    http::get(url).is_ok_and(|response| {
+        response.status().is_success() && http::is_specimen_url(response.url(), font_name)
+    })
+}
+
+/// Downloads a file from the mirrors in `options.mirrors` in order, falling back to the next one on failure,
+/// and to the original URL after the last one. With mirrors, logs which source the file came from.
+pub fn download_from_mirrors(
+    url: &str,
+    filename: &str,
+    options: &Options,
+) -> Result<DownloadedFile, FetchError> {
+    for mirror in &options.mirrors {
+        let Some(mirror_url) = http::mirror_url(url, mirror) else {
+            break;
+        };
+        match download_file(&mirror_url, filename, options) {
+            Ok(file) => {
+                info!(
+                    "Got {color_blue}'{}'{color_reset} from the mirror {color_blue}'{}'{color_reset}.",
+                    filename, mirror
+                );
+                return Ok(file);
+            }
+            Err(e) if http::is_network_down() => return Err(e),
+            Err(e) => println!(
+                "{color_yellow}WARN:{color_reset} Failed to fetch file: '{}' from the mirror '{}'! {} Trying the next source...",
+                filename, mirror, e
+            ),
+        }
+    }
+
+    let file = download_file(url, filename, options)?;
+    if !options.mirrors.is_empty() {
+        info!(
+            "Got {color_blue}'{}'{color_reset} from the original URL {color_bright_black}(no mirror had it){color_reset}.",
+            filename
+        );
+    }
+    Ok(file)
+}
+
+/// Asks the server for the size of a file with a HEAD request, retrying up to `options.retries` times like a download.
+/// Gives `None` if the server doesn't say, or the request keeps failing.
+fn remote_size(url: &str, options: &Options) -> Option<u64> {
+    let mut attempt = 0;
+    loop {
+        if http::is_network_down() {
+            return None;
+        }
+        let kind = match http::head_download(url) {
+            // Not `content_length()`, which gives the size of the (empty) body of the HEAD response.
+            Ok(response) if response.status().is_success() => {
+                return response
+                    .headers()
+                    .get(CONTENT_LENGTH)?
+                    .to_str()
+                    .ok()?
+                    .parse::<u64>()
+                    .ok();
+            }
+            Ok(response) => FailureKind::Status(response.status().as_u16()),
+            Err(e) => FailureKind::classify(&e),
+        };
+        if (kind.is_systemic() && http::record_network_failure(kind, options.max_retries_total))
+            || attempt >= options.retries
+            || !kind.is_retryable()
+        {
+            return None;
+        }
+        attempt += 1;
+        std::thread::sleep(Duration::from_millis(500 * attempt as u64));
+    }
+}
+
+/// Asks the mirrors in `options.mirrors` for the size of a file in order, like `download_from_mirrors`,
+/// and the original URL after the last one.
+fn remote_size_from_mirrors(url: &str, options: &Options) -> Option<u64> {
+    options
+        .mirrors
+        .iter()
+        .filter_map(|mirror| http::mirror_url(url, mirror))
+        .chain([url.to_string()])
+        .find_map(|url| remote_size(&url, options))
+}
+
+#[cfg(test)]
+thread_local! {
+    /// The dirs `create_dir` was called with on this thread, so tests can count the directory creations.
+    static CREATE_DIR_CALLS: std::cell::RefCell<Vec<PathBuf>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Creates a directory and its missing parents, like `mkdir -p`.
+/// Only directories fonty creates get their permissions changed, existing ones are left alone.
+pub fn create_dir(dir: &Path, options: &Options) -> io::Result<()> {
+    #[cfg(test)]
+    CREATE_DIR_CALLS.with_borrow_mut(|calls| calls.push(dir.to_path_buf()));
+
+    let created_dirs = dir
+        .ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .collect::<Vec<_>>();
+
+    create_dir_all(long_path(dir)).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to create directory: '{}'! {}", dir.display(), e),
+        )
+    })?;
+    for dir in created_dirs {
+        set_mode(dir, options.dir_mode);
+    }
+    Ok(())
+}
+
+/// Creates the parent directories of the files about to be written, each one once,
+/// instead of checking them again for every file. A directory that can't be created is left for writing its files
+/// to fail on, so they fail on their own.
+pub fn create_parent_dirs(filepaths: &[PathBuf], options: &Options) {
+    let parent_dirs = filepaths
+        .iter()
+        .filter_map(|filepath| filepath.parent())
+        .collect::<BTreeSet<_>>();
+    for parent_dir in parent_dirs {
+        let _ = create_dir(parent_dir, options);
+    }
+}
+
+/// Writes a file to disk, creating its parent directories if needed.
+/// The contents are written to a temporary file next to it first, which is then renamed over the file (like the registry
+/// is saved), so an interrupted or failed write never leaves a truncated file behind, and the old file stays intact.
+/// The file and the directories created for it get the permissions from the options, and with `--fsync` it is synced to disk.
+/// The error says which step failed, and keeps the kind of the error, e.g. for `print_permission_hints`.
+pub fn write_file_to_disk(filepath: &Path, contents: &[u8], options: &Options) -> io::Result<()> {
+    let (Some(parent_dir), Some(filename)) = (filepath.parent(), filepath.file_name()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid file path: '{}'", filepath.display()),
+        ));
+    };
+
+    // The dirs are usually created up front by `create_parent_dirs`, this is for when one disappeared since.
+    if !parent_dir.is_dir() {
+        create_dir(parent_dir, options)?;
+    }
+
+    // Hidden, so it doesn't show up as a font if it is ever left behind.
+    let temporary_path = parent_dir.join(format!(".{}.tmp", filename.to_string_lossy()));
+    let step_error = |step: &str, path: &Path, e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to {} file: '{}'! {}", step, path.display(), e),
+        )
+    };
+
+    // Create the temporary file, and get reference to it.
+    let mut file = File::create(long_path(&temporary_path))
+        .map_err(|e| step_error("create", &temporary_path, e))?;
+
+    // Write the contents to the file in one go, they are already in memory. Errors can also only show up when flushing
+    // (e.g. a full disk), so the file only counts as written once it is flushed (and synced with --fsync).
+    let written = file
+        .write_all(contents)
+        .and_then(|_| file.flush())
+        .and_then(|_| {
+            if options.fsync {
+                file.sync_all()
+            } else {
+                Ok(())
+            }
+        });
+    drop(file);
+
+    // The permissions are set before renaming, so the file never has other ones.
+    let result = written
+        .map_err(|e| step_error("write to", filepath, e))
+        .and_then(|_| {
+            set_mode(&temporary_path, options.file_mode);
+            std::fs::rename(long_path(&temporary_path), long_path(filepath))
+                .map_err(|e| step_error("replace", filepath, e))
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_file(long_path(&temporary_path));
+    }
+    result
+}
+
+/// Sets the permissions of a file or directory, regardless of the umask.
+/// Failing to do so is not fatal, the fonts are still installed.
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) {
+    use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+    if let Err(e) = std::fs::set_permissions(path, Permissions::from_mode(mode)) {
+        println!(
+            "{color_yellow}WARN:{color_reset} Failed to set the permissions of {color_blue}'{}'{color_reset} to {:o}! Error:\n{}",
+            path.display(),
+            mode,
+            e
+        );
+    }
+}
+
+/// Sets the permissions of a file or directory, regardless of the umask.
+/// Only Unix has permission bits, elsewhere this does nothing.
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) {}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, sync::Arc};
+
+    use url::Url;
+
+    use super::*;
+    use crate::{
+        cli::Cli,
+        config::Config,
+        fixtures::{Fixture, RecordingFetcher, replay::ReplayFetcher},
+        options::resolve_options,
+        sha256::sha256_hex,
+        testing::{MockResponse, MockServer, MockServerFetcher, TestDir},
+    };
+
+    fn options() -> Options {
+        let cli = Cli::parse(["fetch".to_string()].into_iter()).unwrap();
+        resolve_options(&cli, &HashMap::new(), &Config::default()).unwrap()
+    }
+
+    fn manifest(file_refs: &str) -> FontManifest {
+        parse_manifest(&format!(
+            r#"{{"zipName": "Test.zip", "manifest": {{"files": [], "fileRefs": {}}}}}"#,
+            file_refs
+        ))
+        .unwrap()
+    }
+
+    /// A server that has the files `have`, and answers 404 for the others.
+    fn file_server(have: &'static [&'static str]) -> MockServer {
+        MockServer::start(move |request| {
+            if have.contains(&request.path.as_str()) {
+                MockResponse::ok(format!("wOF2 {}", request.path))
+                    .header("Content-Type", "font/woff2")
+            } else {
+                MockResponse::status(404)
+            }
+        })
+    }
+
+    #[test]
+    fn downloads_try_the_mirrors_in_order_before_the_original_url() {
+        let first_mirror = file_server(&[]);
+        let second_mirror = file_server(&["/mirror/s/a.woff2"]);
+        let original = file_server(&["/s/a.woff2", "/s/b.woff2"]);
+        let options = Options {
+            mirrors: vec![
+                Url::parse(&first_mirror.url("/")).unwrap(),
+                Url::parse(&second_mirror.url("/mirror")).unwrap(),
+            ],
+            retries: 0,
+            ..options()
+        };
+        let paths = |server: &MockServer| {
+            server
+                .requests()
+                .into_iter()
+                .map(|request| request.path)
+                .collect::<Vec<_>>()
+        };
+
+        // The first mirror that has the file wins, the original URL is not asked.
+        let file = download_from_mirrors(&original.url("/s/a.woff2"), "a.woff2", &options).unwrap();
+        assert_eq!(file.bytes, b"wOF2 /mirror/s/a.woff2");
+        assert_eq!(paths(&first_mirror), ["/s/a.woff2"]);
+        assert_eq!(paths(&second_mirror), ["/mirror/s/a.woff2"]);
+        assert!(paths(&original).is_empty());
+
+        // Without a mirror that has it, it comes from the original URL.
+        let file = download_from_mirrors(&original.url("/s/b.woff2"), "b.woff2", &options).unwrap();
+        assert_eq!(file.bytes, b"wOF2 /s/b.woff2");
+        assert_eq!(paths(&first_mirror), ["/s/a.woff2", "/s/b.woff2"]);
+        assert_eq!(
+            paths(&second_mirror),
+            ["/mirror/s/a.woff2", "/mirror/s/b.woff2"]
+        );
+        assert_eq!(paths(&original), ["/s/b.woff2"]);
+    }
+
+    #[test]
+    fn fonts_are_valid_if_they_end_up_at_their_specimen_page() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/specimen/Open+Sans" => MockResponse::ok("specimen"),
+            "/specimen/open+sans" => MockResponse::redirect("/specimen/Open+Sans"),
+            "/specimen/Nope" => MockResponse::redirect("/?query=Nope"),
+            "/specimen/Loop" => MockResponse::redirect("/specimen/Loop"),
+            "/specimen/Other" => MockResponse::redirect("/specimen/Open+Sans"),
+            "/" | "/?query=Nope" => MockResponse::ok("search"),
+            _ => MockResponse::status(404),
+        });
+
+        assert!(is_specimen_page(
+            &server.url("/specimen/Open+Sans"),
+            "Open Sans"
+        ));
+        // Google redirects names in another case to the specimen page.
+        assert!(is_specimen_page(
+            &server.url("/specimen/open+sans"),
+            "open sans"
+        ));
+        // Unknown fonts are redirected to the search, which is a success too.
+        assert!(!is_specimen_page(&server.url("/specimen/Nope"), "Nope"));
+        // A specimen page, but of another font.
+        assert!(!is_specimen_page(&server.url("/specimen/Other"), "Other"));
+        assert!(!is_specimen_page(&server.url("/specimen/Loop"), "Loop"));
+        assert!(!is_specimen_page(
+            &server.url("/specimen/Missing"),
+            "Missing"
+        ));
+    }
+
+    #[test]
+    fn downloaded_fonts_start_like_a_font() {
+        for magic in [
+            &b"\x00\x01\x00\x00"[..],
+            b"OTTO",
+            b"true",
+            b"ttcf",
+            b"wOFF",
+            b"wOF2",
+        ] {
+            let body = [magic, b" rest of the font"].concat();
+            assert_eq!(
+                check_downloaded_file("a.ttf", Some(body.len() as u64), Some("font/ttf"), &body),
+                Ok(()),
+                "{:?}",
+                magic
+            );
+        }
+        // Text files can be anything.
+        assert_eq!(
+            check_downloaded_file("OFL.txt", None, Some("text/plain"), b"license"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn broken_downloads_are_rejected() {
+        assert_eq!(
+            check_downloaded_file("a.woff2", Some(0), Some("font/woff2"), b""),
+            Err("Got an empty file.".to_string())
+        );
+        assert_eq!(
+            check_downloaded_file("a.woff2", Some(10), Some("font/woff2"), b"wOF2"),
+            Err("Got 4 bytes, but expected 10.".to_string())
+        );
+        // E.g. an error page of a CDN, served with a success status.
+        let html = b"<!DOCTYPE html><html><body>Error</body></html>";
+        assert_eq!(
+            check_downloaded_file("a.woff2", None, Some("text/html"), html),
+            Err("Got a file that does not look like a font.".to_string())
+        );
+        // Starting like a font doesn't help when it is served as text.
+        assert_eq!(
+            check_downloaded_file("a.woff2", None, Some("text/html; charset=utf-8"), b"wOF2"),
+            Err("Got a file that does not look like a font (Content-Type 'text/html; charset=utf-8').".to_string())
+        );
+    }
+
+    #[test]
+    fn sizes_in_the_manifest_are_parsed() {
+        let font_manifest = manifest(
+            r#"[{"filename": "a.ttf", "url": "http://127.0.0.1:9/a.ttf", "size": 100},
+                {"filename": "b.ttf", "url": "http://127.0.0.1:9/b.ttf"}]"#,
+        );
+        let sizes = font_manifest
+            .file_refs
+            .iter()
+            .map(|file_ref| file_ref.size)
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [Some(100), None]);
+    }
+
+    #[test]
+    fn download_size_uses_the_sizes_in_the_manifest() {
+        // The URLs point nowhere, so the sizes can only come from the manifest.
+        let font_manifest = manifest(
+            r#"[{"filename": "a.ttf", "url": "http://127.0.0.1:9/a.ttf", "size": 100},
+                {"filename": "b.ttf", "url": "http://127.0.0.1:9/b.ttf", "size": 50}]"#,
+        );
+        assert_eq!(font_manifest.download_size(&options(), None, 1000), 150);
+    }
+
+    #[test]
+    fn download_size_stops_asking_past_the_limit() {
+        let font_manifest = manifest(
+            r#"[{"filename": "a.ttf", "url": "http://127.0.0.1:9/a.ttf", "size": 100},
+                {"filename": "b.ttf", "url": "http://127.0.0.1:9/b.ttf"}]"#,
+        );
+        assert_eq!(font_manifest.download_size(&options(), None, 10), 100);
+    }
+
+    /// The body of a fixture of a response of Google Fonts, with its anti-XSSI prefix.
+    fn fixture_payload(fixture: &str) -> String {
+        let fixture: Value = serde_json::from_str(fixture).unwrap();
+        fixture["body"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn a_manifest_in_the_shape_of_googles_is_parsed() {
+        let payload = fixture_payload(include_str!("../tests/fixtures/roboto_mono/0001.json"));
+        let font_manifest =
+            parse_manifest(http::strip_anti_xssi_prefix(&payload).unwrap()).unwrap();
+        let files = font_manifest
+            .files
+            .iter()
+            .map(|file| file.filename.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["OFL.txt", "README.txt"]);
+        assert_eq!(font_manifest.file_refs.len(), 16);
+        assert_eq!(
+            font_manifest.file_refs[0].filename,
+            "RobotoMono-VariableFont_wght.ttf"
+        );
+        assert!(
+            font_manifest
+                .file_refs
+                .iter()
+                .all(|file_ref| file_ref.url.starts_with("https://fonts.gstatic.com/"))
+        );
+    }
+
+    #[test]
+    fn manifests_are_fetched_from_fixtures() {
+        let fixtures = ReplayFetcher::load(Path::new("tests/fixtures/roboto_mono")).unwrap();
+        http::set_fetcher(Arc::new(fixtures));
+        let font_manifest = FontManifest::load("Roboto Mono", &options(), None).unwrap();
+        assert_eq!(font_manifest.files.len(), 2);
+        assert_eq!(font_manifest.file_refs.len(), 16);
+
+        let error = FontManifest::load("Roboto Flex", &options(), None).unwrap_err();
+        assert!(
+            matches!(error, FontyError::InvalidManifest { .. }),
+            "{:?}",
+            error
+        );
+    }
+
+    #[test]
+    fn recorded_fixtures_replay_what_was_served() {
+        let written: Fixture =
+            serde_json::from_str(include_str!("../tests/fixtures/roboto_mono/0001.json")).unwrap();
+        let body = written.body.clone().unwrap();
+        let server = MockServer::start(move |request| match request.path.as_str() {
+            "/download/list?family=Roboto+Mono" => MockResponse::ok(body.clone())
+                .header("Content-Type", "application/json; charset=utf-8"),
+            "/s/test/0.woff2" | "/s/test/1.woff2" => {
+                MockResponse::ok(format!("wOF2 {}", request.path))
+                    .header("Content-Type", "font/woff2")
+            }
+            _ => MockResponse::status(404),
+        });
+        let test_dir = TestDir::new("recorded-fixtures");
+        let fixtures_dir = test_dir.path.join("fixtures");
+        let recorder = RecordingFetcher::new(
+            fixtures_dir.clone(),
+            Arc::new(MockServerFetcher::new(&server)),
+        )
+        .unwrap();
+        http::set_fetcher(Arc::new(recorder));
+
+        let served = FontManifest::load("Roboto Mono", &options(), None).unwrap();
+        // The downloads are recorded on the worker threads.
+        let font_manifest = manifest(
+            r#"[{"filename": "0.woff2", "url": "https://fonts.gstatic.com/s/test/0.woff2"},
+                {"filename": "1.woff2", "url": "https://fonts.gstatic.com/s/test/1.woff2"}]"#,
+        );
+        let options = Options {
+            jobs: 2,
+            ..options()
+        };
+        let results = font_manifest.fetch_files_from_refs(
+            &test_dir.path.join("fonts"),
+            &options,
+            &HashSet::new(),
+            None,
+        );
+        assert!(
+            results
+                .iter()
+                .all(|result| result.status == FileStatus::Written),
+            "{:?}",
+            results
+        );
+
+        // The manifest is recorded just like the fixture that was written by hand.
+        let read_fixture = |name: &str| -> Fixture {
+            serde_json::from_str(&fs::read_to_string(fixtures_dir.join(name)).unwrap()).unwrap()
+        };
+        let recorded = read_fixture("0001.json");
+        assert_eq!(recorded.method, written.method);
+        assert_eq!(recorded.url, written.url);
+        assert_eq!(recorded.final_url, written.final_url);
+        assert_eq!(recorded.status, written.status);
+        assert_eq!(
+            recorded.headers.get("content-type"),
+            written.headers.get("content-type")
+        );
+        assert_eq!(recorded.body, written.body);
+        // Font files only get their hash and size.
+        let mut downloads = [read_fixture("0002.json"), read_fixture("0003.json")];
+        downloads.sort_by(|a, b| a.url.cmp(&b.url));
+        for (index, download) in downloads.iter().enumerate() {
+            let bytes = format!("wOF2 /s/test/{}.woff2", index);
+            assert_eq!(
+                download.url,
+                format!("https://fonts.gstatic.com/s/test/{}.woff2", index)
+            );
+            assert_eq!(download.body, None);
+            assert_eq!(download.body_sha256, Some(sha256_hex(bytes.as_bytes())));
+            assert_eq!(download.body_bytes, Some(bytes.len() as u64));
+        }
+
+        // Replaying the recording gives the manifest that was served.
+        http::set_fetcher(Arc::new(ReplayFetcher::load(&fixtures_dir).unwrap()));
+        let replayed = FontManifest::load("Roboto Mono", &options, None).unwrap();
+        assert_eq!(
+            serde_json::to_value(&replayed.file_refs).unwrap(),
+            serde_json::to_value(&served.file_refs).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&replayed.files).unwrap(),
+            serde_json::to_value(&served.files).unwrap()
+        );
+        assert_eq!(served.file_refs.len(), 16);
+    }
+
+    #[test]
+    fn downloads_on_worker_threads_go_through_the_fetcher_of_the_caller() {
+        let test_dir = TestDir::new("replayed-downloads");
+        let fixtures_dir = test_dir.path.join("fixtures");
+        fs::create_dir(&fixtures_dir).unwrap();
+        let urls = (0..8)
+            .map(|index| format!("https://fonts.gstatic.com/s/test/{}.woff2", index))
+            .collect::<Vec<_>>();
+        for (index, url) in urls.iter().enumerate() {
+            let fixture = Fixture {
+                method: "GET".to_string(),
+                url: url.clone(),
+                final_url: url.clone(),
+                status: 200,
+                headers: [("content-type".to_string(), "font/woff2".to_string())].into(),
+                body: Some(format!("wOF2 font {}", index)),
+                body_sha256: None,
+                body_bytes: None,
             };
-            println!("{color_green}DONE!{color_reset}");
+            let path = fixtures_dir.join(format!("{:04}.json", index + 1));
+            fs::write(path, serde_json::to_string(&fixture).unwrap()).unwrap();
+        }
+        http::set_fetcher(Arc::new(ReplayFetcher::load(&fixtures_dir).unwrap()));
+
+        let file_refs = urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| format!(r#"{{"filename": "{}.woff2", "url": "{}"}}"#, index, url))
+            .collect::<Vec<_>>();
+        let font_manifest = manifest(&format!("[{}]", file_refs.join(",")));
+        let options = Options {
+            jobs: 4,
+            ..options()
+        };
+        let results =
+            font_manifest.fetch_files_from_refs(&test_dir.path, &options, &HashSet::new(), None);
+        for (index, result) in results.iter().enumerate() {
+            assert_eq!(result.status, FileStatus::Written, "{:?}", result);
+            assert_eq!(
+                fs::read_to_string(&result.path).unwrap(),
+                format!("wOF2 font {}", index)
+            );
+        }
+    }
+
+    // The umask of the process, which std has no wrapper for. `mode_t` is 16 bits on the BSDs and macOS.
+    #[cfg(unix)]
+    unsafe extern "C" {
+        #[cfg(target_os = "linux")]
+        fn umask(mask: u32) -> u32;
+        #[cfg(not(target_os = "linux"))]
+        fn umask(mask: u16) -> u16;
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_and_dirs_get_their_modes_regardless_of_the_umask() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_dir = TestDir::new("modes");
+        let mode = |path: &str| {
+            fs::metadata(test_dir.path.join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o777
+        };
+        let cli = Cli::parse(
+            ["fetch", "--mode", "640", "--dir-mode", "750"]
+                .into_iter()
+                .map(String::from),
+        )
+        .unwrap();
+        let custom_options = resolve_options(&cli, &HashMap::new(), &Config::default()).unwrap();
+
+        // Without the modes being set, files would be 0600 and dirs 0700. The umask is process-wide, but the other
+        // tests don't depend on it.
+        let previous_umask = unsafe { umask(0o077) };
+        let written = write_file_to_disk(
+            &test_dir.path.join("default/static/OFL.txt"),
+            b"license",
+            &options(),
+        )
+        .and_then(|_| {
+            write_file_to_disk(
+                &test_dir.path.join("custom/static/OFL.txt"),
+                b"license",
+                &custom_options,
+            )
+        });
+        unsafe { umask(previous_umask) };
+        written.unwrap();
+
+        assert_eq!(mode("default/static/OFL.txt"), 0o644);
+        assert_eq!(mode("default/static"), 0o755);
+        assert_eq!(mode("default"), 0o755);
+        assert_eq!(mode("custom/static/OFL.txt"), 0o640);
+        assert_eq!(mode("custom/static"), 0o750);
+        assert_eq!(mode("custom"), 0o750);
+    }
+
+    #[test]
+    fn files_that_cant_be_written_fail_on_their_own() {
+        let test_dir = TestDir::new("unwritable-files");
+        // A regular file where a directory should be can't be written into, even as root.
+        fs::write(test_dir.path.join("static"), "not a directory").unwrap();
+        let font_manifest = parse_manifest(
+            r#"{"zipName": "Test.zip", "manifest": {"files": [
+                {"filename": "static/OFL.txt", "contents": "license"},
+                {"filename": "README.txt", "contents": "readme"}
+            ], "fileRefs": []}}"#,
+        )
+        .unwrap();
+
+        let results = font_manifest.write_files(&test_dir.path, &options(), &HashSet::new());
+        assert!(
+            matches!(&results[0].status, FileStatus::Failed { reason, failure: FileFailure::Write } if reason.contains("static")),
+            "{:?}",
+            results[0]
+        );
+        assert_eq!(results[1].status, FileStatus::Written);
+        assert_eq!(
+            fs::read_to_string(test_dir.path.join("README.txt")).unwrap(),
+            "readme"
+        );
+    }
+
+    #[test]
+    fn failed_writes_leave_no_temporary_file_behind() {
+        let test_dir = TestDir::new("failed-rename");
+        // A non-empty directory can't be replaced by a file, so renaming the temporary file over it fails.
+        let filepath = test_dir.path.join("Font.ttf");
+        fs::create_dir(&filepath).unwrap();
+        fs::write(filepath.join("inside"), "").unwrap();
+
+        let error = write_file_to_disk(&filepath, b"font", &options()).unwrap_err();
+        assert!(
+            error.to_string().contains("Failed to replace file"),
+            "{}",
+            error
+        );
+        let leftovers = fs::read_dir(&test_dir.path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(leftovers, ["Font.ttf"]);
+    }
+
+    fn manifest_with_files() -> FontManifest {
+        parse_manifest(
+            r#"{"zipName": "Test.zip", "manifest": {"files": [
+                {"filename": "OFL.txt", "contents": "license"}
+            ], "fileRefs": [
+                {"filename": "static/Test-Regular.ttf", "url": "https://fonts.gstatic.com/a.ttf"},
+                {"filename": "Test[wght].ttf", "url": "https://fonts.gstatic.com/b.ttf"}
+            ]}}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn paths_are_prepended_to_every_filename() {
+        assert_eq!(
+            manifest_with_files()
+                .prepend_path_to_files("Test")
+                .filenames(),
+            [
+                "Test/OFL.txt",
+                "Test/static/Test-Regular.ttf",
+                "Test/Test[wght].ttf"
+            ]
+        );
+        // Paths with dirs in them are prepended as a whole, in front of the dirs the filenames already have.
+        assert_eq!(
+            manifest_with_files()
+                .prepend_path_to_files("fonts/Test")
+                .filenames(),
+            [
+                "fonts/Test/OFL.txt",
+                "fonts/Test/static/Test-Regular.ttf",
+                "fonts/Test/Test[wght].ttf"
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_paths_leave_the_filenames_as_they_are() {
+        assert_eq!(
+            manifest_with_files().prepend_path_to_files("").filenames(),
+            ["OFL.txt", "static/Test-Regular.ttf", "Test[wght].ttf"]
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn the_misspelled_name_still_prepends() {
+        assert_eq!(
+            manifest_with_files()
+                .prepand_path_to_files("Test")
+                .filenames(),
+            manifest_with_files()
+                .prepend_path_to_files("Test")
+                .filenames()
+        );
+    }
+
+    /// The dirs `create_dir` was called with on this thread since the last call.
+    fn take_create_dir_calls() -> Vec<PathBuf> {
+        CREATE_DIR_CALLS.with_borrow_mut(std::mem::take)
+    }
+
+    #[test]
+    fn each_parent_dir_is_created_once() {
+        let test_dir = TestDir::new("unique-parent-dirs");
+        let filenames = (0..60)
+            .map(|i| format!("static/Font-{}.ttf", i))
+            .chain((0..5).map(|i| format!("variable/Font-{}.ttf", i)))
+            .chain(["OFL.txt".to_string()])
+            .collect::<Vec<_>>();
+        let files = filenames
+            .iter()
+            .map(|filename| format!(r#"{{"filename": "{}", "contents": "font"}}"#, filename))
+            .collect::<Vec<_>>();
+        let font_manifest = parse_manifest(&format!(
+            r#"{{"zipName": "Test.zip", "manifest": {{"files": [{}], "fileRefs": []}}}}"#,
+            files.join(", ")
+        ))
+        .unwrap();
+        let options = options();
+
+        take_create_dir_calls();
+        create_parent_dirs(&font_manifest.destination_paths(&test_dir.path), &options);
+        let mut expected = vec![
+            test_dir.path.clone(),
+            test_dir.path.join("static"),
+            test_dir.path.join("variable"),
+        ];
+        expected.sort();
+        assert_eq!(take_create_dir_calls(), expected);
+
+        // Writing the files doesn't create the dirs again.
+        let results = font_manifest.write_files(&test_dir.path, &options, &HashSet::new());
+        assert!(
+            results
+                .iter()
+                .all(|result| result.status == FileStatus::Written)
+        );
+        assert_eq!(take_create_dir_calls(), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn dirs_that_disappear_before_writing_are_created_again() {
+        let test_dir = TestDir::new("disappearing-parent-dir");
+        let filepaths = (0..3)
+            .map(|i| test_dir.path.join("static").join(format!("Font-{}.ttf", i)))
+            .collect::<Vec<_>>();
+        let options = options();
+
+        create_parent_dirs(&filepaths, &options);
+        // Something else removes the dir between creating it and writing the files into it.
+        fs::remove_dir(test_dir.path.join("static")).unwrap();
+        take_create_dir_calls();
+
+        for filepath in &filepaths {
+            write_file_to_disk(filepath, b"font", &options).unwrap();
+        }
+        // Only the first write finds the dir missing.
+        assert_eq!(take_create_dir_calls(), [test_dir.path.join("static")]);
+        for filepath in &filepaths {
+            assert_eq!(fs::read(filepath).unwrap(), b"font");
+        }
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let font_manifest = parse_manifest(
+            r#"{"zipName": "Test.zip", "version": 2, "manifest": {"files": [], "fileRefs": [], "extra": {}}}"#,
+        )
+        .unwrap();
+        assert!(font_manifest.files.is_empty());
+        assert!(font_manifest.file_refs.is_empty());
+    }
+
+    #[test]
+    fn changed_schemas_are_described() {
+        let cases = [
+            (
+                "[]",
+                "Expected the response to be an object, found an array!",
+            ),
+            (
+                r#"{"zipName": "Test.zip"}"#,
+                "Expected the response to contain 'manifest'!",
+            ),
+            (
+                r#"{"manifest": []}"#,
+                "Expected 'manifest' to be an object, found an array!",
+            ),
+            (
+                r#"{"manifest": {"fileRefs": {}}}"#,
+                "Expected 'manifest.fileRefs' to be an array, found an object!",
+            ),
+        ];
+        for (json, expected) in cases {
+            assert_eq!(
+                parse_manifest(json).err().as_deref(),
+                Some(expected),
+                "{}",
+                json
+            );
         }
+        let error =
+            parse_manifest(r#"{"manifest": {"fileRefs": [{"filename": "a.ttf"}]}}"#).unwrap_err();
+        assert!(
+            error.starts_with("Invalid 'manifest.fileRefs[0]': "),
+            "{}",
+            error
+        );
     }
 }
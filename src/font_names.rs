@@ -0,0 +1,115 @@
+use std::fmt;
+
+/// Normalizes a font name given on the command line, by removing surrounding whitespace.
+pub fn normalize_font_name(font_name: &str) -> String {
+    font_name.trim().to_string()
+}
+
+/// The name of the directory a family's files are put in.
+pub fn family_dir_name(font_name: &str) -> String {
+    font_name.replace(" ", "_")
+}
+
+/// A font name that is empty, or only whitespace, which no family is called.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EmptyFontName {
+    /// Which of the font names it is, counting from 1.
+    pub position: usize,
+}
+
+impl fmt::Display for EmptyFontName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Font name number {} is empty", self.position)
+    }
+}
+
+/// The unique font names, and every duplicated name with the amount of times it was given.
+pub type PreparedFontNames = (Vec<String>, Vec<(String, usize)>);
+
+/// Prepares the font names given on the command line for fetching.
+/// Names are normalized, and names that only differ in case or surrounding whitespace are collapsed into one.
+/// Returns the unique font names (in the order they were first given), and every duplicated name with the amount of times it was given.
+/// Errors on the first name that is empty after normalizing.
+pub fn prepare_font_names(args: &[String]) -> Result<PreparedFontNames, EmptyFontName> {
+    let mut unique: Vec<String> = Vec::new();
+    let mut counts: Vec<usize> = Vec::new();
+
+    for (index, arg) in args.iter().enumerate() {
+        let font_name = normalize_font_name(arg);
+        if font_name.is_empty() {
+            return Err(EmptyFontName {
+                position: index + 1,
+            });
+        }
+        match unique
+            .iter()
+            .position(|name| name.to_lowercase() == font_name.to_lowercase())
+        {
+            Some(index) => counts[index] += 1,
+            None => {
+                unique.push(font_name);
+                counts.push(1);
+            }
+        }
+    }
+
+    let duplicates = unique
+        .iter()
+        .zip(&counts)
+        .filter(|&(_, &count)| count > 1)
+        .map(|(name, &count)| (name.clone(), count))
+        .collect();
+
+    Ok((unique, duplicates))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn trims_surrounding_whitespace() {
+        assert_eq!(normalize_font_name("  Open Sans\t"), "Open Sans");
+    }
+
+    #[test]
+    fn keeps_unique_names_in_order() {
+        let (unique, duplicates) = prepare_font_names(&args(&["Inter", "Lora", "Roboto"])).unwrap();
+        assert_eq!(unique, ["Inter", "Lora", "Roboto"]);
+        assert!(duplicates.is_empty());
+    }
+
+    #[test]
+    fn collapses_names_differing_in_case_and_whitespace() {
+        let (unique, duplicates) =
+            prepare_font_names(&args(&["Open Sans", " open sans ", "Lora", "OPEN SANS"])).unwrap();
+        assert_eq!(unique, ["Open Sans", "Lora"]);
+        assert_eq!(duplicates, [("Open Sans".to_string(), 3)]);
+    }
+
+    #[test]
+    fn rejects_empty_and_whitespace_only_names() {
+        assert_eq!(
+            prepare_font_names(&args(&["Inter", ""])),
+            Err(EmptyFontName { position: 2 })
+        );
+        assert_eq!(
+            prepare_font_names(&args(&["  \t", "Inter"])),
+            Err(EmptyFontName { position: 1 })
+        );
+    }
+
+    #[test]
+    fn no_names_is_not_an_error() {
+        assert_eq!(prepare_font_names(&[]), Ok((Vec::new(), Vec::new())));
+    }
+
+    #[test]
+    fn family_dirs_have_no_spaces() {
+        assert_eq!(family_dir_name("Noto Sans JP"), "Noto_Sans_JP");
+    }
+}
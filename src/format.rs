@@ -0,0 +1,129 @@
+//! Formatting numbers for output: counts with the right plural, byte counts, sizes and speeds, and dates.
+
+/// A count with the singular or plural of a noun, e.g. `1 file` or `2 files`.
+pub fn plural(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// A count of files, e.g. `1 file` or `0 files`.
+pub fn n_files(count: usize) -> String {
+    plural(count, "file", "files")
+}
+
+/// A count of fonts, e.g. `1 font` or `3 fonts`.
+pub fn n_fonts(count: usize) -> String {
+    plural(count, "font", "fonts")
+}
+
+/// A number with thousands separators, e.g. `1,234,567`.
+pub fn thousands(number: u64) -> String {
+    let digits = number.to_string();
+    let mut separated = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            separated.push(',');
+        }
+        separated.push(digit);
+    }
+    separated
+}
+
+/// A byte count with thousands separators and as a size, e.g. `1,468,006 bytes (1.4 MiB)`. Small counts are only given in bytes.
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes < 1024 {
+        plural(bytes as usize, "byte", "bytes")
+    } else {
+        format!("{} bytes ({})", thousands(bytes), format_size(bytes))
+    }
+}
+
+/// Describes a size in bytes, in powers of 1024, e.g. `1.4 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        1048576..1073741824 => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+        _ => format!("{:.1} GiB", bytes as f64 / 1073741824.0),
+    }
+}
+
+/// Describes a transfer speed, e.g. `2.3 MiB/s`.
+pub fn format_speed(bytes_per_second: f64) -> String {
+    format!("{}/s", format_size(bytes_per_second as u64))
+}
+
+/// Formats a time in seconds since the unix epoch as a UTC date, e.g. `2024-03-04`.
+pub fn format_date(unix_seconds: u64) -> String {
+    // Converts days since the epoch to a civil date, counting from 0000-03-01 so leap days end the year.
+    let days = unix_seconds / (24 * 60 * 60) + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_use_the_singular_only_for_one() {
+        assert_eq!(n_files(0), "0 files");
+        assert_eq!(n_files(1), "1 file");
+        assert_eq!(n_files(2), "2 files");
+        assert_eq!(n_files(1_000_000), "1000000 files");
+        assert_eq!(n_fonts(1), "1 font");
+        assert_eq!(n_fonts(3), "3 fonts");
+    }
+
+    #[test]
+    fn thousands_are_separated() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(1), "1");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1000), "1,000");
+        assert_eq!(thousands(123_456), "123,456");
+        assert_eq!(thousands(1_234_567), "1,234,567");
+        assert_eq!(thousands(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[test]
+    fn sizes_are_given_in_the_largest_fitting_unit() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1), "1 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1_468_006), "1.4 MiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+        // Past GiB, sizes stay in GiB.
+        assert_eq!(format_size(2048 * 1024 * 1024 * 1024), "2048.0 GiB");
+        assert_eq!(format_speed(2.5 * 1048576.0), "2.5 MiB/s");
+    }
+
+    #[test]
+    fn byte_counts_give_the_size_from_a_kibibyte() {
+        assert_eq!(format_bytes(0), "0 bytes");
+        assert_eq!(format_bytes(1), "1 byte");
+        assert_eq!(format_bytes(1023), "1023 bytes");
+        assert_eq!(format_bytes(1024), "1,024 bytes (1.0 KiB)");
+        assert_eq!(format_bytes(1_468_006), "1,468,006 bytes (1.4 MiB)");
+    }
+
+    #[test]
+    fn dates_are_in_utc() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(1), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_709_510_400), "2024-03-04");
+        assert_eq!(format_date(4_102_444_799), "2099-12-31");
+    }
+}
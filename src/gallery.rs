@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use url::Url;
+
+use crate::{
+    catalog::Catalog,
+    font_face::{FontFace, FontWeight, face_from_filename, font_format},
+    format::plural,
+    registry::{Registry, RegistryEntry},
+    sfnt::axis_range,
+};
+
+/// The text every family is shown with, below its name.
+const SAMPLE_TEXT: &str =
+    "The quick brown fox jumps over the lazy dog. Sphinx of black quartz, judge my vow! 0123456789";
+
+/// The heading for families the catalog does not know.
+const UNKNOWN_CATEGORY: &str = "Other";
+
+/// What went into a gallery.
+pub struct GallerySummary {
+    pub families: usize,
+    /// The families none of whose files could be attributed to a weight, so they are not shown.
+    pub skipped: Vec<String>,
+}
+
+/// Escapes text for HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Builds the `@font-face` rules of an installed family, pointing at its files with `file://` URLs.
+/// The weight range of variable fonts is read from the font, one file at a time.
+fn family_faces(family: &RegistryEntry, base_path: &Path) -> Vec<FontFace> {
+    family
+        .files
+        .iter()
+        .filter_map(|file| {
+            let filename = file.path.to_string_lossy();
+            let face_name = face_from_filename(&filename)?;
+            let path = base_path.join(&file.path);
+            let weight = match face_name.weight {
+                Some(weight) => FontWeight::Fixed(weight),
+                None => {
+                    let (min, max) = axis_range(&fs::read(&path).ok()?, b"wght")?;
+                    FontWeight::Range(min.round() as u16, max.round() as u16)
+                }
+            };
+            Some(FontFace {
+                family: family.name.clone(),
+                weight,
+                italic: face_name.italic,
+                url: Url::from_file_path(&path).ok()?.to_string(),
+                format: font_format(&filename)?,
+            })
+        })
+        .collect()
+}
+
+/// Writes a self-contained HTML page showing every installed family in itself, family by family,
+/// so only the faces of one family are in memory at a time. With a catalog, families are grouped by category.
+/// `base_path` has to be absolute, for the `file://` URLs.
+pub fn write_gallery(
+    out: &mut impl Write,
+    registry: &Registry,
+    base_path: &Path,
+    catalog: Option<&Catalog>,
+) -> io::Result<GallerySummary> {
+    let mut groups = BTreeMap::<&str, Vec<&RegistryEntry>>::new();
+    for family in &registry.families {
+        let category = catalog.map(|catalog| {
+            catalog
+                .families
+                .iter()
+                .find(|catalog_family| catalog_family.family.eq_ignore_ascii_case(&family.name))
+                .map_or(UNKNOWN_CATEGORY, |catalog_family| {
+                    catalog_family.category.as_str()
+                })
+        });
+        groups
+            .entry(category.unwrap_or(""))
+            .or_default()
+            .push(family);
+    }
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "  <meta charset=\"utf-8\">")?;
+    writeln!(out, "  <title>Installed fonts</title>")?;
+    writeln!(
+        out,
+        "  <style>\n    body {{ font-family: sans-serif; margin: 2rem auto; max-width: 60rem; padding: 0 1rem; }}\n    section {{ border-bottom: 1px solid #ddd; padding: 1rem 0; }}\n    h3 {{ font-size: 2.5rem; font-weight: 400; margin: 0; }}\n    p {{ font-size: 1.25rem; }}\n    .footnote {{ color: #666; font-size: 0.9rem; }}\n  </style>"
+    )?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "  <h1>Installed fonts</h1>")?;
+
+    let mut summary = GallerySummary {
+        families: 0,
+        skipped: Vec::new(),
+    };
+    for (category, mut families) in groups {
+        families.sort_by_key(|family| family.name.to_lowercase());
+        if !category.is_empty() {
+            writeln!(out, "  <h2>{}</h2>", escape_html(category))?;
+        }
+        for family in families {
+            let faces = family_faces(family, base_path);
+            if faces.is_empty() {
+                summary.skipped.push(family.name.clone());
+                continue;
+            }
+            summary.families += 1;
+
+            let name = escape_html(&family.name);
+            writeln!(out, "  <section>")?;
+            writeln!(out, "    <style>")?;
+            for face in &faces {
+                write!(out, "{}", face.render("      "))?;
+            }
+            writeln!(out, "    </style>")?;
+            // The family name is a CSS string inside an HTML attribute, so quotes in it are escaped for both.
+            let font_family = escape_html(&format!(
+                "'{}'",
+                family.name.replace('\\', "\\\\").replace('\'', "\\'")
+            ));
+            writeln!(
+                out,
+                "    <h3 style=\"font-family: {}, sans-serif\">{}</h3>",
+                font_family, name
+            )?;
+            writeln!(
+                out,
+                "    <p style=\"font-family: {}, sans-serif\">{}</p>",
+                font_family, SAMPLE_TEXT
+            )?;
+            writeln!(
+                out,
+                "    <div class=\"footnote\">{}</div>",
+                plural(faces.len(), "face", "faces")
+            )?;
+            writeln!(out, "  </section>")?;
+        }
+    }
+
+    if !summary.skipped.is_empty() {
+        writeln!(
+            out,
+            "  <p class=\"footnote\">Not shown, as their files could not be attributed to weights: {}.</p>",
+            summary
+                .skipped
+                .iter()
+                .map(|name| escape_html(name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(summary)
+}
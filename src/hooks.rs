@@ -0,0 +1,36 @@
+use std::{
+    io,
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+/// Quotes a value for the shell the hooks run in, so family names with spaces stay one argument.
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Fills in the placeholders of a hook command: `{families}` becomes the installed families and `{base_path}` the font base dir,
+/// each quoted for the shell, with the families separated by spaces.
+pub fn expand_hook_command(command: &str, families: &[String], base_path: &Path) -> String {
+    let families = families
+        .iter()
+        .map(|family| shell_quote(family))
+        .collect::<Vec<_>>()
+        .join(" ");
+    command
+        .replace("{families}", &families)
+        .replace("{base_path}", &shell_quote(&base_path.to_string_lossy()))
+}
+
+/// Runs a hook command through the shell (`sh -c`, or `cmd /C` on Windows), with the output going to the terminal.
+pub fn run_hook(command: &str) -> io::Result<ExitStatus> {
+    if cfg!(windows) {
+        Command::new("cmd").arg("/C").arg(command).status()
+    } else {
+        Command::new("sh").arg("-c").arg(command).status()
+    }
+}
@@ -0,0 +1,1214 @@
+//! Translations of the messages fonty prints, picked from the locale (`LC_ALL`, `LC_MESSAGES` or `LANG`).
+//! Messages are looked up by key in the catalog of the language, falling back to English, with `msg!`.
+
+use crate::color::*;
+use std::{collections::HashMap, ffi::OsString, fmt, sync::OnceLock};
+
+/// The languages fonty has messages in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+/// The language of the messages, set once at startup. English until then.
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+/// Picks the language of the messages from the locale, like gettext: the first of `LC_ALL`, `LC_MESSAGES` and `LANG` that is set.
+/// Locales without a catalog (and `C`/`POSIX`) get English.
+pub fn language_from_env(env: &HashMap<String, OsString>) -> Language {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env.get(*name))
+        .map(|value| value.to_string_lossy())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    // E.g. `de_DE.UTF-8` or `de_AT@euro`.
+    let language = locale.split(['_', '.', '@']).next().unwrap_or("");
+    match language {
+        "de" => Language::German,
+        _ => Language::English,
+    }
+}
+
+/// Sets the language of the messages, for the rest of the run.
+pub fn set_language(language: Language) {
+    let _ = LANGUAGE.set(language);
+}
+
+/// The English messages. Every key has to be in here, the other catalogs may leave some out.
+/// `{name}` is replaced by the argument with that name, `{blue}`, `{gray}`, `{white}`, `{green}`, `{yellow}` and `{reset}` by colors.
+/// Messages with a count have a `.one` and an `.other` key, see `msg_n!`.
+const ENGLISH: &[(&str, &str)] = &[
+    ("fetch.no_fonts", "No fonts specified!"),
+    ("fetch.no_fonts_to_update", "No fonts to update!"),
+    (
+        "fetch.duplicate",
+        "{blue}'{font}'{reset} specified {count} times, fetching once.",
+    ),
+    (
+        "fetch.already_installed",
+        "Already installed, skipping: {blue}'{fonts}'{reset}",
+    ),
+    ("fetch.all_installed", "All fonts are already installed."),
+    (
+        "fetch.base_dir",
+        "Font base dir {white}(installation dir){reset}: {blue}'{path}'{reset} {gray}(from {source}){reset}",
+    ),
+    (
+        "fetch.will_download",
+        "Will download the following fonts: {blue}'{fonts}'{reset}",
+    ),
+    ("fetch.installing", "Installing {blue}'{font}'{reset}..."),
+    ("fetch.registering", "Registering font files..."),
+    ("fetch.done.one", "Download {green}DONE{reset}!"),
+    (
+        "fetch.done.other",
+        "All {count} downloads {green}DONE{reset}!",
+    ),
+    ("sync.up_to_date", "Everything in fonty.toml is installed."),
+    (
+        "remove.will_trash",
+        "The following fonts will be moved to the trash:",
+    ),
+    (
+        "remove.will_delete",
+        "The following fonts will be deleted permanently:",
+    ),
+    ("remove.confirm_trash", "Remove these fonts?"),
+    ("remove.confirm_delete", "Permanently delete these fonts?"),
+    ("remove.aborted", "Not removing any fonts, aborting."),
+    ("remove.trashed.one", "Moved 1 font to the trash."),
+    ("remove.trashed.other", "Moved {count} fonts to the trash."),
+    ("remove.deleted.one", "Deleted 1 font permanently."),
+    ("remove.deleted.other", "Deleted {count} fonts permanently."),
+    ("summary.title", "Summary:"),
+    ("summary.families_installed", "Families installed"),
+    ("summary.families_failed", "Families failed"),
+    ("summary.text_files_written", "Text files written"),
+    ("summary.font_files_downloaded", "Font files downloaded"),
+    ("summary.font_files_from_cache", "Font files from cache"),
+    ("summary.files_skipped", "Skipped (up-to-date)"),
+    ("summary.failures", "Failures"),
+    (
+        "summary.files_not_attempted",
+        "Not attempted (network down)",
+    ),
+    ("summary.total_bytes", "Total bytes"),
+    ("summary.elapsed_time", "Elapsed time"),
+    ("summary.warnings", "Warnings ({count}):"),
+    ("summary.failed_files", "Failed files ({count}):"),
+    ("summary.slowest_files", "Slowest downloads:"),
+    (
+        "registry.read_failed",
+        "Failed to read the registry {blue}'{path}'{reset}, rebuilding it from the installed files! Error: {error}",
+    ),
+    (
+        "registry.corrupted",
+        "The registry {blue}'{path}'{reset} is corrupted, rebuilding it from the installed files! Error: {error}",
+    ),
+    (
+        "error.axes_not_icon_font",
+        "{blue}--axes{reset} only works with icon fonts like {blue}'Material Symbols Outlined'{reset}, not {blue}'{font}'{reset}!",
+    ),
+    (
+        "error.axes_offline",
+        "Icon fonts can't be fetched with {blue}--axes{reset} and {blue}--offline{reset}, the stylesheets are not cached!",
+    ),
+    (
+        "error.axes_only_fetch",
+        "{blue}--axes{reset} only works with {blue}fetch{reset}!",
+    ),
+    (
+        "error.axes_with_faces",
+        "Families with faces (like {blue}'Inter:wght@400;700'{reset}) can't be fetched with {blue}--axes{reset}!",
+    ),
+    (
+        "error.back_up",
+        "Failed to back up the existing files of {blue}'{font}'{reset}, not overwriting them! Error:\n{error}",
+    ),
+    (
+        "error.base_dir_not_utf8",
+        "The font base dir {blue}'{path}'{reset} is not valid UTF-8, so it can't be written into a fontconfig snippet!",
+    ),
+    (
+        "error.cache_gc_usage",
+        "Expected {blue}--max-size{reset} and/or {blue}--max-age{reset} for {blue}cache gc{reset}!",
+    ),
+    (
+        "error.cache_usage",
+        "Expected {blue}cache stats{reset}, {blue}cache clear{reset} or {blue}cache gc{reset}!",
+    ),
+    ("error.catalog_failed", "Failed to get the catalog! {error}"),
+    (
+        "error.clear_cache_dir",
+        "Failed to clear the cache dir {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.config_file",
+        "Failed to find the config file! {error}",
+    ),
+    ("error.config_usage", "Expected {blue}config show{reset}!"),
+    (
+        "error.delete_backups",
+        "Failed to delete old backups! Error:\n{error}",
+    ),
+    (
+        "error.download_release",
+        "Failed to download the release! {error}",
+    ),
+    (
+        "error.evict_cache",
+        "Failed to evict files from the cache dir {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.export_missing",
+        "Failed to read {blue}'{files}'{reset}! Run {blue}fonty repair{reset} to download missing files again.",
+    ),
+    (
+        "error.export_unlicensed",
+        "Refusing to export fonts without their license! No license file is installed for {blue}'{fonts}'{reset}.",
+    ),
+    ("error.fetch_failed.one", "Failed to fetch 1 file:"),
+    ("error.fetch_failed.other", "Failed to fetch {count} files:"),
+    (
+        "error.fetch_file",
+        "Failed to fetch file {yellow}{index}{white}/{downloads}{reset}: {blue}'{url}'{reset}! {error}",
+    ),
+    (
+        "error.fixtures_dir",
+        "Failed to create the fixtures dir {blue}'{path}'{reset}! {error}",
+    ),
+    ("error.font_failed", "{blue}'{font}'{reset}: {error}"),
+    (
+        "error.http_client",
+        "Failed to set up the HTTP client! Error:\n{error}",
+    ),
+    (
+        "error.invalid_archive",
+        "Invalid archive '{archive}', it has to end in .zip, .tar.gz or .tgz!",
+    ),
+    (
+        "error.invalid_axes",
+        "Invalid value '{value}' for --axes flag, {error}!",
+    ),
+    (
+        "error.invalid_axes_for_font",
+        "Invalid value '{value}' for --axes flag for {blue}'{font}'{reset}, {error}!",
+    ),
+    (
+        "error.invalid_choice",
+        "Invalid value '{value}' for {flag} flag, expected one of {choices}!",
+    ),
+    (
+        "error.invalid_count",
+        "Invalid number of fonts '{count}', expected a number!",
+    ),
+    (
+        "error.invalid_font",
+        "Invalid font {blue}'{font}'{reset}, {error}!",
+    ),
+    (
+        "error.invalid_fonts",
+        "The following fonts are invalid: {blue}'{fonts}'{reset}",
+    ),
+    (
+        "error.invalid_number",
+        "Invalid value '{value}' for {flag} flag, expected a number!",
+    ),
+    (
+        "error.invalid_pattern",
+        "Invalid pattern {blue}'{pattern}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.invalid_progress_fd",
+        "Invalid value '{value}' for --progress-fd flag, expected a file descriptor number!",
+    ),
+    (
+        "error.invalid_tag",
+        "Invalid tag {blue}'{tag}'{reset}, tags can't be empty or contain spaces or commas!",
+    ),
+    (
+        "error.latest_release",
+        "Failed to get the latest release! {error}",
+    ),
+    (
+        "error.licenses_without_markdown",
+        "{blue}--with-licenses{reset} only works with {blue}--format markdown{reset}!",
+    ),
+    (
+        "error.manifest_failed",
+        "Failed to get the font manifest for {blue}'{font}'{reset}! {error}",
+    ),
+    (
+        "error.migrate_failed.one",
+        "Failed to migrate 1 file! The other files were moved, run the migration again to retry.",
+    ),
+    (
+        "error.migrate_failed.other",
+        "Failed to migrate {count} files! The other files were moved, run the migration again to retry.",
+    ),
+    (
+        "error.no_archive",
+        "No archive specified! Pass {blue}--archive <path>{reset}, ending in .zip, .tar.gz or .tgz.",
+    ),
+    (
+        "error.no_backups",
+        "There are no backups of {blue}'{font}'{reset}!",
+    ),
+    (
+        "error.no_base_dir",
+        "The font base dir {blue}'{path}'{reset} does not exist! Fetch a font first.",
+    ),
+    (
+        "error.no_cache_dir",
+        "Could not find the cache dir! Set {blue}$XDG_CACHE_HOME{reset} or {blue}$HOME{reset}.",
+    ),
+    (
+        "error.no_coverage",
+        "No coverage keywords given in {blue}--coverage{reset}!",
+    ),
+    (
+        "error.no_files",
+        "Google Fonts returned no files for {blue}'{font}'{reset}!",
+    ),
+    ("error.no_font", "No font specified!"),
+    (
+        "error.no_fontconfig_dir",
+        "Could not find the fontconfig config dir! Set {blue}$XDG_CONFIG_HOME{reset} or {blue}$HOME{reset}.",
+    ),
+    (
+        "error.no_fonts_installed",
+        "No fonts are installed in {blue}'{path}'{reset}!",
+    ),
+    (
+        "error.no_gallery_file",
+        "No output file specified! Pass {blue}--out <path>{reset}, e.g. 'gallery.html'.",
+    ),
+    (
+        "error.no_popularity",
+        "The catalog has no popularity ranks, so the most popular fonts are not known! Try again later with {blue}--refresh-catalog{reset}.",
+    ),
+    ("error.no_query", "No search query specified!"),
+    (
+        "error.not_installed",
+        "The following fonts are not installed: {blue}'{fonts}'{reset}",
+    ),
+    (
+        "error.offline_without_cache_dir",
+        "There is no cache dir to use with --offline! Set {blue}XDG_CACHE_HOME{reset} or {blue}HOME{reset}.",
+    ),
+    (
+        "error.one_count",
+        "Only one number of fonts can be specified!",
+    ),
+    ("error.one_font", "Only one font can be specified!"),
+    (
+        "error.progress_fd",
+        "Can't write progress to file descriptor {fd}! Error: {error}",
+    ),
+    (
+        "error.progress_fd_unsupported",
+        "File descriptors can't be passed on this platform, use {blue}--progress-file{reset} instead of {blue}--progress-fd {fd}{reset}!",
+    ),
+    (
+        "error.progress_file",
+        "Can't write progress to {blue}'{path}'{reset}! Error: {error}",
+    ),
+    (
+        "error.read_cache_dir",
+        "Failed to read the cache dir {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.regex_and_fuzzy",
+        "Only one of {blue}--regex{reset} and {blue}--fuzzy{reset} can be given!",
+    ),
+    (
+        "error.remove_failed",
+        "Failed to remove every file of: {blue}'{fonts}'{reset}! The files that are left are still registered, remove the fonts again to retry.",
+    ),
+    (
+        "error.render_files",
+        "Failed to render the files! Error:\n{error}",
+    ),
+    (
+        "error.render_report",
+        "Failed to render the report! Error:\n{error}",
+    ),
+    (
+        "error.resolve_base_dir",
+        "Failed to resolve the font base dir {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.restore_backup",
+        "Failed to restore the backup {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.rollback_one_font",
+        "Specify exactly one font to roll back!",
+    ),
+    (
+        "error.select_variable_fonts",
+        "Select the fonts to print the variables for, so each variable is only set once.",
+    ),
+    (
+        "error.self_update_disabled",
+        "This build of fonty can't update itself, update it the way it was installed (e.g. with your package manager)!",
+    ),
+    (
+        "error.self_update_offline",
+        "fonty can't update itself with {blue}--offline{reset}!",
+    ),
+    (
+        "error.serialize_catalog",
+        "Failed to serialize the catalog! Error:\n{error}",
+    ),
+    (
+        "error.serialize_info",
+        "Failed to serialize the font info! Error:\n{error}",
+    ),
+    (
+        "error.serialize_installed",
+        "Failed to serialize the installed fonts! Error:\n{error}",
+    ),
+    (
+        "error.serialize_plan",
+        "Failed to serialize the planned files! Error:\n{error}",
+    ),
+    (
+        "error.serialize_report",
+        "Failed to serialize the report! Error:\n{error}",
+    ),
+    (
+        "error.serialize_search",
+        "Failed to serialize the search results! Error:\n{error}",
+    ),
+    (
+        "error.serialize_top",
+        "Failed to serialize the most popular fonts! Error:\n{error}",
+    ),
+    (
+        "error.snippet_exists",
+        "{blue}'{path}'{reset} already exists with other contents! Pass {blue}--force{reset} to overwrite it.",
+    ),
+    (
+        "error.sort_with_all",
+        "The --sort and --tag flags can't be used with --all, the fonts are listed by dir!",
+    ),
+    (
+        "error.sort_with_remote",
+        "The --sort flag can't be used with --remote, the catalog is listed in its own order!",
+    ),
+    (
+        "error.stylesheet_failed",
+        "Failed to get the stylesheet of {blue}'{font}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.stylesheet_unsafe_url",
+        "The stylesheet of {blue}'{font}'{reset} points at a URL that is not allowed, refusing to download it: {blue}'{url}'{reset}: {reason}",
+    ),
+    (
+        "error.tag_usage",
+        "Expected {blue}tag add <tag> <font...>{reset} or {blue}tag remove <tag> [font...]{reset}!",
+    ),
+    (
+        "error.unknown_category",
+        "Unknown category {blue}'{category}'{reset}! The categories are: {blue}'{categories}'{reset}",
+    ),
+    (
+        "error.unknown_font",
+        "Nothing is known about the font {blue}'{font}'{reset}!",
+    ),
+    (
+        "error.unknown_tag_command",
+        "Unknown tag command {blue}'{command}'{reset}, expected 'add' or 'remove'!",
+    ),
+    (
+        "error.unnamed_file",
+        "Can't name the file for {blue}'{url}'{reset}!",
+    ),
+    (
+        "error.unsafe_manifest_path",
+        "Unsafe file path in manifest: {error}",
+    ),
+    (
+        "error.unsafe_manifest_paths",
+        "The font manifest contains unsafe file paths, refusing to write anything:",
+    ),
+    (
+        "error.unsafe_manifest_urls",
+        "The font manifest contains download URLs that are not allowed, refusing to download anything:",
+    ),
+    (
+        "error.update_registry",
+        "Failed to update the registry of installed fonts! Error:\n{error}",
+    ),
+    (
+        "error.variable_collision",
+        "The fonts {blue}'{fonts}'{reset} get the same variable {blue}{name}{reset}!",
+    ),
+    (
+        "error.webkit_no_output_dir",
+        "A web font kit is not installed, pass {blue}--output-dir <dir>{reset} for where to write it!",
+    ),
+    (
+        "error.webkit_offline",
+        "A web font kit can't be built with {blue}--offline{reset}, the stylesheets are not cached!",
+    ),
+    (
+        "error.write_archive",
+        "Failed to write the archive {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.write_gallery",
+        "Failed to write the gallery {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+    (
+        "error.write_snippet",
+        "Failed to write the fontconfig snippet {blue}'{path}'{reset}! Error:\n{error}",
+    ),
+];
+
+/// The German messages.
+const GERMAN: &[(&str, &str)] = &[
+    ("fetch.no_fonts", "Keine Schriftarten angegeben!"),
+    (
+        "fetch.no_fonts_to_update",
+        "Keine Schriftarten zu aktualisieren!",
+    ),
+    (
+        "fetch.duplicate",
+        "{blue}'{font}'{reset} wurde {count}-mal angegeben, wird einmal geladen.",
+    ),
+    (
+        "fetch.already_installed",
+        "Bereits installiert, übersprungen: {blue}'{fonts}'{reset}",
+    ),
+    (
+        "fetch.all_installed",
+        "Alle Schriftarten sind bereits installiert.",
+    ),
+    (
+        "fetch.base_dir",
+        "Schriftarten-Verzeichnis {white}(Installationsort){reset}: {blue}'{path}'{reset} {gray}(aus {source}){reset}",
+    ),
+    (
+        "fetch.will_download",
+        "Folgende Schriftarten werden heruntergeladen: {blue}'{fonts}'{reset}",
+    ),
+    (
+        "fetch.installing",
+        "{blue}'{font}'{reset} wird installiert...",
+    ),
+    ("fetch.registering", "Schriftdateien werden registriert..."),
+    ("fetch.done.one", "Download {green}FERTIG{reset}!"),
+    (
+        "fetch.done.other",
+        "Alle {count} Downloads {green}FERTIG{reset}!",
+    ),
+    ("sync.up_to_date", "Alles aus fonty.toml ist installiert."),
+    (
+        "remove.will_trash",
+        "Folgende Schriftarten werden in den Papierkorb verschoben:",
+    ),
+    (
+        "remove.will_delete",
+        "Folgende Schriftarten werden endgültig gelöscht:",
+    ),
+    ("remove.confirm_trash", "Diese Schriftarten entfernen?"),
+    (
+        "remove.confirm_delete",
+        "Diese Schriftarten endgültig löschen?",
+    ),
+    (
+        "remove.aborted",
+        "Es werden keine Schriftarten entfernt, Abbruch.",
+    ),
+    (
+        "remove.trashed.one",
+        "1 Schriftart in den Papierkorb verschoben.",
+    ),
+    (
+        "remove.trashed.other",
+        "{count} Schriftarten in den Papierkorb verschoben.",
+    ),
+    ("remove.deleted.one", "1 Schriftart endgültig gelöscht."),
+    (
+        "remove.deleted.other",
+        "{count} Schriftarten endgültig gelöscht.",
+    ),
+    ("summary.title", "Zusammenfassung:"),
+    ("summary.families_installed", "Installierte Familien"),
+    ("summary.families_failed", "Fehlgeschlagene Familien"),
+    ("summary.text_files_written", "Geschriebene Textdateien"),
+    (
+        "summary.font_files_downloaded",
+        "Heruntergeladene Schriftdateien",
+    ),
+    (
+        "summary.font_files_from_cache",
+        "Schriftdateien aus dem Cache",
+    ),
+    ("summary.files_skipped", "Übersprungen (aktuell)"),
+    ("summary.failures", "Fehler"),
+    (
+        "summary.files_not_attempted",
+        "Nicht versucht (Netzwerk ausgefallen)",
+    ),
+    ("summary.total_bytes", "Bytes insgesamt"),
+    ("summary.elapsed_time", "Dauer"),
+    ("summary.warnings", "Warnungen ({count}):"),
+    ("summary.failed_files", "Fehlgeschlagene Dateien ({count}):"),
+    ("summary.slowest_files", "Langsamste Downloads:"),
+    (
+        "registry.read_failed",
+        "Die Registry {blue}'{path}'{reset} konnte nicht gelesen werden, sie wird aus den installierten Dateien neu aufgebaut! Fehler: {error}",
+    ),
+    (
+        "registry.corrupted",
+        "Die Registry {blue}'{path}'{reset} ist beschädigt, sie wird aus den installierten Dateien neu aufgebaut! Fehler: {error}",
+    ),
+    (
+        "error.axes_not_icon_font",
+        "{blue}--axes{reset} funktioniert nur mit Icon-Schriftarten wie {blue}'Material Symbols Outlined'{reset}, nicht mit {blue}'{font}'{reset}!",
+    ),
+    (
+        "error.axes_offline",
+        "Icon-Schriftarten können nicht mit {blue}--axes{reset} und {blue}--offline{reset} geladen werden, die Stylesheets werden nicht zwischengespeichert!",
+    ),
+    (
+        "error.axes_only_fetch",
+        "{blue}--axes{reset} funktioniert nur mit {blue}fetch{reset}!",
+    ),
+    (
+        "error.axes_with_faces",
+        "Familien mit Schnitten (wie {blue}'Inter:wght@400;700'{reset}) können nicht mit {blue}--axes{reset} geladen werden!",
+    ),
+    (
+        "error.back_up",
+        "Die vorhandenen Dateien von {blue}'{font}'{reset} konnten nicht gesichert werden, sie werden nicht überschrieben! Fehler:\n{error}",
+    ),
+    (
+        "error.base_dir_not_utf8",
+        "Das Schriftarten-Verzeichnis {blue}'{path}'{reset} ist kein gültiges UTF-8 und kann daher nicht in ein fontconfig-Snippet geschrieben werden!",
+    ),
+    (
+        "error.cache_gc_usage",
+        "{blue}--max-size{reset} und/oder {blue}--max-age{reset} für {blue}cache gc{reset} erwartet!",
+    ),
+    (
+        "error.cache_usage",
+        "{blue}cache stats{reset}, {blue}cache clear{reset} oder {blue}cache gc{reset} erwartet!",
+    ),
+    (
+        "error.catalog_failed",
+        "Der Katalog konnte nicht geladen werden! {error}",
+    ),
+    (
+        "error.clear_cache_dir",
+        "Das Cache-Verzeichnis {blue}'{path}'{reset} konnte nicht geleert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.config_file",
+        "Die Konfigurationsdatei wurde nicht gefunden! {error}",
+    ),
+    ("error.config_usage", "{blue}config show{reset} erwartet!"),
+    (
+        "error.delete_backups",
+        "Alte Sicherungen konnten nicht gelöscht werden! Fehler:\n{error}",
+    ),
+    (
+        "error.download_release",
+        "Das Release konnte nicht heruntergeladen werden! {error}",
+    ),
+    (
+        "error.evict_cache",
+        "Dateien konnten nicht aus dem Cache-Verzeichnis {blue}'{path}'{reset} entfernt werden! Fehler:\n{error}",
+    ),
+    (
+        "error.export_missing",
+        "{blue}'{files}'{reset} konnte nicht gelesen werden! Führe {blue}fonty repair{reset} aus, um fehlende Dateien erneut herunterzuladen.",
+    ),
+    (
+        "error.export_unlicensed",
+        "Schriftarten werden nicht ohne ihre Lizenz exportiert! Für {blue}'{fonts}'{reset} ist keine Lizenzdatei installiert.",
+    ),
+    (
+        "error.fetch_failed.one",
+        "1 Datei konnte nicht geladen werden:",
+    ),
+    (
+        "error.fetch_failed.other",
+        "{count} Dateien konnten nicht geladen werden:",
+    ),
+    (
+        "error.fetch_file",
+        "Datei {yellow}{index}{white}/{downloads}{reset} konnte nicht geladen werden: {blue}'{url}'{reset}! {error}",
+    ),
+    (
+        "error.fixtures_dir",
+        "Das Fixture-Verzeichnis {blue}'{path}'{reset} konnte nicht angelegt werden! {error}",
+    ),
+    ("error.font_failed", "{blue}'{font}'{reset}: {error}"),
+    (
+        "error.http_client",
+        "Der HTTP-Client konnte nicht eingerichtet werden! Fehler:\n{error}",
+    ),
+    (
+        "error.invalid_archive",
+        "Ungültiges Archiv '{archive}', es muss auf .zip, .tar.gz oder .tgz enden!",
+    ),
+    (
+        "error.invalid_axes",
+        "Ungültiger Wert '{value}' für --axes, {error}!",
+    ),
+    (
+        "error.invalid_axes_for_font",
+        "Ungültiger Wert '{value}' für --axes bei {blue}'{font}'{reset}, {error}!",
+    ),
+    (
+        "error.invalid_choice",
+        "Ungültiger Wert '{value}' für {flag}, erwartet wird einer von {choices}!",
+    ),
+    (
+        "error.invalid_count",
+        "Ungültige Anzahl von Schriftarten '{count}', eine Zahl wird erwartet!",
+    ),
+    (
+        "error.invalid_font",
+        "Ungültige Schriftart {blue}'{font}'{reset}, {error}!",
+    ),
+    (
+        "error.invalid_fonts",
+        "Folgende Schriftarten sind ungültig: {blue}'{fonts}'{reset}",
+    ),
+    (
+        "error.invalid_number",
+        "Ungültiger Wert '{value}' für {flag}, eine Zahl wird erwartet!",
+    ),
+    (
+        "error.invalid_pattern",
+        "Ungültiges Muster {blue}'{pattern}'{reset}! Fehler:\n{error}",
+    ),
+    (
+        "error.invalid_progress_fd",
+        "Ungültiger Wert '{value}' für --progress-fd, eine Dateideskriptor-Nummer wird erwartet!",
+    ),
+    (
+        "error.invalid_tag",
+        "Ungültiger Tag {blue}'{tag}'{reset}, Tags dürfen nicht leer sein und keine Leerzeichen oder Kommas enthalten!",
+    ),
+    (
+        "error.latest_release",
+        "Das neueste Release konnte nicht abgefragt werden! {error}",
+    ),
+    (
+        "error.licenses_without_markdown",
+        "{blue}--with-licenses{reset} funktioniert nur mit {blue}--format markdown{reset}!",
+    ),
+    (
+        "error.manifest_failed",
+        "Das Schriftarten-Manifest für {blue}'{font}'{reset} konnte nicht geladen werden! {error}",
+    ),
+    (
+        "error.migrate_failed.one",
+        "1 Datei konnte nicht migriert werden! Die anderen Dateien wurden verschoben, führe die Migration erneut aus, um es noch einmal zu versuchen.",
+    ),
+    (
+        "error.migrate_failed.other",
+        "{count} Dateien konnten nicht migriert werden! Die anderen Dateien wurden verschoben, führe die Migration erneut aus, um es noch einmal zu versuchen.",
+    ),
+    (
+        "error.no_archive",
+        "Kein Archiv angegeben! Gib {blue}--archive <path>{reset} an, mit der Endung .zip, .tar.gz oder .tgz.",
+    ),
+    (
+        "error.no_backups",
+        "Es gibt keine Sicherungen von {blue}'{font}'{reset}!",
+    ),
+    (
+        "error.no_base_dir",
+        "Das Schriftarten-Verzeichnis {blue}'{path}'{reset} existiert nicht! Lade zuerst eine Schriftart.",
+    ),
+    (
+        "error.no_cache_dir",
+        "Das Cache-Verzeichnis wurde nicht gefunden! Setze {blue}$XDG_CACHE_HOME{reset} oder {blue}$HOME{reset}.",
+    ),
+    (
+        "error.no_coverage",
+        "Keine Abdeckungs-Stichwörter in {blue}--coverage{reset} angegeben!",
+    ),
+    (
+        "error.no_files",
+        "Google Fonts hat keine Dateien für {blue}'{font}'{reset} geliefert!",
+    ),
+    ("error.no_font", "Keine Schriftart angegeben!"),
+    (
+        "error.no_fontconfig_dir",
+        "Das fontconfig-Konfigurationsverzeichnis wurde nicht gefunden! Setze {blue}$XDG_CONFIG_HOME{reset} oder {blue}$HOME{reset}.",
+    ),
+    (
+        "error.no_fonts_installed",
+        "In {blue}'{path}'{reset} sind keine Schriftarten installiert!",
+    ),
+    (
+        "error.no_gallery_file",
+        "Keine Ausgabedatei angegeben! Gib {blue}--out <path>{reset} an, z. B. 'gallery.html'.",
+    ),
+    (
+        "error.no_popularity",
+        "Der Katalog enthält keine Beliebtheitsränge, die beliebtesten Schriftarten sind daher unbekannt! Versuche es später erneut mit {blue}--refresh-catalog{reset}.",
+    ),
+    ("error.no_query", "Keine Suchanfrage angegeben!"),
+    (
+        "error.not_installed",
+        "Folgende Schriftarten sind nicht installiert: {blue}'{fonts}'{reset}",
+    ),
+    (
+        "error.offline_without_cache_dir",
+        "Es gibt kein Cache-Verzeichnis für --offline! Setze {blue}XDG_CACHE_HOME{reset} oder {blue}HOME{reset}.",
+    ),
+    (
+        "error.one_count",
+        "Es kann nur eine Anzahl von Schriftarten angegeben werden!",
+    ),
+    (
+        "error.one_font",
+        "Es kann nur eine Schriftart angegeben werden!",
+    ),
+    (
+        "error.progress_fd",
+        "Der Fortschritt kann nicht in den Dateideskriptor {fd} geschrieben werden! Fehler: {error}",
+    ),
+    (
+        "error.progress_fd_unsupported",
+        "Dateideskriptoren können auf dieser Plattform nicht übergeben werden, verwende {blue}--progress-file{reset} statt {blue}--progress-fd {fd}{reset}!",
+    ),
+    (
+        "error.progress_file",
+        "Der Fortschritt kann nicht in {blue}'{path}'{reset} geschrieben werden! Fehler: {error}",
+    ),
+    (
+        "error.read_cache_dir",
+        "Das Cache-Verzeichnis {blue}'{path}'{reset} konnte nicht gelesen werden! Fehler:\n{error}",
+    ),
+    (
+        "error.regex_and_fuzzy",
+        "Nur eines von {blue}--regex{reset} und {blue}--fuzzy{reset} kann angegeben werden!",
+    ),
+    (
+        "error.remove_failed",
+        "Nicht alle Dateien von {blue}'{fonts}'{reset} konnten entfernt werden! Die übrigen Dateien bleiben registriert, entferne die Schriftarten erneut, um es noch einmal zu versuchen.",
+    ),
+    (
+        "error.render_files",
+        "Die Dateien konnten nicht ausgegeben werden! Fehler:\n{error}",
+    ),
+    (
+        "error.render_report",
+        "Der Bericht konnte nicht ausgegeben werden! Fehler:\n{error}",
+    ),
+    (
+        "error.resolve_base_dir",
+        "Das Schriftarten-Verzeichnis {blue}'{path}'{reset} konnte nicht aufgelöst werden! Fehler:\n{error}",
+    ),
+    (
+        "error.restore_backup",
+        "Die Sicherung {blue}'{path}'{reset} konnte nicht wiederhergestellt werden! Fehler:\n{error}",
+    ),
+    (
+        "error.rollback_one_font",
+        "Gib genau eine Schriftart zum Zurücksetzen an!",
+    ),
+    (
+        "error.select_variable_fonts",
+        "Wähle die Schriftarten aus, für die Variablen ausgegeben werden sollen, damit jede Variable nur einmal gesetzt wird.",
+    ),
+    (
+        "error.self_update_disabled",
+        "Dieser Build von fonty kann sich nicht selbst aktualisieren, aktualisiere ihn so, wie er installiert wurde (z. B. mit deinem Paketmanager)!",
+    ),
+    (
+        "error.self_update_offline",
+        "fonty kann sich nicht mit {blue}--offline{reset} aktualisieren!",
+    ),
+    (
+        "error.serialize_catalog",
+        "Der Katalog konnte nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.serialize_info",
+        "Die Schriftart-Informationen konnten nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.serialize_installed",
+        "Die installierten Schriftarten konnten nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.serialize_plan",
+        "Die geplanten Dateien konnten nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.serialize_report",
+        "Der Bericht konnte nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.serialize_search",
+        "Die Suchergebnisse konnten nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.serialize_top",
+        "Die beliebtesten Schriftarten konnten nicht serialisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.snippet_exists",
+        "{blue}'{path}'{reset} existiert bereits mit anderem Inhalt! Gib {blue}--force{reset} an, um es zu überschreiben.",
+    ),
+    (
+        "error.sort_with_all",
+        "--sort und --tag können nicht mit --all verwendet werden, die Schriftarten werden nach Verzeichnis aufgelistet!",
+    ),
+    (
+        "error.sort_with_remote",
+        "--sort kann nicht mit --remote verwendet werden, der Katalog wird in seiner eigenen Reihenfolge aufgelistet!",
+    ),
+    (
+        "error.stylesheet_failed",
+        "Das Stylesheet von {blue}'{font}'{reset} konnte nicht geladen werden! Fehler:\n{error}",
+    ),
+    (
+        "error.stylesheet_unsafe_url",
+        "Das Stylesheet von {blue}'{font}'{reset} verweist auf eine nicht erlaubte URL, sie wird nicht heruntergeladen: {blue}'{url}'{reset}: {reason}",
+    ),
+    (
+        "error.tag_usage",
+        "{blue}tag add <tag> <font...>{reset} oder {blue}tag remove <tag> [font...]{reset} erwartet!",
+    ),
+    (
+        "error.unknown_category",
+        "Unbekannte Kategorie {blue}'{category}'{reset}! Die Kategorien sind: {blue}'{categories}'{reset}",
+    ),
+    (
+        "error.unknown_font",
+        "Über die Schriftart {blue}'{font}'{reset} ist nichts bekannt!",
+    ),
+    (
+        "error.unknown_tag_command",
+        "Unbekannter Tag-Befehl {blue}'{command}'{reset}, 'add' oder 'remove' erwartet!",
+    ),
+    (
+        "error.unnamed_file",
+        "Für {blue}'{url}'{reset} kann kein Dateiname bestimmt werden!",
+    ),
+    (
+        "error.unsafe_manifest_path",
+        "Unsicherer Dateipfad im Manifest: {error}",
+    ),
+    (
+        "error.unsafe_manifest_paths",
+        "Das Schriftarten-Manifest enthält unsichere Dateipfade, es wird nichts geschrieben:",
+    ),
+    (
+        "error.unsafe_manifest_urls",
+        "Das Schriftarten-Manifest enthält nicht erlaubte Download-URLs, es wird nichts heruntergeladen:",
+    ),
+    (
+        "error.update_registry",
+        "Das Verzeichnis der installierten Schriftarten konnte nicht aktualisiert werden! Fehler:\n{error}",
+    ),
+    (
+        "error.variable_collision",
+        "Die Schriftarten {blue}'{fonts}'{reset} bekommen dieselbe Variable {blue}{name}{reset}!",
+    ),
+    (
+        "error.webkit_no_output_dir",
+        "Ein Web-Font-Kit wird nicht installiert, gib mit {blue}--output-dir <dir>{reset} an, wohin es geschrieben werden soll!",
+    ),
+    (
+        "error.webkit_offline",
+        "Ein Web-Font-Kit kann nicht mit {blue}--offline{reset} erstellt werden, die Stylesheets werden nicht zwischengespeichert!",
+    ),
+    (
+        "error.write_archive",
+        "Das Archiv {blue}'{path}'{reset} konnte nicht geschrieben werden! Fehler:\n{error}",
+    ),
+    (
+        "error.write_gallery",
+        "Die Galerie {blue}'{path}'{reset} konnte nicht geschrieben werden! Fehler:\n{error}",
+    ),
+    (
+        "error.write_snippet",
+        "Das fontconfig-Snippet {blue}'{path}'{reset} konnte nicht geschrieben werden! Fehler:\n{error}",
+    ),
+];
+
+/// A catalog of messages: templates by key.
+type Catalog = &'static [(&'static str, &'static str)];
+
+/// The catalog of the language of the messages, English if it wasn't set.
+fn catalog() -> Catalog {
+    match LANGUAGE.get().copied().unwrap_or(Language::English) {
+        Language::English => ENGLISH,
+        Language::German => GERMAN,
+    }
+}
+
+/// Finds the template of a message in a catalog, falling back to English (and then to the key itself).
+fn template(catalog: Catalog, key: &str) -> &str {
+    let find = |catalog: Catalog| {
+        catalog
+            .iter()
+            .find(|(message_key, _)| *message_key == key)
+            .map(|(_, template)| *template)
+    };
+    find(catalog).or_else(|| find(ENGLISH)).unwrap_or(key)
+}
+
+/// Renders a message: its template in the current language, with the placeholders replaced.
+/// Placeholders without an argument (and that are no color) are left as they are. Use `msg!` instead of calling this.
+pub fn message(key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    render(catalog(), key, args)
+}
+
+/// Renders a message from a catalog, see `message`.
+fn render(catalog: Catalog, key: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let template = template(catalog, key);
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let Some(length) = rest[start..].find('}') else {
+            rendered.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let name = &rest[start + 1..start + length];
+        let value = args
+            .iter()
+            .find(|(arg_name, _)| *arg_name == name)
+            .map(|(_, value)| value.to_string())
+            .or_else(|| {
+                let color = match name {
+                    "blue" => color_blue,
+                    "gray" => color_bright_black,
+                    "white" => color_white,
+                    "green" => color_green,
+                    "yellow" => color_bright_yellow,
+                    "reset" => color_reset,
+                    _ => return None,
+                };
+                Some(color.to_string())
+            });
+        match value {
+            Some(value) => rendered.push_str(&value),
+            None => rendered.push_str(&rest[start..=start + length]),
+        }
+        rest = &rest[start + length + 1..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Renders a message with a count: the `.one` key for a count of 1, the `.other` key for the rest, with `{count}` set to the count.
+/// Use `msg_n!` instead of calling this.
+pub fn plural_message(key: &str, count: usize, args: &[(&str, &dyn fmt::Display)]) -> String {
+    render_plural(catalog(), key, count, args)
+}
+
+/// Renders a message with a count from a catalog, see `plural_message`.
+fn render_plural(
+    catalog: Catalog,
+    key: &str,
+    count: usize,
+    args: &[(&str, &dyn fmt::Display)],
+) -> String {
+    let key = format!("{}.{}", key, if count == 1 { "one" } else { "other" });
+    let mut args = args.to_vec();
+    args.push(("count", &count));
+    render(catalog, &key, &args)
+}
+
+/// Renders a message from the catalog by its key, with named arguments: `msg!("fetch.duplicate", font = name, count = 2)`.
+#[macro_export]
+macro_rules! msg {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::message(
+            $key,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*],
+        )
+    };
+}
+
+/// Renders a message with a count from the catalog, see `plural_message`: `msg_n!("remove.deleted", 3)`.
+#[macro_export]
+macro_rules! msg_n {
+    ($key:literal, $count:expr $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::plural_message(
+            $key,
+            $count,
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),*],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, OsString> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect()
+    }
+
+    /// The names of the placeholders in a template, sorted.
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names = template
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn the_language_comes_from_the_first_locale_variable_set() {
+        assert_eq!(language_from_env(&env(&[])), Language::English);
+        assert_eq!(
+            language_from_env(&env(&[("LANG", "de_DE.UTF-8")])),
+            Language::German
+        );
+        assert_eq!(
+            language_from_env(&env(&[("LANG", "de_AT@euro")])),
+            Language::German
+        );
+        assert_eq!(language_from_env(&env(&[("LANG", "de")])), Language::German);
+        assert_eq!(
+            language_from_env(&env(&[("LC_ALL", "en_US.UTF-8"), ("LANG", "de_DE.UTF-8")])),
+            Language::English
+        );
+        assert_eq!(
+            language_from_env(&env(&[
+                ("LC_MESSAGES", "de_CH.UTF-8"),
+                ("LANG", "en_GB.UTF-8")
+            ])),
+            Language::German
+        );
+        // Empty variables are skipped, like unset ones.
+        assert_eq!(
+            language_from_env(&env(&[("LC_ALL", ""), ("LANG", "de_DE.UTF-8")])),
+            Language::German
+        );
+        for locale in ["C", "POSIX", "fr_FR.UTF-8", "deu", "C.UTF-8"] {
+            assert_eq!(
+                language_from_env(&env(&[("LANG", locale)])),
+                Language::English,
+                "{}",
+                locale
+            );
+        }
+    }
+
+    #[test]
+    fn messages_are_looked_up_in_the_catalog_of_the_language() {
+        assert_eq!(
+            render(ENGLISH, "fetch.no_fonts", &[]),
+            "No fonts specified!"
+        );
+        assert_eq!(
+            render(GERMAN, "fetch.no_fonts", &[]),
+            "Keine Schriftarten angegeben!"
+        );
+    }
+
+    #[test]
+    fn missing_messages_fall_back_to_english_and_then_the_key() {
+        let catalog: Catalog = &[("fetch.no_fonts", "Pas de polices !")];
+        assert_eq!(render(catalog, "fetch.no_fonts", &[]), "Pas de polices !");
+        assert_eq!(
+            render(catalog, "fetch.all_installed", &[]),
+            "All fonts are already installed."
+        );
+        assert_eq!(render(catalog, "no.such.key", &[]), "no.such.key");
+        assert_eq!(render(GERMAN, "no.such.key", &[]), "no.such.key");
+    }
+
+    #[test]
+    fn placeholders_are_replaced_by_arguments_and_colors() {
+        let catalog: Catalog = &[
+            ("test.args", "{blue}'{font}'{reset} {count} times"),
+            ("test.unknown", "{font} is {unknown}"),
+            ("test.unclosed", "{font} is {unclosed"),
+        ];
+        assert_eq!(
+            render(catalog, "test.args", &[("font", &"Inter"), ("count", &2)]),
+            format!("{color_blue}'Inter'{color_reset} 2 times")
+        );
+        assert_eq!(
+            render(catalog, "test.unknown", &[("font", &"Inter")]),
+            "Inter is {unknown}"
+        );
+        assert_eq!(
+            render(catalog, "test.unclosed", &[("font", &"Inter")]),
+            "Inter is {unclosed"
+        );
+        // Arguments are not rendered again, so braces in them stay as they are.
+        assert_eq!(
+            render(catalog, "test.unknown", &[("font", &"{unknown}")]),
+            "{unknown} is {unknown}"
+        );
+    }
+
+    #[test]
+    fn counts_pick_the_one_or_other_message() {
+        assert_eq!(
+            render_plural(ENGLISH, "remove.deleted", 1, &[]),
+            "Deleted 1 font permanently."
+        );
+        assert_eq!(
+            render_plural(ENGLISH, "remove.deleted", 0, &[]),
+            "Deleted 0 fonts permanently."
+        );
+        assert_eq!(
+            render_plural(GERMAN, "remove.deleted", 3, &[]),
+            "3 Schriftarten endgültig gelöscht."
+        );
+        assert_eq!(
+            render_plural(GERMAN, "fetch.done", 1, &[]),
+            format!("Download {color_green}FERTIG{color_reset}!")
+        );
+    }
+
+    #[test]
+    fn every_english_message_is_translated_to_german() {
+        for (key, english) in ENGLISH {
+            let german = GERMAN
+                .iter()
+                .find(|(german_key, _)| german_key == key)
+                .map(|(_, german)| *german);
+            let Some(german) = german else {
+                panic!("'{}' has no German message", key);
+            };
+            assert_eq!(placeholders(german), placeholders(english), "{}", key);
+        }
+        for (key, _) in GERMAN {
+            assert!(
+                ENGLISH.iter().any(|(english_key, _)| english_key == key),
+                "'{}' is only in the German catalog",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn keys_are_unique() {
+        for catalog in [ENGLISH, GERMAN] {
+            let mut keys = catalog.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+            keys.sort();
+            let count = keys.len();
+            keys.dedup();
+            assert_eq!(keys.len(), count);
+        }
+    }
+}
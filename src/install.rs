@@ -0,0 +1,864 @@
+//! Installing families into a font base dir, one family after the other: the manifests of the next families are fetched
+//! while the files of one are written and downloaded (up to `--prefetch` ahead, see `run_pipelined`), so only the files
+//! in flight are kept in memory instead of those of the whole run. Every family is installed by `Installer::install_family`:
+//! `install_families` uses it as it is for library users, and the `fetch` command with the command line (asking, printing,
+//! exiting) on top, through `InstallHooks`.
+
+use crate::color::*;
+use std::{
+    collections::HashSet,
+    fmt, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{
+    download_cache::DownloadCache,
+    error::FontyError,
+    exit_codes::{
+        EXIT_CODE_EMPTY_FONT_MANIFEST, EXIT_CODE_INVALID_FONT_MANIFEST,
+        EXIT_CODE_INVALID_FONT_NAME, EXIT_CODE_NOT_CACHED, EXIT_CODE_UNSAFE_PATH,
+        EXIT_CODE_UNSAFE_URL,
+    },
+    family_spec::requested_by,
+    font_manifest::{FileResult, FontManifest, create_parent_dirs},
+    font_names::family_dir_name,
+    format::n_files,
+    options::Options,
+    plan::{PlannedFile, find_case_collisions, find_collisions},
+    registry::{Registry, RegistryEntry, layout_counterpart},
+    safe_path::UnsafePathError,
+    summary::{FailedFamily, FamilySection, InstallReport, RunSummary},
+    warnings::{WarningCode, Warnings},
+    worker_pool::run_pipelined,
+};
+
+/// Why a family can't be installed. Only the family fails, the others of the run are still installed.
+#[derive(Debug)]
+pub enum FamilyError {
+    /// Its manifest could not be fetched (or is not cached, with `--offline`). Boxed, as it is much larger than the others.
+    Manifest(Box<FontyError>),
+    /// Google Fonts returned a manifest without any files for it.
+    Empty,
+    /// Its manifest has file paths outside the font base dir.
+    UnsafePaths(Vec<UnsafePathError>),
+    /// Its manifest downloads from hosts that are not allowed, with the reasons.
+    UnsafeUrls(Vec<(String, String)>),
+    /// Font files of it would be written to the same path as other files (of its own, or of a family before it),
+    /// which can't be told apart. Every group of files is written to the same path.
+    Collisions(Vec<Vec<PlannedFile>>),
+    /// Files of it would end up as the same file as others on a case-insensitive filesystem.
+    CaseCollisions(Vec<Vec<PlannedFile>>),
+    /// Files of it are not cached, so they can't be installed with `--offline`.
+    NotCached(Vec<String>),
+    /// None of its font files has the faces asked for (like `Inter:wght@400;700`), as they are described.
+    MissingFaces(String),
+}
+
+impl FamilyError {
+    /// The exit code of a run in which no family could be installed because of this.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FamilyError::Manifest(e) => e.exit_code(),
+            FamilyError::Empty => EXIT_CODE_EMPTY_FONT_MANIFEST,
+            FamilyError::UnsafePaths(_) => EXIT_CODE_UNSAFE_PATH,
+            FamilyError::UnsafeUrls(_) => EXIT_CODE_UNSAFE_URL,
+            FamilyError::Collisions(_) | FamilyError::CaseCollisions(_) => {
+                EXIT_CODE_INVALID_FONT_MANIFEST
+            }
+            FamilyError::NotCached(_) => EXIT_CODE_NOT_CACHED,
+            FamilyError::MissingFaces(_) => EXIT_CODE_INVALID_FONT_NAME,
+        }
+    }
+
+    /// What is wrong, in a few words (without the files), e.g. for the warning that the family was skipped.
+    pub fn summary(&self) -> &'static str {
+        match self {
+            FamilyError::Manifest(_) => "its font manifest could not be fetched",
+            FamilyError::Empty => "Google Fonts returned no files for it",
+            FamilyError::UnsafePaths(_) => "its font manifest contains unsafe file paths",
+            FamilyError::UnsafeUrls(_) => {
+                "its font manifest contains download URLs that are not allowed"
+            }
+            FamilyError::Collisions(_) => "several of its files would be written to the same path",
+            FamilyError::CaseCollisions(_) => "several of its files only differ in case",
+            FamilyError::NotCached(_) => "some of its files are not cached",
+            FamilyError::MissingFaces(_) => "none of its font files has the faces asked for",
+        }
+    }
+}
+
+impl fmt::Display for FamilyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FamilyError::Manifest(e) => write!(f, "Failed to get the font manifest! {}", e),
+            FamilyError::Empty => write!(f, "Google Fonts returned no files for it."),
+            FamilyError::UnsafePaths(errors) => {
+                write!(
+                    f,
+                    "The font manifest contains unsafe file paths, refusing to write anything:"
+                )?;
+                for error in errors {
+                    write!(f, "\n  {color_blue}{}{color_reset}", error)?;
+                }
+                Ok(())
+            }
+            FamilyError::UnsafeUrls(errors) => {
+                write!(
+                    f,
+                    "The font manifest contains download URLs that are not allowed, refusing to download anything:"
+                )?;
+                for (url, reason) in errors {
+                    write!(f, "\n  {color_blue}'{}'{color_reset}: {}", url, reason)?;
+                }
+                Ok(())
+            }
+            FamilyError::Collisions(collisions) => {
+                write!(f, "Several files would be written to the same path:")?;
+                for files in collisions {
+                    write!(
+                        f,
+                        "\n  {color_blue}'{}'{color_reset}, from:",
+                        files[0].path.display()
+                    )?;
+                    for file in files {
+                        write!(
+                            f,
+                            "\n    {color_bright_black}-{color_reset} {} {color_bright_black}({}){color_reset}",
+                            file.family, file.source
+                        )?;
+                    }
+                }
+                Ok(())
+            }
+            FamilyError::CaseCollisions(collisions) => {
+                write!(
+                    f,
+                    "Several files only differ in case, and would overwrite each other on this filesystem:"
+                )?;
+                for file in collisions.iter().flatten() {
+                    write!(
+                        f,
+                        "\n  {color_blue}'{}'{color_reset} {color_bright_black}({}){color_reset}",
+                        file.path.display(),
+                        file.family
+                    )?;
+                }
+                Ok(())
+            }
+            FamilyError::NotCached(urls) => {
+                write!(
+                    f,
+                    "The following files are not cached, so they can't be installed with --offline:"
+                )?;
+                for url in urls {
+                    write!(f, "\n  {color_blue}'{}'{color_reset}", url)?;
+                }
+                Ok(())
+            }
+            FamilyError::MissingFaces(faces) => write!(
+                f,
+                "None of its font files has the faces {color_blue}'{}'{color_reset}!",
+                faces
+            ),
+        }
+    }
+}
+
+/// The files the families of a run are going to write (or wrote), in the order they were added.
+/// Families are added one after the other, so the files of a family are only checked against the ones before it.
+#[derive(Clone, Debug, Default)]
+pub struct InstallPlan {
+    files: Vec<PlannedFile>,
+}
+
+impl InstallPlan {
+    /// The planned files of every family added so far.
+    pub fn files(&self) -> &[PlannedFile] {
+        &self.files
+    }
+
+    /// Readies the manifest of a family to be installed after the families already in the plan, and adds its files:
+    /// checks that it has files, that they stay inside the font base dir and are downloaded from allowed hosts (the default
+    /// ones and `extra_hosts`), and that they don't collide with each other or with the files planned before.
+    /// Text files (licenses, READMEs, etc.) that collide are moved into the dir of their family. The files of the families
+    /// before are already written then, so they stay where they are. With `case_insensitive`, files that only differ in
+    /// case collide as well. Nothing is added if the family can't be installed.
+    pub fn add_family(
+        &mut self,
+        font_manifest: &mut FontManifest,
+        base_path: &Path,
+        extra_hosts: &[&str],
+        case_insensitive: bool,
+    ) -> Result<(), FamilyError> {
+        if font_manifest.is_empty() {
+            return Err(FamilyError::Empty);
+        }
+        font_manifest
+            .validate_paths(base_path)
+            .map_err(FamilyError::UnsafePaths)?;
+        font_manifest
+            .validate_urls(extra_hosts)
+            .map_err(FamilyError::UnsafeUrls)?;
+
+        let planned_before = self.files.len();
+        let planned_files = self.with_files_of(font_manifest, base_path);
+        let (font_collisions, text_collisions): (Vec<_>, Vec<_>) = find_collisions(&planned_files)
+            .into_iter()
+            .partition(|indices| indices.iter().any(|&index| planned_files[index].is_font));
+        let moved = text_collisions
+            .iter()
+            .flatten()
+            .filter(|&&index| index >= planned_before)
+            .map(|&index| font_manifest.move_into_family_dir(index - planned_before))
+            .count();
+
+        // Moving files into the dir of their family can't make them collide with other families, but it could with the same family.
+        let planned_files = if moved == 0 {
+            planned_files
+        } else {
+            self.with_files_of(font_manifest, base_path)
+        };
+        let font_collisions = if moved == 0 {
+            font_collisions
+        } else {
+            find_collisions(&planned_files)
+        };
+        let files_of = |collisions: Vec<Vec<usize>>| {
+            collisions
+                .into_iter()
+                .map(|indices| {
+                    indices
+                        .into_iter()
+                        .map(|index| planned_files[index].clone())
+                        .collect()
+                })
+                .collect::<Vec<_>>()
+        };
+        if !font_collisions.is_empty() {
+            return Err(FamilyError::Collisions(files_of(font_collisions)));
+        }
+        let case_collisions = find_case_collisions(&planned_files);
+        if case_insensitive && !case_collisions.is_empty() {
+            return Err(FamilyError::CaseCollisions(files_of(case_collisions)));
+        }
+
+        self.files = planned_files;
+        Ok(())
+    }
+
+    /// The planned files, followed by the ones of a manifest.
+    fn with_files_of(&self, font_manifest: &FontManifest, base_path: &Path) -> Vec<PlannedFile> {
+        self.files
+            .iter()
+            .cloned()
+            .chain(font_manifest.planned_files(base_path))
+            .collect()
+    }
+}
+
+/// Fetches the manifest of a family (from the cache with `--offline`), with every file marked as one of the family.
+pub fn load_family_manifest(
+    font_name: &str,
+    options: &Options,
+    cache: Option<&DownloadCache>,
+) -> Result<FontManifest, FamilyError> {
+    let mut font_manifest = FontManifest::load(font_name, options, cache)
+        .map_err(|e| FamilyError::Manifest(Box::new(e)))?;
+    font_manifest.set_family(font_name);
+    Ok(font_manifest)
+}
+
+/// The parts of installing a family that differ between who installs it. `install_families` installs everything as it is,
+/// while the `fetch` command selects faces, asks before overwriting, backs up, and shows the progress and what an update changes.
+pub trait InstallHooks {
+    /// Readies the manifest of a family before it is checked and added to the plan, e.g. to only keep some faces.
+    fn prepare(
+        &mut self,
+        _font_name: &str,
+        font_manifest: FontManifest,
+        _registry: &Registry,
+    ) -> Result<FontManifest, FamilyError> {
+        Ok(font_manifest)
+    }
+
+    /// Called when a family can't be installed, before it is warned about and added to the failed families.
+    fn failed(&mut self, _font_name: &str, _error: &FamilyError) {}
+
+    /// Called once a family is planned, before anything of it is written. Returns the existing files to keep, or `None`
+    /// to write nothing at all (like a dry run). The registry may be loaded again, e.g. once the font base dir was created.
+    fn before_writing(
+        &mut self,
+        _font_name: &str,
+        _font_manifest: &FontManifest,
+        _registry: &mut Registry,
+        _warnings: &mut Warnings,
+    ) -> Option<HashSet<PathBuf>> {
+        Some(HashSet::new())
+    }
+
+    /// Called once the text files of a family are written.
+    fn text_files_written(&mut self) {}
+
+    /// Called once the font files of a family are downloaded (or taken from the cache).
+    fn font_files_fetched(&mut self) {}
+
+    /// Called once a family is recorded in the registry, before it is saved, with the version it had before (if it was
+    /// installed before).
+    fn recorded(
+        &mut self,
+        _font_name: &str,
+        _old_version: Option<Option<String>>,
+        _registry: &mut Registry,
+    ) {
+    }
+}
+
+/// Installs everything as it is.
+impl InstallHooks for () {}
+
+/// Installs families into a font base dir one after the other, keeping what happened for the report. Both `install_families`
+/// and the `fetch` command install every family with `install_family`, what differs between them is in the `InstallHooks`.
+pub struct Installer<'a> {
+    pub base_path: &'a Path,
+    pub options: &'a Options,
+    pub cache: Option<&'a DownloadCache>,
+    /// The hosts downloads are allowed from, besides the default ones.
+    pub extra_hosts: &'a [&'a str],
+    /// Whether files that only differ in case collide.
+    pub case_insensitive: bool,
+    /// Whether the files of every family go into its own dir, like those of a family fetched on its own.
+    pub own_dir: bool,
+    pub registry: Registry,
+    pub plan: InstallPlan,
+    pub warnings: Warnings,
+    /// The families that were installed (or only planned, when the hooks wrote nothing), in order.
+    pub families: Vec<String>,
+    pub failed_families: Vec<FailedFamily>,
+    pub text_files: Vec<FileResult>,
+    pub font_files: Vec<FileResult>,
+    /// Why the registry could not be saved after the last family, if it couldn't.
+    registry_error: Option<io::Error>,
+}
+
+impl<'a> Installer<'a> {
+    /// Starts installing into a font base dir, with its registry and the warnings of the run so far. Downloads are only
+    /// allowed from the default hosts, and families go directly into the font base dir, until the fields say otherwise.
+    pub fn new(
+        base_path: &'a Path,
+        options: &'a Options,
+        cache: Option<&'a DownloadCache>,
+        registry: Registry,
+        warnings: Warnings,
+    ) -> Self {
+        Self {
+            base_path,
+            options,
+            cache,
+            extra_hosts: &[],
+            case_insensitive: false,
+            own_dir: false,
+            registry,
+            plan: InstallPlan::default(),
+            warnings,
+            families: Vec::new(),
+            failed_families: Vec::new(),
+            text_files: Vec::new(),
+            font_files: Vec::new(),
+            registry_error: None,
+        }
+    }
+
+    /// Installs a family: checks its manifest and adds it to the plan, writes its text files and downloads its font files
+    /// (`options.jobs` at a time), and records it in the registry, which is saved after every family, so it knows what was
+    /// installed when the run is interrupted. A family that can't be installed fails on its own, and so do files that can't
+    /// be fetched or written.
+    pub fn install_family(
+        &mut self,
+        font_name: &str,
+        font_manifest: Result<FontManifest, FamilyError>,
+        hooks: &mut impl InstallHooks,
+    ) {
+        let font_manifest = font_manifest.and_then(|font_manifest| {
+            let font_manifest = hooks.prepare(font_name, font_manifest, &self.registry)?;
+            let mut font_manifest = if self.own_dir {
+                font_manifest.prepend_path_to_files(&family_dir_name(font_name))
+            } else {
+                font_manifest
+            };
+            self.plan.add_family(
+                &mut font_manifest,
+                self.base_path,
+                self.extra_hosts,
+                self.case_insensitive,
+            )?;
+            Ok(font_manifest)
+        });
+        let font_manifest = match font_manifest {
+            Ok(font_manifest) => font_manifest,
+            Err(e) => {
+                hooks.failed(font_name, &e);
+                self.warnings.warn(
+                    WarningCode::ManifestFailed,
+                    format!(
+                        "Skipping {color_blue}'{}'{color_reset}, as {}.",
+                        font_name,
+                        e.summary()
+                    ),
+                );
+                self.failed_families.push(FailedFamily {
+                    family: font_name.to_string(),
+                    error: e.to_string(),
+                });
+                return;
+            }
+        };
+        self.families.push(font_name.to_string());
+        warn_about_layout_duplicates(
+            &mut self.warnings,
+            font_name,
+            self.plan.files(),
+            &self.registry,
+            self.base_path,
+        );
+
+        let Some(skip) = hooks.before_writing(
+            font_name,
+            &font_manifest,
+            &mut self.registry,
+            &mut self.warnings,
+        ) else {
+            return;
+        };
+        create_parent_dirs(
+            &font_manifest.destination_paths(self.base_path),
+            self.options,
+        );
+        let text_files = font_manifest.write_files(self.base_path, self.options, &skip);
+        hooks.text_files_written();
+        let font_files =
+            font_manifest.fetch_files_from_refs(self.base_path, self.options, &skip, self.cache);
+        hooks.font_files_fetched();
+
+        // Keep track of what is installed, so it does not have to be guessed from the directories later.
+        let old_version = self
+            .registry
+            .find(font_name)
+            .map(|family| family.version.clone());
+        let family_files = text_files
+            .iter()
+            .chain(&font_files)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.registry
+            .record(font_name, self.base_path, &family_files, self.options);
+        hooks.recorded(font_name, old_version, &mut self.registry);
+        self.registry_error = self.registry.save(self.base_path).err();
+        self.text_files.extend(text_files);
+        self.font_files.extend(font_files);
+    }
+
+    /// Warns if the registry could not be saved after the last family.
+    pub fn warn_if_registry_not_saved(&mut self) {
+        if let Some(e) = self.registry_error.take() {
+            self.warnings.warn(
+                WarningCode::RegistryNotSaved,
+                format!(
+                    "Failed to update the registry of installed fonts! Error:\n{}",
+                    e
+                ),
+            );
+        }
+    }
+
+    /// The report of everything installed, with each family under the argument it was requested with (see `requested_by`).
+    pub fn into_report(self, requested: &[String], started_at: Instant) -> InstallReport {
+        let sections = self
+            .families
+            .iter()
+            .map(|family| {
+                let requested =
+                    requested_by(requested, family).map_or(family.as_str(), String::as_str);
+                family_section(requested, family, &self.registry, self.base_path)
+            })
+            .collect();
+        InstallReport {
+            summary: RunSummary::from_results(
+                &self.families,
+                &self.failed_families,
+                &self.text_files,
+                &self.font_files,
+                started_at.elapsed(),
+            ),
+            sections,
+            families: self.families,
+            failed_families: self.failed_families,
+            timing: None,
+            warnings: self.warnings.into_vec(),
+            files: self.text_files.into_iter().chain(self.font_files).collect(),
+            targets: Vec::new(),
+        }
+    }
+}
+
+/// A family fetched on its own goes into its own dir, but with other fonts directly into the font base dir. Installing it
+/// the other way than before leaves the old copies behind (unless they belong to another font), which is warned about.
+fn warn_about_layout_duplicates(
+    warnings: &mut Warnings,
+    font_name: &str,
+    planned_files: &[PlannedFile],
+    registry: &Registry,
+    base_path: &Path,
+) {
+    let stale_files = planned_files
+        .iter()
+        .filter(|planned_file| planned_file.is_font && planned_file.family == font_name)
+        .filter_map(|planned_file| planned_file.path.strip_prefix(base_path).ok())
+        .map(|path| layout_counterpart(font_name, path))
+        .filter(|stale_path| {
+            base_path.join(stale_path).is_file()
+                && registry
+                    .recorded_by(stale_path)
+                    .is_none_or(|family| family.eq_ignore_ascii_case(font_name))
+                && !planned_files
+                    .iter()
+                    .any(|planned_file| planned_file.path == base_path.join(stale_path))
+        })
+        .count();
+    if stale_files > 0 {
+        warnings.warn(
+            WarningCode::LayoutDuplicate,
+            format!(
+                "{} of {color_blue}'{}'{color_reset} will be installed twice, as {} already in the font base dir in the other layout. Run {color_blue}fonty migrate-layout{color_reset} afterwards to remove the old copies.",
+                n_files(stale_files),
+                font_name,
+                if stale_files == 1 { "it is" } else { "they are" }
+            ),
+        );
+    }
+}
+
+/// The section of an installed family in the report, with the dir its files are in from the registry.
+pub fn family_section(
+    requested: &str,
+    family: &str,
+    registry: &Registry,
+    base_path: &Path,
+) -> FamilySection {
+    FamilySection {
+        requested: requested.to_string(),
+        family: family.to_string(),
+        dir: base_path.join(
+            registry
+                .find(family)
+                .map(RegistryEntry::dir)
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+/// Installs families into a font base dir, like `fonty fetch` without the command line: nothing is asked, existing files
+/// are overwritten, and nothing exits. A family fetched on its own goes into its own dir, with others directly into the
+/// font base dir. Downloads are only allowed from the default hosts (see `http::DEFAULT_ALLOWED_HOSTS`).
+/// A family that can't be installed fails on its own, and so do files that can't be fetched or written. Everything that
+/// happened is in the report, including its exit code. The caller should hold the lock of the font base dir (see `lock_base_dir`).
+pub fn install_families(
+    font_names: &[String],
+    base_path: &Path,
+    options: &Options,
+    cache: Option<&DownloadCache>,
+) -> InstallReport {
+    let started_at = Instant::now();
+    let mut warnings = Warnings::default();
+    let registry = Registry::load(base_path, &mut warnings);
+    let mut installer = Installer::new(base_path, options, cache, registry, warnings);
+    installer.own_dir = font_names.len() == 1;
+
+    run_pipelined(
+        font_names,
+        options.prefetch,
+        |_, font_name| load_family_manifest(font_name, options, cache),
+        |_, font_name, font_manifest| installer.install_family(font_name, font_manifest, &mut ()),
+    );
+    installer.warn_if_registry_not_saved();
+    installer.into_report(font_names, started_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs, sync::Arc};
+
+    use super::*;
+    use crate::{
+        cli::Cli,
+        config::Config,
+        exit_codes::EXIT_CODE_FILE_IO_ERROR,
+        font_manifest::{FileFailure, FileStatus},
+        http,
+        options::resolve_options,
+        testing::{MockResponse, MockServer, MockServerFetcher, TestDir},
+    };
+
+    const FAMILIES: usize = 50;
+    const FILES_PER_FAMILY: usize = 10;
+
+    fn options() -> Options {
+        let cli = Cli::parse(["fetch".to_string()].into_iter()).unwrap();
+        resolve_options(&cli, &HashMap::new(), &Config::default()).unwrap()
+    }
+
+    /// The index of the family a request of the stress server is for, e.g. 12 for `Stress12`.
+    fn family_of(path: &str) -> usize {
+        let family = path.split("Stress").nth(1).unwrap();
+        family
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    /// Google Fonts with the families `Stress0`, `Stress1`, etc., which have a few tiny font files each.
+    fn stress_server() -> MockServer {
+        MockServer::start(|request| {
+            let Some(family) = request.path.strip_prefix("/download/list?family=") else {
+                return MockResponse::ok(format!("wOF2 {}", request.path))
+                    .header("Content-Type", "font/woff2");
+            };
+            let file_refs = (0..FILES_PER_FAMILY)
+                .map(|index| {
+                    format!(
+                        r#"{{"filename": "{family}-{index}.woff2", "url": "https://fonts.gstatic.com/s/{family}/{index}.woff2"}}"#
+                    )
+                })
+                .collect::<Vec<_>>();
+            MockResponse::ok(format!(
+                ")]}}'\n{{\"zipName\": \"{family}.zip\", \"manifest\": {{\"files\": [], \"fileRefs\": [{}]}}}}",
+                file_refs.join(",")
+            ))
+            .header("Content-Type", "application/json")
+        })
+    }
+
+    #[test]
+    fn manifests_are_fetched_at_most_prefetch_families_ahead_of_the_downloads() {
+        let server = stress_server();
+        http::set_fetcher(Arc::new(MockServerFetcher::new(&server)));
+        let test_dir = TestDir::new("pipelined-install");
+        let font_names = (0..FAMILIES)
+            .map(|index| format!("Stress{}", index))
+            .collect::<Vec<_>>();
+        let options = Options {
+            prefetch: 3,
+            ..options()
+        };
+
+        let report = install_families(&font_names, &test_dir.path, &options, None);
+        let results = report.files;
+
+        assert_eq!(results.len(), FAMILIES * FILES_PER_FAMILY);
+        for result in &results {
+            assert_eq!(result.status, FileStatus::Written, "{:?}", result);
+        }
+        assert_eq!(
+            fs::read_to_string(test_dir.path.join("Stress49-9.woff2")).unwrap(),
+            "wOF2 /s/Stress49/9.woff2"
+        );
+
+        // While the files of a family are downloaded, the manifests of the next --prefetch families may be fetched, but no more.
+        let requests = server.requests();
+        let is_manifest = |path: &str| path.starts_with("/download/list");
+        let mut downloading = None;
+        for request in &requests {
+            let family = family_of(&request.path);
+            if is_manifest(&request.path) {
+                let bound = downloading.map_or(0, |downloading| downloading + 1) + options.prefetch;
+                assert!(
+                    family <= bound,
+                    "the manifest of family {} was fetched while family {:?} was downloaded",
+                    family,
+                    downloading
+                );
+            } else {
+                downloading = downloading.max(Some(family));
+            }
+        }
+        // And they are fetched along the way, not all before the first download.
+        let first_download = requests
+            .iter()
+            .position(|request| !is_manifest(&request.path))
+            .unwrap();
+        let last_manifest = requests
+            .iter()
+            .rposition(|request| is_manifest(&request.path))
+            .unwrap();
+        assert!(last_manifest > first_download);
+    }
+
+    #[test]
+    fn families_that_cant_be_written_fail_without_exiting() {
+        let server = stress_server();
+        http::set_fetcher(Arc::new(MockServerFetcher::new(&server)));
+        let test_dir = TestDir::new("read-only-install");
+        // A family fetched on its own goes into its own dir. A regular file in its place can't be written into, even as root.
+        fs::write(test_dir.path.join("Stress0"), "not a directory").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&test_dir.path, fs::Permissions::from_mode(0o555)).unwrap();
+        }
+
+        let report = install_families(&["Stress0".to_string()], &test_dir.path, &options(), None);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&test_dir.path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        assert_eq!(report.files.len(), FILES_PER_FAMILY);
+        for result in &report.files {
+            assert!(
+                matches!(
+                    result.status,
+                    FileStatus::Failed {
+                        failure: FileFailure::Write,
+                        ..
+                    }
+                ),
+                "{:?}",
+                result
+            );
+        }
+        assert_eq!(report.exit_code(false), EXIT_CODE_FILE_IO_ERROR);
+    }
+
+    /// Hooks that note what happened. With `dry_run`, nothing is written.
+    #[derive(Default)]
+    struct RecordingHooks {
+        base_path: PathBuf,
+        dry_run: bool,
+        failed: Vec<String>,
+        /// The families in the registry file on disk, before each family is written.
+        saved_before: Vec<Vec<String>>,
+        recorded: Vec<(String, Option<Option<String>>)>,
+    }
+
+    impl InstallHooks for RecordingHooks {
+        fn failed(&mut self, font_name: &str, _error: &FamilyError) {
+            self.failed.push(font_name.to_string());
+        }
+
+        fn before_writing(
+            &mut self,
+            _font_name: &str,
+            _font_manifest: &FontManifest,
+            _registry: &mut Registry,
+            warnings: &mut Warnings,
+        ) -> Option<HashSet<PathBuf>> {
+            let saved = Registry::load(&self.base_path, warnings);
+            self.saved_before.push(
+                saved
+                    .families
+                    .into_iter()
+                    .map(|family| family.name)
+                    .collect(),
+            );
+            (!self.dry_run).then(HashSet::new)
+        }
+
+        fn recorded(
+            &mut self,
+            font_name: &str,
+            old_version: Option<Option<String>>,
+            _registry: &mut Registry,
+        ) {
+            self.recorded.push((font_name.to_string(), old_version));
+        }
+    }
+
+    /// Installs families with hooks, like `install_families` does with none.
+    fn install_with(
+        font_names: &[&str],
+        base_path: &Path,
+        options: &Options,
+        hooks: &mut RecordingHooks,
+    ) -> InstallReport {
+        let mut warnings = Warnings::default();
+        let registry = Registry::load(base_path, &mut warnings);
+        let mut installer = Installer::new(base_path, options, None, registry, warnings);
+        for font_name in font_names {
+            installer.install_family(
+                font_name,
+                load_family_manifest(font_name, options, None),
+                hooks,
+            );
+        }
+        installer.warn_if_registry_not_saved();
+        let requested = font_names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        installer.into_report(&requested, Instant::now())
+    }
+
+    #[test]
+    fn the_registry_is_saved_after_every_family() {
+        let server = stress_server();
+        http::set_fetcher(Arc::new(MockServerFetcher::new(&server)));
+        let test_dir = TestDir::new("install-hooks");
+        let mut hooks = RecordingHooks {
+            base_path: test_dir.path.clone(),
+            ..RecordingHooks::default()
+        };
+
+        let report = install_with(
+            &["Stress0", "Stress1"],
+            &test_dir.path,
+            &options(),
+            &mut hooks,
+        );
+        assert_eq!(report.families, ["Stress0", "Stress1"]);
+        assert_eq!(report.files.len(), 2 * FILES_PER_FAMILY);
+        assert_eq!(hooks.saved_before, [vec![], vec!["Stress0".to_string()]]);
+        assert_eq!(
+            hooks.recorded,
+            [("Stress0".to_string(), None), ("Stress1".to_string(), None)]
+        );
+
+        // Installing again knows the versions of before.
+        let mut hooks = RecordingHooks {
+            base_path: test_dir.path.clone(),
+            ..RecordingHooks::default()
+        };
+        install_with(&["Stress0"], &test_dir.path, &options(), &mut hooks);
+        assert_eq!(hooks.recorded, [("Stress0".to_string(), Some(None))]);
+    }
+
+    #[test]
+    fn hooks_can_plan_families_without_writing_them() {
+        let server = stress_server();
+        http::set_fetcher(Arc::new(MockServerFetcher::new(&server)));
+        let test_dir = TestDir::new("install-hooks-dry-run");
+        let mut hooks = RecordingHooks {
+            base_path: test_dir.path.clone(),
+            dry_run: true,
+            ..RecordingHooks::default()
+        };
+
+        // `Stress0` twice collides with itself, so the second one fails.
+        let report = install_with(
+            &["Stress0", "Stress0"],
+            &test_dir.path,
+            &options(),
+            &mut hooks,
+        );
+        assert_eq!(report.families, ["Stress0"]);
+        assert!(report.files.is_empty());
+        assert_eq!(hooks.failed, ["Stress0"]);
+        assert_eq!(report.failed_families.len(), 1);
+        assert!(hooks.recorded.is_empty());
+        assert!(!test_dir.path.join("registry.json").exists());
+        assert_eq!(report.warnings.len(), 1);
+        assert_eq!(report.warnings[0].code, WarningCode::ManifestFailed);
+    }
+}
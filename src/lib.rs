@@ -0,0 +1,57 @@
+//! fonty fetches fonts from Google Fonts and installs them. The modules are a library, so they can be benchmarked
+//! (see `benches/`); the command line lives in `main.rs` and `commands/`.
+
+pub mod archive;
+pub mod axes;
+pub mod backup;
+pub mod base_path;
+pub mod catalog;
+pub mod cli;
+pub mod color;
+pub mod config;
+pub mod csv;
+pub mod download_cache;
+pub mod error;
+pub mod exit_codes;
+pub mod family_spec;
+pub mod fixtures;
+pub mod font_face;
+pub mod font_manifest;
+pub mod font_names;
+pub mod format;
+pub mod gallery;
+pub mod hooks;
+pub mod http;
+pub mod i18n;
+pub mod install;
+pub mod license;
+pub mod lock;
+pub mod manpage;
+pub mod nix;
+pub mod notify;
+pub mod noto;
+pub mod options;
+pub mod output;
+pub mod permission_hints;
+pub mod plan;
+pub mod progress;
+pub mod project_manifest;
+pub mod prompt;
+pub mod registry;
+pub mod report;
+pub mod safe_path;
+#[cfg(feature = "self-update")]
+pub mod self_update;
+pub mod sfnt;
+pub mod sha256;
+pub mod shell_env;
+pub mod summary;
+pub mod system_integration;
+pub mod table;
+pub mod targets;
+#[cfg(test)]
+pub mod testing;
+pub mod update_diff;
+pub mod warnings;
+pub mod webkit;
+pub mod worker_pool;
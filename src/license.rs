@@ -0,0 +1,101 @@
+/// The names of the license files Google Fonts ships with families, uppercased.
+pub const LICENSE_FILENAMES: &[&str] = &["OFL.TXT", "LICENSE.TXT", "UFL.TXT"];
+
+/// What is reported for a license that can't be identified.
+pub const UNKNOWN_LICENSE: &str = "UNKNOWN";
+
+/// How many lines at the start of a license file are looked at. The name of the license is always in its heading,
+/// though the OFL starts with the copyright lines of the family.
+const HEADING_LINES: usize = 10;
+
+/// The SPDX identifiers of the licenses fonts on Google Fonts use, and a phrase in the heading of each license.
+const LICENSE_HEADINGS: &[(&str, &str)] = &[
+    ("OFL-1.1", "SIL OPEN FONT LICENSE VERSION 1.1"),
+    ("OFL-1.1", "SIL OPEN FONT LICENSE, VERSION 1.1"),
+    ("Apache-2.0", "APACHE LICENSE VERSION 2.0"),
+    ("Apache-2.0", "APACHE LICENSE, VERSION 2.0"),
+    ("UFL-1.0", "UBUNTU FONT LICENCE VERSION 1.0"),
+    ("UFL-1.0", "UBUNTU FONT LICENCE, VERSION 1.0"),
+];
+
+/// Checks if a file is a license file, by its name.
+pub fn is_license_file(filename: &str) -> bool {
+    let filename = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    LICENSE_FILENAMES.contains(&filename.to_uppercase().as_str())
+}
+
+/// Identifies a license by the heading of its text, as an SPDX identifier.
+/// Anything that does not name a known license and version is `UNKNOWN`, it is never guessed from the filename.
+pub fn detect_license(text: &str) -> &'static str {
+    // Join the heading into one line, so it does not matter how the license wraps it.
+    let heading = text
+        .lines()
+        .take(HEADING_LINES)
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase();
+    LICENSE_HEADINGS
+        .iter()
+        .find(|(_, phrase)| heading.contains(phrase))
+        .map_or(UNKNOWN_LICENSE, |(spdx_id, _)| spdx_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn licenses_are_detected_by_their_heading() {
+        let cases = [
+            (
+                "Copyright 2020 The Inter Project Authors (https://github.com/rsms/inter)\n\nThis Font Software is licensed under the SIL Open Font License, Version 1.1.\n",
+                "OFL-1.1",
+            ),
+            // The heading may wrap anywhere, and be in any case.
+            (
+                "Copyright 2011 The Lobster Project Authors\n\nThis Font Software is licensed under the SIL Open\nFont License, Version 1.1.",
+                "OFL-1.1",
+            ),
+            (
+                "\n                                 Apache License\n                           Version 2.0, January 2004\n",
+                "Apache-2.0",
+            ),
+            (
+                "-------------------------------\nUBUNTU FONT LICENCE Version 1.0\n-------------------------------\n",
+                "UFL-1.0",
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(detect_license(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn unknown_licenses_are_not_guessed() {
+        let cases = [
+            "",
+            "All rights reserved.",
+            // A license without a version could be any version.
+            "This Font Software is licensed under the SIL Open Font License.",
+            "Apache License Version 1.1",
+            // The name has to be in the heading, not further down.
+            &format!(
+                "{}SIL Open Font License, Version 1.1",
+                "Copyright\n".repeat(HEADING_LINES)
+            ),
+        ];
+        for text in cases {
+            assert_eq!(detect_license(text), UNKNOWN_LICENSE, "{}", text);
+        }
+    }
+
+    #[test]
+    fn license_files_are_found_by_their_name() {
+        assert!(is_license_file("OFL.txt"));
+        assert!(is_license_file("static/LICENSE.txt"));
+        assert!(is_license_file("ufl.TXT"));
+        assert!(!is_license_file("README.txt"));
+        assert!(!is_license_file("OFL.ttf"));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::color::*;
+use std::{
+    fmt,
+    fs::{File, OpenOptions, TryLockError},
+    io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    exit_codes::{EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_LOCKED},
+    format::plural,
+    info,
+};
+
+/// The name of the lock file, directly inside the font base dir.
+/// It is never deleted, as deleting it while another process waits for it would let two processes in.
+pub const LOCK_FILENAME: &str = ".fonty.lock";
+
+/// How often a held lock is tried again.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An error while locking a font base dir.
+#[derive(Debug)]
+pub enum LockError {
+    /// The lock file can't be opened or locked.
+    Io { path: PathBuf, error: io::Error },
+    /// Another process held the lock for longer than the timeout.
+    Timeout { path: PathBuf, timeout: Duration },
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Io { path, error } => write!(
+                f,
+                "Failed to lock the font base dir with '{}'! Error:\n{}",
+                path.display(),
+                error
+            ),
+            LockError::Timeout { path, timeout } => write!(
+                f,
+                "Another fonty process still has the font base dir locked with '{}' after {}! Try again later, or raise --lock-timeout.",
+                path.display(),
+                plural(timeout.as_secs() as usize, "second", "seconds")
+            ),
+        }
+    }
+}
+
+impl LockError {
+    /// The exit code to exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LockError::Io { .. } => EXIT_CODE_FILE_IO_ERROR,
+            LockError::Timeout { .. } => EXIT_CODE_LOCKED,
+        }
+    }
+}
+
+/// An exclusive (advisory) lock on a font base dir, held until it is dropped or the process exits.
+pub struct BaseDirLock {
+    _file: File,
+}
+
+/// Locks a font base dir for changing it, so fonty processes sharing it don't interleave their writes.
+/// While another process holds the lock, this waits for up to `timeout`, saying so once.
+/// Returns `None` if the dir doesn't exist (yet), as there is nothing to lock then.
+pub fn lock_base_dir(
+    base_path: &Path,
+    timeout: Duration,
+) -> Result<Option<BaseDirLock>, LockError> {
+    if !base_path.is_dir() {
+        return Ok(None);
+    }
+    let path = base_path.join(LOCK_FILENAME);
+    let io_error = |error| LockError::Io {
+        path: path.clone(),
+        error,
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(io_error)?;
+
+    let started_at = Instant::now();
+    let mut waiting = false;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(Some(BaseDirLock { _file: file })),
+            Err(TryLockError::Error(error)) => return Err(io_error(error)),
+            Err(TryLockError::WouldBlock) if started_at.elapsed() >= timeout => {
+                return Err(LockError::Timeout { path, timeout });
+            }
+            Err(TryLockError::WouldBlock) => {
+                if !waiting {
+                    info!(
+                        "Waiting for another fonty process to finish with {color_blue}'{}'{color_reset}...",
+                        base_path.display()
+                    );
+                    waiting = true;
+                }
+                thread::sleep(RETRY_INTERVAL);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+
+    #[test]
+    fn a_held_lock_times_out() {
+        let test_dir = TestDir::new("lock-held");
+        let _lock = lock_base_dir(&test_dir.path, Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        assert!(test_dir.path.join(LOCK_FILENAME).is_file());
+
+        let error = lock_base_dir(&test_dir.path, Duration::ZERO).err().unwrap();
+        assert!(matches!(error, LockError::Timeout { .. }));
+        assert_eq!(error.exit_code(), EXIT_CODE_LOCKED);
+    }
+
+    #[test]
+    fn missing_dirs_are_not_locked() {
+        let test_dir = TestDir::new("lock-missing");
+        let lock = lock_base_dir(&test_dir.path.join("missing"), Duration::ZERO).unwrap();
+        assert!(lock.is_none());
+    }
+}
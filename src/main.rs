@@ -1,95 +1,340 @@
-use inline_colorization::*;
-use std::env;
+use fonty::color::*;
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::Instant,
+};
 
-use crate::{
-    exit_codes::EXIT_CODE_INVALID_FONT_NAME,
-    font_manifest::{FontManifest, get_font_base_path},
+use fonty::{
+    base_path::{BasePath, BasePathEnv, BasePathError, Platform, resolve_base_path},
+    cli::{Cli, print_commands_help, print_flags_help},
+    color::set_colors_enabled,
+    config::{Config, resolve_config_file},
+    exit_codes::{EXIT_CODE_CONFIG_ERROR, EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_ARGUMENTS},
+    fail,
+    fixtures::set_record_fixtures,
+    http::{enable_http_stats, init_client, print_http_stats, set_debug_http, set_offline},
+    i18n::{language_from_env, set_language},
+    manpage::render_manpage,
+    msg,
+    notify::{enable_notifications, exit},
+    options::resolve_options,
+    output::{set_debug, set_quiet},
+    progress,
 };
 
-mod exit_codes;
-mod font_manifest;
+mod commands;
 
-fn main() {
-    // The first argument is the action to perform, valid actions: help, fetch
-    let action = env::args().nth(1).unwrap_or("help".to_string());
-    let args = env::args().skip(2).collect::<Vec<_>>();
-
-    if action != "fetch" {
-        println!("{color_blue}{style_bold}Usage:{color_reset}{style_reset}");
-        println!(
-            "{color_bright_black}fonty {color_blue}fetch <font1> {color_bright_blue}[font2] [font3] [...]{color_reset}"
-        );
-        println!(
-            "{color_yellow}->{color_reset} Fetches the specified fonts from Google Fonts. (Specify at least one, but multiple is posssible.)"
-        );
-        println!(
-            "{color_yellow}->{color_reset} If a font has spaces in its name, remember to quote or escape the font name."
-        );
-        println!("{color_bright_black}fonty {color_blue}help{color_reset}");
-        println!("{color_yellow}->{color_reset} Prints this help message.");
-        let fonty_base_dir = get_font_base_path();
-        println!(
-            "\n{color_yellow}->{color_reset} Font base dir {color_white}(installation dir){color_reset}: {color_blue}'{}'{color_reset}",
-            fonty_base_dir
+use commands::{
+    cache::cache,
+    clean::clean,
+    config::show_config,
+    diff::diff,
+    export::export,
+    fetch::fetch,
+    fontconfig::fontconfig,
+    gallery::gallery,
+    info::info,
+    list::{list_all, list_installed, list_remote},
+    lock_base_dir_or_exit,
+    migrate_layout::migrate_layout,
+    nix_prefetch::nix_prefetch,
+    remove::remove,
+    repair::repair,
+    report::report,
+    rollback::rollback,
+    search::search,
+    self_update::self_update,
+    shell_env::shell_env,
+    tag::tag,
+    top::top,
+    webkit::webkit,
+};
+
+/// Prints the help message, including where fonts will be installed.
+fn print_help(base_path: &Result<BasePath, BasePathError>) {
+    print_commands_help();
+    print_flags_help();
+    match base_path {
+        Ok(base_path) => println!(
+            "\n{color_yellow}->{color_reset} Font base dir {color_white}(installation dir){color_reset}: {color_blue}'{}'{color_reset} {color_bright_black}(from {}){color_reset}",
+            base_path.path.display(),
+            base_path.source
+        ),
+        Err(e) => println!(
+            "\n{color_yellow}->{color_reset} Font base dir {color_white}(installation dir){color_reset}: {color_red}unknown{color_reset} {color_bright_black}({}){color_reset}",
+            e
+        ),
+    }
+    println!(
+        "{color_yellow}->{color_reset} Change the font base dir with the {color_blue}--base-path{color_reset} flag, or the {color_blue}$FONTY_BASE_PATH{color_reset} environment variable."
+    );
+}
+
+/// Opens where `--progress-fd` or `--progress-file` writes the progress events to. Exits if it can't be written to.
+fn open_progress_or_exit(cli: &Cli) {
+    if let Some(fd) = cli.flag_value("--progress-fd") {
+        let Ok(fd) = fd.parse::<u32>() else {
+            fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!("error.invalid_progress_fd", value = fd)
+            );
+        };
+        #[cfg(unix)]
+        if let Err(e) = progress::open_progress_fd(fd) {
+            fail!(
+                EXIT_CODE_INVALID_ARGUMENTS,
+                "{}",
+                msg!("error.progress_fd", fd = fd, error = e)
+            );
+        }
+        #[cfg(not(unix))]
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.progress_fd_unsupported", fd = fd)
         );
-        println!(
-            "{color_yellow}->{color_reset} Change the font base dir with the {color_blue}$FONTY_BASE_PATH{color_reset} environment variable."
+    }
+    if let Some(path) = cli.flag_value("--progress-file")
+        && let Err(e) = progress::open_progress_file(Path::new(path))
+    {
+        fail!(
+            EXIT_CODE_INVALID_ARGUMENTS,
+            "{}",
+            msg!("error.progress_file", path = path, error = e)
         );
-        std::process::exit(0);
     }
+}
 
-    if args.is_empty() {
-        println!("{color_red}ERROR:{color_reset} No fonts specified!");
-        std::process::exit(EXIT_CODE_INVALID_FONT_NAME);
-    }
+fn main() {
+    // The first argument is the action to perform, valid actions: help, fetch, search, info
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => fail!(EXIT_CODE_INVALID_ARGUMENTS, "{}", e),
+    };
 
-    let invalid_fonts = args
-        .iter()
-        .filter(|&arg| !FontManifest::check_if_valid_font(arg))
-        .collect::<Vec<_>>();
+    let env = BasePathEnv::from_process_env();
+    // Before anything else, so every command uses the same config file.
+    let config_file = match resolve_config_file(&cli, &env, Platform::current()) {
+        Ok(config_file) => config_file,
+        Err(e) => fail!(e.exit_code(), "{}", msg!("error.config_file", error = e)),
+    };
+    let config = match Config::load(config_file.as_ref()) {
+        Ok(config) => config,
+        Err(e) => fail!(e.exit_code(), "{}", e),
+    };
+    let options = match resolve_options(&cli, &env.vars, &config) {
+        Ok(options) => options,
+        Err(e) => fail!(e.exit_code(), "{}", e),
+    };
+    set_colors_enabled(options.color.enabled(&env.vars));
+    set_language(language_from_env(&env.vars));
+    if options.notify {
+        enable_notifications(options.notify_after);
+    }
 
-    if !invalid_fonts.is_empty() {
-        println!(
-            "{color_red}ERROR:{color_reset} The following fonts are invalid: {color_blue}'{}'{color_reset}",
-            invalid_fonts // I don't even know ... this seems needed though! :)
-                .iter()
-                .map(|x| x.as_str())
-                .collect::<Vec<_>>()
-                .join(&format!("'{color_bright_black}, {color_blue}'"))
-        );
-        std::process::exit(EXIT_CODE_INVALID_FONT_NAME);
+    if cli.action == "manpage" {
+        print!("{}", render_manpage());
+        exit(0);
     }
 
-    println!(
-        "{color_cyan}INFO:{color_reset} Will download the following fonts: {color_blue}'{}'{color_reset}",
-        args.join(&format!("'{color_bright_black}, {color_blue}'"))
-    );
+    open_progress_or_exit(&cli);
 
-    let fonty_base_dir = get_font_base_path();
-    println!(
-        "{color_cyan}INFO:{color_reset} Font base dir {color_white}(installation dir){color_reset}: {color_blue}'{}'{color_reset}",
-        fonty_base_dir
-    );
+    let base_path = resolve_base_path(&cli, &env, config.base_path.as_deref());
 
-    let font_manifest = {
-        let font_manifest = FontManifest::fetch(args.iter().map(|s| s.as_str()).collect()).unwrap();
+    if ![
+        "fetch",
+        "search",
+        "info",
+        "nix-prefetch",
+        "list",
+        "top",
+        "tag",
+        "remove",
+        "update",
+        "diff",
+        "sync",
+        "repair",
+        "report",
+        "export",
+        "env",
+        "migrate-layout",
+        "fontconfig",
+        "gallery",
+        "cache",
+        "config",
+        "self-update",
+        "rollback",
+        "clean",
+        "noto",
+    ]
+    .contains(&cli.action.as_str())
+    {
+        print_help(&base_path);
+        exit(0);
+    }
 
-        // If there is only one pending font download, prepend a directory with the name of the font,
-        // so all font files end up in their own sub directory.
-        if args.len() == 1 {
-            font_manifest.prepand_path_to_files(&args[0].replace(" ", "_"))
+    let started_at = Instant::now();
+    // The JSON report has to be the only thing on stdout, for it to be parseable.
+    let json = cli.has_flag("--json");
+    set_quiet(cli.has_flag("--quiet") || json);
+    set_debug_http(cli.has_flag("--debug-http"));
+    set_debug(cli.has_flag("--debug") || cli.has_flag("--debug-http"));
+    if let Some(dir) = cli.flag_value("--record-fixtures")
+        && let Err(e) = set_record_fixtures(PathBuf::from(dir))
+    {
+        fail!(
+            EXIT_CODE_FILE_IO_ERROR,
+            "{}",
+            msg!("error.fixtures_dir", path = dir, error = e)
+        );
+    }
+    if cli.has_flag("--http-stats") {
+        enable_http_stats();
+    }
+    // With --offline, the HTTP client is never set up, so nothing can make a request by accident.
+    if options.offline {
+        set_offline(true);
+    } else if let Err(e) = init_client(&options, &cli.flag_values("--allow-host")) {
+        fail!(
+            EXIT_CODE_CONFIG_ERROR,
+            "{}",
+            msg!("error.http_client", error = e)
+        );
+    }
+
+    if cli.action == "search" {
+        search(&cli, &options, &env, json);
+        exit(0);
+    }
+    if cli.action == "info" {
+        info(&cli, &options, &env, json);
+        exit(0);
+    }
+    if cli.action == "nix-prefetch" {
+        nix_prefetch(&cli, &options, &env);
+        exit(0);
+    }
+    if cli.action == "cache" {
+        cache(&cli, &options, &env);
+        exit(0);
+    }
+    if cli.action == "self-update" {
+        self_update(&cli, &options);
+        exit(0);
+    }
+    if cli.action == "config" {
+        show_config(&cli, &config, config_file.as_ref(), &base_path);
+        exit(0);
+    }
+    if cli.action == "top" {
+        top(&cli, &options, &env, json);
+        exit(0);
+    }
+    if cli.action == "list" {
+        if cli.has_flag("--remote") {
+            list_remote(&cli, &options, &env, json);
         } else {
-            font_manifest
+            match &base_path {
+                Ok(base_path) if cli.has_flag("--all") => list_all(&cli, &env, base_path, json),
+                Ok(base_path) => list_installed(&cli, base_path, json),
+                Err(e) => fail!(e.exit_code(), "{}", e),
+            }
         }
+        exit(0);
+    }
+
+    let base_path = match base_path {
+        Ok(base_path) => base_path,
+        Err(e) => fail!(e.exit_code(), "{}", e),
     };
 
-    println!(
-        "{color_cyan}INFO:{color_reset} Writing text files... {color_white}(Licenes, READMEs, etc.){color_reset}"
-    );
-    font_manifest.write_files();
+    // Commands changing the font base dir hold its lock until they exit. Reading it needs no lock.
+    let changes_base_dir = [
+        "fetch",
+        "update",
+        "sync",
+        "noto",
+        "remove",
+        "tag",
+        "repair",
+        "rollback",
+        "clean",
+        "migrate-layout",
+    ]
+    .contains(&cli.action.as_str())
+        && !cli.has_flag("--dry-run")
+        && !(cli.action == "repair" && cli.has_flag("--check"));
+    let base_dir_lock = if changes_base_dir {
+        lock_base_dir_or_exit(&base_path, &options)
+    } else {
+        None
+    };
 
-    println!("{color_cyan}INFO:{color_reset} Downloading font files...");
-    font_manifest.fetch_files_from_refs();
+    if cli.action == "tag" {
+        tag(&cli, &base_path);
+        exit(0);
+    }
+    if cli.action == "remove" {
+        remove(&cli, &options, &env, &base_path);
+        exit(0);
+    }
+    if cli.action == "rollback" {
+        rollback(&cli, &options, &base_path);
+        exit(0);
+    }
+    if cli.action == "clean" {
+        clean(&base_path, &options);
+        exit(0);
+    }
+    if cli.action == "report" {
+        report(&cli, &base_path);
+        exit(0);
+    }
+    if cli.action == "diff" {
+        diff(&cli, &options, &env, &base_path);
+        exit(0);
+    }
+    if cli.action == "migrate-layout" {
+        migrate_layout(&cli, &options, &base_path);
+        exit(0);
+    }
+    if cli.action == "env" {
+        shell_env(&cli, &base_path);
+        exit(0);
+    }
+    if cli.action == "fetch" && cli.has_flag("--webkit") {
+        webkit(&cli, &options, &env, &base_path);
+        if cli.has_flag("--http-stats") {
+            print_http_stats();
+        }
+        exit(0);
+    }
+    if cli.action == "gallery" {
+        gallery(&cli, &env, &base_path);
+        exit(0);
+    }
+    if cli.action == "fontconfig" {
+        fontconfig(&cli, &options, &env, &base_path);
+        exit(0);
+    }
+    if cli.action == "export" {
+        export(&cli, &base_path);
+        exit(0);
+    }
+    if cli.action == "repair" {
+        repair(&cli, &options, &env, &base_path);
+        exit(0);
+    }
 
-    println!("{color_cyan}INFO:{color_reset} All downloads {color_green}DONE{color_reset}!");
+    fetch(
+        &cli,
+        &options,
+        &env,
+        &base_path,
+        base_dir_lock,
+        started_at,
+        json,
+    );
 }
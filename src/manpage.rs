@@ -0,0 +1,115 @@
+use crate::{
+    cli::{COMMANDS, ENV_VARS, FLAGS},
+    exit_codes::EXIT_CODES,
+};
+
+/// Escapes text for use in roff.
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('-', "\\-");
+    // Lines starting with `.` or `'` would be read as roff requests.
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Renders the man page for fonty, generated from the same definitions as the help message so they can't drift apart.
+pub fn render_manpage() -> String {
+    let mut page = String::new();
+    let mut line = |text: &str| {
+        page.push_str(text);
+        page.push('\n');
+    };
+
+    line(&format!(
+        ".TH FONTY 1 \"\" \"fonty {}\" \"User Commands\"",
+        env!("CARGO_PKG_VERSION")
+    ));
+    line(".SH NAME");
+    line("fonty \\- download fonts from Google Fonts");
+    line(".SH SYNOPSIS");
+    line(".B fonty");
+    line("\\fIcommand\\fR [\\fIoptions\\fR] [\\fIargs\\fR...]");
+
+    line(".SH COMMANDS");
+    for spec in COMMANDS {
+        line(".TP");
+        let args = format!("{} {}", spec.args, spec.optional_args);
+        line(format!("\\fB{}\\fR {}", escape(spec.name), escape(args.trim())).trim_end());
+        line(&escape(&spec.description.join(" ")));
+    }
+
+    line(".SH OPTIONS");
+    for spec in FLAGS {
+        line(".TP");
+        let names = spec
+            .names
+            .iter()
+            .map(|name| format!("\\fB{}\\fR", escape(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match spec.value_name {
+            Some(value_name) => line(&format!("{} \\fI{}\\fR", names, escape(value_name))),
+            None => line(&names),
+        }
+        line(&escape(spec.description));
+    }
+
+    line(".SH ENVIRONMENT");
+    for spec in ENV_VARS {
+        line(".TP");
+        line(&format!("\\fB{}\\fR", escape(spec.name)));
+        line(&escape(spec.description));
+    }
+
+    line(".SH EXIT STATUS");
+    for (code, description) in EXIT_CODES {
+        line(".TP");
+        line(&format!("\\fB{}\\fR", code));
+        line(&escape(description));
+    }
+
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_command_flag_and_variable_is_in_the_man_page() {
+        let page = render_manpage();
+        let lines = page.lines().collect::<Vec<_>>();
+        for spec in COMMANDS {
+            let heading = format!("\\fB{}\\fR", escape(spec.name));
+            let index = lines
+                .iter()
+                .position(|line| *line == heading || line.starts_with(&format!("{} ", heading)))
+                .unwrap_or_else(|| panic!("'{}' is not in the man page", spec.name));
+            assert_eq!(lines[index - 1], ".TP", "{}", spec.name);
+            assert_eq!(
+                lines[index + 1],
+                escape(&spec.description.join(" ")),
+                "{}",
+                spec.name
+            );
+        }
+        for spec in FLAGS {
+            for name in spec.names {
+                assert!(
+                    page.contains(&format!("\\fB{}\\fR", escape(name))),
+                    "{}",
+                    name
+                );
+            }
+        }
+        for spec in ENV_VARS {
+            assert!(
+                page.contains(&format!("\\fB{}\\fR", escape(spec.name))),
+                "{}",
+                spec.name
+            );
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::str::FromStr;
+
+use crate::font_manifest::HashedFileRef;
+
+/// The formats `fonty nix-prefetch` can print the files in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NixFormat {
+    Nix,
+    Json,
+}
+
+impl FromStr for NixFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "nix" => Ok(NixFormat::Nix),
+            "json" => Ok(NixFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The arguments for `pkgs.fetchurl` to fetch a file from a font manifest.
+#[derive(Clone, Debug, Serialize)]
+pub struct NixFetch {
+    /// A valid store path name for the file.
+    pub name: String,
+    pub url: String,
+    /// The SHA-256 of the file, as hex (which Nix accepts as is).
+    pub sha256: String,
+}
+
+impl From<&HashedFileRef> for NixFetch {
+    fn from(file_ref: &HashedFileRef) -> Self {
+        Self {
+            name: store_name(&file_ref.filename),
+            url: file_ref.url.clone(),
+            sha256: file_ref.sha256.clone(),
+        }
+    }
+}
+
+/// Turns a filename from a manifest into a name Nix accepts for a store path: the part after the last `/`,
+/// with every character Nix does not allow replaced by `-`.
+fn store_name(filename: &str) -> String {
+    let name = filename.rsplit('/').next().unwrap_or(filename);
+    let name = name
+        .chars()
+        .map(|character| match character {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '+' | '-' | '.' | '_' | '?' | '=' => character,
+            _ => '-',
+        })
+        .collect::<String>();
+    // Store path names can't start with a dot.
+    match name.strip_prefix('.') {
+        Some(name) => format!("-{}", name),
+        None => name,
+    }
+}
+
+/// Quotes a string for a Nix expression.
+fn nix_string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace("${", "\\${")
+    )
+}
+
+/// Renders the files as a Nix list of `pkgs.fetchurl` arguments, or as a JSON list.
+/// Files embedded in the manifest (which have no URL) are listed in a comment in the Nix expression.
+pub fn render_nix_fetches(
+    fetches: &[NixFetch],
+    embedded_files: &[&str],
+    format: NixFormat,
+) -> Result<String, String> {
+    match format {
+        NixFormat::Json => serde_json::to_string_pretty(fetches)
+            .map(|fetches| fetches + "\n")
+            .map_err(|e| e.to_string()),
+        NixFormat::Nix => {
+            let mut expression = String::new();
+            if !embedded_files.is_empty() {
+                expression.push_str(
+                    "# These files are embedded in the font manifest, and can't be fetched on their own:\n",
+                );
+                for filename in embedded_files {
+                    expression.push_str(&format!("# - {}\n", filename.replace('\n', " ")));
+                }
+            }
+            expression.push_str("[\n");
+            for fetch in fetches {
+                expression.push_str(&format!(
+                    "  {{\n    name = {};\n    url = {};\n    sha256 = {};\n  }}\n",
+                    nix_string(&fetch.name),
+                    nix_string(&fetch.url),
+                    nix_string(&fetch.sha256)
+                ));
+            }
+            expression.push_str("]\n");
+            Ok(expression)
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Desktop notifications at the end of long runs, with `--notify`.
+//! Without the `notifications` feature, nothing is ever sent.
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    debug,
+    progress::{self, ProgressEvent},
+};
+
+/// When the run started, and how long it has to take for a notification. Only set with `--notify`.
+static NOTIFY: OnceLock<(Instant, Duration)> = OnceLock::new();
+
+/// Sends a notification when the run ends, if it took at least `threshold` from now on.
+pub fn enable_notifications(threshold: Duration) {
+    let _ = NOTIFY.set((Instant::now(), threshold));
+}
+
+/// Removes the ANSI color codes from a message, as notifications show them as is.
+pub fn strip_colors(message: &str) -> String {
+    let mut stripped = String::with_capacity(message.len());
+    let mut chars = message.chars();
+    while let Some(character) = chars.next() {
+        if character == '\x1b' {
+            // Skips up to the end of the escape sequence, e.g. `\x1b[34m`.
+            for character in chars.by_ref() {
+                if character.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(character);
+        }
+    }
+    stripped
+}
+
+/// Notifies that the run ended, successfully or not, if notifications are enabled and the run was long enough.
+/// The end of the run is also a progress event, with `--progress-fd`.
+/// A missing notification backend is not an error, it only gets a debug log line.
+pub fn notify_finished(success: bool, message: &str) {
+    progress::emit(&ProgressEvent::Finished {
+        success,
+        message: &strip_colors(message),
+    });
+    let Some((started_at, threshold)) = NOTIFY.get() else {
+        return;
+    };
+    if started_at.elapsed() < *threshold {
+        return;
+    }
+    let title = if success { "fonty" } else { "fonty failed" };
+    if let Err(e) = send_notification(title, &strip_colors(message)) {
+        debug!("Could not send a desktop notification: {}", e);
+    }
+}
+
+/// Exits with an exit code, notifying about the failure first if it isn't 0. Used instead of `std::process::exit`,
+/// so every failure is notified about, not only the ones from `fail!` (which notifies with its message).
+pub fn exit(exit_code: i32) -> ! {
+    if exit_code != 0 {
+        notify_finished(false, &format!("Exited with code {}.", exit_code));
+    }
+    std::process::exit(exit_code)
+}
+
+/// Sends a notification with `notify-send` on Linux (and other Unixes), or `osascript` on macOS.
+#[cfg(feature = "notifications")]
+fn send_notification(title: &str, body: &str) -> Result<(), String> {
+    use std::process::{Command, Stdio};
+
+    let mut command = if cfg!(target_os = "macos") {
+        let quote =
+            |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification {} with title {}",
+            quote(body),
+            quote(title)
+        ));
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.arg("--app-name=fonty").arg(title).arg(body);
+        command
+    } else {
+        return Err("no notification backend on this platform".to_string());
+    };
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("the notification backend failed ({})", status))
+    }
+}
+
+/// Without the `notifications` feature, there is no backend.
+#[cfg(not(feature = "notifications"))]
+fn send_notification(_title: &str, _body: &str) -> Result<(), String> {
+    Err("fonty was built without the notifications feature".to_string())
+}
@@ -0,0 +1,292 @@
+//! The Noto families to install for a mix of scripts, so text in all of them has a fallback font: `fonty noto --coverage latin,cjk-sc,emoji`.
+
+use std::fmt;
+
+/// What a coverage keyword installs.
+#[derive(Clone, Copy, Debug)]
+pub struct NotoCoverage {
+    pub keyword: &'static str,
+    pub description: &'static str,
+    pub family: &'static str,
+    /// Roughly how much the family downloads (every static and variable file), in MiB, for the plan.
+    /// This is an estimate from when the table was last updated, the real sizes change with every release.
+    pub size_mib: u64,
+}
+
+/// The coverage keywords, in the order they are listed in.
+pub const NOTO_COVERAGE: &[NotoCoverage] = &[
+    NotoCoverage {
+        keyword: "latin",
+        description: "Latin, Greek and Cyrillic",
+        family: "Noto Sans",
+        size_mib: 30,
+    },
+    NotoCoverage {
+        keyword: "serif",
+        description: "Latin, Greek and Cyrillic, with serifs",
+        family: "Noto Serif",
+        size_mib: 35,
+    },
+    NotoCoverage {
+        keyword: "mono",
+        description: "Latin, Greek and Cyrillic, monospaced",
+        family: "Noto Sans Mono",
+        size_mib: 10,
+    },
+    NotoCoverage {
+        keyword: "cjk-sc",
+        description: "Chinese (Simplified)",
+        family: "Noto Sans SC",
+        size_mib: 90,
+    },
+    NotoCoverage {
+        keyword: "cjk-tc",
+        description: "Chinese (Traditional)",
+        family: "Noto Sans TC",
+        size_mib: 60,
+    },
+    NotoCoverage {
+        keyword: "cjk-hk",
+        description: "Chinese (Hong Kong)",
+        family: "Noto Sans HK",
+        size_mib: 60,
+    },
+    NotoCoverage {
+        keyword: "cjk-jp",
+        description: "Japanese",
+        family: "Noto Sans JP",
+        size_mib: 50,
+    },
+    NotoCoverage {
+        keyword: "cjk-kr",
+        description: "Korean",
+        family: "Noto Sans KR",
+        size_mib: 55,
+    },
+    NotoCoverage {
+        keyword: "emoji",
+        description: "Color emoji",
+        family: "Noto Color Emoji",
+        size_mib: 25,
+    },
+    NotoCoverage {
+        keyword: "arabic",
+        description: "Arabic",
+        family: "Noto Sans Arabic",
+        size_mib: 10,
+    },
+    NotoCoverage {
+        keyword: "hebrew",
+        description: "Hebrew",
+        family: "Noto Sans Hebrew",
+        size_mib: 2,
+    },
+    NotoCoverage {
+        keyword: "devanagari",
+        description: "Devanagari (Hindi, Marathi, Nepali, ...)",
+        family: "Noto Sans Devanagari",
+        size_mib: 8,
+    },
+    NotoCoverage {
+        keyword: "bengali",
+        description: "Bengali",
+        family: "Noto Sans Bengali",
+        size_mib: 8,
+    },
+    NotoCoverage {
+        keyword: "tamil",
+        description: "Tamil",
+        family: "Noto Sans Tamil",
+        size_mib: 5,
+    },
+    NotoCoverage {
+        keyword: "thai",
+        description: "Thai",
+        family: "Noto Sans Thai",
+        size_mib: 3,
+    },
+    NotoCoverage {
+        keyword: "armenian",
+        description: "Armenian",
+        family: "Noto Sans Armenian",
+        size_mib: 2,
+    },
+    NotoCoverage {
+        keyword: "georgian",
+        description: "Georgian",
+        family: "Noto Sans Georgian",
+        size_mib: 3,
+    },
+    NotoCoverage {
+        keyword: "ethiopic",
+        description: "Ethiopic (Amharic, Tigrinya, ...)",
+        family: "Noto Sans Ethiopic",
+        size_mib: 8,
+    },
+    NotoCoverage {
+        keyword: "symbols",
+        description: "Symbols and pictographs",
+        family: "Noto Sans Symbols",
+        size_mib: 2,
+    },
+    NotoCoverage {
+        keyword: "math",
+        description: "Mathematical notation",
+        family: "Noto Sans Math",
+        size_mib: 1,
+    },
+];
+
+/// The estimated download size of a coverage keyword's family, in bytes.
+fn estimated_size(coverage: &NotoCoverage) -> u64 {
+    coverage.size_mib << 20
+}
+
+/// A Noto family to install, with the coverage keywords that asked for it.
+#[derive(Clone, Debug)]
+pub struct NotoFamily {
+    pub family: &'static str,
+    pub estimated_size: u64,
+    pub keywords: Vec<&'static str>,
+}
+
+/// Coverage keywords that are not in the table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownCoverage {
+    pub unknown: Vec<String>,
+}
+
+impl fmt::Display for UnknownCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Unknown coverage {} '{}'! The supported keywords are: '{}'",
+            if self.unknown.len() == 1 {
+                "keyword"
+            } else {
+                "keywords"
+            },
+            self.unknown.join("', '"),
+            NOTO_COVERAGE
+                .iter()
+                .map(|coverage| coverage.keyword)
+                .collect::<Vec<_>>()
+                .join("', '")
+        )
+    }
+}
+
+/// Resolves coverage keywords (like `latin,cjk-sc,emoji`, case-insensitive) to the Noto families to install, in the order given.
+/// Keywords asking for the same family share it. Fails with the unknown keywords if there are any.
+pub fn resolve_coverage(keywords: &str) -> Result<Vec<NotoFamily>, UnknownCoverage> {
+    let mut families: Vec<NotoFamily> = Vec::new();
+    let mut unknown = Vec::new();
+    for keyword in keywords
+        .split(',')
+        .map(str::trim)
+        .filter(|keyword| !keyword.is_empty())
+    {
+        let Some(coverage) = NOTO_COVERAGE
+            .iter()
+            .find(|coverage| coverage.keyword.eq_ignore_ascii_case(keyword))
+        else {
+            unknown.push(keyword.to_string());
+            continue;
+        };
+        match families
+            .iter_mut()
+            .find(|family| family.family == coverage.family)
+        {
+            Some(family) if !family.keywords.contains(&coverage.keyword) => {
+                family.keywords.push(coverage.keyword)
+            }
+            Some(_) => {}
+            None => families.push(NotoFamily {
+                family: coverage.family,
+                estimated_size: estimated_size(coverage),
+                keywords: vec![coverage.keyword],
+            }),
+        }
+    }
+    if unknown.is_empty() {
+        Ok(families)
+    } else {
+        Err(UnknownCoverage { unknown })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn families(keywords: &str) -> Vec<&'static str> {
+        resolve_coverage(keywords)
+            .unwrap()
+            .iter()
+            .map(|family| family.family)
+            .collect()
+    }
+
+    #[test]
+    fn keywords_map_to_their_families_in_order() {
+        assert_eq!(
+            families("latin,cjk-sc,emoji"),
+            ["Noto Sans", "Noto Sans SC", "Noto Color Emoji"]
+        );
+        assert_eq!(
+            families(" Emoji , LATIN ,"),
+            ["Noto Color Emoji", "Noto Sans"]
+        );
+        assert!(families("").is_empty());
+        for coverage in NOTO_COVERAGE {
+            assert_eq!(families(coverage.keyword), [coverage.family]);
+        }
+
+        let resolved = resolve_coverage("latin,mono,latin").unwrap();
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].keywords, ["latin"]);
+        assert_eq!(resolved[0].estimated_size, 30 << 20);
+    }
+
+    #[test]
+    fn the_table_has_each_keyword_and_family_once() {
+        let keywords = NOTO_COVERAGE
+            .iter()
+            .map(|coverage| coverage.keyword)
+            .collect::<HashSet<_>>();
+        assert_eq!(keywords.len(), NOTO_COVERAGE.len());
+        let families = NOTO_COVERAGE
+            .iter()
+            .map(|coverage| coverage.family)
+            .collect::<HashSet<_>>();
+        assert_eq!(families.len(), NOTO_COVERAGE.len());
+        for coverage in NOTO_COVERAGE {
+            // Keywords are matched ignoring case, and can't have the separator in them.
+            assert_eq!(coverage.keyword, coverage.keyword.to_lowercase());
+            assert!(!coverage.keyword.contains([',', ' ']));
+            assert!(coverage.family.starts_with("Noto "));
+            assert!(coverage.size_mib > 0);
+        }
+    }
+
+    #[test]
+    fn unknown_keywords_list_the_supported_ones() {
+        let error = resolve_coverage("latin,klingon,elvish").unwrap_err();
+        assert_eq!(error.unknown, ["klingon", "elvish"]);
+        let message = error.to_string();
+        assert!(
+            message.starts_with("Unknown coverage keywords 'klingon', 'elvish'! The supported keywords are: 'latin', 'serif', "),
+            "{}",
+            message
+        );
+        assert!(message.ends_with("'symbols', 'math'"), "{}", message);
+        assert!(
+            resolve_coverage("klingon")
+                .unwrap_err()
+                .to_string()
+                .starts_with("Unknown coverage keyword 'klingon'!")
+        );
+    }
+}
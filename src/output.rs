@@ -0,0 +1,113 @@
+use std::{
+    io::{IsTerminal, Write},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether informational output should be suppressed, set once from the command line.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Suppresses (or enables) informational output.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Checks if informational output is suppressed.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Whether debug output should be printed, set once from the command line.
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Enables (or disables) debug output.
+pub fn set_debug(debug: bool) {
+    DEBUG.store(debug, Ordering::Relaxed);
+}
+
+/// Checks if debug output is enabled.
+pub fn is_debug() -> bool {
+    DEBUG.load(Ordering::Relaxed)
+}
+
+/// Prints a debug line (prefixed with `DEBUG:`) to stderr, only with `--debug` (or `--debug-http`).
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::output::is_debug() {
+            eprintln!(
+                "{}DEBUG:{} {}",
+                $crate::color::color_bright_black,
+                $crate::color::color_reset,
+                format_args!($($arg)*)
+            );
+        }
+    };
+}
+
+/// Prints an informational line (prefixed with `INFO:`), unless output is suppressed with `--quiet`.
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            println!(
+                "{}INFO:{} {}",
+                $crate::color::color_cyan,
+                $crate::color::color_reset,
+                format_args!($($arg)*)
+            );
+        }
+    };
+}
+
+/// Prints an error line (prefixed with `ERROR:`). Errors are always printed, even with `--quiet`.
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => {
+        println!(
+            "{}ERROR:{} {}",
+            $crate::color::color_red,
+            $crate::color::color_reset,
+            format_args!($($arg)*)
+        )
+    };
+}
+
+/// Prints an error line (like `error!`) and exits with the given exit code, notifying about it with `--notify`.
+#[macro_export]
+macro_rules! fail {
+    ($exit_code:expr, $($arg:tt)*) => {{
+        $crate::error!($($arg)*);
+        $crate::notify::notify_finished(false, &format!($($arg)*));
+        std::process::exit($exit_code)
+    }};
+}
+
+/// Prints long output through a pager (`$PAGER`, or `less`) when stdout is a terminal.
+/// Without a terminal, or if the pager can't be started, the output is printed as is.
+pub fn page_output(output: &str) {
+    if std::io::stdout().is_terminal() {
+        let pager = std::env::var("PAGER")
+            .ok()
+            .filter(|pager| !pager.trim().is_empty())
+            .unwrap_or_else(|| "less".to_string());
+        let mut pager = pager.split_whitespace();
+        if let Some(program) = pager.next() {
+            let mut command = Command::new(program);
+            command.args(pager).stdin(Stdio::piped());
+            // Let less show the colors, and quit right away when the output fits on the screen.
+            if std::env::var_os("LESS").is_none() {
+                command.env("LESS", "FRX");
+            }
+            if let Ok(mut child) = command.spawn() {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // The user may quit the pager before reading everything, that is fine.
+                    let _ = stdin.write_all(output.as_bytes());
+                }
+                let _ = child.wait();
+                return;
+            }
+        }
+    }
+    print!("{}", output);
+}
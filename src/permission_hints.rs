@@ -0,0 +1,211 @@
+use crate::color::*;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::base_path::nearest_existing_ancestor;
+
+/// Checks if an error means fonty may not write somewhere, as opposed to e.g. a full disk.
+pub fn is_permission_error(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
+/// Finds what could not be written when writing to `path` failed: the file itself if it exists, or else the closest existing dir.
+fn unwritable_path(path: &Path) -> PathBuf {
+    if path.is_file() {
+        return path.to_path_buf();
+    }
+    nearest_existing_ancestor(path.parent().unwrap_or(path)).to_path_buf()
+}
+
+/// Finds the name of a user in the contents of `/etc/passwd` (`name:password:uid:...` per line).
+/// Lines that don't have a name and a numeric uid are skipped.
+#[cfg(unix)]
+fn passwd_user_name(passwd: &str, uid: u32) -> Option<&str> {
+    passwd.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next().filter(|name| !name.is_empty())?;
+        (fields.nth(1)?.parse::<u32>().ok()? == uid).then_some(name)
+    })
+}
+
+/// Describes the owner of a file by the name of the user in `/etc/passwd`. Users that aren't in there (e.g. from LDAP)
+/// are shown by their uid, like `uid 1001`.
+#[cfg(unix)]
+fn describe_owner(uid: u32) -> String {
+    let passwd = std::fs::read_to_string("/etc/passwd").unwrap_or_default();
+    match passwd_user_name(&passwd, uid) {
+        Some(name) => name.to_string(),
+        None => format!("uid {}", uid),
+    }
+}
+
+/// Says who owns a path and its permissions, like `'/usr/share/fonts' is owned by root (mode 755).`,
+/// and whether it is owned by root, so re-running with sudo is worth suggesting.
+#[cfg(unix)]
+fn owner_hint(path: &Path) -> Option<(String, bool)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path).ok()?;
+    let hint = format!(
+        "{color_blue}'{}'{color_reset} is owned by {} {color_bright_black}(mode {:o}){color_reset}.",
+        path.display(),
+        describe_owner(metadata.uid()),
+        metadata.mode() & 0o7777
+    );
+    Some((hint, metadata.uid() == 0))
+}
+
+/// Only Unix has owners and permission bits to show.
+#[cfg(not(unix))]
+fn owner_hint(_path: &Path) -> Option<(String, bool)> {
+    None
+}
+
+/// Explains an error writing to `path` if it is about permissions (or a read-only filesystem):
+/// which file or dir can't be written, who owns it, and how to install somewhere else. Other errors get no hints.
+pub fn permission_hints(path: &Path, kind: io::ErrorKind) -> Vec<String> {
+    if !is_permission_error(kind) {
+        return Vec::new();
+    }
+
+    let mut hints = Vec::new();
+    let unwritable_path = unwritable_path(path);
+    let mut owned_by_root = false;
+    if kind == io::ErrorKind::ReadOnlyFilesystem {
+        hints.push(format!(
+            "{color_blue}'{}'{color_reset} is on a read-only filesystem.",
+            unwritable_path.display()
+        ));
+    } else if let Some((hint, is_root)) = owner_hint(&unwritable_path) {
+        hints.push(hint);
+        owned_by_root = is_root;
+    }
+    hints.push(format!(
+        "Set {color_blue}FONTY_BASE_PATH{color_reset} or pass {color_blue}--output-dir{color_reset} to install into a writable dir instead."
+    ));
+    if owned_by_root {
+        hints.push(format!(
+            "For {color_blue}--system{color_reset} installs, re-run with {color_blue}sudo{color_reset}."
+        ));
+    }
+    hints
+}
+
+/// Prints the `permission_hints` for an error writing to `path`.
+pub fn print_permission_hints(path: &Path, kind: io::ErrorKind) {
+    for hint in permission_hints(path, kind) {
+        println!("{color_yellow}HINT:{color_reset} {}", hint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(unix)]
+    use std::fs;
+
+    use super::*;
+    use crate::testing::TestDir;
+
+    const WRITABLE_DIR_HINT: &str = "to install into a writable dir instead.";
+    #[cfg(unix)]
+    const SUDO_HINT: &str = "re-run with";
+
+    #[cfg(unix)]
+    #[test]
+    fn user_names_are_found_in_passwd() {
+        let passwd = "\
+# A comment
+root:x:0:0:root:/root:/bin/bash
+
+broken
+nouid:x
+:x:1002:1002::/home:/bin/sh
+text:x:abc:100::/:/bin/sh
+alice:x:1000:1000:Alice:/home/alice:/bin/zsh
+bob:x:1001:1001::/home/bob:/bin/sh";
+        assert_eq!(passwd_user_name(passwd, 0), Some("root"));
+        assert_eq!(passwd_user_name(passwd, 1000), Some("alice"));
+        assert_eq!(passwd_user_name(passwd, 1001), Some("bob"));
+        // Lines without a name or a numeric uid don't match anything.
+        assert_eq!(passwd_user_name(passwd, 1002), None);
+        assert_eq!(passwd_user_name(passwd, 1234), None);
+        assert_eq!(passwd_user_name("", 0), None);
+    }
+
+    #[test]
+    fn other_errors_get_no_hints() {
+        let hints = permission_hints(Path::new("/fonts/a.ttf"), io::ErrorKind::StorageFull);
+        assert!(hints.is_empty());
+    }
+
+    #[test]
+    fn read_only_filesystems_are_named() {
+        let test_dir = TestDir::new("hints-read-only");
+        let hints = permission_hints(
+            &test_dir.path.join("Roboto/Roboto-Regular.ttf"),
+            io::ErrorKind::ReadOnlyFilesystem,
+        );
+        assert_eq!(
+            hints[0],
+            format!(
+                "{color_blue}'{}'{color_reset} is on a read-only filesystem.",
+                test_dir.path.display()
+            )
+        );
+        assert!(hints[1].ends_with(WRITABLE_DIR_HINT));
+        assert_eq!(hints.len(), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unwritable_dirs_are_shown_with_their_owner_and_mode() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let test_dir = TestDir::new("hints-unwritable");
+        let dir = test_dir.path.join("fonts");
+        fs::create_dir(&dir).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o555)).unwrap();
+
+        // The family dir doesn't exist yet, so the font base dir is what can't be written.
+        let hints = permission_hints(
+            &dir.join("Roboto/Roboto-Regular.ttf"),
+            io::ErrorKind::PermissionDenied,
+        );
+        let uid = fs::metadata(&dir).unwrap().uid();
+        assert_eq!(
+            hints[0],
+            format!(
+                "{color_blue}'{}'{color_reset} is owned by {} {color_bright_black}(mode 555){color_reset}.",
+                dir.display(),
+                describe_owner(uid)
+            )
+        );
+        assert!(hints[1].ends_with(WRITABLE_DIR_HINT));
+        // Only dirs owned by root are worth re-running with sudo for.
+        assert_eq!(hints.iter().any(|hint| hint.contains(SUDO_HINT)), uid == 0);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dirs_owned_by_root_suggest_sudo() {
+        // The root dir is owned by root, whoever runs the tests.
+        let hints = permission_hints(
+            Path::new("/fonty-missing-dir/Roboto/Roboto-Regular.ttf"),
+            io::ErrorKind::PermissionDenied,
+        );
+        assert!(
+            hints[0].starts_with(&format!("{color_blue}'/'{color_reset} is owned by root ")),
+            "{}",
+            hints[0]
+        );
+        assert!(hints[1].ends_with(WRITABLE_DIR_HINT));
+        assert!(hints[2].contains(SUDO_HINT));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::color::*;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{font_manifest::FileResult, registry::Registry};
+
+/// File extensions of font binaries.
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "woff", "woff2"];
+
+/// Checks if a file is a font binary (as opposed to a license, README, etc.), by its extension.
+pub fn is_font_file(filename: &str) -> bool {
+    filename
+        .rsplit_once('.')
+        .is_some_and(|(_, extension)| FONT_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Where the contents of a planned file come from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "type")]
+pub enum PlannedSource {
+    /// The contents are part of the manifest.
+    Embedded,
+    /// The contents are downloaded from a URL.
+    Download { url: String },
+}
+
+impl fmt::Display for PlannedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedSource::Embedded => write!(f, "embedded in manifest"),
+            PlannedSource::Download { url } => write!(f, "{}", url),
+        }
+    }
+}
+
+/// A file that will be written, before anything is written.
+#[derive(Clone, Debug, Serialize)]
+pub struct PlannedFile {
+    pub family: String,
+    /// The filename from the manifest, relative to the font base dir.
+    pub filename: String,
+    /// The full path the file will be written to.
+    pub path: PathBuf,
+    pub source: PlannedSource,
+    pub is_font: bool,
+}
+
+/// Finds planned files that would be written to the same path.
+/// Returns the indices of the colliding files for every path that is written more than once.
+pub fn find_collisions(planned_files: &[PlannedFile]) -> Vec<Vec<usize>> {
+    let mut by_path: HashMap<&PathBuf, Vec<usize>> = HashMap::new();
+    for (index, planned_file) in planned_files.iter().enumerate() {
+        by_path.entry(&planned_file.path).or_default().push(index);
+    }
+
+    let mut collisions = by_path
+        .into_values()
+        .filter(|indices| indices.len() > 1)
+        .collect::<Vec<_>>();
+    // Keep the order of the manifest, so messages are stable between runs.
+    collisions.sort();
+    collisions
+}
+
+/// Finds planned files whose paths only differ in case, which end up as the same file on case-insensitive filesystems
+/// (the default on macOS and Windows). Paths that are exactly the same are left to `find_collisions`.
+pub fn find_case_collisions(planned_files: &[PlannedFile]) -> Vec<Vec<usize>> {
+    let mut by_path: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, planned_file) in planned_files.iter().enumerate() {
+        by_path
+            .entry(planned_file.path.to_string_lossy().to_lowercase())
+            .or_default()
+            .push(index);
+    }
+
+    let mut collisions = by_path
+        .into_values()
+        .filter(|indices| {
+            indices
+                .iter()
+                .any(|&index| planned_files[index].path != planned_files[indices[0]].path)
+        })
+        .collect::<Vec<_>>();
+    collisions.sort();
+    collisions
+}
+
+/// Prints the planned files, as shown by `--dry-run`.
+pub fn print_plan(planned_files: &[PlannedFile]) {
+    println!("{color_blue}{style_bold}Planned files:{color_reset}{style_reset}");
+    for planned_file in planned_files {
+        println!(
+            "  {color_blue}'{}'{color_reset} {color_bright_black}({}, from {}){color_reset}",
+            planned_file.path.display(),
+            planned_file.family,
+            planned_file.source
+        );
+    }
+}
+
+/// A difference between the planned font files of a family and the ones on disk after installing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Discrepancy {
+    /// A planned font file that is not on disk, e.g. because it failed to download.
+    Missing { family: String, path: PathBuf },
+    /// A font file that is on disk (or was written) for the family, but was not planned, e.g. one left from installing
+    /// more faces before.
+    Extra { family: String, path: PathBuf },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discrepancy::Missing { family, path } => write!(
+                f,
+                "{color_blue}'{}'{color_reset} of {} is missing",
+                path.display(),
+                family
+            ),
+            Discrepancy::Extra { family, path } => write!(
+                f,
+                "{color_blue}'{}'{color_reset} of {} was not asked for",
+                path.display(),
+                family
+            ),
+        }
+    }
+}
+
+/// Checks the planned font files of `families` against the results of writing them and what is on disk now.
+/// The directories of the planned files are searched for other font files too, unless they are the font base dir itself
+/// (which is shared with other families). Files the registry records for another family aren't extra.
+pub fn audit_installed(
+    planned_files: &[PlannedFile],
+    results: &[FileResult],
+    families: &[&str],
+    registry: &Registry,
+    base_path: &Path,
+) -> Vec<Discrepancy> {
+    let mut discrepancies = Vec::new();
+    for &family in families {
+        let planned = planned_files
+            .iter()
+            .filter(|planned_file| planned_file.is_font && planned_file.family == family)
+            .map(|planned_file| planned_file.path.as_path())
+            .collect::<HashSet<_>>();
+        let written = results
+            .iter()
+            .filter(|result| result.family == family && !result.status.is_failed())
+            .map(|result| result.path.as_path())
+            .filter(|path| path.to_str().is_some_and(is_font_file))
+            .collect::<HashSet<_>>();
+
+        let mut missing = planned
+            .iter()
+            .filter(|path| !written.contains(*path) || !path.is_file())
+            .collect::<Vec<_>>();
+        missing.sort();
+        discrepancies.extend(missing.into_iter().map(|path| Discrepancy::Missing {
+            family: family.to_string(),
+            path: path.to_path_buf(),
+        }));
+
+        let mut extra = written
+            .iter()
+            .filter(|path| !planned.contains(*path))
+            .map(|path| path.to_path_buf())
+            .collect::<HashSet<_>>();
+        let dirs = planned
+            .iter()
+            .filter_map(|path| path.parent())
+            .filter(|dir| *dir != base_path)
+            .collect::<HashSet<_>>();
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            extra.extend(entries.flatten().map(|entry| entry.path()).filter(|path| {
+                path.is_file()
+                    && path.to_str().is_some_and(is_font_file)
+                    && !planned.contains(path.as_path())
+                    && path
+                        .strip_prefix(base_path)
+                        .ok()
+                        .and_then(|relative_path| registry.recorded_by(relative_path))
+                        .is_none_or(|owner| owner.eq_ignore_ascii_case(family))
+            }));
+        }
+        let mut extra = extra.into_iter().collect::<Vec<_>>();
+        extra.sort();
+        discrepancies.extend(extra.into_iter().map(|path| Discrepancy::Extra {
+            family: family.to_string(),
+            path,
+        }));
+    }
+    discrepancies
+}
@@ -0,0 +1,128 @@
+//! Machine-readable progress, for GUIs wrapping fonty: one JSON object per line, written to `--progress-fd` or `--progress-file`,
+//! while stdout and stderr keep the normal output.
+//!
+//! Every event has an `event` key: `started` (with the `families` and the number of `files`), `file` for every file that is done
+//! (with its `family`, `path`, `status`, `bytes`, `from_cache`, and how many files are `done` of the `total`),
+//! and `finished` at the end of the run (with `success` and the `message`). Families are installed one after the other, so `files`
+//! only counts the files of the first family, and `total` grows as the manifests of the others arrive.
+
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::{
+        Mutex, OnceLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+};
+
+use crate::font_manifest::{FileResult, FileStatus};
+
+/// Where the events go, set once at startup. No events are written without it.
+static PROGRESS: OnceLock<Mutex<File>> = OnceLock::new();
+/// How many files there are in total, and how many are done, for the `file` events.
+static TOTAL_FILES: AtomicUsize = AtomicUsize::new(0);
+static DONE_FILES: AtomicUsize = AtomicUsize::new(0);
+/// Whether the `finished` event was written, as a run that fails after its summary would otherwise finish twice.
+static FINISHED: AtomicBool = AtomicBool::new(false);
+
+/// A progress event, see the module docs.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Started {
+        families: &'a [String],
+        files: usize,
+    },
+    File {
+        family: &'a str,
+        path: &'a Path,
+        #[serde(flatten)]
+        status: &'a FileStatus,
+        bytes: u64,
+        from_cache: bool,
+        done: usize,
+        total: usize,
+    },
+    Finished {
+        success: bool,
+        message: &'a str,
+    },
+}
+
+/// Keeps the file the events go to. It is opened for writing at startup, so a bad descriptor fails right away, not halfway through.
+fn set_progress(file: File) {
+    let _ = PROGRESS.set(Mutex::new(file));
+}
+
+/// Writes the events to an already open file descriptor, through `/dev/fd` so fonty never closes a descriptor it doesn't own.
+/// stdin, stdout and stderr can't be used, as they are for the normal input and output.
+#[cfg(unix)]
+pub fn open_progress_fd(fd: u32) -> io::Result<()> {
+    if fd <= 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "stdin, stdout and stderr can't be used for progress",
+        ));
+    }
+    let file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(format!("/dev/fd/{}", fd))
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("file descriptor {} is not open", fd),
+            ),
+            _ => e,
+        })?;
+    set_progress(file);
+    Ok(())
+}
+
+/// Writes the events to a file, replacing it. This is the equivalent of `--progress-fd` where descriptors aren't inherited (Windows).
+pub fn open_progress_file(path: &Path) -> io::Result<()> {
+    set_progress(File::create(path)?);
+    Ok(())
+}
+
+/// Writes an event, if there is somewhere to write it to. A GUI that went away is no reason to stop installing fonts.
+pub fn emit(event: &ProgressEvent) {
+    let Some(progress) = PROGRESS.get() else {
+        return;
+    };
+    if let ProgressEvent::Finished { .. } = event
+        && FINISHED.swap(true, Ordering::Relaxed)
+    {
+        return;
+    }
+    let Ok(line) = serde_json::to_string(event) else {
+        return;
+    };
+    let mut progress = progress.lock().unwrap();
+    let _ = writeln!(progress, "{}", line).and_then(|_| progress.flush());
+}
+
+/// Writes the `started` event, with the number of files the `file` events count up to.
+pub fn started(families: &[String], files: usize) {
+    TOTAL_FILES.store(files, Ordering::Relaxed);
+    emit(&ProgressEvent::Started { families, files });
+}
+
+/// Adds the files of a family to the `total` of the `file` events, for the families after the first one.
+pub fn add_files(files: usize) {
+    TOTAL_FILES.fetch_add(files, Ordering::Relaxed);
+}
+
+/// Writes a `file` event for a file that is done, written or not.
+pub fn file_done(result: &FileResult) {
+    emit(&ProgressEvent::File {
+        family: &result.family,
+        path: &result.path,
+        status: &result.status,
+        bytes: result.bytes,
+        from_cache: result.from_cache,
+        done: DONE_FILES.fetch_add(1, Ordering::Relaxed) + 1,
+        total: TOTAL_FILES.load(Ordering::Relaxed),
+    });
+}
@@ -0,0 +1,105 @@
+use serde::Deserialize;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::exit_codes::EXIT_CODE_CONFIG_ERROR;
+
+/// The name of the file listing the fonts a project (or user) wants installed.
+pub const PROJECT_MANIFEST_FILENAME: &str = "fonty.toml";
+
+/// An error while finding or reading a `fonty.toml`.
+#[derive(Debug)]
+pub enum ProjectManifestError {
+    /// There is no `fonty.toml` in any of the searched places.
+    NotFound { searched: Vec<PathBuf> },
+    /// The file exists, but can't be read.
+    Read { path: PathBuf, error: String },
+    /// The file is not valid (for fonty).
+    Invalid { path: PathBuf, error: String },
+}
+
+impl fmt::Display for ProjectManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectManifestError::NotFound { searched } => write!(
+                f,
+                "Could not find a {}! Looked for '{}'.",
+                PROJECT_MANIFEST_FILENAME,
+                searched
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("', '")
+            ),
+            ProjectManifestError::Read { path, error } => {
+                write!(f, "Failed to read '{}'! Error:\n{}", path.display(), error)
+            }
+            ProjectManifestError::Invalid { path, error } => {
+                write!(f, "Invalid '{}'! Error:\n{}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl ProjectManifestError {
+    /// The exit code to exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        EXIT_CODE_CONFIG_ERROR
+    }
+}
+
+/// A `fonty.toml` as it is written.
+#[derive(Debug, Deserialize)]
+struct ProjectManifestContents {
+    fonts: Vec<String>,
+}
+
+/// The fonts listed in a `fonty.toml`, e.g.:
+///
+/// ```toml
+/// fonts = ["Inter", "Source Serif 4"]
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProjectManifest {
+    pub path: PathBuf,
+    pub fonts: Vec<String>,
+}
+
+impl ProjectManifest {
+    /// Finds the `fonty.toml` of the project in the current dir, or else the user's one in the config dir.
+    pub fn find(
+        current_dir: &Path,
+        config_dir: Option<&Path>,
+    ) -> Result<Self, ProjectManifestError> {
+        let searched = std::iter::once(current_dir)
+            .chain(config_dir)
+            .map(|dir| dir.join(PROJECT_MANIFEST_FILENAME))
+            .collect::<Vec<_>>();
+        match searched.iter().find(|path| path.is_file()) {
+            Some(path) => Self::load(path),
+            None => Err(ProjectManifestError::NotFound { searched }),
+        }
+    }
+
+    /// Reads a `fonty.toml`.
+    pub fn load(path: &Path) -> Result<Self, ProjectManifestError> {
+        let contents = fs::read_to_string(path).map_err(|e| ProjectManifestError::Read {
+            path: path.to_path_buf(),
+            error: e.to_string(),
+        })?;
+        let invalid = |error: String| ProjectManifestError::Invalid {
+            path: path.to_path_buf(),
+            error,
+        };
+
+        let contents: ProjectManifestContents =
+            toml::from_str(&contents).map_err(|e| invalid(e.to_string().trim_end().to_string()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fonts: contents.fonts,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+//! Asking the user to confirm something, the same way everywhere: overwriting files, removing fonts, large downloads.
+
+use std::{
+    fmt,
+    io::{self, BufRead, IsTerminal, Write},
+};
+
+use crate::exit_codes::EXIT_CODE_ABORTED;
+
+/// Why a question could not be answered.
+#[derive(Debug)]
+pub enum PromptError {
+    /// Stdin is not a terminal and `--yes` was not given, so there is nobody to ask.
+    NotInteractive,
+    Io(io::Error),
+}
+
+impl PromptError {
+    /// Not getting an answer aborts, like answering no.
+    pub fn exit_code(&self) -> i32 {
+        EXIT_CODE_ABORTED
+    }
+}
+
+impl fmt::Display for PromptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromptError::NotInteractive => {
+                write!(f, "Can't ask for confirmation without a terminal.")
+            }
+            PromptError::Io(e) => write!(f, "Failed to ask for confirmation! Error: {}", e),
+        }
+    }
+}
+
+/// Asks yes/no questions, reading the answers from `reader` and printing the questions to `writer`.
+/// Normally stdin and stdout, see `Prompt::stdio`.
+pub struct Prompt<R, W> {
+    reader: R,
+    writer: W,
+    /// Whether there is someone to answer, i.e. whether the reader is a terminal.
+    interactive: bool,
+    /// Whether every question is answered with yes without asking, with `--yes`.
+    assume_yes: bool,
+}
+
+impl Prompt<io::StdinLock<'static>, io::Stdout> {
+    /// A prompt on stdin and stdout, which is only interactive if stdin is a terminal.
+    pub fn stdio(assume_yes: bool) -> Self {
+        let stdin = io::stdin();
+        let interactive = stdin.is_terminal();
+        Self::new(stdin.lock(), io::stdout(), interactive, assume_yes)
+    }
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    pub fn new(reader: R, writer: W, interactive: bool, assume_yes: bool) -> Self {
+        Self {
+            reader,
+            writer,
+            interactive,
+            assume_yes,
+        }
+    }
+
+    /// Asks a yes/no question. An empty answer (or the end of the input) gives `default`, anything but yes or no asks again.
+    pub fn confirm(&mut self, question: &str, default: bool) -> Result<bool, PromptError> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+        if !self.interactive {
+            return Err(PromptError::NotInteractive);
+        }
+
+        let choices = if default { "[Y/n]" } else { "[y/N]" };
+        loop {
+            write!(self.writer, "{} {} ", question, choices).map_err(PromptError::Io)?;
+            self.writer.flush().map_err(PromptError::Io)?;
+
+            let mut answer = String::new();
+            if self
+                .reader
+                .read_line(&mut answer)
+                .map_err(PromptError::Io)?
+                == 0
+            {
+                writeln!(self.writer).map_err(PromptError::Io)?;
+                return Ok(default);
+            }
+            match answer.trim().to_lowercase().as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => writeln!(self.writer, "Please answer yes or no.").map_err(PromptError::Io)?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Asks `question` with the given input, returning the answer and everything that was printed.
+    fn ask(
+        input: &str,
+        interactive: bool,
+        assume_yes: bool,
+        default: bool,
+    ) -> (Result<bool, PromptError>, String) {
+        let mut output = Vec::new();
+        let answer = Prompt::new(input.as_bytes(), &mut output, interactive, assume_yes)
+            .confirm("Overwrite?", default);
+        (answer, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn yes_answers_without_asking() {
+        for interactive in [true, false] {
+            let (answer, output) = ask("n\n", interactive, true, false);
+            assert!(answer.unwrap());
+            assert_eq!(output, "");
+        }
+    }
+
+    #[test]
+    fn without_a_terminal_nobody_is_asked() {
+        let (answer, output) = ask("y\n", false, false, true);
+        let error = answer.unwrap_err();
+        assert!(matches!(error, PromptError::NotInteractive));
+        assert_eq!(error.exit_code(), EXIT_CODE_ABORTED);
+        assert_eq!(output, "");
+    }
+
+    #[test]
+    fn empty_answers_give_the_default() {
+        assert_eq!(ask("\n", true, false, false).1, "Overwrite? [y/N] ");
+        assert_eq!(ask("\n", true, false, true).1, "Overwrite? [Y/n] ");
+        for default in [true, false] {
+            assert_eq!(ask("\n", true, false, default).0.unwrap(), default);
+            assert_eq!(ask("  \n", true, false, default).0.unwrap(), default);
+            // The end of the input gives the default too, on a line of its own.
+            let (answer, output) = ask("", true, false, default);
+            assert_eq!(answer.unwrap(), default);
+            assert!(output.ends_with(" \n"), "{:?}", output);
+        }
+    }
+
+    #[test]
+    fn yes_and_no_are_understood_in_any_case() {
+        for (input, expected) in [
+            ("y\n", true),
+            ("YES\n", true),
+            (" Yes \n", true),
+            ("n\n", false),
+            ("No\n", false),
+        ] {
+            assert_eq!(
+                ask(input, true, false, !expected).0.unwrap(),
+                expected,
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn other_answers_ask_again() {
+        let (answer, output) = ask("maybe\nsure\ny\n", true, false, false);
+        assert!(answer.unwrap());
+        assert_eq!(
+            output,
+            concat!(
+                "Overwrite? [y/N] Please answer yes or no.\n",
+                "Overwrite? [y/N] Please answer yes or no.\n",
+                "Overwrite? [y/N] "
+            )
+        );
+        // Invalid answers until the input ends give the default.
+        assert!(ask("maybe\n", true, false, true).0.unwrap());
+    }
+}
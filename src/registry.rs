@@ -0,0 +1,1083 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    font_manifest::{FileResult, FileStatus},
+    font_names::family_dir_name,
+    msg,
+    options::Options,
+    plan::is_font_file,
+    safe_path::long_path,
+    sfnt::font_version,
+    warnings::{WarningCode, Warnings},
+};
+
+/// The name of the registry file, directly inside the font base dir.
+const REGISTRY_FILENAME: &str = "registry.json";
+
+/// How often a snapshot of the registry is read, as long as it can't be parsed, and how long to wait in between.
+const SNAPSHOT_ATTEMPTS: usize = 3;
+const SNAPSHOT_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A file that belongs to an installed family.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistryFile {
+    /// The path of the file, relative to the font base dir.
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// The filename from the manifest, if the file was written under another name because it had to be sanitized (on Windows).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_filename: Option<String>,
+}
+
+/// The options a family was installed with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InstallOptions {
+    /// The permissions of the files, in octal.
+    pub file_mode: String,
+    /// The permissions of the directories, in octal.
+    pub dir_mode: String,
+}
+
+/// An installed family.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    /// The name of the family, exactly as it was requested.
+    pub name: String,
+    /// When the family was installed, in seconds since the unix epoch. Unknown for families found by scanning.
+    pub installed_at: Option<u64>,
+    /// The version of fonty that installed the family. Unknown for families found by scanning.
+    pub fonty_version: Option<String>,
+    pub files: Vec<RegistryFile>,
+    /// The version of the fonts, from their `name` table (e.g. `3.011`). Unknown if no font file could be parsed.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Unknown for families found by scanning.
+    pub options: Option<InstallOptions>,
+    /// The groups the family was tagged with (e.g. `web`), sorted.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Removes the dirs left empty by removing a file, up to the font base dir.
+fn remove_empty_dirs(base_path: &Path, path: &Path) {
+    // Directories that still have files in them fail to be removed, which stops the walk up.
+    let mut dir = path.parent();
+    while let Some(parent_dir) = dir
+        && parent_dir != base_path
+        && parent_dir.starts_with(base_path)
+        && fs::remove_dir(parent_dir).is_ok()
+    {
+        dir = parent_dir.parent();
+    }
+}
+
+/// Where the same file of a family is in the other layout: in the dir of the family (like single-font fetches
+/// install it) for a file directly in the font base dir (like fetches of several fonts install it), and the other way around.
+/// Paths are relative to the font base dir.
+pub fn layout_counterpart(family: &str, path: &Path) -> PathBuf {
+    let family_dir = family_dir_name(family);
+    match path.strip_prefix(&family_dir) {
+        Ok(flat_path) => flat_path.to_path_buf(),
+        Err(_) => Path::new(&family_dir).join(path),
+    }
+}
+
+/// A font file of a family that is installed in both layouts, from different fetches. fontconfig picks up both copies.
+/// Paths are relative to the font base dir.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutDuplicate {
+    pub family: String,
+    /// The one recorded in the registry.
+    pub path: PathBuf,
+    /// The one in the other layout, left from an earlier fetch.
+    pub stale_path: PathBuf,
+}
+
+/// What moving a family into the dir of its family did, or would do with `--dry-run`.
+#[derive(Debug, Default)]
+pub struct LayoutMigration {
+    /// The files moved into the dir of the family, from and to.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// The stale copies in the font base dir that were removed.
+    pub removed: Vec<PathBuf>,
+    /// The files that could not be moved or removed.
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+/// The orders installed families can be listed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InstalledSortOrder {
+    /// Alphabetically by name, ignoring case.
+    Name,
+    /// The largest families first.
+    Size,
+    /// The most recently installed families first. Families found by scanning come last.
+    Date,
+}
+
+impl FromStr for InstalledSortOrder {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "name" => Ok(InstalledSortOrder::Name),
+            "size" => Ok(InstalledSortOrder::Size),
+            "date" => Ok(InstalledSortOrder::Date),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Sorts installed families. The sort is stable, so families that compare equal keep the registry's order.
+pub fn sort_installed(families: &mut [&RegistryEntry], sort_order: InstalledSortOrder) {
+    match sort_order {
+        InstalledSortOrder::Name => families.sort_by_key(|family| family.name.to_lowercase()),
+        InstalledSortOrder::Size => {
+            families.sort_by_key(|family| std::cmp::Reverse(family.bytes()))
+        }
+        // `None` sorts before any `Some`, so reversing puts unknown dates last.
+        InstalledSortOrder::Date => {
+            families.sort_by_key(|family| std::cmp::Reverse(family.installed_at))
+        }
+    }
+}
+
+impl RegistryEntry {
+    /// The size of the files of the family, as recorded when they were written.
+    pub fn bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.bytes).sum()
+    }
+
+    /// The dir the files of the family are in (the deepest one they all share), relative to the font base dir.
+    /// Empty for families installed together with others, as their files are directly in the font base dir.
+    pub fn dir(&self) -> PathBuf {
+        let mut files = self.files.iter().filter_map(|file| file.path.parent());
+        let Some(first) = files.next() else {
+            return PathBuf::new();
+        };
+        let mut dir = first.to_path_buf();
+        for parent in files {
+            while !parent.starts_with(&dir) {
+                dir.pop();
+            }
+        }
+        dir
+    }
+
+    /// Deletes the files of the family (or moves them to the trash with `to_trash`), and the directories left empty by that
+    /// (inside the font base dir). Files that are already gone are fine, the other failures are returned.
+    pub fn remove_files(&self, base_path: &Path, to_trash: bool) -> Vec<(PathBuf, io::Error)> {
+        let mut errors = Vec::new();
+        for file in &self.files {
+            let path = base_path.join(&file.path);
+            // The trash doesn't say if a file was already gone, so that is checked first.
+            let result = match fs::symlink_metadata(long_path(&path)) {
+                Err(e) => Err(e),
+                Ok(_) if to_trash => trash::delete(&path).map_err(io::Error::other),
+                Ok(_) => fs::remove_file(long_path(&path)),
+            };
+            match result {
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    errors.push((path, e));
+                    continue;
+                }
+            }
+
+            remove_empty_dirs(base_path, &path);
+        }
+        errors
+    }
+}
+
+/// The families installed in a font base dir, stored as `registry.json` in it.
+/// Unlike scanning the directories, this knows the exact name every family was requested with.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    pub families: Vec<RegistryEntry>,
+}
+
+impl Registry {
+    /// The path of the registry file in a font base dir.
+    fn path(base_path: &Path) -> PathBuf {
+        base_path.join(REGISTRY_FILENAME)
+    }
+
+    /// Loads the registry of a font base dir.
+    /// Installs from before the registry existed have no registry file, their families are found by scanning instead.
+    /// A registry file that can't be read or is corrupted is rebuilt the same way, with a warning.
+    pub fn load(base_path: &Path, warnings: &mut Warnings) -> Self {
+        let path = Self::path(base_path);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::scan(base_path),
+            Err(e) => {
+                warnings.warn(
+                    WarningCode::RegistryRebuilt,
+                    msg!("registry.read_failed", path = path.display(), error = e),
+                );
+                return Self::scan(base_path);
+            }
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(registry) => registry,
+            Err(e) => {
+                warnings.warn(
+                    WarningCode::RegistryRebuilt,
+                    msg!("registry.corrupted", path = path.display(), error = e),
+                );
+                Self::scan(base_path)
+            }
+        }
+    }
+
+    /// Loads the registry for only reading it, like `list` does. It doesn't wait for the lock of the font base dir,
+    /// so it works while another fonty process is changing it, and never writes anything.
+    /// fonty replaces the registry file as a whole, but as other writers may not, a registry that can't be parsed is read
+    /// again a few times before it is taken as corrupted.
+    pub fn load_snapshot(base_path: &Path, warnings: &mut Warnings) -> Self {
+        for _ in 1..SNAPSHOT_ATTEMPTS {
+            match fs::read_to_string(Self::path(base_path)) {
+                Ok(contents) => {
+                    if let Ok(registry) = serde_json::from_str(&contents) {
+                        return registry;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::scan(base_path),
+                Err(_) => {}
+            }
+            thread::sleep(SNAPSHOT_RETRY_INTERVAL);
+        }
+        Self::load(base_path, warnings)
+    }
+
+    /// Builds a registry from the directories in a font base dir, one family per directory (like single-font fetches install them).
+    /// The exact names are not known, so they are guessed from the directory names. Dot-dirs (like the backups) are fonty's own.
+    fn scan(base_path: &Path) -> Self {
+        let Ok(entries) = fs::read_dir(base_path) else {
+            return Self::default();
+        };
+
+        let mut families = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry.path().is_dir() && !entry.file_name().to_string_lossy().starts_with('.')
+            })
+            .map(|entry| {
+                let mut files = Vec::new();
+                scan_files(base_path, &entry.path(), &mut files);
+                RegistryEntry {
+                    name: entry.file_name().to_string_lossy().replace("_", " "),
+                    installed_at: None,
+                    fonty_version: None,
+                    version: family_version(base_path, &files),
+                    files,
+                    options: None,
+                    tags: Vec::new(),
+                }
+            })
+            .collect::<Vec<_>>();
+        families.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { families }
+    }
+
+    /// Records (or replaces) an installed family with the files that were written or kept for it.
+    /// A family none of whose files were written or kept is not installed, so it is not recorded (and an earlier entry
+    /// for it is left alone), or it would be skipped as installed from then on (e.g. by `fetch --only-new`).
+    /// If some of its files failed (e.g. in an `update`), the new files are merged into the earlier entry: its files that
+    /// are still on disk stay recorded as well, so they aren't left behind by `remove`.
+    pub fn record(
+        &mut self,
+        name: &str,
+        base_path: &Path,
+        results: &[FileResult],
+        options: &Options,
+    ) {
+        let mut files = results
+            .iter()
+            .filter(|result| result.family == name && !result.status.is_failed())
+            .map(|result| RegistryFile {
+                path: result
+                    .path
+                    .strip_prefix(base_path)
+                    .unwrap_or(&result.path)
+                    .to_path_buf(),
+                bytes: match &result.status {
+                    FileStatus::Written => result.bytes,
+                    // Kept files were not written, so their size comes from disk.
+                    _ => fs::metadata(&result.path).map_or(0, |metadata| metadata.len()),
+                },
+                original_filename: result.original_filename.clone(),
+            })
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return;
+        }
+        let partially_failed = results
+            .iter()
+            .any(|result| result.family == name && result.status.is_failed());
+        if partially_failed && let Some(family) = self.find(name) {
+            let kept_files = family
+                .files
+                .iter()
+                .filter(|file| {
+                    !files.iter().any(|new_file| new_file.path == file.path)
+                        && long_path(&base_path.join(&file.path)).is_file()
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            files.extend(kept_files);
+        }
+
+        // Reinstalling a family keeps its tags.
+        let tags = self
+            .find(name)
+            .map(|family| family.tags.clone())
+            .unwrap_or_default();
+        let entry = RegistryEntry {
+            name: name.to_string(),
+            installed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since_epoch| since_epoch.as_secs()),
+            fonty_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            version: family_version(base_path, &files),
+            files,
+            options: Some(InstallOptions {
+                file_mode: format!("{:o}", options.file_mode),
+                dir_mode: format!("{:o}", options.dir_mode),
+            }),
+            tags,
+        };
+
+        match self.find_mut(name) {
+            Some(family) => *family = entry,
+            None => self.families.push(entry),
+        }
+    }
+
+    /// Records a family with an entry as it is (e.g. one from a backup), replacing the one with the same name.
+    pub fn insert(&mut self, entry: RegistryEntry) {
+        match self.find_mut(&entry.name) {
+            Some(family) => *family = entry,
+            None => self.families.push(entry),
+        }
+    }
+
+    /// Finds an installed family by name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<&RegistryEntry> {
+        self.families
+            .iter()
+            .find(|family| family.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Finds an installed family by name, ignoring case.
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut RegistryEntry> {
+        self.families
+            .iter_mut()
+            .find(|family| family.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Updates the recorded size of a file of an installed family, e.g. after repairing it.
+    pub fn update_file(&mut self, name: &str, path: &Path, bytes: u64) {
+        if let Some(file) = self
+            .find_mut(name)
+            .and_then(|family| family.files.iter_mut().find(|file| file.path == path))
+        {
+            file.bytes = bytes;
+        }
+    }
+
+    /// Lists the installed families with a tag.
+    pub fn tagged(&self, tag: &str) -> Vec<&RegistryEntry> {
+        self.families
+            .iter()
+            .filter(|family| family.tags.iter().any(|family_tag| family_tag == tag))
+            .collect()
+    }
+
+    /// Tags an installed family. Returns false if the family is not installed.
+    pub fn add_tag(&mut self, name: &str, tag: &str) -> bool {
+        let Some(family) = self.find_mut(name) else {
+            return false;
+        };
+        if !family.tags.iter().any(|family_tag| family_tag == tag) {
+            family.tags.push(tag.to_string());
+            family.tags.sort();
+        }
+        true
+    }
+
+    /// Removes a tag from an installed family, without uninstalling it. Returns false if the family did not have the tag.
+    pub fn remove_tag(&mut self, name: &str, tag: &str) -> bool {
+        let Some(family) = self.find_mut(name) else {
+            return false;
+        };
+        let tag_count = family.tags.len();
+        family.tags.retain(|family_tag| family_tag != tag);
+        family.tags.len() != tag_count
+    }
+
+    /// Removes an installed family from the registry (and so from all its tags), returning it. Its files are left alone.
+    pub fn remove(&mut self, name: &str) -> Option<RegistryEntry> {
+        let index = self
+            .families
+            .iter()
+            .position(|family| family.name.to_lowercase() == name.to_lowercase())?;
+        Some(self.families.remove(index))
+    }
+
+    /// Deletes the files of the families (or moves them to the trash), and removes them from the registry (and so from their tags).
+    /// If some files of a family can't be deleted, only those stay recorded, so removing it can be tried again.
+    /// Returns the families that were not removed completely, with the files that failed. The registry is not saved.
+    pub fn remove_families(
+        &mut self,
+        base_path: &Path,
+        font_names: &[String],
+        to_trash: bool,
+    ) -> Vec<(String, Vec<(PathBuf, io::Error)>)> {
+        let mut failed = Vec::new();
+        for font_name in font_names {
+            let Some(family) = self.find(font_name) else {
+                continue;
+            };
+            let errors = family.remove_files(base_path, to_trash);
+            if errors.is_empty() {
+                self.remove(font_name);
+                continue;
+            }
+
+            if let Some(family) = self.find_mut(font_name) {
+                family.files.retain(|file| {
+                    errors
+                        .iter()
+                        .any(|(path, _)| *path == base_path.join(&file.path))
+                });
+            }
+            failed.push((font_name.clone(), errors));
+        }
+        failed
+    }
+
+    /// Finds the installed family a file (relative to the font base dir) belongs to.
+    pub fn recorded_by(&self, path: &Path) -> Option<&str> {
+        self.families
+            .iter()
+            .find(|family| family.files.iter().any(|file| file.path == path))
+            .map(|family| family.name.as_str())
+    }
+
+    /// Finds the font files of the families that are also on disk in the other layout (see `layout_counterpart`).
+    /// A copy that belongs to another family is not stale, so it is left out.
+    pub fn layout_duplicates(&self, base_path: &Path) -> Vec<LayoutDuplicate> {
+        self.families
+            .iter()
+            .flat_map(|family| {
+                family
+                    .files
+                    .iter()
+                    .filter(|file| file.path.to_str().is_some_and(is_font_file))
+                    .map(|file| LayoutDuplicate {
+                        family: family.name.clone(),
+                        path: file.path.clone(),
+                        stale_path: layout_counterpart(&family.name, &file.path),
+                    })
+            })
+            .filter(|duplicate| {
+                base_path.join(&duplicate.stale_path).is_file()
+                    && self.recorded_by(&duplicate.stale_path).is_none()
+            })
+            .collect()
+    }
+
+    /// Moves the files of a family that are directly in the font base dir into the dir of the family, and removes the stale
+    /// copies of its files left there by earlier fetches. A file is only removed after its copy in the dir of the family was
+    /// checked against the registry, and files that also belong to other families (like a shared license) are copied instead.
+    /// The registry is updated for every file moved, but not saved.
+    pub fn migrate_layout(
+        &mut self,
+        name: &str,
+        base_path: &Path,
+        dry_run: bool,
+    ) -> LayoutMigration {
+        let mut migration = LayoutMigration::default();
+        let Some(family) = self.find(name) else {
+            return migration;
+        };
+        let family_name = family.name.clone();
+        let family_dir = family_dir_name(&family_name);
+        let files = family.files.clone();
+
+        for (index, file) in files.iter().enumerate() {
+            if file.path.starts_with(&family_dir) {
+                let stale_path = layout_counterpart(&family_name, &file.path);
+                if !base_path.join(&stale_path).is_file() || self.recorded_by(&stale_path).is_some()
+                {
+                    continue;
+                }
+                if file.check(base_path).is_some() {
+                    migration.failed.push((
+                        file.path.clone(),
+                        io::Error::other("it is missing or damaged, run fonty repair first"),
+                    ));
+                    continue;
+                }
+                if !dry_run {
+                    let stale_file = base_path.join(&stale_path);
+                    if let Err(e) = fs::remove_file(long_path(&stale_file)) {
+                        migration.failed.push((stale_path, e));
+                        continue;
+                    }
+                    remove_empty_dirs(base_path, &stale_file);
+                }
+                migration.removed.push(stale_path);
+                continue;
+            }
+
+            let new_path = Path::new(&family_dir).join(&file.path);
+            if file.check(base_path).is_some() {
+                migration.failed.push((
+                    file.path.clone(),
+                    io::Error::other("it is missing or damaged, run fonty repair first"),
+                ));
+                continue;
+            }
+            if !dry_run {
+                let shared = self.families.iter().any(|other| {
+                    other.name != family_name
+                        && other
+                            .files
+                            .iter()
+                            .any(|other_file| other_file.path == file.path)
+                });
+                let (from, to) = (base_path.join(&file.path), base_path.join(&new_path));
+                let result = to
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| match fs::remove_file(long_path(&to)) {
+                        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                        _ => Ok(()),
+                    })
+                    .and_then(|_| {
+                        if shared {
+                            fs::copy(&from, &to).map(|_| ())
+                        } else {
+                            fs::rename(&from, &to)
+                        }
+                    });
+                if let Err(e) = result {
+                    migration.failed.push((file.path.clone(), e));
+                    continue;
+                }
+                if !shared {
+                    remove_empty_dirs(base_path, &from);
+                }
+                if let Some(family) = self.find_mut(&family_name) {
+                    family.files[index].path = new_path.clone();
+                }
+            }
+            migration.moved.push((file.path.clone(), new_path));
+        }
+        migration
+    }
+
+    /// Saves the registry into a font base dir.
+    /// It is written to a temporary file first and then renamed, so a crash can't leave a half-written registry behind.
+    /// If that fails, the temporary file is removed again.
+    pub fn save(&self, base_path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        let temporary_path = base_path.join(format!("{}.tmp", REGISTRY_FILENAME));
+        let result = fs::write(&temporary_path, contents)
+            .and_then(|_| fs::rename(&temporary_path, Self::path(base_path)));
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary_path);
+        }
+        result
+    }
+}
+
+/// What is wrong with an installed file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileProblem {
+    Missing,
+    SizeMismatch { expected: u64, found: u64 },
+}
+
+impl RegistryFile {
+    /// Checks the file against the filesystem. Returns `None` if it is fine.
+    pub fn check(&self, base_path: &Path) -> Option<FileProblem> {
+        match fs::metadata(base_path.join(&self.path)) {
+            Err(_) => Some(FileProblem::Missing),
+            Ok(metadata) if metadata.len() != self.bytes => Some(FileProblem::SizeMismatch {
+                expected: self.bytes,
+                found: metadata.len(),
+            }),
+            Ok(_) => None,
+        }
+    }
+}
+
+/// The extensions of the font files whose version can be read.
+const SFNT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc"];
+
+/// Reads the version of a family from the first of its font files that can be parsed.
+fn family_version(base_path: &Path, files: &[RegistryFile]) -> Option<String> {
+    files
+        .iter()
+        .filter(|file| {
+            file.path.extension().is_some_and(|extension| {
+                SFNT_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())
+            })
+        })
+        .find_map(|file| font_version(&fs::read(base_path.join(&file.path)).ok()?))
+}
+
+/// Collects every file in a directory (recursively), with paths relative to the font base dir.
+fn scan_files(base_path: &Path, dir: &Path, files: &mut Vec<RegistryFile>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            scan_files(base_path, &path, files);
+        } else if let Ok(metadata) = entry.metadata() {
+            files.push(RegistryFile {
+                path: path.strip_prefix(base_path).unwrap_or(&path).to_path_buf(),
+                bytes: metadata.len(),
+                original_filename: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        cli::Cli, config::Config, font_manifest::FileFailure, http::FailureKind,
+        options::resolve_options, testing::TestDir,
+    };
+
+    fn options() -> Options {
+        let cli = Cli::parse(["fetch".to_string()].into_iter()).unwrap();
+        resolve_options(&cli, &HashMap::new(), &Config::default()).unwrap()
+    }
+
+    fn result(base_path: &Path, path: &str, status: FileStatus) -> FileResult {
+        FileResult {
+            family: "Roboto".to_string(),
+            path: base_path.join(path),
+            status,
+            bytes: 4,
+            from_cache: false,
+            seconds: None,
+            transfer_seconds: None,
+            bytes_per_second: None,
+            original_filename: None,
+            content_type: None,
+        }
+    }
+
+    fn failed() -> FileStatus {
+        FileStatus::Failed {
+            reason: "Got status '404'.".to_string(),
+            failure: FileFailure::Download(FailureKind::Status(404)),
+        }
+    }
+
+    fn recorded_paths(registry: &Registry, name: &str) -> Vec<PathBuf> {
+        registry
+            .find(name)
+            .unwrap()
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn families_whose_files_all_failed_are_not_recorded() {
+        let test_dir = TestDir::new("registry-all-failed");
+        let mut registry = Registry::default();
+        let results = [
+            result(&test_dir.path, "Roboto/A.ttf", failed()),
+            result(&test_dir.path, "Roboto/B.ttf", failed()),
+        ];
+
+        registry.record("Roboto", &test_dir.path, &results, &options());
+        assert!(registry.find("Roboto").is_none());
+    }
+
+    #[test]
+    fn a_failed_refetch_leaves_the_entry_alone() {
+        let test_dir = TestDir::new("registry-failed-refetch");
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[result(&test_dir.path, "Roboto/A.ttf", FileStatus::Written)],
+            &options(),
+        );
+        registry.add_tag("Roboto", "web");
+
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[result(&test_dir.path, "Roboto/A.ttf", failed())],
+            &options(),
+        );
+        assert_eq!(
+            recorded_paths(&registry, "Roboto"),
+            [PathBuf::from("Roboto/A.ttf")]
+        );
+        assert_eq!(registry.find("Roboto").unwrap().tags, ["web"]);
+    }
+
+    #[test]
+    fn a_partially_failed_refetch_keeps_the_files_still_on_disk() {
+        let test_dir = TestDir::new("registry-partial-refetch");
+        fs::create_dir(test_dir.path.join("Roboto")).unwrap();
+        for file in ["A.ttf", "B.ttf"] {
+            fs::write(test_dir.path.join("Roboto").join(file), "font").unwrap();
+        }
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(&test_dir.path, "Roboto/A.ttf", FileStatus::Written),
+                result(&test_dir.path, "Roboto/B.ttf", FileStatus::Written),
+                result(&test_dir.path, "Roboto/C.ttf", FileStatus::Written),
+            ],
+            &options(),
+        );
+
+        // C.ttf was never actually written, so it is not kept.
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(&test_dir.path, "Roboto/A.ttf", FileStatus::Written),
+                result(&test_dir.path, "Roboto/B.ttf", failed()),
+                result(&test_dir.path, "Roboto/C.ttf", failed()),
+            ],
+            &options(),
+        );
+        assert_eq!(
+            recorded_paths(&registry, "Roboto"),
+            [PathBuf::from("Roboto/A.ttf"), PathBuf::from("Roboto/B.ttf")]
+        );
+    }
+
+    #[test]
+    fn a_partially_failed_update_is_merged_with_the_earlier_entry() {
+        let test_dir = TestDir::new("registry-partial-update");
+        fs::create_dir(test_dir.path.join("Roboto")).unwrap();
+        for file in ["Roboto-Regular.ttf", "Roboto-Bold.ttf", "OFL.txt"] {
+            fs::write(test_dir.path.join("Roboto").join(file), "font").unwrap();
+        }
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Regular.ttf",
+                    FileStatus::Written,
+                ),
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Bold.ttf",
+                    FileStatus::Written,
+                ),
+                result(&test_dir.path, "Roboto/OFL.txt", FileStatus::Written),
+            ],
+            &options(),
+        );
+        registry.add_tag("Roboto", "web");
+
+        // The new version renamed a file and added one, and the download of another one failed.
+        fs::write(test_dir.path.join("Roboto/Roboto-Italic.ttf"), "newer font").unwrap();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Italic.ttf",
+                    FileStatus::Written,
+                ),
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Regular.ttf",
+                    FileStatus::Skipped,
+                ),
+                result(&test_dir.path, "Roboto/Roboto-Bold.ttf", failed()),
+            ],
+            &options(),
+        );
+        let mut paths = recorded_paths(&registry, "Roboto");
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("Roboto/OFL.txt"),
+                PathBuf::from("Roboto/Roboto-Bold.ttf"),
+                PathBuf::from("Roboto/Roboto-Italic.ttf"),
+                PathBuf::from("Roboto/Roboto-Regular.ttf"),
+            ]
+        );
+        assert_eq!(registry.find("Roboto").unwrap().tags, ["web"]);
+
+        // Once the update goes through, files that are gone for good are no longer recorded.
+        fs::remove_file(test_dir.path.join("Roboto/OFL.txt")).unwrap();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Italic.ttf",
+                    FileStatus::Skipped,
+                ),
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Regular.ttf",
+                    FileStatus::Skipped,
+                ),
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Bold.ttf",
+                    FileStatus::Written,
+                ),
+            ],
+            &options(),
+        );
+        assert_eq!(recorded_paths(&registry, "Roboto").len(), 3);
+    }
+
+    #[test]
+    fn removing_the_last_family_leaves_an_empty_registry() {
+        let test_dir = TestDir::new("registry-remove-last");
+        fs::create_dir_all(test_dir.path.join("Roboto/static")).unwrap();
+        for file in ["Roboto/static/Roboto-Regular.ttf", "Roboto/OFL.txt"] {
+            fs::write(test_dir.path.join(file), "font").unwrap();
+        }
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(
+                    &test_dir.path,
+                    "Roboto/static/Roboto-Regular.ttf",
+                    FileStatus::Written,
+                ),
+                result(&test_dir.path, "Roboto/OFL.txt", FileStatus::Written),
+            ],
+            &options(),
+        );
+        registry.add_tag("Roboto", "web");
+        registry.save(&test_dir.path).unwrap();
+
+        let failed = registry.remove_families(&test_dir.path, &["roboto".to_string()], false);
+        assert!(failed.is_empty(), "{:?}", failed);
+        registry.save(&test_dir.path).unwrap();
+
+        // The family dir is gone, but the font base dir (with the registry in it) stays.
+        assert!(!test_dir.path.join("Roboto").exists());
+        assert!(test_dir.path.is_dir());
+        let mut registry = Registry::load(&test_dir.path, &mut Warnings::default());
+        assert!(registry.families.is_empty());
+        assert!(registry.tagged("web").is_empty());
+        assert!(!registry.add_tag("Roboto", "web"));
+        let saved: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(Registry::path(&test_dir.path)).unwrap())
+                .unwrap();
+        assert_eq!(saved, serde_json::json!({ "families": [] }));
+    }
+
+    #[test]
+    fn files_that_cant_be_removed_stay_recorded() {
+        let test_dir = TestDir::new("registry-remove-partial");
+        fs::create_dir_all(test_dir.path.join("Roboto/Roboto-Bold.ttf")).unwrap();
+        // A non-empty directory where a file should be can't be removed like a file.
+        fs::write(test_dir.path.join("Roboto/Roboto-Bold.ttf/inside"), "").unwrap();
+        fs::write(test_dir.path.join("Roboto/Roboto-Regular.ttf"), "font").unwrap();
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Regular.ttf",
+                    FileStatus::Written,
+                ),
+                result(
+                    &test_dir.path,
+                    "Roboto/Roboto-Bold.ttf",
+                    FileStatus::Written,
+                ),
+            ],
+            &options(),
+        );
+        registry.add_tag("Roboto", "web");
+
+        let failed = registry.remove_families(&test_dir.path, &["Roboto".to_string()], false);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "Roboto");
+        assert_eq!(
+            recorded_paths(&registry, "Roboto"),
+            [PathBuf::from("Roboto/Roboto-Bold.ttf")]
+        );
+        assert_eq!(registry.tagged("web").len(), 1);
+    }
+
+    #[test]
+    fn foreign_entries_are_never_removed() {
+        let test_dir = TestDir::new("registry-remove-foreign");
+        fs::create_dir_all(test_dir.path.join("Roboto")).unwrap();
+        fs::write(test_dir.path.join("Roboto/Roboto-Regular.ttf"), "font").unwrap();
+        // Fonts put into the font base dir by something else, next to the family and inside its dir.
+        fs::create_dir_all(test_dir.path.join("Foreign")).unwrap();
+        fs::write(test_dir.path.join("Foreign/Foreign.ttf"), "font").unwrap();
+        fs::write(test_dir.path.join("Roboto/Mine.ttf"), "font").unwrap();
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[result(
+                &test_dir.path,
+                "Roboto/Roboto-Regular.ttf",
+                FileStatus::Written,
+            )],
+            &options(),
+        );
+        registry.save(&test_dir.path).unwrap();
+
+        // With a registry, foreign dirs are not families, so `remove` and `update` can't select them.
+        let mut registry = Registry::load(&test_dir.path, &mut Warnings::default());
+        assert!(registry.find("Foreign").is_none());
+        assert_eq!(registry.recorded_by(Path::new("Roboto/Mine.ttf")), None);
+
+        let failed = registry.remove_families(
+            &test_dir.path,
+            &["Foreign".to_string(), "Roboto".to_string()],
+            false,
+        );
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert!(!test_dir.path.join("Roboto/Roboto-Regular.ttf").exists());
+        assert!(test_dir.path.join("Roboto/Mine.ttf").is_file());
+        assert!(test_dir.path.join("Foreign/Foreign.ttf").is_file());
+    }
+
+    #[test]
+    fn snapshots_dont_wait_for_the_lock() {
+        let test_dir = TestDir::new("registry-snapshot-locked");
+        fs::create_dir(test_dir.path.join("Roboto")).unwrap();
+        fs::write(test_dir.path.join("Roboto/Roboto-Regular.ttf"), "font").unwrap();
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[result(
+                &test_dir.path,
+                "Roboto/Roboto-Regular.ttf",
+                FileStatus::Written,
+            )],
+            &options(),
+        );
+        registry.save(&test_dir.path).unwrap();
+
+        // The lock is held like by another process changing the font base dir.
+        let _lock = crate::lock::lock_base_dir(&test_dir.path, Duration::ZERO)
+            .unwrap()
+            .unwrap();
+        let started_at = std::time::Instant::now();
+        let registry = Registry::load_snapshot(&test_dir.path, &mut Warnings::default());
+        assert!(started_at.elapsed() < Duration::from_secs(1));
+        assert!(registry.find("Roboto").is_some());
+    }
+
+    #[test]
+    fn snapshots_read_a_half_written_registry_again() {
+        let test_dir = TestDir::new("registry-snapshot-half-written");
+        let mut registry = Registry::default();
+        registry.record(
+            "Roboto",
+            &test_dir.path,
+            &[result(
+                &test_dir.path,
+                "Roboto/Roboto-Regular.ttf",
+                FileStatus::Written,
+            )],
+            &options(),
+        );
+        let contents = serde_json::to_string_pretty(&registry).unwrap();
+        fs::write(
+            Registry::path(&test_dir.path),
+            &contents[..contents.len() / 2],
+        )
+        .unwrap();
+
+        // Another writer finishes the file while the first read is waited out.
+        let path = Registry::path(&test_dir.path);
+        let writer = thread::spawn(move || {
+            thread::sleep(SNAPSHOT_RETRY_INTERVAL / 2);
+            fs::write(path, contents).unwrap();
+        });
+        let mut warnings = Warnings::default();
+        let registry = Registry::load_snapshot(&test_dir.path, &mut warnings);
+        writer.join().unwrap();
+        assert_eq!(
+            recorded_paths(&registry, "Roboto"),
+            [PathBuf::from("Roboto/Roboto-Regular.ttf")]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn corrupted_registries_are_rebuilt_with_a_warning() {
+        let test_dir = TestDir::new("registry-corrupted");
+        fs::create_dir(test_dir.path.join("Roboto")).unwrap();
+        fs::write(test_dir.path.join("Roboto/Roboto-Regular.ttf"), "font").unwrap();
+        fs::write(Registry::path(&test_dir.path), "{\"families\": [").unwrap();
+
+        let mut warnings = Warnings::default();
+        let registry = Registry::load(&test_dir.path, &mut warnings);
+        assert!(registry.find("Roboto").is_some());
+        let warnings = warnings.into_vec();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::RegistryRebuilt);
+        assert!(
+            warnings[0].message.contains("registry.json"),
+            "{}",
+            warnings[0].message
+        );
+
+        // Without a registry file, the installed files are scanned without a warning.
+        fs::remove_file(Registry::path(&test_dir.path)).unwrap();
+        let mut warnings = Warnings::default();
+        assert!(
+            Registry::load(&test_dir.path, &mut warnings)
+                .find("Roboto")
+                .is_some()
+        );
+        assert!(warnings.is_empty());
+    }
+}
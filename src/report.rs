@@ -0,0 +1,182 @@
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    csv::CsvWriter,
+    license::{UNKNOWN_LICENSE, detect_license, is_license_file},
+    registry::{Registry, RegistryEntry},
+};
+
+/// The formats a report can be printed in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Json,
+    Csv,
+    Markdown,
+}
+
+impl FromStr for ReportFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "json" => Ok(ReportFormat::Json),
+            "csv" => Ok(ReportFormat::Csv),
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            _ => Err(()),
+        }
+    }
+}
+
+/// An installed family, as listed in the report.
+#[derive(Clone, Debug, Serialize)]
+pub struct FamilyReport {
+    pub name: String,
+    pub version: Option<String>,
+    /// The SPDX identifier of the license, or `UNKNOWN`.
+    pub license: &'static str,
+    /// The paths of the files, relative to the font base dir.
+    pub files: Vec<PathBuf>,
+    pub total_bytes: u64,
+    /// The full text of the license file, if there is one.
+    #[serde(skip)]
+    pub license_text: Option<String>,
+}
+
+impl FamilyReport {
+    /// Describes an installed family, reading its license file to identify the license.
+    fn new(family: &RegistryEntry, base_path: &Path) -> Self {
+        let license_text = family
+            .files
+            .iter()
+            .filter(|file| is_license_file(&file.path.to_string_lossy()))
+            .find_map(|file| fs::read_to_string(base_path.join(&file.path)).ok());
+        let mut files = family
+            .files
+            .iter()
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        files.sort();
+        Self {
+            name: family.name.clone(),
+            version: family.version.clone(),
+            license: license_text
+                .as_deref()
+                .map_or(UNKNOWN_LICENSE, detect_license),
+            files,
+            total_bytes: family.files.iter().map(|file| file.bytes).sum(),
+            license_text,
+        }
+    }
+}
+
+/// Describes every installed family in the registry, sorted by name (and their files by path),
+/// so the report only changes when the installed fonts do.
+pub fn build_report(registry: &Registry, base_path: &Path) -> Vec<FamilyReport> {
+    let mut families = registry
+        .families
+        .iter()
+        .map(|family| FamilyReport::new(family, base_path))
+        .collect::<Vec<_>>();
+    families.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    families
+}
+
+/// Escapes the characters that would break a Markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Wraps text in a fenced code block, with a fence longer than any run of backticks in it.
+fn markdown_code_block(text: &str) -> String {
+    let longest_run = text
+        .split(|character| character != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{}text\n{}\n{}\n", fence, text.trim_end(), fence)
+}
+
+/// Renders a report in a format. Files are separated by `;` in CSV, and listed with `<br>` in Markdown.
+/// With `with_licenses`, the Markdown report ends with the full text of every license, in a collapsible section per family.
+pub fn render_report(
+    families: &[FamilyReport],
+    format: ReportFormat,
+    with_licenses: bool,
+) -> Result<String, String> {
+    let files = |family: &FamilyReport| {
+        family
+            .files
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+    };
+
+    match format {
+        ReportFormat::Json => serde_json::to_string_pretty(families)
+            .map(|report| report + "\n")
+            .map_err(|e| e.to_string()),
+        ReportFormat::Csv => {
+            let mut report =
+                CsvWriter::new(&["name", "version", "license", "files", "total_bytes"]);
+            for family in families {
+                report.row(&[
+                    family.name.as_str(),
+                    family.version.as_deref().unwrap_or(""),
+                    family.license,
+                    &files(family).join(";"),
+                    &family.total_bytes.to_string(),
+                ]);
+            }
+            Ok(report.finish())
+        }
+        ReportFormat::Markdown => {
+            let mut report = String::from(
+                "| Family | Version | License | Files | Size (bytes) |\n| --- | --- | --- | --- | ---: |\n",
+            );
+            for family in families {
+                report.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    markdown_cell(&family.name),
+                    markdown_cell(family.version.as_deref().unwrap_or("unknown")),
+                    family.license,
+                    files(family)
+                        .iter()
+                        .map(|file| markdown_cell(file))
+                        .collect::<Vec<_>>()
+                        .join("<br>"),
+                    family.total_bytes
+                ));
+            }
+            if with_licenses {
+                report.push_str("\n## Licenses\n");
+                for family in families {
+                    let Some(license_text) = &family.license_text else {
+                        continue;
+                    };
+                    report.push_str(&format!(
+                        "\n<details>\n<summary>{} ({})</summary>\n\n{}\n</details>\n",
+                        family
+                            .name
+                            .replace('&', "&amp;")
+                            .replace('<', "&lt;")
+                            .replace('>', "&gt;"),
+                        family.license,
+                        markdown_code_block(license_text)
+                    ));
+                }
+            }
+            Ok(report)
+        }
+    }
+}
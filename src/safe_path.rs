@@ -0,0 +1,456 @@
+use std::{
+    borrow::Cow,
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Whether filenames from manifests are sanitized before writing them, as Windows can't write every name.
+const SANITIZE_FILENAMES: bool = cfg!(windows);
+
+/// Names Windows reserves for devices. They can't be used as filenames, in any case and with any extension.
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The characters Windows doesn't allow in filenames (besides the separators and control characters).
+const WINDOWS_RESERVED_CHARACTERS: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+/// Windows doesn't allow paths longer than this, unless they are prefixed with `\\?\`.
+const WINDOWS_MAX_PATH: usize = 260;
+
+/// Why a filename from the manifest is not safe to write to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnsafePathReason {
+    Empty,
+    NulByte,
+    Absolute,
+    ParentComponent,
+    OutsideBasePath,
+    /// A symlink inside the base path points somewhere outside of it.
+    SymlinkOutsideBasePath,
+}
+
+impl fmt::Display for UnsafePathReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsafePathReason::Empty => write!(f, "it is empty"),
+            UnsafePathReason::NulByte => write!(f, "it contains a NUL byte"),
+            UnsafePathReason::Absolute => write!(f, "it is an absolute path"),
+            UnsafePathReason::ParentComponent => write!(f, "it contains a '..' component"),
+            UnsafePathReason::OutsideBasePath => write!(f, "it ends up outside the font base dir"),
+            UnsafePathReason::SymlinkOutsideBasePath => {
+                write!(
+                    f,
+                    "it goes through a symlink pointing outside the font base dir"
+                )
+            }
+        }
+    }
+}
+
+/// A filename from the manifest that is not safe to write to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsafePathError {
+    pub filename: String,
+    pub reason: UnsafePathReason,
+}
+
+impl fmt::Display for UnsafePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}': {}", self.filename.escape_debug(), self.reason)
+    }
+}
+
+/// Builds the path on disk for a file in the manifest, making sure it stays inside the base path.
+/// Filenames in the manifest always use `/` as separator, so they are joined component by component to get native separators.
+/// `\` is treated as a separator too when checking for `..`, as it is one on Windows.
+/// On Windows, each component is sanitized with `sanitize_windows_component`, so reserved names and characters can be written.
+pub fn resolve_manifest_path(base_path: &Path, filename: &str) -> Result<PathBuf, UnsafePathError> {
+    resolve_path(base_path, filename, SANITIZE_FILENAMES)
+}
+
+/// `resolve_manifest_path`, with the sanitizing of Windows names turned on or off, so it can be tested on every platform.
+fn resolve_path(
+    base_path: &Path,
+    filename: &str,
+    sanitize: bool,
+) -> Result<PathBuf, UnsafePathError> {
+    let error = |reason| UnsafePathError {
+        filename: filename.to_string(),
+        reason,
+    };
+
+    if filename.contains('\0') {
+        return Err(error(UnsafePathReason::NulByte));
+    }
+
+    if filename.starts_with(['/', '\\']) || Path::new(filename).has_root() {
+        return Err(error(UnsafePathReason::Absolute));
+    }
+
+    let components = filename
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect::<Vec<_>>();
+    if components.is_empty() {
+        return Err(error(UnsafePathReason::Empty));
+    }
+    if components
+        .iter()
+        .any(|component| component.split('\\').any(|part| part == ".."))
+    {
+        return Err(error(UnsafePathReason::ParentComponent));
+    }
+
+    let path = components
+        .iter()
+        .fold(base_path.to_path_buf(), |path, component| {
+            if sanitize {
+                path.join(sanitize_windows_component(component).as_ref())
+            } else {
+                path.join(component)
+            }
+        });
+
+    // Catches anything the checks above missed, e.g. Windows drive prefixes like `C:`.
+    let only_normal_components = path.strip_prefix(base_path).is_ok_and(|relative| {
+        relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    });
+    if !only_normal_components {
+        return Err(error(UnsafePathReason::OutsideBasePath));
+    }
+
+    Ok(path)
+}
+
+/// Makes one component of a manifest filename writable on Windows. This is the whole scheme:
+/// - the characters `<>:"|?*` and control characters become `_`,
+/// - trailing dots and spaces (which Windows drops) become `_`,
+/// - a reserved device name (like `CON` or `aux.txt`) gets `_` appended to the part before the first dot, so `CON.txt` becomes `CON_.txt`.
+///
+/// Works the same on every platform, it is only applied on Windows.
+pub fn sanitize_windows_component(component: &str) -> Cow<'_, str> {
+    let mut sanitized = component
+        .chars()
+        .map(|character| {
+            if WINDOWS_RESERVED_CHARACTERS.contains(&character) || character.is_control() {
+                '_'
+            } else {
+                character
+            }
+        })
+        .collect::<String>();
+
+    let trimmed_length = sanitized.trim_end_matches(['.', ' ']).len();
+    if trimmed_length < sanitized.len() {
+        let trailing = sanitized.len() - trimmed_length;
+        sanitized.truncate(trimmed_length);
+        sanitized.push_str(&"_".repeat(trailing));
+    }
+
+    let stem_length = sanitized.find('.').unwrap_or(sanitized.len());
+    if WINDOWS_RESERVED_NAMES.contains(&sanitized[..stem_length].to_uppercase().as_str()) {
+        sanitized.insert(stem_length, '_');
+    }
+
+    if sanitized == component {
+        Cow::Borrowed(component)
+    } else {
+        Cow::Owned(sanitized)
+    }
+}
+
+/// Applies `sanitize_windows_component` to every component of a manifest filename (separated by `/`).
+pub fn sanitize_windows_filename(filename: &str) -> Cow<'_, str> {
+    let sanitized = filename
+        .split('/')
+        .map(sanitize_windows_component)
+        .collect::<Vec<_>>()
+        .join("/");
+    if sanitized == filename {
+        Cow::Borrowed(filename)
+    } else {
+        Cow::Owned(sanitized)
+    }
+}
+
+/// Checks if a manifest filename is written under another name on this platform, because it had to be sanitized.
+pub fn is_sanitized(filename: &str) -> bool {
+    SANITIZE_FILENAMES && matches!(sanitize_windows_filename(filename), Cow::Owned(_))
+}
+
+/// Prefixes an absolute Windows path with `\\?\` (or `\\?\UNC\` for network shares) if it is too long for the usual API.
+/// Returns `None` if it doesn't need (or can't get) the prefix. Works on paths as strings, so it is the same on every platform.
+pub fn extended_length_path(path: &str) -> Option<String> {
+    if path.len() < WINDOWS_MAX_PATH || path.starts_with(r"\\?\") {
+        return None;
+    }
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", share));
+    }
+    let mut characters = path.chars();
+    let is_drive_path = characters
+        .next()
+        .is_some_and(|drive| drive.is_ascii_alphabetic())
+        && characters.next() == Some(':')
+        && characters.next() == Some('\\');
+    // The prefix turns off the normalizing of `/` and `..`, so only paths that are normalized already get it.
+    if is_drive_path && !path.contains('/') {
+        Some(format!(r"\\?\{}", path))
+    } else {
+        None
+    }
+}
+
+/// The path to use for file system calls: on Windows, long paths get the extended-length prefix.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    if cfg!(windows)
+        && let Some(extended) = extended_length_path(&path.to_string_lossy())
+    {
+        return Cow::Owned(PathBuf::from(extended));
+    }
+    Cow::Borrowed(path)
+}
+
+/// Checks that a path built with `resolve_manifest_path` does not escape the base path through symlinks that already exist on disk.
+/// The base path itself may be a symlink (e.g. onto another filesystem), only symlinks inside of it are checked.
+pub fn check_symlinks(
+    base_path: &Path,
+    path: &Path,
+    filename: &str,
+) -> Result<(), UnsafePathError> {
+    // If the base path does not exist yet, nothing inside of it can be a symlink.
+    let Ok(canonical_base_path) = fs::canonicalize(base_path) else {
+        return Ok(());
+    };
+
+    // The file itself is checked too, as writing to a symlink writes to wherever it points.
+    let Some(existing) = path
+        .ancestors()
+        .find(|ancestor| fs::symlink_metadata(ancestor).is_ok())
+    else {
+        return Ok(());
+    };
+
+    // A dangling symlink can not be canonicalized, and is not safe to write through either.
+    let inside = fs::canonicalize(existing)
+        .is_ok_and(|canonical| canonical.starts_with(&canonical_base_path));
+    if inside {
+        Ok(())
+    } else {
+        Err(UnsafePathError {
+            filename: filename.to_string(),
+            reason: UnsafePathReason::SymlinkOutsideBasePath,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestDir;
+    use serde_json::Value;
+    #[cfg(unix)]
+    use std::os::unix::fs::symlink;
+
+    /// The reasons each file of a manifest's `fileRefs` is rejected for, or `None` for the files that are safe to write.
+    fn rejected(file_refs: &str) -> Vec<Option<UnsafePathReason>> {
+        let file_refs: Vec<Value> = serde_json::from_str(file_refs).unwrap();
+        file_refs
+            .iter()
+            .map(|file_ref| {
+                resolve_manifest_path(
+                    Path::new("/fonts/Google"),
+                    file_ref["filename"].as_str().unwrap(),
+                )
+                .err()
+                .map(|error| error.reason)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn safe_filenames_stay_inside_the_base_path() {
+        assert_eq!(
+            resolve_manifest_path(Path::new("/fonts/Google"), "static/./Roboto-Bold.ttf"),
+            Ok(PathBuf::from("/fonts/Google/static/Roboto-Bold.ttf"))
+        );
+        assert_eq!(
+            rejected(
+                r#"[{"filename": "Roboto.ttf", "url": "u"}, {"filename": "a//b..c.ttf", "url": "u"}]"#
+            ),
+            [None, None]
+        );
+    }
+
+    #[test]
+    fn malicious_manifests_are_rejected() {
+        let file_refs = r#"[
+            {"filename": "../../.bashrc", "url": "u"},
+            {"filename": "static/../../x.ttf", "url": "u"},
+            {"filename": "static\\..\\..\\x.ttf", "url": "u"},
+            {"filename": "/etc/passwd", "url": "u"},
+            {"filename": "\\Windows\\x.ttf", "url": "u"},
+            {"filename": "a.ttf\u0000.txt", "url": "u"},
+            {"filename": "", "url": "u"},
+            {"filename": "./", "url": "u"}
+        ]"#;
+        assert_eq!(
+            rejected(file_refs),
+            [
+                Some(UnsafePathReason::ParentComponent),
+                Some(UnsafePathReason::ParentComponent),
+                Some(UnsafePathReason::ParentComponent),
+                Some(UnsafePathReason::Absolute),
+                Some(UnsafePathReason::Absolute),
+                Some(UnsafePathReason::NulByte),
+                Some(UnsafePathReason::Empty),
+                Some(UnsafePathReason::Empty),
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_names_and_characters_are_sanitized() {
+        let cases = [
+            ("Roboto-Regular.ttf", "Roboto-Regular.ttf"),
+            ("CON", "CON_"),
+            ("con.ttf", "con_.ttf"),
+            ("Aux.tar.gz", "Aux_.tar.gz"),
+            ("LPT9.ttf", "LPT9_.ttf"),
+            // Only whole names are reserved.
+            ("CONSOLE.ttf", "CONSOLE.ttf"),
+            ("COM10.ttf", "COM10.ttf"),
+            ("a<b>c:d\"e|f?g*h.ttf", "a_b_c_d_e_f_g_h.ttf"),
+            ("tab\there.ttf", "tab_here.ttf"),
+            ("trailing. .", "trailing___"),
+            // Replacing the trailing dot already makes the name no longer reserved.
+            ("NUL.", "NUL_"),
+        ];
+        for (component, expected) in cases {
+            assert_eq!(
+                sanitize_windows_component(component),
+                expected,
+                "{}",
+                component
+            );
+        }
+        assert!(matches!(
+            sanitize_windows_component("Roboto.ttf"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn sanitized_filenames_map_each_component() {
+        assert_eq!(
+            sanitize_windows_filename("static/aux/Font:Bold?.ttf"),
+            "static/aux_/Font_Bold_.ttf"
+        );
+        assert!(matches!(
+            sanitize_windows_filename("static/Roboto.ttf"),
+            Cow::Borrowed(_)
+        ));
+
+        // The path written to is the sanitized filename, and only when sanitizing.
+        let base_path = Path::new("fonts");
+        let filename = "static/aux/Font:Bold?.ttf";
+        assert_eq!(
+            resolve_path(base_path, filename, true),
+            Ok(base_path.join("static").join("aux_").join("Font_Bold_.ttf"))
+        );
+        assert_eq!(
+            resolve_path(base_path, filename, false),
+            Ok(base_path.join("static").join("aux").join("Font:Bold?.ttf"))
+        );
+        // Names that need no sanitizing are never reported as written under another name.
+        assert!(!is_sanitized("static/Roboto.ttf"));
+        assert_eq!(is_sanitized(filename), cfg!(windows));
+    }
+
+    #[test]
+    fn long_windows_paths_get_the_extended_length_prefix() {
+        let long_name = "a".repeat(WINDOWS_MAX_PATH);
+        assert_eq!(extended_length_path(r"C:\fonts\Roboto.ttf"), None);
+        assert_eq!(
+            extended_length_path(&format!(r"C:\fonts\{}.ttf", long_name)),
+            Some(format!(r"\\?\C:\fonts\{}.ttf", long_name))
+        );
+        assert_eq!(
+            extended_length_path(&format!(r"\\server\share\{}.ttf", long_name)),
+            Some(format!(r"\\?\UNC\server\share\{}.ttf", long_name))
+        );
+        // Paths that have the prefix already, aren't absolute or aren't normalized are left alone.
+        for path in [
+            format!(r"\\?\C:\fonts\{}.ttf", long_name),
+            format!(r"fonts\{}.ttf", long_name),
+            format!(r"C:/fonts/{}.ttf", long_name),
+            format!("/home/fonts/{}.ttf", long_name),
+        ] {
+            assert_eq!(extended_length_path(&path), None, "{}", path);
+        }
+        // Only paths at or over the limit get it.
+        let just_short = format!(r"C:\{}", "a".repeat(WINDOWS_MAX_PATH - 4));
+        assert_eq!(extended_length_path(&just_short), None);
+        assert!(extended_length_path(&format!("{}a", just_short)).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_base_path_are_rejected() {
+        let test_dir = TestDir::new("safe-path-escape");
+        let base_path = test_dir.path.join("fonts");
+        fs::create_dir_all(base_path.join("static")).unwrap();
+        symlink(&test_dir.path, base_path.join("escape")).unwrap();
+        symlink(base_path.join("static"), base_path.join("alias")).unwrap();
+        symlink(
+            test_dir.path.join("missing"),
+            base_path.join("dangling.ttf"),
+        )
+        .unwrap();
+
+        let check = |filename: &str| {
+            let path = resolve_manifest_path(&base_path, filename).unwrap();
+            check_symlinks(&base_path, &path, filename).map_err(|error| error.reason)
+        };
+        assert_eq!(check("static/a.ttf"), Ok(()));
+        // A symlink that stays inside of the base path is fine.
+        assert_eq!(check("alias/a.ttf"), Ok(()));
+        assert_eq!(
+            check("escape/a.ttf"),
+            Err(UnsafePathReason::SymlinkOutsideBasePath)
+        );
+        assert_eq!(
+            check("dangling.ttf"),
+            Err(UnsafePathReason::SymlinkOutsideBasePath)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_base_path_itself_may_be_a_symlink() {
+        let test_dir = TestDir::new("safe-path-base-symlink");
+        let target = test_dir.path.join("other-disk").join("fonts");
+        fs::create_dir_all(target.join("static")).unwrap();
+        fs::write(target.join("static").join("a.ttf"), "font").unwrap();
+        let base_path = test_dir.path.join("fonts");
+        symlink(&target, &base_path).unwrap();
+        symlink(&test_dir.path, target.join("escape")).unwrap();
+
+        let check = |filename: &str| {
+            let path = resolve_manifest_path(&base_path, filename).unwrap();
+            check_symlinks(&base_path, &path, filename).map_err(|error| error.reason)
+        };
+        assert_eq!(check("static/a.ttf"), Ok(()));
+        assert_eq!(check("static/b.ttf"), Ok(()));
+        assert_eq!(check("new/a.ttf"), Ok(()));
+        // Symlinks inside of it are still checked against where it points.
+        assert_eq!(
+            check("escape/a.ttf"),
+            Err(UnsafePathReason::SymlinkOutsideBasePath)
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! `fonty self-update`: replaces the running executable with the latest release from GitHub.
+//! Only built with the `self-update` feature, so distro packages can leave it out.
+
+use serde::Deserialize;
+use std::{
+    env::{self, consts},
+    fmt, fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    exit_codes::{EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_INVALID_FONT_MANIFEST, EXIT_CODE_NET_ERROR},
+    fixtures, http,
+    sha256::sha256_hex,
+};
+
+/// The latest release of fonty, from the GitHub releases API.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/BurnyLlama/google-font-fetcher/releases/latest";
+
+/// An error while updating fonty. The running executable is never left replaced halfway.
+#[derive(Debug)]
+pub enum SelfUpdateError {
+    /// A request failed.
+    Request { url: String, error: String },
+    /// The release is not what was expected, e.g. it has no build for this platform or the checksum does not match.
+    InvalidRelease { error: String },
+    /// The executable could not be replaced.
+    Replace { path: PathBuf, error: String },
+}
+
+impl fmt::Display for SelfUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfUpdateError::Request { url, error } => {
+                write!(f, "Request to '{}' failed! Error:\n{}", url, error)
+            }
+            SelfUpdateError::InvalidRelease { error } => write!(f, "{}", error),
+            SelfUpdateError::Replace { path, error } => write!(
+                f,
+                "Failed to replace '{}', it was left as it was! Error:\n{}",
+                path.display(),
+                error
+            ),
+        }
+    }
+}
+
+impl SelfUpdateError {
+    /// The exit code to exit with for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SelfUpdateError::Request { .. } => EXIT_CODE_NET_ERROR,
+            SelfUpdateError::InvalidRelease { .. } => EXIT_CODE_INVALID_FONT_MANIFEST,
+            SelfUpdateError::Replace { .. } => EXIT_CODE_FILE_IO_ERROR,
+        }
+    }
+}
+
+/// A file attached to a release.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// A release of fonty. Only the fields fonty uses are parsed.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Release {
+    /// The tag of the release, e.g. `v0.2.0`.
+    pub tag_name: String,
+    #[serde(default)]
+    pub assets: Vec<ReleaseAsset>,
+}
+
+impl Release {
+    /// The version of the release, without the `v` of the tag.
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+}
+
+/// The target triple fonty was built for, as the release builds are named, e.g. `x86_64-unknown-linux-gnu`.
+pub fn current_target() -> String {
+    let env = if cfg!(target_env = "musl") {
+        "musl"
+    } else if cfg!(target_env = "msvc") {
+        "msvc"
+    } else {
+        "gnu"
+    };
+    match consts::OS {
+        "linux" => format!("{}-unknown-linux-{}", consts::ARCH, env),
+        "macos" => format!("{}-apple-darwin", consts::ARCH),
+        "windows" => format!("{}-pc-windows-{}", consts::ARCH, env),
+        os => format!("{}-unknown-{}", consts::ARCH, os),
+    }
+}
+
+/// The name of the release build for the current platform, e.g. `fonty-x86_64-unknown-linux-gnu`.
+/// Its checksum is published next to it, with `.sha256` appended.
+fn asset_name() -> String {
+    format!("fonty-{}{}", current_target(), consts::EXE_SUFFIX)
+}
+
+/// Checks if a version is newer than another, comparing their dot-separated numbers (`0.10.0` is newer than `0.9.1`).
+/// Anything after a `-` (like `-beta.1`) is ignored.
+pub fn is_newer(version: &str, than: &str) -> bool {
+    let numbers = |version: &str| {
+        version
+            .split('-')
+            .next()
+            .unwrap_or("")
+            .split('.')
+            .map(|number| number.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    };
+    numbers(version) > numbers(than)
+}
+
+/// Gets the latest release from GitHub.
+pub fn latest_release() -> Result<Release, SelfUpdateError> {
+    let request_error = |error: String| SelfUpdateError::Request {
+        url: LATEST_RELEASE_URL.to_string(),
+        error,
+    };
+    let response = http::get(LATEST_RELEASE_URL).map_err(|e| request_error(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(request_error(format!(
+            "Got status '{}'.",
+            response.status()
+        )));
+    }
+    let body = response.text().map_err(|e| request_error(e.to_string()))?;
+    fixtures::record_body(body.as_bytes());
+    serde_json::from_str(&body).map_err(|e| SelfUpdateError::InvalidRelease {
+        error: format!(
+            "Invalid release from '{}'! Error:\n{}",
+            LATEST_RELEASE_URL, e
+        ),
+    })
+}
+
+/// Finds an asset of a release by its name.
+fn find_asset<'a>(release: &'a Release, name: &str) -> Result<&'a ReleaseAsset, SelfUpdateError> {
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.name == name)
+        .ok_or_else(|| SelfUpdateError::InvalidRelease {
+            error: format!(
+                "The release '{}' has no '{}', there may be no build for this platform ({}).",
+                release.tag_name,
+                name,
+                current_target()
+            ),
+        })
+}
+
+/// Downloads an asset of a release. The asset URLs redirect to GitHub's storage (`objects.githubusercontent.com`), so this
+/// goes through the shared client, not the one for font files, whose redirects may only lead to the hosts fonts come from.
+fn download_asset(asset: &ReleaseAsset) -> Result<Vec<u8>, SelfUpdateError> {
+    let request_error = |error: String| SelfUpdateError::Request {
+        url: asset.browser_download_url.clone(),
+        error,
+    };
+    let response =
+        http::get(&asset.browser_download_url).map_err(|e| request_error(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(request_error(format!(
+            "Got status '{}'.",
+            response.status()
+        )));
+    }
+    let bytes = response.bytes().map_err(|e| request_error(e.to_string()))?;
+    fixtures::record_body(&bytes);
+    Ok(bytes.to_vec())
+}
+
+/// Downloads the build of a release for the current platform, and checks it against its published SHA-256.
+pub fn download_release(release: &Release) -> Result<Vec<u8>, SelfUpdateError> {
+    let name = asset_name();
+    let binary_asset = find_asset(release, &name)?;
+    let checksum_asset = find_asset(release, &format!("{}.sha256", name))?;
+
+    // The checksum file is in the format of `sha256sum`: the hash, then the filename.
+    let checksum = String::from_utf8_lossy(&download_asset(checksum_asset)?)
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    let binary = download_asset(binary_asset)?;
+    let actual = sha256_hex(&binary);
+    if actual != checksum {
+        return Err(SelfUpdateError::InvalidRelease {
+            error: format!(
+                "The SHA-256 of '{}' is {}, but the release says it should be '{}'! Not installing it.",
+                name, actual, checksum
+            ),
+        });
+    }
+    Ok(binary)
+}
+
+/// Replaces the running executable with a new one. The new one is written next to it first and then renamed over it,
+/// so it is replaced all at once or not at all. Returns the path of the executable.
+pub fn replace_executable(binary: &[u8]) -> Result<PathBuf, SelfUpdateError> {
+    let path =
+        env::current_exe()
+            .and_then(fs::canonicalize)
+            .map_err(|e| SelfUpdateError::Replace {
+                path: PathBuf::from("fonty"),
+                error: e.to_string(),
+            })?;
+    let replace_error = |error: std::io::Error| SelfUpdateError::Replace {
+        path: path.clone(),
+        error: error.to_string(),
+    };
+
+    let temporary_path = path.with_file_name(format!(".fonty-update-{}", std::process::id()));
+    let result =
+        write_executable(&temporary_path, &path, binary).and_then(|_| swap(&temporary_path, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    result.map_err(replace_error)?;
+    Ok(path)
+}
+
+/// Writes the new executable, with the permissions of the current one.
+fn write_executable(path: &Path, current_path: &Path, binary: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(binary)?;
+    file.sync_all()?;
+    fs::set_permissions(path, fs::metadata(current_path)?.permissions())
+}
+
+/// Puts the new executable in place of the current one.
+#[cfg(not(windows))]
+fn swap(new_path: &Path, path: &Path) -> std::io::Result<()> {
+    // Renaming is atomic, and the running process keeps the old file open until it exits.
+    fs::rename(new_path, path)
+}
+
+/// Puts the new executable in place of the current one. On Windows, a running executable can't be replaced,
+/// but it can be renamed, so it is moved aside first (and deleted by the next update).
+#[cfg(windows)]
+fn swap(new_path: &Path, path: &Path) -> std::io::Result<()> {
+    let old_path = path.with_extension("old.exe");
+    let _ = fs::remove_file(&old_path);
+    fs::rename(path, &old_path)?;
+    if let Err(e) = fs::rename(new_path, path) {
+        // Put the old executable back, so there still is one.
+        let _ = fs::rename(&old_path, path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockResponse, MockServer};
+
+    /// A release whose assets are served by `github`, which redirects them to `storage` on another host, like GitHub does.
+    /// `storage` serves the binary `binary`, with the checksum `checksum`.
+    fn redirected_release(binary: &'static [u8], checksum: String) -> (Release, MockServer) {
+        let name = asset_name();
+        let checksum_line = format!("{}  {}\n", checksum, name);
+        let storage = MockServer::start(move |request| match request.path.as_str() {
+            "/binary" => MockResponse::ok(binary),
+            "/checksum" => MockResponse::ok(checksum_line.clone()),
+            _ => MockResponse::status(404),
+        });
+        let storage_url = storage.localhost_url("");
+        let github = MockServer::start(move |request| {
+            let path = request.path.trim_start_matches("/releases/download");
+            MockResponse::redirect(&format!("{}{}", storage_url, path))
+        });
+        let asset = |name: String, path: &str| ReleaseAsset {
+            name,
+            browser_download_url: github.url(path),
+        };
+        let release = Release {
+            tag_name: "v99.0.0".to_string(),
+            assets: vec![
+                asset(name.clone(), "/releases/download/binary"),
+                asset(format!("{}.sha256", name), "/releases/download/checksum"),
+            ],
+        };
+        (release, storage)
+    }
+
+    #[test]
+    fn release_assets_are_downloaded_through_redirects_to_other_hosts() {
+        let (release, storage) = redirected_release(b"new fonty", sha256_hex(b"new fonty"));
+        assert_eq!(download_release(&release).unwrap(), b"new fonty");
+        let paths = storage
+            .requests()
+            .into_iter()
+            .map(|request| request.path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["/checksum", "/binary"]);
+    }
+
+    #[test]
+    fn redirected_releases_with_a_wrong_checksum_are_rejected() {
+        let (release, storage) = redirected_release(b"tampered fonty", sha256_hex(b"new fonty"));
+        let error = download_release(&release).unwrap_err();
+        assert!(
+            matches!(error, SelfUpdateError::InvalidRelease { .. }),
+            "{}",
+            error
+        );
+        assert_eq!(error.exit_code(), EXIT_CODE_INVALID_FONT_MANIFEST);
+        // Both assets were downloaded through the redirects, so the checksum was checked.
+        assert_eq!(storage.requests().len(), 2);
+    }
+
+    #[test]
+    fn releases_without_a_build_for_this_platform_are_rejected() {
+        let release = Release {
+            tag_name: "v99.0.0".to_string(),
+            assets: Vec::new(),
+        };
+        let error = download_release(&release).unwrap_err();
+        assert!(error.to_string().contains(&asset_name()), "{}", error);
+    }
+}
@@ -0,0 +1,320 @@
+/// The ID of the version string in the `name` table, e.g. `Version 3.011`.
+const NAME_ID_VERSION: u16 = 5;
+
+/// Reads a big-endian u16 at an offset, if the data is long enough.
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a big-endian u32 at an offset, if the data is long enough.
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a big-endian 16.16 fixed-point number at an offset, if the data is long enough.
+fn read_fixed(data: &[u8], offset: usize) -> Option<f32> {
+    Some(read_u32(data, offset)? as i32 as f32 / 65536.0)
+}
+
+/// Finds a table in the font starting at `font_offset`, returning its offset and length.
+/// A table directory that doesn't fit into the data is broken, and gives `None`.
+fn find_table(data: &[u8], font_offset: usize, tag: &[u8; 4]) -> Option<(usize, usize)> {
+    let table_count = read_u16(data, font_offset.checked_add(4)?)?;
+    data.get(font_offset..font_offset + 12 + table_count as usize * 16)?;
+    (0..table_count as usize)
+        .map(|index| font_offset + 12 + index * 16)
+        .find(|&record| data.get(record..record + 4) == Some(tag))
+        .and_then(|record| {
+            Some((
+                read_u32(data, record + 8)? as usize,
+                read_u32(data, record + 12)? as usize,
+            ))
+        })
+}
+
+/// Decodes a string from the `name` table. Unicode and Windows strings are UTF-16BE, Macintosh ones are (close enough to) ASCII.
+/// UTF-16 strings of an odd length are broken, and give `None`.
+fn decode_name(platform_id: u16, bytes: &[u8]) -> Option<String> {
+    match platform_id {
+        0 | 3 if !bytes.len().is_multiple_of(2) => None,
+        0 | 3 => String::from_utf16(
+            &bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        )
+        .ok(),
+        1 => Some(bytes.iter().map(|&byte| byte as char).collect()),
+        _ => None,
+    }
+}
+
+/// Turns a version string like `Version 3.011; ttfautohint (v1.8)` into `3.011`.
+fn normalize_version(version: &str) -> Option<String> {
+    let version = version.trim();
+    let version = version
+        .strip_prefix("Version")
+        .or_else(|| version.strip_prefix("version"))
+        .unwrap_or(version);
+    let version = version
+        .trim_start()
+        .split(|character: char| character == ';' || character.is_whitespace())
+        .next()?;
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Reads the version of a TrueType/OpenType font (or the first font of a collection) from its `name` table.
+/// This parses just enough of the sfnt format for that. Compressed formats (WOFF, WOFF2) and broken fonts give `None`.
+pub fn font_version(data: &[u8]) -> Option<String> {
+    let font_offset = match data.get(0..4)? {
+        b"ttcf" => read_u32(data, 12)? as usize,
+        _ => 0,
+    };
+    let (name_offset, name_length) = find_table(data, font_offset, b"name")?;
+    let name_table = data.get(name_offset..name_offset.checked_add(name_length)?)?;
+
+    let record_count = read_u16(name_table, 2)?;
+    let strings_offset = read_u16(name_table, 4)? as usize;
+    // More records than fit into the table means it is broken.
+    name_table.get(..6 + record_count as usize * 12)?;
+    let mut versions = (0..record_count as usize)
+        .map(|index| 6 + index * 12)
+        .filter_map(|record| {
+            let platform_id = read_u16(name_table, record)?;
+            if read_u16(name_table, record + 6)? != NAME_ID_VERSION {
+                return None;
+            }
+            let length = read_u16(name_table, record + 8)? as usize;
+            let offset = strings_offset + read_u16(name_table, record + 10)? as usize;
+            let version = decode_name(platform_id, name_table.get(offset..offset + length)?)?;
+            Some((platform_id, version))
+        })
+        .collect::<Vec<_>>();
+    // Prefer the Windows string, which every font from Google Fonts has.
+    versions.sort_by_key(|(platform_id, _)| *platform_id != 3);
+    versions
+        .into_iter()
+        .find_map(|(_, version)| normalize_version(&version))
+}
+
+/// Reads the range of a variation axis (e.g. `wght`) of a variable font from its `fvar` table, as `(min, max)`.
+/// Fonts without the axis (or that are not variable) give `None`.
+pub fn axis_range(data: &[u8], axis_tag: &[u8; 4]) -> Option<(f32, f32)> {
+    let font_offset = match data.get(0..4)? {
+        b"ttcf" => read_u32(data, 12)? as usize,
+        _ => 0,
+    };
+    let (fvar_offset, fvar_length) = find_table(data, font_offset, b"fvar")?;
+    let fvar_table = data.get(fvar_offset..fvar_offset.checked_add(fvar_length)?)?;
+
+    let axes_offset = read_u16(fvar_table, 4)? as usize;
+    let axis_count = read_u16(fvar_table, 8)? as usize;
+    let axis_size = read_u16(fvar_table, 10)? as usize;
+    fvar_table.get(axes_offset..axes_offset + axis_count * axis_size)?;
+    (0..axis_count)
+        .map(|index| axes_offset + index * axis_size)
+        .find(|&record| fvar_table.get(record..record + 4) == Some(axis_tag))
+        .and_then(|record| {
+            Some((
+                read_fixed(fvar_table, record + 4)?,
+                read_fixed(fvar_table, record + 12)?,
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a font with the given tables, in that order.
+    fn font(tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0, 1, 0, 0];
+        data.extend((tables.len() as u16).to_be_bytes());
+        data.extend([0; 6]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, table) in tables {
+            data.extend(*tag);
+            data.extend([0; 4]);
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((table.len() as u32).to_be_bytes());
+            offset += table.len();
+        }
+        for (_, table) in tables {
+            data.extend(table);
+        }
+        data
+    }
+
+    /// Builds a `name` table with records of `(platform ID, name ID, string)`.
+    fn name_table(records: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+        let strings_offset = 6 + records.len() * 12;
+        let mut table = [0, records.len() as u16, strings_offset as u16]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+        let mut strings: Vec<u8> = Vec::new();
+        for (platform_id, name_id, string) in records {
+            let record = [
+                *platform_id,
+                0,
+                0,
+                *name_id,
+                string.len() as u16,
+                strings.len() as u16,
+            ];
+            table.extend(record.iter().flat_map(|value| value.to_be_bytes()));
+            strings.extend(string);
+        }
+        table.extend(strings);
+        table
+    }
+
+    /// Builds an `fvar` table with axes of `(tag, min, default, max)`.
+    fn fvar_table(axes: &[(&[u8; 4], f32, f32, f32)]) -> Vec<u8> {
+        let mut table = [1, 0, 16, 2, axes.len() as u16, 20, 0, 0]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect::<Vec<_>>();
+        for (tag, min, default, max) in axes {
+            table.extend(*tag);
+            for value in [min, default, max] {
+                table.extend(((value * 65536.0) as i32).to_be_bytes());
+            }
+            table.extend([0; 4]);
+        }
+        table
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    /// A variable font with a Macintosh and a Windows version string.
+    fn variable_font() -> Vec<u8> {
+        font(&[
+            (b"fvar", fvar_table(&[(b"wght", 100.0, 400.0, 900.0)])),
+            (
+                b"name",
+                name_table(&[
+                    (1, NAME_ID_VERSION, b"Version 1.000".to_vec()),
+                    (3, 1, utf16("Inter")),
+                    (
+                        3,
+                        NAME_ID_VERSION,
+                        utf16("Version 3.011; ttfautohint (v1.8)"),
+                    ),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn versions_and_axes_are_read() {
+        let data = variable_font();
+        assert_eq!(font_version(&data).as_deref(), Some("3.011"));
+        assert_eq!(axis_range(&data, b"wght"), Some((100.0, 900.0)));
+        assert_eq!(axis_range(&data, b"wdth"), None);
+
+        // The first font of a collection is read.
+        let mut collection = b"ttcf\0\x02\0\0\0\0\0\x01\0\0\0\x10".to_vec();
+        collection.extend(font(&[(
+            b"name",
+            name_table(&[(1, NAME_ID_VERSION, b"Version 2.1".to_vec())]),
+        )]));
+        // The table offsets are from the start of the collection.
+        let name_offset = read_u32(&collection, 16 + 12 + 8).unwrap() + 16;
+        collection.splice(36..40, name_offset.to_be_bytes());
+        assert_eq!(font_version(&collection).as_deref(), Some("2.1"));
+    }
+
+    #[test]
+    fn versions_are_read_from_real_fonts() {
+        // DejaVu Sans Mono 2.37 without its glyphs, see `tests/fixtures/README.md`.
+        let data = include_bytes!("../tests/fixtures/fonts/DejaVuSansMono-NoGlyphs.ttf");
+        assert_eq!(font_version(data).as_deref(), Some("2.37"));
+        assert_eq!(axis_range(data, b"wght"), None);
+    }
+
+    #[test]
+    fn truncated_fonts_give_none() {
+        let data = variable_font();
+        for length in 0..data.len() {
+            assert_eq!(font_version(&data[..length]), None, "{}", length);
+            axis_range(&data[..length], b"wght");
+        }
+        assert_eq!(font_version(b"wOF2"), None);
+    }
+
+    #[test]
+    fn tables_past_the_end_give_none() {
+        for (offset, length) in [(0xFFFF_FFF0, 0x100), (28, u32::MAX), (u32::MAX, 1)] {
+            let mut data = font(&[(
+                b"name",
+                name_table(&[(3, NAME_ID_VERSION, utf16("Version 1.0"))]),
+            )]);
+            data.splice(
+                20..28,
+                [offset, length]
+                    .iter()
+                    .flat_map(|value| value.to_be_bytes()),
+            );
+            assert_eq!(font_version(&data), None);
+            assert_eq!(axis_range(&data, b"wght"), None);
+        }
+
+        // More tables than the font has room for.
+        let mut data = variable_font();
+        data.splice(4..6, u16::MAX.to_be_bytes());
+        data.truncate(64);
+        assert_eq!(font_version(&data), None);
+        assert_eq!(axis_range(&data, b"wght"), None);
+    }
+
+    #[test]
+    fn huge_name_records_give_none() {
+        let table = name_table(&[(3, NAME_ID_VERSION, utf16("Version 1.0"))]);
+
+        // More records than the table has.
+        let mut huge_count = table.clone();
+        huge_count.splice(2..4, u16::MAX.to_be_bytes());
+        // A string longer than the table.
+        let mut huge_length = table.clone();
+        huge_length.splice(14..16, u16::MAX.to_be_bytes());
+        // Strings past the end of the table.
+        let mut huge_offset = table.clone();
+        huge_offset.splice(4..6, u16::MAX.to_be_bytes());
+        for table in [huge_count, huge_length, huge_offset] {
+            assert_eq!(font_version(&font(&[(b"name", table)])), None);
+        }
+    }
+
+    #[test]
+    fn huge_axis_counts_give_none() {
+        let mut table = fvar_table(&[(b"wdth", 75.0, 100.0, 125.0)]);
+        table.splice(8..10, u16::MAX.to_be_bytes());
+        assert_eq!(axis_range(&font(&[(b"fvar", table)]), b"wdth"), None);
+    }
+
+    #[test]
+    fn collections_with_bad_offsets_give_none() {
+        for offset in [u32::MAX, 0xFFFF_FFF0, 16, 0] {
+            let mut data = b"ttcf\0\x02\0\0\0\0\0\x01".to_vec();
+            data.extend(offset.to_be_bytes());
+            assert_eq!(font_version(&data), None, "{}", offset);
+            assert_eq!(axis_range(&data, b"wght"), None, "{}", offset);
+        }
+    }
+
+    #[test]
+    fn broken_utf16_names_give_none() {
+        let mut odd_length = utf16("Version 1.0");
+        odd_length.push(b'1');
+        let unpaired_surrogate = vec![0xD8, 0x00, 0, b'1'];
+        for string in [odd_length, unpaired_surrogate] {
+            let data = font(&[(b"name", name_table(&[(3, NAME_ID_VERSION, string)]))]);
+            assert_eq!(font_version(&data), None);
+        }
+    }
+}
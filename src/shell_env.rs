@@ -0,0 +1,165 @@
+//! Environment variables with the directories of installed families, printed by `fonty env` for shells to source,
+//! like `export FONT_INTER_DIR='/home/me/.local/share/fonts/Google/Inter'`.
+
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+/// The shells the variables can be printed for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    /// sh, bash, zsh and the like.
+    Posix,
+    Fish,
+    /// PowerShell.
+    Pwsh,
+}
+
+impl FromStr for Shell {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "posix" | "sh" | "bash" | "zsh" => Ok(Shell::Posix),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::Pwsh),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The name of the variable for a family: `FONT_`, the family name in uppercase with everything but ASCII letters and
+/// digits turned into underscores, and `_DIR`. E.g. `Noto Sans JP` becomes `FONT_NOTO_SANS_JP_DIR`.
+pub fn env_var_name(family: &str) -> String {
+    let slug = family
+        .chars()
+        .map(|character| match character {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => character.to_ascii_uppercase(),
+            _ => '_',
+        })
+        .collect::<String>();
+    format!("FONT_{}_DIR", slug)
+}
+
+/// Finds families that get the same variable name, which would overwrite each other.
+/// Returns the variable name with the families for every name that is used more than once, sorted by the name.
+pub fn find_name_collisions<'a>(families: &[&'a str]) -> Vec<(String, Vec<&'a str>)> {
+    let mut by_name: HashMap<String, Vec<&str>> = HashMap::new();
+    for family in families {
+        by_name
+            .entry(env_var_name(family))
+            .or_default()
+            .push(family);
+    }
+    let mut collisions = by_name
+        .into_iter()
+        .filter(|(_, families)| families.len() > 1)
+        .collect::<Vec<_>>();
+    collisions.sort();
+    collisions
+}
+
+/// Renders the line setting a variable to a path, quoted so the shell takes the path as is.
+pub fn render_assignment(shell: Shell, name: &str, path: &Path) -> String {
+    let path = path.to_string_lossy();
+    match shell {
+        Shell::Posix => format!("export {}='{}'", name, path.replace('\'', r"'\''")),
+        Shell::Fish => format!(
+            "set -gx {} '{}'",
+            name,
+            path.replace('\\', r"\\").replace('\'', r"\'")
+        ),
+        Shell::Pwsh => format!("$env:{} = '{}'", name, path.replace('\'', "''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variable_names_are_slugs_of_the_family() {
+        assert_eq!(env_var_name("Inter"), "FONT_INTER_DIR");
+        assert_eq!(env_var_name("Noto Sans JP"), "FONT_NOTO_SANS_JP_DIR");
+        assert_eq!(env_var_name("M PLUS 1p"), "FONT_M_PLUS_1P_DIR");
+        assert_eq!(env_var_name("Noto-Sans"), "FONT_NOTO_SANS_DIR");
+        assert_eq!(
+            env_var_name("Zilla Slab's.Highlight"),
+            "FONT_ZILLA_SLAB_S_HIGHLIGHT_DIR"
+        );
+        // The prefix keeps names starting with a digit valid.
+        assert_eq!(env_var_name("42dot Sans"), "FONT_42DOT_SANS_DIR");
+        // Every non-ASCII character becomes an underscore, even the letters.
+        assert_eq!(
+            env_var_name("Crimson Pro Ünïcödé"),
+            "FONT_CRIMSON_PRO__N_C_D__DIR"
+        );
+        assert_eq!(env_var_name("源ノ角"), "FONT_____DIR");
+    }
+
+    #[test]
+    fn families_with_the_same_variable_collide() {
+        let collisions =
+            find_name_collisions(&["Noto Sans", "Inter", "Noto-Sans", "noto sans", "Roboto"]);
+        assert_eq!(
+            collisions,
+            [(
+                "FONT_NOTO_SANS_DIR".to_string(),
+                vec!["Noto Sans", "Noto-Sans", "noto sans"]
+            )]
+        );
+        assert!(find_name_collisions(&["Inter", "Roboto", "Noto Sans"]).is_empty());
+
+        let collisions = find_name_collisions(&["B-B", "A A", "B B", "A_A"]);
+        let names = collisions
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["FONT_A_A_DIR", "FONT_B_B_DIR"]);
+    }
+
+    #[test]
+    fn paths_are_quoted_for_each_shell() {
+        let path = Path::new("/home/me/My Fonts/O'Brien");
+        assert_eq!(
+            render_assignment(Shell::Posix, "FONT_INTER_DIR", path),
+            r"export FONT_INTER_DIR='/home/me/My Fonts/O'\''Brien'"
+        );
+        assert_eq!(
+            render_assignment(Shell::Fish, "FONT_INTER_DIR", path),
+            r"set -gx FONT_INTER_DIR '/home/me/My Fonts/O\'Brien'"
+        );
+        assert_eq!(
+            render_assignment(Shell::Pwsh, "FONT_INTER_DIR", path),
+            "$env:FONT_INTER_DIR = '/home/me/My Fonts/O''Brien'"
+        );
+
+        // Fish is the only shell with escapes in single quotes, so backslashes (like in Windows paths) are escaped too.
+        let path = Path::new(r"C:\Fonts\O'Brien");
+        assert_eq!(
+            render_assignment(Shell::Posix, "FONT_INTER_DIR", path),
+            r"export FONT_INTER_DIR='C:\Fonts\O'\''Brien'"
+        );
+        assert_eq!(
+            render_assignment(Shell::Fish, "FONT_INTER_DIR", path),
+            r"set -gx FONT_INTER_DIR 'C:\\Fonts\\O\'Brien'"
+        );
+        assert_eq!(
+            render_assignment(Shell::Pwsh, "FONT_INTER_DIR", path),
+            r"$env:FONT_INTER_DIR = 'C:\Fonts\O''Brien'"
+        );
+    }
+
+    #[test]
+    fn shells_are_parsed_by_name() {
+        for (name, shell) in [
+            ("posix", Shell::Posix),
+            ("bash", Shell::Posix),
+            ("ZSH", Shell::Posix),
+            ("fish", Shell::Fish),
+            ("PowerShell", Shell::Pwsh),
+            ("pwsh", Shell::Pwsh),
+        ] {
+            assert_eq!(name.parse(), Ok(shell), "{}", name);
+        }
+        assert_eq!("cmd".parse::<Shell>(), Err(()));
+    }
+}
@@ -0,0 +1,580 @@
+use crate::color::*;
+use serde::Serialize;
+use std::{
+    fmt::Write,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    exit_codes::{
+        EXIT_CODE_FILE_IO_ERROR, EXIT_CODE_NET_ERROR, EXIT_CODE_NOT_CACHED,
+        EXIT_CODE_PARTIAL_FAILURE, EXIT_CODE_WARNINGS,
+    },
+    font_manifest::{FileFailure, FileResult, FileStatus},
+    format::{format_bytes, format_size, format_speed},
+    msg,
+    targets::TargetReport,
+    warnings::{Warning, render_warnings},
+};
+
+/// The totals of a fetch, shown at the end of the run.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct RunSummary {
+    pub families_installed: usize,
+    pub families_failed: usize,
+    pub text_files_written: usize,
+    pub font_files_downloaded: usize,
+    pub font_files_from_cache: usize,
+    pub files_skipped: usize,
+    pub failures: usize,
+    /// Files that were not even tried, as the network was down by then. They are not counted as failures.
+    pub files_not_attempted: usize,
+    pub total_bytes: u64,
+    pub elapsed_seconds: f64,
+    /// The slowest downloads (at most `SLOWEST_FILES`), slowest first. Files from the cache aren't downloaded, so they aren't in here.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub slowest_files: Vec<SlowFile>,
+}
+
+/// How many of the slowest downloads the summary shows.
+const SLOWEST_FILES: usize = 3;
+
+/// A download in the summary's list of the slowest ones.
+#[derive(Clone, Debug, Serialize)]
+pub struct SlowFile {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub transfer_seconds: f64,
+    pub bytes_per_second: f64,
+}
+
+/// How long a phase of a fetch took.
+#[derive(Clone, Debug, Serialize)]
+pub struct PhaseTiming {
+    pub phase: &'static str,
+    pub seconds: f64,
+}
+
+/// How long each phase of a fetch took, in order. Recorded with `--timing`.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct PhaseTimings {
+    phases: Vec<PhaseTiming>,
+}
+
+impl PhaseTimings {
+    /// Records that a phase started at `started_at` ended now. Returns now, when the next phase starts.
+    /// A phase recorded again (e.g. for every family) adds up.
+    pub fn record(&mut self, phase: &'static str, started_at: Instant) -> Instant {
+        let now = Instant::now();
+        let seconds = (now - started_at).as_secs_f64();
+        match self.phases.iter_mut().find(|timing| timing.phase == phase) {
+            Some(timing) => timing.seconds += seconds,
+            None => self.phases.push(PhaseTiming { phase, seconds }),
+        }
+        now
+    }
+
+    /// Renders the phases as an aligned table.
+    pub fn render_table(&self) -> String {
+        let label_width = self
+            .phases
+            .iter()
+            .map(|timing| timing.phase.len())
+            .max()
+            .unwrap_or(0);
+
+        let mut table = format!("\n{color_blue}{style_bold}Timing:{color_reset}{style_reset}\n");
+        for timing in &self.phases {
+            let _ = writeln!(
+                table,
+                "  {color_bright_black}{:<label_width$}{color_reset}  {color_white}{:>7.3}s{color_reset}",
+                timing.phase, timing.seconds
+            );
+        }
+        table
+    }
+}
+
+/// A family that was not installed as its manifest could not be fetched, while the others were.
+#[derive(Clone, Debug, Serialize)]
+pub struct FailedFamily {
+    pub family: String,
+    /// The error, with the start of Google's response if there was one.
+    pub error: String,
+}
+
+/// An installed family, with the name it was asked for by and where its files went.
+#[derive(Clone, Debug, Serialize)]
+pub struct FamilySection {
+    /// As given on the command line, e.g. `inter:wght@400`. The family name for families that weren't given by name
+    /// (e.g. all installed ones with update, or the ones in a URL).
+    pub requested: String,
+    pub family: String,
+    /// The dir the files of the family are in, which is the font base dir for families installed together with others.
+    pub dir: PathBuf,
+}
+
+/// Everything that happened during a fetch. The summary at the end of the run, the `--json` output
+/// and the exit code are all computed from it.
+#[derive(Clone, Debug, Serialize)]
+pub struct InstallReport {
+    pub families: Vec<String>,
+    pub sections: Vec<FamilySection>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed_families: Vec<FailedFamily>,
+    pub summary: RunSummary,
+    /// Only with `--timing`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<PhaseTimings>,
+    /// What went wrong without failing the whole run, e.g. invalid fonts skipped with `--ignore-invalid`.
+    pub warnings: Vec<Warning>,
+    pub files: Vec<FileResult>,
+    /// Only with `--also-output`: the files of every target, the font base dir first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetReport>,
+}
+
+impl InstallReport {
+    /// The exit code of the run. Files that failed fail it with the code for what made them fail: the disk first,
+    /// then the network (also if files were not attempted because of it), then files not cached with `--offline`,
+    /// and a partial failure for files that failed on their own (e.g. a 404).
+    /// Part of it failing otherwise (e.g. a skipped family) always fails it, any other warning only with `--deny-warnings`.
+    pub fn exit_code(&self, deny_warnings: bool) -> i32 {
+        let failures = self
+            .files
+            .iter()
+            .filter_map(|file| match &file.status {
+                FileStatus::Failed { failure, .. } => Some(*failure),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let not_attempted = self
+            .files
+            .iter()
+            .any(|file| matches!(file.status, FileStatus::NotAttempted { .. }));
+
+        if failures.contains(&FileFailure::Write) {
+            EXIT_CODE_FILE_IO_ERROR
+        } else if not_attempted || failures.iter().any(|failure| failure.is_network()) {
+            EXIT_CODE_NET_ERROR
+        } else if failures.contains(&FileFailure::NotCached) {
+            EXIT_CODE_NOT_CACHED
+        } else if !failures.is_empty()
+            || self
+                .warnings
+                .iter()
+                .any(|warning| warning.code.is_partial_failure())
+        {
+            EXIT_CODE_PARTIAL_FAILURE
+        } else if deny_warnings && !self.warnings.is_empty() {
+            EXIT_CODE_WARNINGS
+        } else {
+            0
+        }
+    }
+
+    /// Renders the report for `--json`.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Renders the totals, the files that failed with the reasons, the warnings again (so they aren't lost among
+    /// the rest of the output) and the timing.
+    pub fn render_summary(&self) -> String {
+        let mut summary = self.summary.render_table();
+        summary.push_str(&self.render_failed_files());
+        summary.push_str(&render_warnings(&self.warnings));
+        if let Some(timing) = &self.timing {
+            summary.push_str(&timing.render_table());
+        }
+        summary
+    }
+
+    /// Renders the files that couldn't be fetched as a section below the totals. Empty if none failed.
+    fn render_failed_files(&self) -> String {
+        let failed_files = self
+            .files
+            .iter()
+            .filter_map(|file| match &file.status {
+                FileStatus::Failed { reason, .. } => Some((file, reason)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if failed_files.is_empty() {
+            return String::new();
+        }
+        let mut section = format!(
+            "\n{color_red}{style_bold}{}{color_reset}{style_reset}\n",
+            msg!("summary.failed_files", count = failed_files.len())
+        );
+        for (file, reason) in failed_files {
+            let _ = writeln!(
+                section,
+                "  {color_blue}'{}'{color_reset}: {}",
+                file.path.display(),
+                // Errors from reqwest come on their own line, which would break the list.
+                reason.replace('\n', " ")
+            );
+        }
+        section
+    }
+}
+
+impl RunSummary {
+    /// Computes the totals from the results of writing the text files and downloading the font files.
+    /// `families` are the installed ones, `failed_families` the ones that could not be fetched.
+    pub fn from_results(
+        families: &[String],
+        failed_families: &[FailedFamily],
+        text_files: &[FileResult],
+        font_files: &[FileResult],
+        elapsed: Duration,
+    ) -> Self {
+        let count = |results: &[FileResult], status: FileStatus| {
+            results
+                .iter()
+                .filter(|result| result.status == status)
+                .count()
+        };
+        let all_results = || text_files.iter().chain(font_files);
+        let cached_font_files = font_files
+            .iter()
+            .filter(|result| result.status == FileStatus::Written && result.from_cache)
+            .count();
+
+        Self {
+            families_installed: families.len(),
+            families_failed: failed_families.len(),
+            text_files_written: count(text_files, FileStatus::Written),
+            font_files_downloaded: count(font_files, FileStatus::Written) - cached_font_files,
+            font_files_from_cache: cached_font_files,
+            files_skipped: all_results()
+                .filter(|result| result.status == FileStatus::Skipped)
+                .count(),
+            failures: all_results()
+                .filter(|result| matches!(result.status, FileStatus::Failed { .. }))
+                .count(),
+            files_not_attempted: all_results()
+                .filter(|result| matches!(result.status, FileStatus::NotAttempted { .. }))
+                .count(),
+            total_bytes: all_results()
+                .filter(|result| result.status == FileStatus::Written)
+                .map(|result| result.bytes)
+                .sum(),
+            elapsed_seconds: elapsed.as_secs_f64(),
+            slowest_files: slowest_files(font_files),
+        }
+    }
+
+    /// Renders the totals as an aligned table.
+    pub fn render_table(&self) -> String {
+        let rows = [
+            (
+                msg!("summary.families_installed"),
+                self.families_installed.to_string(),
+            ),
+            (
+                msg!("summary.families_failed"),
+                self.families_failed.to_string(),
+            ),
+            (
+                msg!("summary.text_files_written"),
+                self.text_files_written.to_string(),
+            ),
+            (
+                msg!("summary.font_files_downloaded"),
+                self.font_files_downloaded.to_string(),
+            ),
+            (
+                msg!("summary.font_files_from_cache"),
+                self.font_files_from_cache.to_string(),
+            ),
+            (
+                msg!("summary.files_skipped"),
+                self.files_skipped.to_string(),
+            ),
+            (msg!("summary.failures"), self.failures.to_string()),
+            (
+                msg!("summary.files_not_attempted"),
+                self.files_not_attempted.to_string(),
+            ),
+            (msg!("summary.total_bytes"), format_bytes(self.total_bytes)),
+            (
+                msg!("summary.elapsed_time"),
+                format!("{:.1}s", self.elapsed_seconds),
+            ),
+        ];
+        // Translated labels can have non-ASCII characters, which take one column but more bytes.
+        let label_width = rows
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let value_width = rows.iter().map(|(_, value)| value.len()).max().unwrap_or(0);
+
+        let mut table = format!(
+            "\n{color_blue}{style_bold}{}{color_reset}{style_reset}\n",
+            msg!("summary.title")
+        );
+        for (label, value) in rows {
+            let _ = writeln!(
+                table,
+                "  {color_bright_black}{:<label_width$}{color_reset}  {color_white}{:>value_width$}{color_reset}",
+                label, value
+            );
+        }
+
+        if !self.slowest_files.is_empty() {
+            let _ = writeln!(
+                table,
+                "\n{color_blue}{style_bold}{}{color_reset}{style_reset}",
+                msg!("summary.slowest_files")
+            );
+            for file in &self.slowest_files {
+                let _ = writeln!(
+                    table,
+                    "  {color_white}{:>12}{color_reset}  {color_bright_black}{} in {:.2}s{color_reset}  {color_blue}'{}'{color_reset}",
+                    format_speed(file.bytes_per_second),
+                    format_size(file.bytes),
+                    file.transfer_seconds,
+                    file.path.display()
+                );
+            }
+        }
+        table
+    }
+}
+
+/// Finds the slowest downloads, by their speed rather than how long they took, as bigger files take longer anyway.
+fn slowest_files(font_files: &[FileResult]) -> Vec<SlowFile> {
+    let mut downloads = font_files
+        .iter()
+        .filter_map(|result| {
+            Some(SlowFile {
+                path: result.path.clone(),
+                bytes: result.bytes,
+                transfer_seconds: result.transfer_seconds?,
+                bytes_per_second: result.bytes_per_second?,
+            })
+        })
+        .collect::<Vec<_>>();
+    downloads.sort_by(|a, b| a.bytes_per_second.total_cmp(&b.bytes_per_second));
+    downloads.truncate(SLOWEST_FILES);
+    downloads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http::FailureKind, notify::strip_colors, warnings::WarningCode};
+
+    fn file(path: &str, status: FileStatus) -> FileResult {
+        FileResult {
+            family: "Inter".to_string(),
+            path: PathBuf::from(path),
+            status,
+            bytes: 10,
+            from_cache: false,
+            seconds: None,
+            transfer_seconds: None,
+            bytes_per_second: None,
+            original_filename: None,
+            content_type: None,
+        }
+    }
+
+    fn report(files: Vec<FileResult>, warnings: Vec<Warning>) -> InstallReport {
+        InstallReport {
+            families: vec!["Inter".to_string()],
+            sections: Vec::new(),
+            failed_families: Vec::new(),
+            summary: RunSummary::from_results(
+                &["Inter".to_string()],
+                &[],
+                &[],
+                &files,
+                Duration::ZERO,
+            ),
+            timing: None,
+            warnings,
+            files,
+            targets: Vec::new(),
+        }
+    }
+
+    fn warning(code: WarningCode) -> Warning {
+        Warning {
+            code,
+            message: "Something happened.".to_string(),
+        }
+    }
+
+    fn failed(reason: &str, failure: FileFailure) -> FileStatus {
+        FileStatus::Failed {
+            reason: reason.to_string(),
+            failure,
+        }
+    }
+
+    fn not_attempted() -> FileStatus {
+        FileStatus::NotAttempted {
+            reason: "Skipped, as the network seems to be down.".to_string(),
+        }
+    }
+
+    #[test]
+    fn exit_codes() {
+        let written = || file("Inter/a.ttf", FileStatus::Written);
+        let not_found = || {
+            file(
+                "Inter/b.ttf",
+                failed(
+                    "Got status '404'.",
+                    FileFailure::Download(FailureKind::Status(404)),
+                ),
+            )
+        };
+        assert_eq!(report(vec![written()], Vec::new()).exit_code(true), 0);
+        assert_eq!(
+            report(vec![written(), not_found()], Vec::new()).exit_code(false),
+            EXIT_CODE_PARTIAL_FAILURE
+        );
+        assert_eq!(
+            report(vec![written()], vec![warning(WarningCode::HookFailed)]).exit_code(false),
+            EXIT_CODE_PARTIAL_FAILURE
+        );
+        let duplicate = || vec![warning(WarningCode::DuplicateFont)];
+        assert_eq!(report(vec![written()], duplicate()).exit_code(false), 0);
+        assert_eq!(
+            report(vec![written()], duplicate()).exit_code(true),
+            EXIT_CODE_WARNINGS
+        );
+    }
+
+    #[test]
+    fn exit_codes_follow_what_made_the_files_fail() {
+        let exit_code = |statuses: Vec<FileStatus>| {
+            let files = statuses
+                .into_iter()
+                .enumerate()
+                .map(|(index, status)| file(&format!("Inter/{}.ttf", index), status))
+                .collect();
+            report(files, Vec::new()).exit_code(false)
+        };
+        let timeout = || failed("timed out", FileFailure::Download(FailureKind::Timeout));
+        let invalid = || {
+            failed(
+                "Got an empty file.",
+                FileFailure::Download(FailureKind::Other),
+            )
+        };
+        let write = || failed("Failed to create file", FileFailure::Write);
+        let not_cached = || failed("It is not cached.", FileFailure::NotCached);
+        let network_down = || failed("The network seems to be down.", FileFailure::NetworkDown);
+
+        assert_eq!(exit_code(vec![write()]), EXIT_CODE_FILE_IO_ERROR);
+        assert_eq!(exit_code(vec![timeout()]), EXIT_CODE_NET_ERROR);
+        assert_eq!(
+            exit_code(vec![network_down(), not_attempted(), not_attempted()]),
+            EXIT_CODE_NET_ERROR
+        );
+        assert_eq!(exit_code(vec![not_cached()]), EXIT_CODE_NOT_CACHED);
+        assert_eq!(exit_code(vec![invalid()]), EXIT_CODE_PARTIAL_FAILURE);
+        assert_eq!(
+            exit_code(vec![FileStatus::Written, not_attempted()]),
+            EXIT_CODE_NET_ERROR
+        );
+        assert_eq!(
+            exit_code(vec![
+                FileStatus::Skipped,
+                failed(
+                    "Got status '410 Gone'.",
+                    FileFailure::Download(FailureKind::Status(410))
+                )
+            ]),
+            EXIT_CODE_PARTIAL_FAILURE
+        );
+
+        // The disk goes before the network, which goes before the files failing on their own.
+        assert_eq!(
+            exit_code(vec![invalid(), timeout(), write()]),
+            EXIT_CODE_FILE_IO_ERROR
+        );
+        assert_eq!(exit_code(vec![invalid(), timeout()]), EXIT_CODE_NET_ERROR);
+        assert_eq!(
+            exit_code(vec![invalid(), not_cached()]),
+            EXIT_CODE_NOT_CACHED
+        );
+    }
+
+    #[test]
+    fn files_not_attempted_are_not_counted_as_failures() {
+        let report = report(
+            vec![
+                file("Inter/a.ttf", failed("timed out", FileFailure::NetworkDown)),
+                file("Inter/b.ttf", not_attempted()),
+                file("Inter/c.ttf", not_attempted()),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(report.summary.failures, 1);
+        assert_eq!(report.summary.files_not_attempted, 2);
+        let summary = strip_colors(&report.render_summary());
+        assert!(summary.contains("'Inter/a.ttf': timed out"), "{}", summary);
+        assert!(!summary.contains("'Inter/b.ttf'"), "{}", summary);
+    }
+
+    #[test]
+    fn failed_files_are_counted_and_listed_with_the_reason() {
+        let report = report(
+            vec![
+                file("Inter/a.ttf", FileStatus::Written),
+                file(
+                    "Inter/b.ttf",
+                    failed(
+                        "Error:\ntimed out",
+                        FileFailure::Download(FailureKind::Timeout),
+                    ),
+                ),
+            ],
+            Vec::new(),
+        );
+        assert_eq!(report.summary.failures, 1);
+        assert_eq!(report.summary.total_bytes, 10);
+        let summary = strip_colors(&report.render_summary());
+        assert!(
+            summary.contains("'Inter/b.ttf': Error: timed out"),
+            "{}",
+            summary
+        );
+        assert!(!summary.contains("'Inter/a.ttf'"), "{}", summary);
+    }
+
+    #[test]
+    fn failed_files_have_the_reason_next_to_the_status_in_json() {
+        let report = report(
+            vec![
+                file("Inter/a.ttf", FileStatus::Written),
+                file(
+                    "Inter/b.ttf",
+                    failed(
+                        "Got status '404'.",
+                        FileFailure::Download(FailureKind::Status(404)),
+                    ),
+                ),
+                file("Inter/c.ttf", not_attempted()),
+            ],
+            Vec::new(),
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["files"][0]["status"], "written");
+        assert!(json["files"][0].get("reason").is_none());
+        assert_eq!(json["files"][1]["status"], "failed");
+        assert_eq!(json["files"][1]["reason"], "Got status '404'.");
+        assert_eq!(json["files"][1]["failure"], "status_404");
+        assert_eq!(json["files"][2]["status"], "not_attempted");
+        assert_eq!(json["summary"]["files_not_attempted"], 1);
+    }
+}
@@ -0,0 +1,607 @@
+use crate::color::*;
+use serde::Serialize;
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    base_path::{BasePathEnv, Platform},
+    info,
+    options::Options,
+    plan::is_font_file,
+    warnings::{WarningCode, Warnings},
+};
+
+/// The program refreshing fontconfig's font cache.
+const FONT_CACHE_PROGRAM: &str = "fc-cache";
+
+/// Refreshes the OS font cache for the base path, so newly installed fonts show up in apps, unless `--no-refresh` was given.
+/// Failing to refresh the cache is not fatal, the fonts will show up once the cache is refreshed some other way.
+pub fn refresh_font_cache(base_path: &Path, options: &Options, warnings: &mut Warnings) {
+    // Only fontconfig has a cache that needs refreshing. macOS and Windows pick up new fonts on their own.
+    if cfg!(unix) && !cfg!(target_os = "macos") {
+        refresh_font_cache_with(FONT_CACHE_PROGRAM, base_path, options, warnings);
+    }
+}
+
+/// Refreshes the font cache with a program that takes the arguments of `fc-cache`.
+fn refresh_font_cache_with(
+    program: &str,
+    base_path: &Path,
+    options: &Options,
+    warnings: &mut Warnings,
+) {
+    if !options.refresh_font_cache {
+        return;
+    }
+    info!("Refreshing font cache {color_bright_black}({program}){color_reset}...");
+    if let Some(warning) = run_font_cache_refresh(program, base_path) {
+        warnings.warn(WarningCode::FontCacheNotRefreshed, warning);
+    }
+}
+
+/// Runs the program refreshing the font cache (`fc-cache`) for the base path.
+/// Returns what to warn about if it could not be run, or failed.
+fn run_font_cache_refresh(program: &str, base_path: &Path) -> Option<String> {
+    match Command::new(program).arg("-f").arg(base_path).output() {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(format!(
+            "{color_blue}{program}{color_reset} failed with status '{}'! Run {color_blue}'{program} -f'{color_reset} manually for the fonts to show up.",
+            output.status
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => Some(format!(
+            "{color_blue}{program}{color_reset} was not found, skipping font cache refresh."
+        )),
+        Err(e) => Some(format!(
+            "Failed to run {color_blue}{program}{color_reset}! Error:\n{}",
+            e
+        )),
+    }
+}
+
+/// The name of the fontconfig snippet `fonty fontconfig --install` writes into fontconfig's `conf.d` dir.
+pub const FONTCONFIG_SNIPPET_FILENAME: &str = "50-fonty.conf";
+
+/// Escapes the characters that can't appear as-is in XML text.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Renders a fontconfig configuration file that adds the font base dir to the dirs fontconfig scans,
+/// for when it is not inside one of the dirs fontconfig already knows about.
+pub fn fontconfig_snippet(base_path: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<!DOCTYPE fontconfig SYSTEM \"urn:fontconfig:fonts.dtd\">\n",
+            "<!-- Generated by fonty, adds its font base dir to the dirs fontconfig scans for fonts. -->\n",
+            "<fontconfig>\n",
+            "  <dir>{}</dir>\n",
+            "</fontconfig>\n",
+        ),
+        escape_xml(base_path)
+    )
+}
+
+/// What installing the fontconfig snippet did.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SnippetInstall {
+    /// The snippet was already there with the same contents, so nothing was written.
+    AlreadyInstalled,
+    Written,
+}
+
+/// Why the fontconfig snippet could not be installed.
+#[derive(Debug)]
+pub enum SnippetInstallError {
+    /// A file with other contents is in the way, and `force` was not given.
+    Exists,
+    Io(std::io::Error),
+}
+
+/// Writes the fontconfig snippet to `snippet_path`, creating its dir if needed.
+/// An existing file with other contents is only overwritten with `force`.
+pub fn install_fontconfig_snippet(
+    snippet_path: &Path,
+    snippet: &str,
+    force: bool,
+) -> Result<SnippetInstall, SnippetInstallError> {
+    match fs::read_to_string(snippet_path) {
+        Ok(existing) if existing == snippet => return Ok(SnippetInstall::AlreadyInstalled),
+        Ok(_) if !force => return Err(SnippetInstallError::Exists),
+        _ => {}
+    }
+
+    // Write to a temporary file first, so fontconfig never reads a half-written snippet.
+    let conf_dir = snippet_path.parent().unwrap_or(Path::new("."));
+    let mut temporary_name = snippet_path.file_name().unwrap_or_default().to_owned();
+    temporary_name.push(".tmp");
+    let temporary_path = conf_dir.join(temporary_name);
+    fs::create_dir_all(conf_dir)
+        .and_then(|_| fs::write(&temporary_path, snippet))
+        .and_then(|_| fs::rename(&temporary_path, snippet_path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary_path);
+            SnippetInstallError::Io(e)
+        })?;
+    Ok(SnippetInstall::Written)
+}
+
+/// The name a font file is listed under in the Windows fonts registry key, like `Inter-Regular (TrueType)`. Windows
+/// calls `.otf` files OpenType fonts and `.ttf` files TrueType fonts. Other files aren't registered, so they get no name.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn font_registry_name(font_file: &Path) -> Option<String> {
+    let kind = match font_file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("ttf") => "TrueType",
+        Some("otf") => "OpenType",
+        _ => return None,
+    };
+    let font_name = font_file.file_stem().and_then(|stem| stem.to_str())?;
+    Some(format!("{} ({})", font_name, kind))
+}
+
+/// Registers installed font files with the OS, so applications pick them up.
+/// On Windows, per-user fonts are only visible to applications when they are listed in the per-user registry key.
+#[cfg(windows)]
+pub fn register_fonts(font_files: &[PathBuf]) {
+    const FONTS_REGISTRY_KEY: &str = r"HKCU\Software\Microsoft\Windows NT\CurrentVersion\Fonts";
+
+    for font_file in font_files {
+        let Some(font_name) = font_registry_name(font_file) else {
+            continue;
+        };
+
+        let status = Command::new("reg")
+            .args([
+                "add",
+                FONTS_REGISTRY_KEY,
+                "/v",
+                &font_name,
+                "/t",
+                "REG_SZ",
+                "/d",
+            ])
+            .arg(font_file)
+            .arg("/f")
+            .output();
+
+        match status {
+            Ok(output) if output.status.success() => (),
+            Ok(output) => println!(
+                "{color_yellow}WARN:{color_reset} Failed to register font {color_blue}'{}'{color_reset}! Got status '{}'.",
+                font_file.display(),
+                output.status
+            ),
+            Err(e) => println!(
+                "{color_yellow}WARN:{color_reset} Failed to register font {color_blue}'{}'{color_reset}! Error:\n{}",
+                font_file.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Registers installed font files with the OS, so applications pick them up.
+/// Only Windows needs fonts to be registered, other platforms find them by scanning the font dirs.
+#[cfg(not(windows))]
+pub fn register_fonts(_font_files: &[PathBuf]) {
+    println!(
+        "{color_yellow}WARN:{color_reset} {color_blue}--register{color_reset} only does something on Windows, skipping."
+    );
+}
+
+/// The standard font dirs of a platform (for the current user and system-wide), which other programs install fonts into too.
+/// Dirs built from unset environment variables are left out, the dirs don't have to exist.
+pub fn standard_font_dirs(env: &BasePathEnv, platform: Platform) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    match platform {
+        Platform::Unix => {
+            match (&env.xdg_data_home, &env.home) {
+                (Some(xdg_data_home), _) => dirs.push(Path::new(xdg_data_home).join("fonts")),
+                (None, Some(home)) => dirs.push(Path::new(home).join(".local/share/fonts")),
+                (None, None) => {}
+            }
+            // As in the XDG base directory spec, an unset or empty XDG_DATA_DIRS means the default.
+            let data_dirs = env
+                .vars
+                .get("XDG_DATA_DIRS")
+                .map(|data_dirs| data_dirs.to_string_lossy().into_owned())
+                .filter(|data_dirs| !data_dirs.is_empty())
+                .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+            dirs.extend(
+                data_dirs
+                    .split(':')
+                    .filter(|data_dir| !data_dir.is_empty())
+                    .map(|data_dir| Path::new(data_dir).join("fonts")),
+            );
+        }
+        Platform::MacOs => {
+            if let Some(home) = &env.home {
+                dirs.push(Path::new(home).join("Library/Fonts"));
+            }
+            dirs.push(PathBuf::from("/Library/Fonts"));
+        }
+        Platform::Windows => {
+            if let Some(local_app_data) = &env.local_app_data {
+                dirs.push(
+                    Path::new(local_app_data)
+                        .join("Microsoft")
+                        .join("Windows")
+                        .join("Fonts"),
+                );
+            }
+            if let Some(windows_dir) = env.vars.get("WINDIR") {
+                dirs.push(Path::new(windows_dir).join("Fonts"));
+            }
+        }
+    }
+    // XDG_DATA_DIRS may list a dir twice.
+    let mut seen = Vec::new();
+    dirs.retain(|dir| {
+        let new = !seen.contains(dir);
+        seen.push(dir.clone());
+        new
+    });
+    dirs
+}
+
+/// A font installed in a font dir: a dir of font files (usually a family), or a single font file.
+#[derive(Clone, Debug, Serialize)]
+pub struct FontDirEntry {
+    /// The name of the dir or file.
+    pub name: String,
+    pub path: PathBuf,
+    /// How many font files there are, not counting licenses and the like.
+    pub font_files: usize,
+    pub bytes: u64,
+    /// Whether fonty installed it, i.e. whether it is in the registry.
+    pub managed: bool,
+}
+
+/// Counts the font files in a dir (recursively) and their size.
+fn count_font_files(dir: &Path) -> (usize, u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return (0, 0);
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                count_font_files(&path)
+            } else if is_font_file(&entry.file_name().to_string_lossy()) {
+                (1, entry.metadata().map_or(0, |metadata| metadata.len()))
+            } else {
+                (0, 0)
+            }
+        })
+        .fold((0, 0), |(files, bytes), (more_files, more_bytes)| {
+            (files + more_files, bytes + more_bytes)
+        })
+}
+
+/// Lists the fonts directly inside a font dir: the dirs with font files in them, and the loose font files, sorted by name.
+/// Hidden entries are left out. `is_managed` says which paths fonty installed.
+pub fn scan_font_dir(dir: &Path, is_managed: impl Fn(&Path) -> bool) -> Vec<FontDirEntry> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut font_dir_entries = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (font_files, bytes) = if path.is_dir() {
+                count_font_files(&path)
+            } else if is_font_file(&name) {
+                (1, entry.metadata().map_or(0, |metadata| metadata.len()))
+            } else {
+                (0, 0)
+            };
+            (font_files > 0).then(|| FontDirEntry {
+                managed: is_managed(&path),
+                name,
+                path,
+                font_files,
+                bytes,
+            })
+        })
+        .collect::<Vec<_>>();
+    font_dir_entries.sort_by_key(|entry| entry.name.to_lowercase());
+    font_dir_entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, ffi::OsString};
+
+    use super::*;
+    use crate::{cli::Cli, config::Config, options::resolve_options, testing::TestDir};
+
+    fn env(vars: &[(&str, &str)]) -> BasePathEnv {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), OsString::from(value)))
+            .collect::<HashMap<_, _>>();
+        BasePathEnv {
+            home: vars.get("HOME").cloned(),
+            xdg_data_home: vars.get("XDG_DATA_HOME").cloned(),
+            local_app_data: vars.get("LOCALAPPDATA").cloned(),
+            vars,
+            ..BasePathEnv::default()
+        }
+    }
+
+    #[test]
+    fn fonts_are_registered_as_truetype_or_opentype_by_their_extension() {
+        assert_eq!(
+            font_registry_name(Path::new("fonts/Inter/Inter-Regular.ttf")).as_deref(),
+            Some("Inter-Regular (TrueType)")
+        );
+        assert_eq!(
+            font_registry_name(Path::new("fonts/Inter/Inter-Bold.OTF")).as_deref(),
+            Some("Inter-Bold (OpenType)")
+        );
+        assert_eq!(
+            font_registry_name(Path::new("fonts/Inter/Inter-Regular.woff2")),
+            None
+        );
+        assert_eq!(font_registry_name(Path::new("fonts/Inter/OFL.txt")), None);
+    }
+
+    // Runs `true` and `false` as stand-ins for `fc-cache`, which only exist on Unix.
+    #[cfg(unix)]
+    #[test]
+    fn failing_font_cache_refreshes_are_warned_about() {
+        let base_path = Path::new("/nonexistent/fonts");
+        assert_eq!(run_font_cache_refresh("true", base_path), None);
+
+        let warning = run_font_cache_refresh("fonty-test-no-such-fc-cache", base_path).unwrap();
+        assert!(warning.contains("was not found"), "{}", warning);
+
+        let warning = run_font_cache_refresh("false", base_path).unwrap();
+        assert!(
+            warning.contains("failed with status 'exit status: 1'"),
+            "{}",
+            warning
+        );
+        assert!(warning.contains("'false -f'"), "{}", warning);
+    }
+
+    /// The options of a command line, like `fetch Roboto --no-refresh`.
+    fn options(args: &[&str]) -> Options {
+        let cli = Cli::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+        resolve_options(&cli, &HashMap::new(), &Config::default()).unwrap()
+    }
+
+    // A fake `fc-cache` that records the arguments it was run with, one per line.
+    #[cfg(unix)]
+    #[test]
+    fn the_font_cache_is_refreshed_unless_no_refresh_is_given() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_dir = TestDir::new("fake-fc-cache");
+        let program = test_dir.path.join("fc-cache");
+        let argv = test_dir.path.join("argv");
+        fs::write(
+            &program,
+            format!("#!/bin/sh\nprintf '%s\\n' \"$@\" > '{}'\n", argv.display()),
+        )
+        .unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let base_path = test_dir.path.join("fonts");
+        let program = program.to_str().unwrap();
+
+        let mut warnings = Warnings::default();
+        refresh_font_cache_with(
+            program,
+            &base_path,
+            &options(&["fetch", "Roboto", "--no-refresh"]),
+            &mut warnings,
+        );
+        assert!(!argv.exists());
+
+        refresh_font_cache_with(
+            program,
+            &base_path,
+            &options(&["fetch", "Roboto"]),
+            &mut warnings,
+        );
+        assert_eq!(
+            fs::read_to_string(&argv).unwrap(),
+            format!("-f\n{}\n", base_path.display())
+        );
+        assert!(warnings.is_empty());
+
+        // A failing refresh ends up in the warnings of the run.
+        refresh_font_cache_with(
+            "false",
+            &base_path,
+            &options(&["fetch", "Roboto"]),
+            &mut warnings,
+        );
+        let warnings = warnings.into_vec();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::FontCacheNotRefreshed);
+    }
+
+    #[test]
+    fn snippets_match_the_golden_files() {
+        assert_eq!(
+            fontconfig_snippet("/home/me/.local/share/fonts"),
+            include_str!("../tests/fixtures/fontconfig/plain.conf")
+        );
+        assert_eq!(
+            fontconfig_snippet("/home/a&b/<fonts>/\"it's\""),
+            include_str!("../tests/fixtures/fontconfig/escaped.conf")
+        );
+    }
+
+    #[test]
+    fn snippets_are_only_overwritten_with_force() {
+        let dir = TestDir::new("fontconfig-snippet");
+        let snippet_path = dir.path.join("conf.d").join(FONTCONFIG_SNIPPET_FILENAME);
+        let snippet = fontconfig_snippet("/fonts");
+
+        // The conf.d dir is created when missing.
+        assert_eq!(
+            install_fontconfig_snippet(&snippet_path, &snippet, false).unwrap(),
+            SnippetInstall::Written
+        );
+        assert_eq!(fs::read_to_string(&snippet_path).unwrap(), snippet);
+        assert_eq!(
+            install_fontconfig_snippet(&snippet_path, &snippet, false).unwrap(),
+            SnippetInstall::AlreadyInstalled
+        );
+
+        fs::write(&snippet_path, "<fontconfig>mine</fontconfig>").unwrap();
+        assert!(matches!(
+            install_fontconfig_snippet(&snippet_path, &snippet, false),
+            Err(SnippetInstallError::Exists)
+        ));
+        assert_eq!(
+            fs::read_to_string(&snippet_path).unwrap(),
+            "<fontconfig>mine</fontconfig>"
+        );
+
+        assert_eq!(
+            install_fontconfig_snippet(&snippet_path, &snippet, true).unwrap(),
+            SnippetInstall::Written
+        );
+        assert_eq!(fs::read_to_string(&snippet_path).unwrap(), snippet);
+        // No temporary file is left behind.
+        assert_eq!(
+            fs::read_dir(snippet_path.parent().unwrap())
+                .unwrap()
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn failed_snippet_writes_are_errors() {
+        let dir = TestDir::new("fontconfig-snippet-blocked");
+        // A file where the conf.d dir should be.
+        fs::write(dir.path.join("conf.d"), "").unwrap();
+        let snippet_path = dir.path.join("conf.d").join(FONTCONFIG_SNIPPET_FILENAME);
+        assert!(matches!(
+            install_fontconfig_snippet(&snippet_path, &fontconfig_snippet("/fonts"), true),
+            Err(SnippetInstallError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn unix_font_dirs_follow_the_xdg_base_dirs() {
+        assert_eq!(
+            standard_font_dirs(&env(&[("HOME", "/home/me")]), Platform::Unix),
+            [
+                PathBuf::from("/home/me/.local/share/fonts"),
+                PathBuf::from("/usr/local/share/fonts"),
+                PathBuf::from("/usr/share/fonts"),
+            ]
+        );
+        assert_eq!(
+            standard_font_dirs(
+                &env(&[
+                    ("HOME", "/home/me"),
+                    ("XDG_DATA_HOME", "/data"),
+                    ("XDG_DATA_DIRS", "/opt/share::/usr/share:/opt/share"),
+                ]),
+                Platform::Unix
+            ),
+            [
+                PathBuf::from("/data/fonts"),
+                PathBuf::from("/opt/share/fonts"),
+                PathBuf::from("/usr/share/fonts"),
+            ]
+        );
+        // An empty XDG_DATA_DIRS means the default, and without a home there is no user dir.
+        assert_eq!(
+            standard_font_dirs(&env(&[("XDG_DATA_DIRS", "")]), Platform::Unix),
+            [
+                PathBuf::from("/usr/local/share/fonts"),
+                PathBuf::from("/usr/share/fonts"),
+            ]
+        );
+    }
+
+    #[test]
+    fn macos_and_windows_font_dirs() {
+        let env = env(&[
+            ("HOME", "/Users/me"),
+            ("XDG_DATA_HOME", "/data"),
+            ("LOCALAPPDATA", "C:/Users/me/AppData/Local"),
+            ("WINDIR", "C:/Windows"),
+        ]);
+        assert_eq!(
+            standard_font_dirs(&env, Platform::MacOs),
+            [
+                PathBuf::from("/Users/me/Library/Fonts"),
+                PathBuf::from("/Library/Fonts"),
+            ]
+        );
+        assert_eq!(
+            standard_font_dirs(&env, Platform::Windows),
+            [
+                Path::new("C:/Users/me/AppData/Local")
+                    .join("Microsoft")
+                    .join("Windows")
+                    .join("Fonts"),
+                Path::new("C:/Windows").join("Fonts"),
+            ]
+        );
+        assert_eq!(
+            standard_font_dirs(&BasePathEnv::default(), Platform::MacOs),
+            [PathBuf::from("/Library/Fonts")]
+        );
+    }
+
+    #[test]
+    fn font_dir_entries_are_marked_as_managed_or_foreign() {
+        let dir = TestDir::new("scan-font-dir");
+        fs::create_dir_all(dir.path.join("Roboto/static")).unwrap();
+        fs::write(dir.path.join("Roboto/static/Roboto-Regular.ttf"), "font").unwrap();
+        fs::write(dir.path.join("Roboto/OFL.txt"), "license").unwrap();
+        fs::create_dir(dir.path.join("Foreign")).unwrap();
+        fs::write(dir.path.join("Foreign/Foreign.otf"), "font!").unwrap();
+        fs::write(dir.path.join("Loose.ttf"), "loose").unwrap();
+        // Dirs without fonts and fonty's own dot-dirs are left out.
+        fs::create_dir(dir.path.join("Empty")).unwrap();
+        fs::create_dir(dir.path.join(".backups")).unwrap();
+        fs::write(dir.path.join(".backups/Old.ttf"), "old").unwrap();
+
+        let entries = scan_font_dir(&dir.path, |path| path.ends_with("Roboto"));
+        let summary = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.name.as_str(),
+                    entry.font_files,
+                    entry.bytes,
+                    entry.managed,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("Foreign", 1, 5, false),
+                ("Loose.ttf", 1, 5, false),
+                ("Roboto", 1, 4, true),
+            ]
+        );
+    }
+}
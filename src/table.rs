@@ -0,0 +1,73 @@
+use crate::color::*;
+
+/// A table for the terminal: a header row, then one row per record, with the columns padded to line up.
+/// Cells are measured in characters, so they should not contain color codes.
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    /// The columns padded on the left, like numbers.
+    right_aligned: Vec<usize>,
+}
+
+impl Table {
+    /// Starts a table with its header row.
+    pub fn new(header: &[&str]) -> Self {
+        Self {
+            header: header.iter().map(|title| title.to_string()).collect(),
+            rows: Vec::new(),
+            right_aligned: Vec::new(),
+        }
+    }
+
+    /// Aligns a column (counting from 0) to the right.
+    pub fn align_right(mut self, column: usize) -> Self {
+        self.right_aligned.push(column);
+        self
+    }
+
+    /// Adds a row. Every row should have as many cells as the header.
+    pub fn row<S: AsRef<str>>(&mut self, cells: &[S]) {
+        self.rows
+            .push(cells.iter().map(|cell| cell.as_ref().to_string()).collect());
+    }
+
+    /// The table, indented by two spaces like the other listings, with the header in gray.
+    pub fn render(&self) -> String {
+        let mut widths = self
+            .header
+            .iter()
+            .map(|title| title.chars().count())
+            .collect::<Vec<_>>();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let render_row = |cells: &[String]| {
+            let cells = cells
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, &width))| {
+                    if self.right_aligned.contains(&column) {
+                        format!("{:>width$}", cell)
+                    } else {
+                        format!("{:<width$}", cell)
+                    }
+                })
+                .collect::<Vec<_>>();
+            format!("  {}", cells.join("  ").trim_end())
+        };
+
+        let mut output = format!(
+            "{color_bright_black}{}{color_reset}\n",
+            render_row(&self.header)
+        );
+        for row in &self.rows {
+            output.push_str(&render_row(row));
+            output.push('\n');
+        }
+        output
+    }
+}
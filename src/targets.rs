@@ -0,0 +1,96 @@
+//! Additional output targets, given with `--also-output`. The files are only downloaded once, into the font base dir,
+//! and then hardlinked (or copied, where that's not possible) into every target, with the same layout.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    base_path::{BasePathEnv, BasePathError, expand_path_vars, normalize_path},
+    font_manifest::{FileResult, FileStatus},
+};
+
+/// A file that could not be put into a target.
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// The files put into a target (or installed into the font base dir, for the primary one), for the JSON report.
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetReport {
+    pub dir: PathBuf,
+    pub primary: bool,
+    pub files: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<TargetFailure>,
+}
+
+/// Resolves the dirs given with `--also-output` like the font base dir: `~` and variables are expanded,
+/// and the path is made absolute. A target that is the font base dir itself is left out.
+pub fn resolve_targets(
+    values: &[&str],
+    env: &BasePathEnv,
+    base_path: &Path,
+) -> Result<Vec<PathBuf>, BasePathError> {
+    let mut targets: Vec<PathBuf> = Vec::new();
+    for value in values {
+        let path = PathBuf::from(expand_path_vars(value, env)?);
+        let path = normalize_path(&std::path::absolute(&path).unwrap_or(path));
+        if path != base_path && !targets.contains(&path) {
+            targets.push(path);
+        }
+    }
+    Ok(targets)
+}
+
+/// Hardlinks a file to `destination`, replacing what is there. Falls back to copying it, e.g. across filesystems.
+fn link_or_copy(source: &Path, destination: &Path) -> io::Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::remove_file(destination) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::hard_link(source, destination).or_else(|_| fs::copy(source, destination).map(|_| ()))
+}
+
+/// Puts the installed files (all results that didn't fail) into a target, at the same paths relative to it as
+/// they have relative to the font base dir. Returns the results for the target, and the files that failed.
+pub fn copy_to_target(
+    base_path: &Path,
+    target: &Path,
+    results: &[FileResult],
+) -> (Vec<FileResult>, Vec<TargetFailure>) {
+    let mut copied = Vec::new();
+    let mut failed = Vec::new();
+    for result in results.iter().filter(|result| !result.status.is_failed()) {
+        // Everything is installed inside the font base dir, but a path outside of it must not end up replacing itself.
+        let Ok(relative_path) = result.path.strip_prefix(base_path) else {
+            failed.push(TargetFailure {
+                path: result.path.clone(),
+                error: "not inside the font base dir".to_string(),
+            });
+            continue;
+        };
+        let destination = target.join(relative_path);
+        match link_or_copy(&result.path, &destination) {
+            Ok(()) => copied.push(FileResult {
+                bytes: fs::metadata(&destination).map_or(result.bytes, |metadata| metadata.len()),
+                path: destination,
+                status: FileStatus::Written,
+                ..result.clone()
+            }),
+            Err(e) => failed.push(TargetFailure {
+                path: destination,
+                error: e.to_string(),
+            }),
+        }
+    }
+    (copied, failed)
+}
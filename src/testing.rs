@@ -0,0 +1,264 @@
+//! Helpers shared by the tests of several modules.
+
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use reqwest::{
+    ResponseBuilderExt,
+    blocking::{Client, RequestBuilder, Response},
+    redirect::Policy,
+};
+use url::Url;
+
+use crate::http::HttpFetcher;
+
+/// How many test dirs were made, which keeps the dirs of tests running at the same time apart.
+static TEST_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// A new, empty temporary dir, which is removed again when the test is done.
+pub struct TestDir {
+    pub path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "fonty-{}-{}-{}",
+            name,
+            std::process::id(),
+            TEST_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A request the mock server got.
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub method: String,
+    /// The path with the query, e.g. `/css2?family=Inter`.
+    pub path: String,
+    /// The headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+}
+
+impl MockRequest {
+    /// Gets the value of a header by its (lowercase) name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A response of the mock server.
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl MockResponse {
+    /// A `200 OK` with a body.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// An empty response with a status, like `404`.
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// A `302 Found` to another URL.
+    pub fn redirect(location: &str) -> Self {
+        Self::status(302).header("Location", location)
+    }
+
+    /// Adds a header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// The function answering the requests of a mock server.
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+/// An HTTP/1.1 server on a free port of 127.0.0.1, which answers every request with a handler. Connections are kept
+/// alive like by a real server, so tests can count how many were opened. It runs until the test process exits.
+pub struct MockServer {
+    addr: SocketAddr,
+    connections: Arc<AtomicUsize>,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub fn start(handler: impl Fn(&MockRequest) -> MockResponse + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = Self {
+            addr: listener.local_addr().unwrap(),
+            connections: Arc::new(AtomicUsize::new(0)),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        };
+        let handler: Arc<Handler> = Arc::new(handler);
+        let (connections, requests) = (server.connections.clone(), server.requests.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                connections.fetch_add(1, Ordering::Relaxed);
+                let (handler, requests) = (handler.clone(), requests.clone());
+                thread::spawn(move || serve_connection(stream, &*handler, &requests));
+            }
+        });
+        server
+    }
+
+    /// The URL of a path on the server, like `http://127.0.0.1:1234/a.ttf`.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// The URL of a path on the server by another host name (`localhost`), which a client treats as another host.
+    pub fn localhost_url(&self, path: &str) -> String {
+        format!("http://localhost:{}{}", self.addr.port(), path)
+    }
+
+    /// How many connections were opened to the server.
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    /// The requests the server got, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Answers the requests on a connection, until the client closes it.
+fn serve_connection(stream: TcpStream, handler: &Handler, requests: &Mutex<Vec<MockRequest>>) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return;
+        };
+        let mut request = MockRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers: Vec::new(),
+        };
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let Some((name, value)) = line.trim_end().split_once(':') else {
+                break;
+            };
+            request
+                .headers
+                .push((name.to_lowercase(), value.trim().to_string()));
+        }
+        let body_length = request
+            .header("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; body_length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+
+        requests.lock().unwrap().push(request.clone());
+        let response = handler(&request);
+        let mut head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\n",
+            response.status,
+            response.body.len()
+        );
+        for (name, value) in &response.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let written = writer.write_all(head.as_bytes()).and_then(|_| {
+            if request.method == "HEAD" {
+                Ok(())
+            } else {
+                writer.write_all(&response.body)
+            }
+        });
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+/// Sends the requests made through the shared clients to a mock server, by their path and query, so code fetching from
+/// Google Fonts (`https://fonts.google.com/...`) can be run against one. The responses keep the URL that was asked for.
+pub struct MockServerFetcher {
+    base_url: String,
+    client: Client,
+}
+
+impl MockServerFetcher {
+    pub fn new(server: &MockServer) -> Self {
+        Self {
+            base_url: server.url(""),
+            client: Client::builder().redirect(Policy::none()).build().unwrap(),
+        }
+    }
+}
+
+impl HttpFetcher for MockServerFetcher {
+    fn send(&self, method: &str, url: &str, _request: RequestBuilder) -> reqwest::Result<Response> {
+        let url = Url::parse(url).expect("requests have valid URLs");
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let method =
+            reqwest::Method::from_bytes(method.as_bytes()).expect("requests have valid methods");
+        let served = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .send()?;
+        let mut response = http::Response::builder().status(served.status()).url(url);
+        for (name, value) in served.headers() {
+            response = response.header(name, value);
+        }
+        let body = served.bytes()?.to_vec();
+        Ok(response
+            .body(body)
+            .expect("served responses are valid")
+            .into())
+    }
+}
@@ -0,0 +1,367 @@
+//! What `fonty update` changes in each family, worked out from the registry and the new manifest before anything is written.
+
+use crate::color::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    download_cache::DownloadCache,
+    font_manifest::FontManifest,
+    format::{format_size, n_files},
+    plan::{PlannedFile, PlannedSource, is_font_file},
+    registry::Registry,
+    sfnt::font_version,
+};
+
+/// A file of a family that is added, removed or replaced by an update. Paths are relative to the font base dir.
+#[derive(Clone, Debug)]
+pub enum FileChange {
+    Added {
+        path: PathBuf,
+        bytes: Option<u64>,
+    },
+    /// A file that is installed, but no longer in the manifest.
+    Removed {
+        path: PathBuf,
+        bytes: u64,
+    },
+    Changed {
+        path: PathBuf,
+        old_bytes: u64,
+        new_bytes: Option<u64>,
+        old_version: Option<String>,
+        new_version: Option<String>,
+    },
+}
+
+/// The changes an update makes to one family.
+#[derive(Clone, Debug)]
+pub struct FamilyDiff {
+    pub family: String,
+    pub changes: Vec<FileChange>,
+    pub unchanged: usize,
+    /// Installed files that are still in the manifest, but whose new contents aren't known (not in the download cache),
+    /// so they can't be compared. They don't count as changes.
+    pub unknown: usize,
+}
+
+/// The new contents of a planned file, if they are known without downloading: embedded in the manifest, or in the download cache.
+fn new_contents(
+    font_manifest: &FontManifest,
+    planned_file: &PlannedFile,
+    cache: Option<&DownloadCache>,
+) -> Option<Vec<u8>> {
+    match &planned_file.source {
+        PlannedSource::Embedded => font_manifest
+            .embedded_contents(&planned_file.filename)
+            .map(|contents| contents.as_bytes().to_vec()),
+        PlannedSource::Download { url } => cache?.load_blob(url),
+    }
+}
+
+/// The version of a font file from its contents. Other files have none.
+fn file_version(path: &Path, contents: &[u8]) -> Option<String> {
+    is_font_file(&path.to_string_lossy())
+        .then(|| font_version(contents))
+        .flatten()
+}
+
+/// Compares the installed files of each family with the files of the new manifest.
+/// Installed files whose new contents are not known (not cached yet) are counted as unknown instead of changed.
+pub fn diff_update(
+    families: &[String],
+    registry: &Registry,
+    font_manifest: &FontManifest,
+    base_path: &Path,
+    cache: Option<&DownloadCache>,
+) -> Vec<FamilyDiff> {
+    let planned_files = font_manifest.planned_files(base_path);
+    families
+        .iter()
+        .map(|family| {
+            let installed_files = registry
+                .find(family)
+                .map(|entry| entry.files.as_slice())
+                .unwrap_or_default();
+            let mut diff = FamilyDiff {
+                family: family.clone(),
+                changes: Vec::new(),
+                unchanged: 0,
+                unknown: 0,
+            };
+
+            let mut new_paths = Vec::new();
+            for planned_file in planned_files.iter().filter(|file| &file.family == family) {
+                let path = planned_file
+                    .path
+                    .strip_prefix(base_path)
+                    .unwrap_or(&planned_file.path)
+                    .to_path_buf();
+                let contents = new_contents(font_manifest, planned_file, cache);
+                let new_bytes = contents.as_ref().map(|contents| contents.len() as u64);
+                match installed_files.iter().find(|file| file.path == path) {
+                    None => diff.changes.push(FileChange::Added {
+                        path: path.clone(),
+                        bytes: new_bytes,
+                    }),
+                    Some(installed_file) => {
+                        let old_contents = fs::read(&planned_file.path).ok();
+                        if contents.is_none() {
+                            diff.unknown += 1;
+                        } else if old_contents == contents {
+                            diff.unchanged += 1;
+                        } else {
+                            diff.changes.push(FileChange::Changed {
+                                // The file on disk may have changed since it was installed.
+                                old_bytes: old_contents
+                                    .as_ref()
+                                    .map_or(installed_file.bytes, |contents| contents.len() as u64),
+                                old_version: old_contents
+                                    .and_then(|contents| file_version(&path, &contents)),
+                                new_version: contents
+                                    .and_then(|contents| file_version(&path, &contents)),
+                                path: path.clone(),
+                                new_bytes,
+                            });
+                        }
+                    }
+                }
+                new_paths.push(path);
+            }
+            for installed_file in installed_files {
+                if !new_paths.contains(&installed_file.path) {
+                    diff.changes.push(FileChange::Removed {
+                        path: installed_file.path.clone(),
+                        bytes: installed_file.bytes,
+                    });
+                }
+            }
+            diff
+        })
+        .collect()
+}
+
+/// Describes a size that may not be known yet.
+fn format_new_size(bytes: Option<u64>) -> String {
+    bytes.map_or("?".to_string(), format_size)
+}
+
+/// Prints the changes of every family: added files in green, removed ones in red and changed ones in yellow.
+pub fn print_update_diff(diffs: &[FamilyDiff]) {
+    for diff in diffs {
+        println!(
+            "{color_blue}{style_bold}Changes to '{}':{color_reset}{style_reset}",
+            diff.family
+        );
+        for change in &diff.changes {
+            match change {
+                FileChange::Added { path, bytes } => println!(
+                    "  {color_green}+ {}{color_reset} {color_bright_black}({}){color_reset}",
+                    path.display(),
+                    format_new_size(*bytes)
+                ),
+                FileChange::Removed { path, bytes } => println!(
+                    "  {color_red}- {}{color_reset} {color_bright_black}({}){color_reset}",
+                    path.display(),
+                    format_size(*bytes)
+                ),
+                FileChange::Changed {
+                    path,
+                    old_bytes,
+                    new_bytes,
+                    old_version,
+                    new_version,
+                } => {
+                    let versions = match (old_version, new_version) {
+                        (Some(old_version), Some(new_version)) if old_version != new_version => {
+                            format!(", v{} → v{}", old_version, new_version)
+                        }
+                        _ => String::new(),
+                    };
+                    println!(
+                        "  {color_yellow}~ {}{color_reset} {color_bright_black}({} → {}{}){color_reset}",
+                        path.display(),
+                        format_size(*old_bytes),
+                        format_new_size(*new_bytes),
+                        versions
+                    );
+                }
+            }
+        }
+        if diff.unchanged > 0 {
+            println!(
+                "  {color_bright_black}{} unchanged{color_reset}",
+                n_files(diff.unchanged)
+            );
+        }
+        if diff.unknown > 0 {
+            println!(
+                "  {color_bright_black}{} not compared (not in the download cache){color_reset}",
+                n_files(diff.unknown)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        font_manifest::parse_manifest,
+        http::strip_anti_xssi_prefix,
+        registry::{RegistryEntry, RegistryFile},
+        testing::TestDir,
+    };
+
+    const VARIABLE_URL: &str = "https://fonts.gstatic.com/s/robotomono/v23/L0xuDF4xlVMF-BfR8bXMIhJHg45mwgGEFl0_3vqPQ--5Ip2sSQ.ttf";
+    const ITALIC_URL: &str = "https://fonts.gstatic.com/s/robotomono/v23/L0xTDF4xlVMF-BfR8bXMIjhOsXG-q2oeuFoqFrlnAIe2Imhk1T8rbociImtElOUlYIw.ttf";
+
+    /// The manifest of Roboto Mono, from its fixture.
+    fn roboto_mono() -> FontManifest {
+        let fixture: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/roboto_mono/0001.json")).unwrap();
+        let payload = fixture["body"].as_str().unwrap();
+        let mut font_manifest = parse_manifest(strip_anti_xssi_prefix(payload).unwrap()).unwrap();
+        font_manifest.set_family("Roboto Mono");
+        font_manifest
+    }
+
+    /// Installs files into the font base dir, and records them as Roboto Mono.
+    fn install(base_path: &Path, files: &[(&str, &str)]) -> Registry {
+        let mut registry = Registry::default();
+        registry.insert(RegistryEntry {
+            name: "Roboto Mono".to_string(),
+            installed_at: None,
+            fonty_version: None,
+            files: files
+                .iter()
+                .map(|(path, contents)| {
+                    let full_path = base_path.join(path);
+                    fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+                    fs::write(&full_path, contents).unwrap();
+                    RegistryFile {
+                        path: PathBuf::from(path),
+                        bytes: contents.len() as u64,
+                        original_filename: None,
+                    }
+                })
+                .collect(),
+            version: None,
+            options: None,
+            tags: Vec::new(),
+        });
+        registry
+    }
+
+    fn changed_paths(diff: &FamilyDiff) -> Vec<String> {
+        diff.changes
+            .iter()
+            .filter_map(|change| match change {
+                FileChange::Changed { path, .. } => Some(path.display().to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn files_are_added_removed_changed_or_unchanged() {
+        let test_dir = TestDir::new("update-diff");
+        let base_path = test_dir.path.join("fonts");
+        let cache = DownloadCache::new(&test_dir.path.join("cache"), None);
+        let font_manifest = roboto_mono();
+        let ofl = font_manifest
+            .embedded_contents("OFL.txt")
+            .unwrap()
+            .to_string();
+        let registry = install(
+            &base_path,
+            &[
+                ("OFL.txt", &ofl),
+                ("README.txt", "An older readme"),
+                ("RobotoMono-VariableFont_wght.ttf", "font v23"),
+                ("RobotoMono-Italic-VariableFont_wght.ttf", "font v22"),
+                ("RobotoMono-Retired.ttf", "font"),
+            ],
+        );
+        cache.store_blob(VARIABLE_URL, b"font v23", None).unwrap();
+        cache
+            .store_blob(ITALIC_URL, b"italic font v23", None)
+            .unwrap();
+
+        let diffs = diff_update(
+            &["Roboto Mono".to_string()],
+            &registry,
+            &font_manifest,
+            &base_path,
+            Some(&cache),
+        );
+        assert_eq!(diffs.len(), 1);
+        let diff = &diffs[0];
+        assert_eq!(diff.family, "Roboto Mono");
+        assert_eq!(diff.unchanged, 2);
+        assert_eq!(diff.unknown, 0);
+        assert_eq!(
+            changed_paths(diff),
+            ["README.txt", "RobotoMono-Italic-VariableFont_wght.ttf"]
+        );
+        assert!(diff.changes.iter().any(|change| matches!(
+            change,
+            FileChange::Changed { path, old_bytes: 8, new_bytes: Some(15), .. }
+                if path == Path::new("RobotoMono-Italic-VariableFont_wght.ttf")
+        )));
+        let removed = diff
+            .changes
+            .iter()
+            .filter_map(|change| match change {
+                FileChange::Removed { path, bytes } => Some((path.clone(), *bytes)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(removed, [(PathBuf::from("RobotoMono-Retired.ttf"), 4)]);
+        // The other 14 font files of the manifest aren't installed yet, and aren't cached either.
+        let added = diff
+            .changes
+            .iter()
+            .filter(|change| matches!(change, FileChange::Added { bytes: None, .. }))
+            .count();
+        assert_eq!(added, 14);
+    }
+
+    #[test]
+    fn files_that_arent_cached_are_unknown_not_changed() {
+        let test_dir = TestDir::new("update-diff-uncached");
+        let base_path = test_dir.path.join("fonts");
+        let font_manifest = roboto_mono();
+        let installed = font_manifest
+            .planned_files(&base_path)
+            .into_iter()
+            .map(|planned_file| {
+                let contents = font_manifest
+                    .embedded_contents(&planned_file.filename)
+                    .unwrap_or("font")
+                    .to_string();
+                (planned_file.filename, contents)
+            })
+            .collect::<Vec<_>>();
+        let installed = installed
+            .iter()
+            .map(|(path, contents)| (path.as_str(), contents.as_str()))
+            .collect::<Vec<_>>();
+        let registry = install(&base_path, &installed);
+
+        let diffs = diff_update(
+            &["Roboto Mono".to_string()],
+            &registry,
+            &font_manifest,
+            &base_path,
+            None,
+        );
+        assert!(diffs[0].changes.is_empty(), "{:?}", diffs[0].changes);
+        assert_eq!(diffs[0].unchanged, 2);
+        assert_eq!(diffs[0].unknown, 16);
+    }
+}
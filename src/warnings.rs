@@ -0,0 +1,130 @@
+//! Warnings: what went wrong during a run without failing it (or only failing part of it).
+//! They are printed to stderr as they happen (so they don't end up in output that is parsed, like `--json`),
+//! repeated after the summary, and listed in the JSON report.
+//! With `--deny-warnings`, any warning fails the run.
+
+use crate::color::*;
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::{msg, notify::strip_colors};
+
+/// What a warning is about. The codes are stable, so scripts can filter for specific warnings in the JSON report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCode {
+    /// A font name was given more than once, it is only fetched once.
+    DuplicateFont,
+    /// A font is not on Google Fonts, and was skipped with `--ignore-invalid`.
+    InvalidFontSkipped,
+    /// The manifest of a family could not be fetched, or it can't be installed as it is (see `install::FamilyError`),
+    /// so the family was skipped.
+    ManifestFailed,
+    /// The registry of installed fonts could not be saved.
+    RegistryNotSaved,
+    /// The post-install hook failed, or could not be run.
+    HookFailed,
+    /// The font files on disk after installing specific faces are not the ones that were planned.
+    AuditFailed,
+    /// Files could not be put into a target given with `--also-output`. The font base dir is left as installed.
+    TargetFailed,
+    /// A family is about to be installed in the other layout (in its own dir, or directly in the font base dir) than
+    /// its font files already are, so they will be installed twice.
+    LayoutDuplicate,
+    /// The registry of installed fonts could not be read or is corrupted, so it was rebuilt from the installed files.
+    RegistryRebuilt,
+    /// The font cache could not be refreshed (`fc-cache` is missing or failed), so new fonts may not show up right away.
+    FontCacheNotRefreshed,
+}
+
+impl WarningCode {
+    /// The code as it is printed and in the JSON report, like `duplicate-font`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DuplicateFont => "duplicate-font",
+            Self::InvalidFontSkipped => "invalid-font-skipped",
+            Self::ManifestFailed => "manifest-failed",
+            Self::RegistryNotSaved => "registry-not-saved",
+            Self::HookFailed => "hook-failed",
+            Self::AuditFailed => "audit-failed",
+            Self::TargetFailed => "target-failed",
+            Self::LayoutDuplicate => "layout-duplicate",
+            Self::RegistryRebuilt => "registry-rebuilt",
+            Self::FontCacheNotRefreshed => "font-cache-not-refreshed",
+        }
+    }
+
+    /// Whether part of the run failed, so it ends with the partial failure exit code even without `--deny-warnings`.
+    pub fn is_partial_failure(self) -> bool {
+        matches!(
+            self,
+            Self::InvalidFontSkipped
+                | Self::ManifestFailed
+                | Self::HookFailed
+                | Self::AuditFailed
+                | Self::TargetFailed
+        )
+    }
+}
+
+/// A warning, with its message without colors.
+#[derive(Clone, Debug, Serialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+}
+
+/// Renders the warnings of a run as a section below the summary. Empty without warnings.
+pub fn render_warnings(warnings: &[Warning]) -> String {
+    if warnings.is_empty() {
+        return String::new();
+    }
+    let mut section = format!(
+        "\n{color_yellow}{style_bold}{}{color_reset}{style_reset}\n",
+        msg!("summary.warnings", count = warnings.len())
+    );
+    for warning in warnings {
+        let _ = writeln!(
+            section,
+            "  {} {color_bright_black}[{}]{color_reset}",
+            warning.message,
+            warning.code.as_str()
+        );
+    }
+    section
+}
+
+/// The warnings of a run, in the order they happened.
+#[derive(Clone, Debug, Default)]
+pub struct Warnings {
+    warnings: Vec<Warning>,
+}
+
+impl Warnings {
+    /// Prints a warning to stderr right away (even with `--quiet`, like errors) and keeps it for the end of the run.
+    /// The message may have colors, they are left out of the kept one.
+    pub fn warn(&mut self, code: WarningCode, message: impl AsRef<str>) {
+        let message = message.as_ref();
+        eprintln!(
+            "{color_yellow}WARN:{color_reset} {} {color_bright_black}[{}]{color_reset}",
+            message,
+            code.as_str()
+        );
+        self.warnings.push(Warning {
+            code,
+            message: strip_colors(message),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<Warning> {
+        self.warnings
+    }
+}
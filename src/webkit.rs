@@ -0,0 +1,260 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// The stylesheet of a web font kit, in the output dir.
+pub const WEBKIT_STYLESHEET_FILENAME: &str = "fonts.css";
+
+/// The fragment with the preload hints of a web font kit, in the output dir.
+pub const WEBKIT_PRELOAD_FILENAME: &str = "preload-links.html";
+
+/// The `font-display` values a web font kit can be generated with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontDisplay {
+    Swap,
+    Optional,
+    Block,
+    Fallback,
+}
+
+impl FromStr for FontDisplay {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "swap" => Ok(FontDisplay::Swap),
+            "optional" => Ok(FontDisplay::Optional),
+            "block" => Ok(FontDisplay::Block),
+            "fallback" => Ok(FontDisplay::Fallback),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for FontDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontDisplay::Swap => write!(f, "swap"),
+            FontDisplay::Optional => write!(f, "optional"),
+            FontDisplay::Block => write!(f, "block"),
+            FontDisplay::Fallback => write!(f, "fallback"),
+        }
+    }
+}
+
+/// Builds the family spec for the CSS API that asks for every variant of a family, e.g. `Lora:ital,wght@0,400;1,400`.
+/// Variants are given as in the catalog (`400`, `700i`). Without any, the CSS API only gives the regular face.
+pub fn css2_family_spec(font_name: &str, variants: &[String]) -> String {
+    let mut faces = variants
+        .iter()
+        .filter_map(|variant| {
+            let (weight, italic) = match variant.strip_suffix('i') {
+                Some(weight) => (weight, true),
+                None => (variant.as_str(), false),
+            };
+            Some((italic, weight.parse::<u16>().ok()?))
+        })
+        .collect::<Vec<_>>();
+    // The CSS API wants the tuples sorted, and rejects duplicates.
+    faces.sort();
+    faces.dedup();
+
+    if faces.is_empty() {
+        font_name.to_string()
+    } else if faces.iter().any(|(italic, _)| *italic) {
+        format!(
+            "{}:ital,wght@{}",
+            font_name,
+            faces
+                .iter()
+                .map(|(italic, weight)| format!("{},{}", *italic as u8, weight))
+                .collect::<Vec<_>>()
+                .join(";")
+        )
+    } else {
+        format!(
+            "{}:wght@{}",
+            font_name,
+            faces
+                .iter()
+                .map(|(_, weight)| weight.to_string())
+                .collect::<Vec<_>>()
+                .join(";")
+        )
+    }
+}
+
+/// A face from a stylesheet, with what is needed to download it and to order preload hints.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CssFontFace {
+    /// The URLs in the `src` of the rule.
+    pub urls: Vec<String>,
+    /// The (first) weight of the face, 400 if the rule does not say.
+    pub weight: u16,
+    pub italic: bool,
+}
+
+/// Finds the URLs in a CSS value, e.g. `url(https://...) format('woff2')`, with the quotes around them removed.
+fn find_urls(value: &str) -> Vec<(usize, usize)> {
+    let mut urls = Vec::new();
+    let mut position = 0;
+    while let Some(start) = value[position..].find("url(") {
+        let start = position + start + "url(".len();
+        let Some(length) = value[start..].find(')') else {
+            break;
+        };
+        // The span without surrounding quotes.
+        let (mut url_start, mut url_end) = (start, start + length);
+        let url = &value[url_start..url_end];
+        if url.len() >= 2 && (url.starts_with(['\'', '"'])) && url.ends_with(&url[..1]) {
+            url_start += 1;
+            url_end -= 1;
+        }
+        urls.push((url_start, url_end));
+        position = start + length + 1;
+    }
+    urls
+}
+
+/// Splits a stylesheet into the parts outside and inside `@font-face` blocks, as `(is_block, text)`.
+/// The block text is what is between the braces.
+fn split_font_face_blocks(css: &str) -> Vec<(bool, &str)> {
+    let mut parts = Vec::new();
+    let mut rest = css;
+    while let Some(start) = rest.find("@font-face") {
+        let Some(open) = rest[start..].find('{').map(|open| start + open + 1) else {
+            break;
+        };
+        let Some(close) = rest[open..].find('}').map(|close| open + close) else {
+            break;
+        };
+        parts.push((false, &rest[..open]));
+        parts.push((true, &rest[open..close]));
+        rest = &rest[close..];
+    }
+    parts.push((false, rest));
+    parts
+}
+
+/// Splits the declarations of a block into `(property, value)` pairs, keeping the original text of each declaration.
+fn declarations(block: &str) -> impl Iterator<Item = (&str, &str, &str)> {
+    block.split_inclusive(';').filter_map(|declaration| {
+        let (property, value) = declaration.split_once(':')?;
+        Some((
+            declaration,
+            property.trim(),
+            value.trim().trim_end_matches(';').trim(),
+        ))
+    })
+}
+
+/// Lists the `@font-face` rules of a stylesheet, in order.
+pub fn parse_font_faces(css: &str) -> Vec<CssFontFace> {
+    split_font_face_blocks(css)
+        .into_iter()
+        .filter(|(is_block, _)| *is_block)
+        .map(|(_, block)| {
+            let mut face = CssFontFace {
+                urls: Vec::new(),
+                weight: 400,
+                italic: false,
+            };
+            for (_, property, value) in declarations(block) {
+                match property {
+                    "src" => face.urls.extend(
+                        find_urls(value)
+                            .into_iter()
+                            .map(|(start, end)| value[start..end].to_string()),
+                    ),
+                    "font-weight" => {
+                        if let Some(weight) = value
+                            .split_whitespace()
+                            .next()
+                            .and_then(|weight| weight.parse().ok())
+                        {
+                            face.weight = weight;
+                        }
+                    }
+                    "font-style" => face.italic = value != "normal",
+                    _ => {}
+                }
+            }
+            face
+        })
+        .collect()
+}
+
+/// Rewrites a stylesheet for a web font kit: URLs are replaced using `urls` (URLs not in it are kept),
+/// and with `display` every `@font-face` rule gets that `font-display` (replacing the one it had).
+/// Everything else, like the `unicode-range` of each rule and the comments naming the subsets, is kept as it is.
+pub fn rewrite_css(
+    css: &str,
+    urls: &HashMap<String, String>,
+    display: Option<FontDisplay>,
+) -> String {
+    let mut rewritten = String::with_capacity(css.len());
+    for (is_block, text) in split_font_face_blocks(css) {
+        if !is_block {
+            rewritten.push_str(text);
+            continue;
+        }
+
+        let mut block = String::with_capacity(text.len());
+        let mut rest = text;
+        for (declaration, property, _) in declarations(text) {
+            // Keep whatever is between the declarations (usually nothing).
+            let offset = rest.find(declaration).unwrap_or(0);
+            block.push_str(&rest[..offset]);
+            rest = &rest[offset + declaration.len()..];
+
+            match property {
+                "font-display" if display.is_some() => continue,
+                "src" => {
+                    let mut declaration = declaration.to_string();
+                    for (start, end) in find_urls(&declaration).into_iter().rev() {
+                        if let Some(local_url) = urls.get(&declaration[start..end]) {
+                            declaration.replace_range(start..end, local_url);
+                        }
+                    }
+                    if let Some(display) = display {
+                        // Put font-display right before src, with the same indentation.
+                        let indent =
+                            &declaration[..declaration.len() - declaration.trim_start().len()];
+                        block.push_str(&format!("{}font-display: {};", indent, display));
+                    }
+                    block.push_str(&declaration);
+                }
+                _ => block.push_str(declaration),
+            }
+        }
+        block.push_str(rest);
+        rewritten.push_str(&block);
+    }
+    rewritten
+}
+
+/// Renders `<link rel="preload">` tags for the WOFF2 files of the faces, regular (upright 400) faces first,
+/// then the other upright weights and then the italic ones, from light to heavy.
+/// `urls` maps the URLs in the stylesheet to the ones the kit uses.
+pub fn render_preload_links(faces: &[CssFontFace], urls: &HashMap<String, String>) -> String {
+    let mut preloads = faces
+        .iter()
+        .flat_map(|face| face.urls.iter().map(move |url| (face, url)))
+        .filter(|(_, url)| url.to_lowercase().ends_with(".woff2"))
+        .filter_map(|(face, url)| {
+            Some((face.italic, face.weight != 400, face.weight, urls.get(url)?))
+        })
+        .collect::<Vec<_>>();
+    // Stable, so the faces of one weight keep the order of the stylesheet.
+    preloads.sort_by_key(|(italic, not_regular, weight, _)| (*italic, *not_regular, *weight));
+
+    let mut seen = std::collections::HashSet::new();
+    preloads
+        .into_iter()
+        .filter(|(_, _, _, url)| seen.insert(url.as_str()))
+        .map(|(_, _, _, url)| {
+            format!(
+                "<link rel=\"preload\" href=\"{}\" as=\"font\" type=\"font/woff2\" crossorigin>\n",
+                url.replace('&', "&amp;").replace('"', "&quot;")
+            )
+        })
+        .collect()
+}
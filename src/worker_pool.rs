@@ -0,0 +1,217 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Condvar, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crate::http;
+
+/// Runs `job` for every item (with its index), on up to `jobs` threads at the same time.
+/// The results are returned in the same order as the items, no matter in which order the jobs finish.
+/// The requests made by the jobs go through the fetcher of the calling thread (see `http::set_fetcher`).
+pub fn run_parallel<T, R>(items: &[T], jobs: usize, job: impl Fn(usize, &T) -> R + Sync) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let next_index = AtomicUsize::new(0);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<_>>());
+    let fetcher = http::current_fetcher();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                http::set_fetcher(fetcher.clone());
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result = job(index, item);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed by a worker"))
+        .collect()
+}
+
+/// Where a pipeline (see `run_pipelined`) is: the next item to produce, how many items were handed to the consumer,
+/// and the results produced ahead of it.
+struct PipelineState<R> {
+    next_index: usize,
+    consumed: usize,
+    ready: BTreeMap<usize, R>,
+    /// Set once the consumer is done (or gave up, e.g. by panicking), so the producers stop waiting for it.
+    stopped: bool,
+}
+
+/// Stops the producers of a pipeline when the consumer is done, however it is done.
+struct StopPipeline<'a, R> {
+    state: &'a Mutex<PipelineState<R>>,
+    changed: &'a Condvar,
+}
+
+impl<R> Drop for StopPipeline<'_, R> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.stopped = true;
+        }
+        self.changed.notify_all();
+    }
+}
+
+/// Runs `produce` for every item (with its index) on up to `depth` threads, and `consume` for every result on the calling
+/// thread, in the same order as the items. Items are produced while earlier ones are consumed, but never more than `depth`
+/// ahead of the one being consumed, so the results don't pile up when consuming is the slower part (like fetching the
+/// manifests of the next families while the files of one are downloaded).
+/// The requests made by `produce` go through the fetcher of the calling thread (see `http::set_fetcher`).
+pub fn run_pipelined<T, R>(
+    items: &[T],
+    depth: usize,
+    produce: impl Fn(usize, &T) -> R + Sync,
+    mut consume: impl FnMut(usize, &T, R),
+) where
+    T: Sync,
+    R: Send,
+{
+    let depth = depth.max(1);
+    let state = Mutex::new(PipelineState {
+        next_index: 0,
+        consumed: 0,
+        ready: BTreeMap::new(),
+        stopped: false,
+    });
+    let changed = Condvar::new();
+    let fetcher = http::current_fetcher();
+
+    thread::scope(|scope| {
+        for _ in 0..depth.min(items.len()) {
+            scope.spawn(|| {
+                http::set_fetcher(fetcher.clone());
+                loop {
+                    let index = {
+                        let mut state = changed
+                            .wait_while(state.lock().unwrap(), |state| {
+                                !state.stopped
+                                    && state.next_index < items.len()
+                                    && state.next_index >= state.consumed + depth
+                            })
+                            .unwrap();
+                        if state.stopped || state.next_index >= items.len() {
+                            break;
+                        }
+                        state.next_index += 1;
+                        state.next_index - 1
+                    };
+                    let result = produce(index, &items[index]);
+                    state.lock().unwrap().ready.insert(index, result);
+                    changed.notify_all();
+                }
+            });
+        }
+
+        let _stop = StopPipeline {
+            state: &state,
+            changed: &changed,
+        };
+        for (index, item) in items.iter().enumerate() {
+            let result = {
+                let mut state = changed
+                    .wait_while(state.lock().unwrap(), |state| {
+                        !state.ready.contains_key(&index)
+                    })
+                    .unwrap();
+                state.consumed = index + 1;
+                state.ready.remove(&index).expect("the result was produced")
+            };
+            // The producers may go on with the next items while this one is consumed.
+            changed.notify_all();
+            consume(index, item, result);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::AtomicBool, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn pipelined_results_are_consumed_in_order() {
+        let items = (0..50).collect::<Vec<usize>>();
+        let mut consumed = Vec::new();
+        run_pipelined(
+            &items,
+            4,
+            |index, item| {
+                // Later items finish first, which must not change the order.
+                thread::sleep(Duration::from_micros(((50 - index) * 20) as u64));
+                item * 2
+            },
+            |index, item, result| {
+                assert_eq!(result, item * 2);
+                consumed.push(index);
+            },
+        );
+        assert_eq!(consumed, items);
+    }
+
+    #[test]
+    fn pipelines_produce_at_most_depth_items_ahead() {
+        let items = (0..200).collect::<Vec<usize>>();
+        let consuming = AtomicUsize::new(0);
+        let ahead = AtomicUsize::new(0);
+        let max_ahead = AtomicUsize::new(0);
+        let overtaken = AtomicBool::new(false);
+        run_pipelined(
+            &items,
+            3,
+            |index, _| {
+                // Produced, or being produced, but not consumed yet. The one just handed to the consumer counts until
+                // `consume` gets to it, as does the index it was at.
+                let now_ahead = ahead.fetch_add(1, Ordering::SeqCst) + 1;
+                max_ahead.fetch_max(now_ahead, Ordering::SeqCst);
+                if index > consuming.load(Ordering::SeqCst) + 3 + 1 {
+                    overtaken.store(true, Ordering::SeqCst);
+                }
+            },
+            |index, _, _| {
+                consuming.store(index, Ordering::SeqCst);
+                ahead.fetch_sub(1, Ordering::SeqCst);
+                // Consuming is the slow part, like downloading the files of a family.
+                thread::sleep(Duration::from_micros(200));
+            },
+        );
+        assert!(max_ahead.load(Ordering::SeqCst) <= 3 + 1);
+        assert!(!overtaken.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn pipelines_stop_when_the_consumer_panics() {
+        let items = (0..100).collect::<Vec<usize>>();
+        let produced = AtomicUsize::new(0);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run_pipelined(
+                &items,
+                2,
+                |_, _| {
+                    produced.fetch_add(1, Ordering::SeqCst);
+                },
+                |index, _, _| assert!(index < 5, "giving up"),
+            )
+        }));
+        assert!(result.is_err());
+        assert!(produced.load(Ordering::SeqCst) <= 5 + 1 + 2);
+    }
+}